@@ -0,0 +1,14 @@
+-- Named API tokens for devices and scripts. Only SHA-256 hashes of the tokens
+-- are stored. Scopes are `read`, `write`, `admin`, `list:<id>:read` and
+-- `list:<id>:write`. Revoked tokens are kept for reference.
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(200) NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
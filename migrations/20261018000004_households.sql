@@ -0,0 +1,65 @@
+-- Households are isolated tenants: every list, category and name belongs to
+-- one, and users only see the households they are members of
+CREATE TABLE IF NOT EXISTS households (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(200) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS household_members (
+    household_id INTEGER NOT NULL REFERENCES households (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (household_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS household_members_user_id_idx ON household_members (user_id);
+
+-- Existing data and users move into a default household
+INSERT INTO households (name) VALUES ('Home');
+
+ALTER TABLE lists ADD COLUMN household_id INTEGER REFERENCES households (id) ON DELETE CASCADE;
+ALTER TABLE categories ADD COLUMN household_id INTEGER REFERENCES households (id) ON DELETE CASCADE;
+ALTER TABLE names ADD COLUMN household_id INTEGER REFERENCES households (id) ON DELETE CASCADE;
+ALTER TABLE api_tokens ADD COLUMN household_id INTEGER REFERENCES households (id) ON DELETE CASCADE;
+-- Household the invited user joins
+ALTER TABLE invitations ADD COLUMN household_id INTEGER REFERENCES households (id) ON DELETE CASCADE;
+
+UPDATE lists SET household_id = (SELECT min(id) FROM households);
+UPDATE categories SET household_id = (SELECT min(id) FROM households);
+UPDATE names SET household_id = (SELECT min(id) FROM households);
+UPDATE api_tokens SET household_id = (SELECT min(id) FROM households);
+UPDATE invitations SET household_id = (SELECT min(id) FROM households);
+
+INSERT INTO household_members (household_id, user_id)
+SELECT (SELECT min(id) FROM households), id FROM users;
+
+ALTER TABLE lists ALTER COLUMN household_id SET NOT NULL;
+ALTER TABLE categories ALTER COLUMN household_id SET NOT NULL;
+ALTER TABLE names ALTER COLUMN household_id SET NOT NULL;
+ALTER TABLE api_tokens ALTER COLUMN household_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS lists_household_id_idx ON lists (household_id);
+
+-- Category and name uniqueness becomes per household. The original unique
+-- constraints on `name` are dropped whatever they are called.
+DO $$
+DECLARE
+    c RECORD;
+BEGIN
+    FOR c IN
+        SELECT con.conrelid::regclass AS tbl, con.conname
+        FROM pg_constraint con
+        JOIN pg_attribute att
+          ON att.attrelid = con.conrelid AND att.attnum = ANY (con.conkey)
+        WHERE con.contype = 'u'
+          AND con.conrelid IN ('categories'::regclass, 'names'::regclass)
+          AND array_length(con.conkey, 1) = 1
+          AND att.attname = 'name'
+    LOOP
+        EXECUTE format('ALTER TABLE %s DROP CONSTRAINT %I', c.tbl, c.conname);
+    END LOOP;
+END $$;
+
+ALTER TABLE categories ADD CONSTRAINT categories_household_id_name_key UNIQUE (household_id, name);
+ALTER TABLE names ADD CONSTRAINT names_household_id_name_key UNIQUE (household_id, name);
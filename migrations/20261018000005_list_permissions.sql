@@ -0,0 +1,64 @@
+-- Groups of household members that lists can be shared with
+CREATE TABLE IF NOT EXISTS groups (
+    id SERIAL PRIMARY KEY,
+    household_id INTEGER NOT NULL REFERENCES households (id) ON DELETE CASCADE,
+    name VARCHAR(200) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CONSTRAINT groups_household_id_name_key UNIQUE (household_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS group_members (
+    group_id INTEGER NOT NULL REFERENCES groups (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS group_members_user_id_idx ON group_members (user_id);
+
+-- Roles on a single list, from least to most
+CREATE TYPE list_role AS ENUM ('viewer', 'editor', 'owner');
+
+-- Lists are shared with the whole household unless they are private. Existing
+-- lists stay shared and have no owner.
+ALTER TABLE lists ADD COLUMN owner_id INTEGER REFERENCES users (id) ON DELETE SET NULL;
+ALTER TABLE lists ADD COLUMN private BOOLEAN NOT NULL DEFAULT false;
+
+-- Roles granted to single users or groups; the owner is `lists.owner_id`
+CREATE TABLE IF NOT EXISTS list_permissions (
+    id SERIAL PRIMARY KEY,
+    list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+    user_id INTEGER REFERENCES users (id) ON DELETE CASCADE,
+    group_id INTEGER REFERENCES groups (id) ON DELETE CASCADE,
+    role list_role NOT NULL CHECK (role <> 'owner'),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((user_id IS NULL) <> (group_id IS NULL)),
+    CONSTRAINT list_permissions_list_id_user_id_key UNIQUE (list_id, user_id),
+    CONSTRAINT list_permissions_list_id_group_id_key UNIQUE (list_id, group_id)
+);
+
+-- The role a user has on a list, or NULL if they cannot see it: the owner
+-- owns it, everyone edits shared lists (and owns shared lists without an
+-- owner), and grants apply directly or through a group. NULL without a user.
+CREATE OR REPLACE FUNCTION list_role(p_list_id INTEGER, p_user_id INTEGER)
+RETURNS list_role
+LANGUAGE sql STABLE STRICT
+AS $$
+    SELECT GREATEST(
+        CASE WHEN l.owner_id = p_user_id THEN 'owner'::list_role END,
+        CASE
+            WHEN l.private THEN NULL
+            WHEN l.owner_id IS NULL THEN 'owner'::list_role
+            ELSE 'editor'::list_role
+        END,
+        (
+            SELECT max(p.role)
+            FROM list_permissions p
+            WHERE p.list_id = l.id
+              AND (p.user_id = p_user_id
+                   OR p.group_id IN (SELECT group_id FROM group_members WHERE user_id = p_user_id))
+        )
+    )
+    FROM lists l
+    WHERE l.id = p_list_id
+$$;
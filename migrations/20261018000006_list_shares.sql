@@ -0,0 +1,14 @@
+-- Read-only links to a single list for people without an account. Only the
+-- token hash is stored, like for sessions and API tokens.
+CREATE TABLE IF NOT EXISTS list_shares (
+    id SERIAL PRIMARY KEY,
+    list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS list_shares_list_id_idx ON list_shares (list_id);
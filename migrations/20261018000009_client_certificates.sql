@@ -0,0 +1,11 @@
+-- API tokens can be bound to a client certificate instead of a secret. The
+-- certificate is named by an identity it carries: `CN=<common name>`,
+-- `DNS:<name>` or `URI:<uri>` from its subject alternative names.
+ALTER TABLE api_tokens ALTER COLUMN token_hash DROP NOT NULL;
+ALTER TABLE api_tokens ADD COLUMN client_certificate TEXT;
+ALTER TABLE api_tokens ADD CONSTRAINT api_tokens_credential_check
+    CHECK ((token_hash IS NULL) <> (client_certificate IS NULL));
+
+-- A certificate can be bound again once its token is revoked
+CREATE UNIQUE INDEX IF NOT EXISTS api_tokens_client_certificate_idx
+    ON api_tokens (client_certificate) WHERE revoked_at IS NULL;
@@ -0,0 +1,15 @@
+-- Household roles: managers may invite people to their household
+ALTER TABLE household_members ADD COLUMN role TEXT NOT NULL DEFAULT 'member'
+    CHECK (role IN ('member', 'manager'));
+
+-- Invitations become join codes that may be used several times (unlimited if
+-- `max_uses` is null) and carry the household role their users get. `used_by`
+-- and `used_at` record the latest use.
+ALTER TABLE invitations ADD COLUMN role TEXT NOT NULL DEFAULT 'member'
+    CHECK (role IN ('member', 'manager'));
+ALTER TABLE invitations ADD COLUMN max_uses INTEGER DEFAULT 1 CHECK (max_uses > 0);
+ALTER TABLE invitations ADD COLUMN uses INTEGER NOT NULL DEFAULT 0;
+
+UPDATE invitations SET uses = 1 WHERE used_at IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS invitations_household_id_idx ON invitations (household_id);
@@ -0,0 +1,128 @@
+-- Append-only log of every change to the audited tables, written by triggers
+-- so that cascading updates and deletes are recorded as well. Who made the
+-- change comes from the `lister.audit_context` setting, which the server sets
+-- on every connection it hands out (see src/audit.rs).
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+    -- user, token, system, job or cli; NULL for anonymous requests like registering
+    actor TEXT,
+    -- Username, token name or job name at the time of the change
+    actor_name TEXT,
+    -- No foreign keys: entries outlive the users and tokens they name
+    user_id INTEGER,
+    token_id INTEGER,
+    request_id TEXT,
+    -- Table and primary key of the changed row (comma-separated if composite)
+    entity TEXT NOT NULL,
+    entity_id TEXT,
+    action TEXT NOT NULL CHECK (action IN ('create', 'update', 'delete')),
+    before JSONB,
+    after JSONB
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_occurred_at_idx ON audit_log (occurred_at DESC);
+CREATE INDEX IF NOT EXISTS audit_log_entity_idx ON audit_log (entity, entity_id);
+CREATE INDEX IF NOT EXISTS audit_log_user_id_idx ON audit_log (user_id);
+CREATE INDEX IF NOT EXISTS audit_log_request_id_idx ON audit_log (request_id);
+
+-- Arguments: primary key columns, columns whose values are never logged (only
+-- whether they changed), and columns that are not worth an entry on their own
+-- (e.g. `last_used_at`). All comma-separated.
+CREATE OR REPLACE FUNCTION audit_row() RETURNS trigger AS $$
+DECLARE
+    context JSONB := NULLIF(current_setting('lister.audit_context', true), '')::jsonb;
+    keys TEXT[] := string_to_array(TG_ARGV[0], ',');
+    redacted TEXT[] := string_to_array(COALESCE(TG_ARGV[1], ''), ',');
+    ignored TEXT[] := string_to_array(COALESCE(TG_ARGV[2], ''), ',');
+    old_row JSONB;
+    new_row JSONB;
+    row_id TEXT;
+    col TEXT;
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        old_row := to_jsonb(OLD);
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        new_row := to_jsonb(NEW);
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND old_row - ignored = new_row - ignored THEN
+        RETURN NULL;
+    END IF;
+
+    SELECT string_agg(COALESCE(new_row, old_row) ->> k, ',' ORDER BY n)
+    INTO row_id
+    FROM unnest(keys) WITH ORDINALITY AS t (k, n);
+
+    FOREACH col IN ARRAY redacted LOOP
+        IF new_row IS NOT NULL AND new_row ->> col IS NOT NULL THEN
+            new_row := jsonb_set(new_row, ARRAY[col], CASE
+                WHEN old_row IS NOT NULL AND old_row -> col IS NOT DISTINCT FROM new_row -> col
+                    THEN '"[redacted]"'::jsonb
+                ELSE '"[changed]"'::jsonb
+            END);
+        END IF;
+        IF old_row IS NOT NULL AND old_row ->> col IS NOT NULL THEN
+            old_row := jsonb_set(old_row, ARRAY[col], '"[redacted]"'::jsonb);
+        END IF;
+    END LOOP;
+
+    INSERT INTO audit_log (actor, actor_name, user_id, token_id, request_id, entity, entity_id, action, before, after)
+    VALUES (
+        context ->> 'actor',
+        context ->> 'actorName',
+        (context ->> 'userId')::int,
+        (context ->> 'tokenId')::int,
+        context ->> 'requestId',
+        TG_TABLE_NAME,
+        row_id,
+        CASE TG_OP WHEN 'INSERT' THEN 'create' WHEN 'UPDATE' THEN 'update' ELSE 'delete' END,
+        old_row,
+        new_row
+    );
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Entries are only ever deleted by the retention job, never changed
+CREATE OR REPLACE FUNCTION audit_log_immutable() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_immutable BEFORE UPDATE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_immutable();
+
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON lists
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON items
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON categories
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON names
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON users
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id', 'password_hash');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON households
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON household_members
+    FOR EACH ROW EXECUTE FUNCTION audit_row('household_id,user_id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON groups
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON group_members
+    FOR EACH ROW EXECUTE FUNCTION audit_row('group_id,user_id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON list_permissions
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON list_shares
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id', 'token_hash', 'last_used_at');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON api_tokens
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id', 'token_hash', 'last_used_at');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON invitations
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id', 'token_hash');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON passkeys
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id', '', 'sign_count,last_used_at');
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON user_identities
+    FOR EACH ROW EXECUTE FUNCTION audit_row('id', '', 'synced_at');
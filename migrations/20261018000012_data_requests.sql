@@ -0,0 +1,25 @@
+-- Personal data exports and account deletions, processed in the background.
+-- Requests outlive the account they are about, so `user_id` has no foreign
+-- key and the username is kept for reference.
+CREATE TABLE IF NOT EXISTS data_requests (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL CHECK (kind IN ('export', 'deletion')),
+    user_id INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    -- Deletions only: what happens to the lists the user owns
+    list_policy TEXT CHECK (list_policy IN ('transfer', 'release', 'delete')),
+    transfer_to INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'running', 'succeeded', 'failed')),
+    -- The export document, once finished
+    result JSONB,
+    error TEXT,
+    requested_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+    CHECK ((kind = 'deletion') = (list_policy IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS data_requests_user_id_idx ON data_requests (user_id);
+CREATE INDEX IF NOT EXISTS data_requests_status_idx ON data_requests (status) WHERE status IN ('pending', 'running');
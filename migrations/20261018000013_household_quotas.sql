@@ -0,0 +1,22 @@
+-- Per-household overrides of the quotas set with the QUOTA_* variables.
+-- NULL keeps the server default.
+CREATE TABLE IF NOT EXISTS household_quotas (
+    household_id INTEGER PRIMARY KEY REFERENCES households (id) ON DELETE CASCADE,
+    lists BIGINT CHECK (lists >= 0),
+    items_per_list BIGINT CHECK (items_per_list >= 0),
+    names BIGINT CHECK (names >= 0),
+    categories BIGINT CHECK (categories >= 0),
+    requests_per_day BIGINT CHECK (requests_per_day >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TRIGGER audit AFTER INSERT OR UPDATE OR DELETE ON household_quotas
+    FOR EACH ROW EXECUTE FUNCTION audit_row('household_id');
+
+-- API requests per household and day (UTC), for the `requests_per_day` quota
+CREATE TABLE IF NOT EXISTS household_requests (
+    household_id INTEGER NOT NULL REFERENCES households (id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    count BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (household_id, day)
+);
@@ -0,0 +1,72 @@
+-- Changes to items, streamed to clients as Server-Sent Events. Every event is
+-- also sent on the `item_events` channel, so each API instance learns about
+-- changes made through the others; the table lets clients resume from the
+-- last event they received.
+CREATE TABLE IF NOT EXISTS item_events (
+    id BIGSERIAL PRIMARY KEY,
+    household_id INTEGER NOT NULL REFERENCES households (id) ON DELETE CASCADE,
+    -- No foreign key: the events of deleted items outlive their list
+    list_id INTEGER NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('created', 'updated', 'toggled', 'deleted')),
+    -- The item as returned by the API, before its deletion for `deleted`
+    item JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS item_events_household_id_idx ON item_events (household_id, id);
+CREATE INDEX IF NOT EXISTS item_events_created_at_idx ON item_events (created_at);
+
+CREATE OR REPLACE FUNCTION item_event() RETURNS trigger AS $$
+DECLARE
+    changed items;
+    event_kind TEXT;
+    event item_events;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        changed := NEW;
+        event_kind := 'created';
+    ELSIF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        event_kind := 'deleted';
+    ELSIF to_jsonb(NEW) = to_jsonb(OLD) THEN
+        RETURN NULL;
+    ELSIF to_jsonb(NEW) - 'inCart' = to_jsonb(OLD) - 'inCart' THEN
+        changed := NEW;
+        event_kind := 'toggled';
+    ELSE
+        changed := NEW;
+        event_kind := 'updated';
+    END IF;
+
+    -- Items deleted along with their list have no household left to tell
+    INSERT INTO item_events (household_id, list_id, kind, item)
+    SELECT lists.household_id, changed.list, event_kind, jsonb_build_object(
+        'id', changed.id,
+        'name', changed.name,
+        'amount', changed.amount::text,
+        'amountUnit', changed."amountUnit",
+        'inCart', changed."inCart",
+        'list', changed.list,
+        'category', changed.category
+    )
+    FROM lists
+    WHERE lists.id = changed.list
+    RETURNING * INTO event;
+
+    IF event.id IS NOT NULL THEN
+        PERFORM pg_notify('item_events', jsonb_build_object(
+            'id', event.id,
+            'householdId', event.household_id,
+            'listId', event.list_id,
+            'kind', event.kind,
+            'item', event.item,
+            'createdAt', event.created_at
+        )::text);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER item_events AFTER INSERT OR UPDATE OR DELETE ON items
+    FOR EACH ROW EXECUTE FUNCTION item_event();
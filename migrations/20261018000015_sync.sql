@@ -0,0 +1,168 @@
+-- Delta sync of lists, items, categories and names (see src/services/sync.rs).
+--
+-- Every change to a row stamps it with the next `sync_version`, which orders
+-- the changes, and the ID of the writing transaction, which tells clients'
+-- cursors apart from changes still being committed. Deleted rows leave a
+-- tombstone stamped the same way.
+CREATE SEQUENCE IF NOT EXISTS sync_version;
+
+ALTER TABLE lists
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT nextval('sync_version'),
+    ADD COLUMN IF NOT EXISTS version_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+ALTER TABLE items
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT nextval('sync_version'),
+    ADD COLUMN IF NOT EXISTS version_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+ALTER TABLE categories
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT nextval('sync_version'),
+    ADD COLUMN IF NOT EXISTS version_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+ALTER TABLE names
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT nextval('sync_version'),
+    ADD COLUMN IF NOT EXISTS version_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+CREATE INDEX IF NOT EXISTS lists_version_xid_idx ON lists (household_id, version_xid);
+CREATE INDEX IF NOT EXISTS items_version_xid_idx ON items (list, version_xid);
+CREATE INDEX IF NOT EXISTS categories_version_xid_idx ON categories (household_id, version_xid);
+CREATE INDEX IF NOT EXISTS names_version_xid_idx ON names (household_id, version_xid);
+
+-- Inserts are stamped by the column defaults
+CREATE OR REPLACE FUNCTION sync_touch() RETURNS trigger AS $$
+BEGIN
+    IF NEW IS NOT DISTINCT FROM OLD THEN
+        RETURN NEW;
+    END IF;
+
+    NEW.created_at := OLD.created_at;
+    NEW.updated_at := now();
+    NEW.version := nextval('sync_version');
+    NEW.version_xid := pg_current_xact_id();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sync_touch BEFORE UPDATE ON lists
+    FOR EACH ROW EXECUTE FUNCTION sync_touch();
+CREATE TRIGGER sync_touch BEFORE UPDATE ON items
+    FOR EACH ROW EXECUTE FUNCTION sync_touch();
+CREATE TRIGGER sync_touch BEFORE UPDATE ON categories
+    FOR EACH ROW EXECUTE FUNCTION sync_touch();
+CREATE TRIGGER sync_touch BEFORE UPDATE ON names
+    FOR EACH ROW EXECUTE FUNCTION sync_touch();
+
+CREATE TABLE IF NOT EXISTS tombstones (
+    id BIGSERIAL PRIMARY KEY,
+    household_id INTEGER NOT NULL REFERENCES households (id) ON DELETE CASCADE,
+    -- `lists`, `items`, `categories` or `names`
+    entity TEXT NOT NULL,
+    entity_id INTEGER NOT NULL,
+    -- The list itself, or the item's list; NULL for categories and names
+    list_id INTEGER,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    version BIGINT NOT NULL DEFAULT nextval('sync_version'),
+    version_xid xid8 NOT NULL DEFAULT pg_current_xact_id()
+);
+
+CREATE INDEX IF NOT EXISTS tombstones_version_xid_idx ON tombstones (household_id, version_xid);
+CREATE INDEX IF NOT EXISTS tombstones_deleted_at_idx ON tombstones (deleted_at);
+
+-- Cursors from before this transaction ID may have missed purged tombstones
+-- (a single row, written by the `purge_tombstones` job)
+CREATE TABLE IF NOT EXISTS sync_horizon (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    xid BIGINT NOT NULL
+);
+
+-- Rows deleted along with their household leave nothing behind, and items
+-- deleted along with their list are covered by the list's tombstone
+CREATE OR REPLACE FUNCTION sync_tombstone() RETURNS trigger AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'items' THEN
+        INSERT INTO tombstones (household_id, entity, entity_id, list_id)
+        SELECT lists.household_id, 'items', OLD.id, OLD.list
+        FROM lists
+        WHERE lists.id = OLD.list;
+    ELSE
+        INSERT INTO tombstones (household_id, entity, entity_id, list_id)
+        SELECT households.id, TG_TABLE_NAME, OLD.id,
+               CASE WHEN TG_TABLE_NAME = 'lists' THEN OLD.id END
+        FROM households
+        WHERE households.id = OLD.household_id;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sync_tombstone AFTER DELETE ON lists
+    FOR EACH ROW EXECUTE FUNCTION sync_tombstone();
+CREATE TRIGGER sync_tombstone AFTER DELETE ON items
+    FOR EACH ROW EXECUTE FUNCTION sync_tombstone();
+CREATE TRIGGER sync_tombstone AFTER DELETE ON categories
+    FOR EACH ROW EXECUTE FUNCTION sync_tombstone();
+CREATE TRIGGER sync_tombstone AFTER DELETE ON names
+    FOR EACH ROW EXECUTE FUNCTION sync_tombstone();
+
+-- Item events compare rows without the sync columns, so a checked-off item is
+-- still `toggled` rather than `updated`
+CREATE OR REPLACE FUNCTION item_event() RETURNS trigger AS $$
+DECLARE
+    changed items;
+    event_kind TEXT;
+    event item_events;
+    sync_columns TEXT[] := ARRAY['created_at', 'updated_at', 'version', 'version_xid'];
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        changed := NEW;
+        event_kind := 'created';
+    ELSIF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        event_kind := 'deleted';
+    ELSIF to_jsonb(NEW) - sync_columns = to_jsonb(OLD) - sync_columns THEN
+        RETURN NULL;
+    ELSIF to_jsonb(NEW) - sync_columns - 'inCart' = to_jsonb(OLD) - sync_columns - 'inCart' THEN
+        changed := NEW;
+        event_kind := 'toggled';
+    ELSE
+        changed := NEW;
+        event_kind := 'updated';
+    END IF;
+
+    -- Items deleted along with their list have no household left to tell
+    INSERT INTO item_events (household_id, list_id, kind, item)
+    SELECT lists.household_id, changed.list, event_kind, jsonb_build_object(
+        'id', changed.id,
+        'name', changed.name,
+        'amount', changed.amount::text,
+        'amountUnit', changed."amountUnit",
+        'inCart', changed."inCart",
+        'list', changed.list,
+        'category', changed.category
+    )
+    FROM lists
+    WHERE lists.id = changed.list
+    RETURNING * INTO event;
+
+    IF event.id IS NOT NULL THEN
+        PERFORM pg_notify('item_events', jsonb_build_object(
+            'id', event.id,
+            'householdId', event.household_id,
+            'listId', event.list_id,
+            'kind', event.kind,
+            'item', event.item,
+            'createdAt', event.created_at
+        )::text);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
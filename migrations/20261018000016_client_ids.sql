@@ -0,0 +1,139 @@
+-- Client-generated IDs and offline replay (see POST /api/sync).
+--
+-- Lists and items get a UUID, which offline clients choose themselves when
+-- creating them, so they can refer to new rows before these have synced.
+ALTER TABLE lists ADD COLUMN IF NOT EXISTS uuid UUID NOT NULL DEFAULT gen_random_uuid();
+ALTER TABLE items ADD COLUMN IF NOT EXISTS uuid UUID NOT NULL DEFAULT gen_random_uuid();
+
+CREATE UNIQUE INDEX IF NOT EXISTS lists_uuid_key ON lists (uuid);
+CREATE UNIQUE INDEX IF NOT EXISTS items_uuid_key ON items (uuid);
+
+-- When each field was last changed, for resolving conflicting offline changes
+-- field by field. Replayed changes are stamped with the time the client made
+-- them, from the `lister.changed_at` setting; all others with the current time.
+ALTER TABLE lists ADD COLUMN IF NOT EXISTS field_updated_at JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE items ADD COLUMN IF NOT EXISTS field_updated_at JSONB NOT NULL DEFAULT '{}';
+
+CREATE OR REPLACE FUNCTION field_updated_at() RETURNS trigger AS $$
+DECLARE
+    changed_at TIMESTAMPTZ := COALESCE(
+        NULLIF(current_setting('lister.changed_at', true), '')::timestamptz,
+        now()
+    );
+    bookkeeping TEXT[] := ARRAY['id', 'uuid', 'created_at', 'updated_at', 'version',
+                                'version_xid', 'field_updated_at'];
+    old_row JSONB := CASE WHEN TG_OP = 'UPDATE' THEN to_jsonb(OLD) ELSE '{}' END;
+BEGIN
+    NEW.field_updated_at := NEW.field_updated_at || COALESCE((
+        SELECT jsonb_object_agg(field.key, changed_at)
+        FROM jsonb_each(to_jsonb(NEW) - bookkeeping) field
+        WHERE field.value IS DISTINCT FROM old_row -> field.key
+    ), '{}');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Runs before `sync_touch`, which then sees the stamped row
+CREATE TRIGGER field_updated_at BEFORE INSERT OR UPDATE ON lists
+    FOR EACH ROW EXECUTE FUNCTION field_updated_at();
+CREATE TRIGGER field_updated_at BEFORE INSERT OR UPDATE ON items
+    FOR EACH ROW EXECUTE FUNCTION field_updated_at();
+
+-- Replayed operations, so a batch sent again is not applied twice
+CREATE TABLE IF NOT EXISTS sync_operations (
+    household_id INTEGER NOT NULL REFERENCES households (id) ON DELETE CASCADE,
+    -- Chosen by the client
+    id UUID NOT NULL,
+    user_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (household_id, id)
+);
+
+CREATE INDEX IF NOT EXISTS sync_operations_applied_at_idx ON sync_operations (applied_at);
+
+-- Tombstones remember the UUID, so replayed changes to deleted rows are recognized
+ALTER TABLE tombstones ADD COLUMN IF NOT EXISTS entity_uuid UUID;
+
+CREATE INDEX IF NOT EXISTS tombstones_entity_uuid_idx ON tombstones (entity_uuid);
+
+CREATE OR REPLACE FUNCTION sync_tombstone() RETURNS trigger AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'items' THEN
+        INSERT INTO tombstones (household_id, entity, entity_id, entity_uuid, list_id)
+        SELECT lists.household_id, 'items', OLD.id, OLD.uuid, OLD.list
+        FROM lists
+        WHERE lists.id = OLD.list;
+    ELSE
+        INSERT INTO tombstones (household_id, entity, entity_id, entity_uuid, list_id)
+        SELECT households.id, TG_TABLE_NAME, OLD.id, (to_jsonb(OLD) ->> 'uuid')::uuid,
+               CASE WHEN TG_TABLE_NAME = 'lists' THEN OLD.id END
+        FROM households
+        WHERE households.id = OLD.household_id;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Events carry the UUID as well. Items deleted before this migration never
+-- had one a client could know, any will do.
+UPDATE item_events
+SET item = item || jsonb_build_object('uuid', COALESCE(
+    (SELECT items.uuid FROM items WHERE items.id = (item_events.item ->> 'id')::int),
+    gen_random_uuid()
+));
+
+CREATE OR REPLACE FUNCTION item_event() RETURNS trigger AS $$
+DECLARE
+    changed items;
+    event_kind TEXT;
+    event item_events;
+    sync_columns TEXT[] := ARRAY['created_at', 'updated_at', 'version', 'version_xid',
+                                 'field_updated_at'];
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        changed := NEW;
+        event_kind := 'created';
+    ELSIF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        event_kind := 'deleted';
+    ELSIF to_jsonb(NEW) - sync_columns = to_jsonb(OLD) - sync_columns THEN
+        RETURN NULL;
+    ELSIF to_jsonb(NEW) - sync_columns - 'inCart' = to_jsonb(OLD) - sync_columns - 'inCart' THEN
+        changed := NEW;
+        event_kind := 'toggled';
+    ELSE
+        changed := NEW;
+        event_kind := 'updated';
+    END IF;
+
+    -- Items deleted along with their list have no household left to tell
+    INSERT INTO item_events (household_id, list_id, kind, item)
+    SELECT lists.household_id, changed.list, event_kind, jsonb_build_object(
+        'id', changed.id,
+        'uuid', changed.uuid,
+        'name', changed.name,
+        'amount', changed.amount::text,
+        'amountUnit', changed."amountUnit",
+        'inCart', changed."inCart",
+        'list', changed.list,
+        'category', changed.category
+    )
+    FROM lists
+    WHERE lists.id = changed.list
+    RETURNING * INTO event;
+
+    IF event.id IS NOT NULL THEN
+        PERFORM pg_notify('item_events', jsonb_build_object(
+            'id', event.id,
+            'householdId', event.household_id,
+            'listId', event.list_id,
+            'kind', event.kind,
+            'item', event.item,
+            'createdAt', event.created_at
+        )::text);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
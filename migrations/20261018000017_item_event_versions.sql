@@ -0,0 +1,60 @@
+-- Events carry the item's version, the `ETag` clients send in `If-Match`.
+-- Events older than this migration get 0, which matches no ETag.
+UPDATE item_events
+SET item = item || jsonb_build_object('version', 0);
+
+CREATE OR REPLACE FUNCTION item_event() RETURNS trigger AS $$
+DECLARE
+    changed items;
+    event_kind TEXT;
+    event item_events;
+    sync_columns TEXT[] := ARRAY['created_at', 'updated_at', 'version', 'version_xid',
+                                 'field_updated_at'];
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        changed := NEW;
+        event_kind := 'created';
+    ELSIF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        event_kind := 'deleted';
+    ELSIF to_jsonb(NEW) - sync_columns = to_jsonb(OLD) - sync_columns THEN
+        RETURN NULL;
+    ELSIF to_jsonb(NEW) - sync_columns - 'inCart' = to_jsonb(OLD) - sync_columns - 'inCart' THEN
+        changed := NEW;
+        event_kind := 'toggled';
+    ELSE
+        changed := NEW;
+        event_kind := 'updated';
+    END IF;
+
+    -- Items deleted along with their list have no household left to tell
+    INSERT INTO item_events (household_id, list_id, kind, item)
+    SELECT lists.household_id, changed.list, event_kind, jsonb_build_object(
+        'id', changed.id,
+        'uuid', changed.uuid,
+        'name', changed.name,
+        'amount', changed.amount::text,
+        'amountUnit', changed."amountUnit",
+        'inCart', changed."inCart",
+        'list', changed.list,
+        'category', changed.category,
+        'version', changed.version
+    )
+    FROM lists
+    WHERE lists.id = changed.list
+    RETURNING * INTO event;
+
+    IF event.id IS NOT NULL THEN
+        PERFORM pg_notify('item_events', jsonb_build_object(
+            'id', event.id,
+            'householdId', event.household_id,
+            'listId', event.list_id,
+            'kind', event.kind,
+            'item', event.item,
+            'createdAt', event.created_at
+        )::text);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,86 @@
+-- Event IDs are taken in insert order, but transactions commit in any order,
+-- so an event can appear after ones with higher IDs. Events remember their
+-- transaction and the oldest transaction still running when they were
+-- recorded: a stream that has seen an event has seen everything of the
+-- transactions before that, and catches up on the later ones (see events.rs).
+ALTER TABLE item_events
+    ADD COLUMN IF NOT EXISTS xid xid8 NOT NULL DEFAULT pg_current_xact_id(),
+    ADD COLUMN IF NOT EXISTS snapshot_xmin xid8 NOT NULL
+        DEFAULT pg_snapshot_xmin(pg_current_snapshot());
+
+CREATE INDEX IF NOT EXISTS item_events_xid_idx ON item_events (household_id, xid);
+
+CREATE OR REPLACE FUNCTION item_event() RETURNS trigger AS $$
+DECLARE
+    changed items;
+    event_kind TEXT;
+    event item_events;
+    sync_columns TEXT[] := ARRAY['created_at', 'updated_at', 'version', 'version_xid',
+                                 'field_updated_at'];
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        changed := NEW;
+        event_kind := 'created';
+    ELSIF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        event_kind := 'deleted';
+    ELSIF to_jsonb(NEW) - sync_columns = to_jsonb(OLD) - sync_columns THEN
+        RETURN NULL;
+    ELSIF to_jsonb(NEW) - sync_columns - 'inCart' = to_jsonb(OLD) - sync_columns - 'inCart' THEN
+        changed := NEW;
+        event_kind := 'toggled';
+    ELSE
+        changed := NEW;
+        event_kind := 'updated';
+    END IF;
+
+    -- Items deleted along with their list have no household left to tell
+    INSERT INTO item_events (household_id, list_id, kind, item)
+    SELECT lists.household_id, changed.list, event_kind, jsonb_build_object(
+        'id', changed.id,
+        'uuid', changed.uuid,
+        'name', changed.name,
+        'amount', changed.amount::text,
+        'amountUnit', changed."amountUnit",
+        'inCart', changed."inCart",
+        'list', changed.list,
+        'category', changed.category,
+        'version', changed.version
+    )
+    FROM lists
+    WHERE lists.id = changed.list
+    RETURNING * INTO event;
+
+    IF event.id IS NOT NULL THEN
+        PERFORM pg_notify('item_events', jsonb_build_object(
+            'id', event.id,
+            'householdId', event.household_id,
+            'listId', event.list_id,
+            'kind', event.kind,
+            'item', event.item,
+            'createdAt', event.created_at,
+            'xid', event.xid::text::bigint
+        )::text);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Streams remember which lists their user may see; changes to who may see a
+-- list are announced on the `list_access` channel so they look again.
+CREATE OR REPLACE FUNCTION list_access_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('list_access', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER list_access_changed AFTER UPDATE OF owner_id, private, household_id ON lists
+    FOR EACH STATEMENT EXECUTE FUNCTION list_access_changed();
+
+CREATE TRIGGER list_access_changed AFTER INSERT OR UPDATE OR DELETE ON list_permissions
+    FOR EACH STATEMENT EXECUTE FUNCTION list_access_changed();
+
+CREATE TRIGGER list_access_changed AFTER INSERT OR UPDATE OR DELETE ON group_members
+    FOR EACH STATEMENT EXECUTE FUNCTION list_access_changed();
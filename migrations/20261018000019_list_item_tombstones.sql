@@ -0,0 +1,18 @@
+-- Items deleted along with their list leave tombstones too, so replayed
+-- changes to them are recognized as changes to deleted rows. The items'
+-- own trigger cannot do it, as their list is already gone when it runs.
+-- Lists deleted along with their household leave nothing behind.
+CREATE OR REPLACE FUNCTION sync_tombstone_list_items() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO tombstones (household_id, entity, entity_id, entity_uuid, list_id)
+    SELECT households.id, 'items', items.id, items.uuid, items.list
+    FROM items
+    JOIN households ON households.id = OLD.household_id
+    WHERE items.list = OLD.id;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sync_tombstone_list_items BEFORE DELETE ON lists
+    FOR EACH ROW EXECUTE FUNCTION sync_tombstone_list_items();
@@ -0,0 +1,199 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    response::Redirect,
+    Extension, Form,
+};
+use maud::{html, Markup};
+use serde::Deserialize;
+
+use super::{households::ConsoleHousehold, layout, redirect_with, CsrfToken, Flash};
+use crate::{
+    error::Result, models::UpdateCategoryRequest, services, state::AppState, validation::Validate,
+};
+
+#[derive(Default, Deserialize)]
+pub struct CategoryFilter {
+    #[serde(default)]
+    q: String,
+    #[serde(flatten)]
+    flash: Flash,
+}
+
+#[derive(Deserialize)]
+pub struct RenameForm {
+    name: String,
+    back: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeForm {
+    target: i32,
+    back: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteForm {
+    back: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CategoryUsage {
+    id: i32,
+    name: String,
+    items: i64,
+    names: i64,
+}
+
+/// GET /admin/categories - Browse and filter categories with usage counts
+pub async fn index(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Extension(csrf): Extension<CsrfToken>,
+    OriginalUri(uri): OriginalUri,
+    Query(filter): Query<CategoryFilter>,
+) -> Result<Markup> {
+    let categories = sqlx::query_as::<_, CategoryUsage>(
+        r#"
+        SELECT c.id, c.name,
+               (SELECT COUNT(*)
+                FROM items i
+                JOIN lists l ON l.id = i.list
+                WHERE i.category = c.name AND l.household_id = c.household_id) as items,
+               (SELECT COUNT(*)
+                FROM names n
+                WHERE n.category = c.name AND n.household_id = c.household_id) as names
+        FROM categories c
+        WHERE c.household_id = $1
+        ORDER BY c.name ASC
+        "#,
+    )
+    .bind(household.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let needle = filter.q.trim().to_lowercase();
+    let back = uri.to_string();
+
+    Ok(layout(
+        &format!("Categories · {}", household.name),
+        Some(&csrf),
+        html! {
+            (filter.flash.render())
+            form method="get" action="/admin/categories" {
+                input type="search" name="q" value=(filter.q) placeholder="Filter by name";
+                " "
+                button type="submit" { "Filter" }
+            }
+            table {
+                thead {
+                    tr { th { "Name" } th { "Items" } th { "Names" } th { "Merge into" } th {} }
+                }
+                tbody {
+                    @for category in categories.iter().filter(|c| c.name.to_lowercase().contains(&needle)) {
+                        tr {
+                            td {
+                                form.inline method="post" action={ "/admin/categories/" (category.id) } {
+                                    (csrf)
+                                    input type="hidden" name="back" value=(back);
+                                    input name="name" value=(category.name) required;
+                                    " "
+                                    button type="submit" { "Rename" }
+                                }
+                            }
+                            td { (category.items) }
+                            td { a href=(names_link(&category.name)) { (category.names) } }
+                            td {
+                                form.inline method="post" action={ "/admin/categories/" (category.id) "/merge" } {
+                                    (csrf)
+                                    input type="hidden" name="back" value=(back);
+                                    select name="target" {
+                                        @for target in categories.iter().filter(|t| t.id != category.id) {
+                                            option value=(target.id) { (target.name) }
+                                        }
+                                    }
+                                    " "
+                                    button type="submit" { "Merge" }
+                                }
+                            }
+                            td {
+                                form.inline method="post" action={ "/admin/categories/" (category.id) "/delete" } {
+                                    (csrf)
+                                    input type="hidden" name="back" value=(back);
+                                    button type="submit" { "Delete" }
+                                }
+                            }
+                        }
+                    }
+                    @if categories.is_empty() {
+                        tr { td.muted colspan="5" { "No categories yet." } }
+                    }
+                }
+            }
+        },
+    ))
+}
+
+fn names_link(category: &str) -> String {
+    let query = serde_urlencoded::to_string([("category", category)]).unwrap_or_default();
+    format!("/admin/names?{query}")
+}
+
+/// POST /admin/categories/:id - Rename a category
+pub async fn rename(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Path(id): Path<i32>,
+    Form(form): Form<RenameForm>,
+) -> Redirect {
+    let outcome = async {
+        let mut request = UpdateCategoryRequest { name: form.name };
+        request.validated()?;
+
+        let mut tx = state.pool.begin().await?;
+        let renamed = services::categories::rename(&mut tx, household.id, id, &request.name).await?;
+        tx.commit().await?;
+
+        Ok(format!("Renamed to \"{}\"", renamed.name))
+    }
+    .await;
+
+    redirect_with(form.back.as_deref(), "/admin/categories", outcome)
+}
+
+/// POST /admin/categories/:id/merge - Merge a category into another one
+pub async fn merge(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Path(id): Path<i32>,
+    Form(form): Form<MergeForm>,
+) -> Redirect {
+    let outcome = async {
+        let mut tx = state.pool.begin().await?;
+        let merged = services::categories::merge(&mut tx, household.id, id, form.target).await?;
+        tx.commit().await?;
+
+        Ok(format!("Merged into \"{}\"", merged.name))
+    }
+    .await;
+
+    redirect_with(form.back.as_deref(), "/admin/categories", outcome)
+}
+
+/// POST /admin/categories/:id/delete - Delete a category
+pub async fn delete(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Path(id): Path<i32>,
+    Form(form): Form<DeleteForm>,
+) -> Redirect {
+    let outcome = async {
+        let mut tx = state.pool.begin().await?;
+        services::categories::delete(&mut tx, household.id, id).await?;
+        tx.commit().await?;
+
+        Ok("Category deleted".to_string())
+    }
+    .await;
+
+    redirect_with(form.back.as_deref(), "/admin/categories", outcome)
+}
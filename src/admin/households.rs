@@ -0,0 +1,121 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::{header, request::Parts},
+    response::{IntoResponse, Redirect, Response},
+    Extension,
+};
+use maud::{html, Markup};
+
+use super::{layout, CsrfToken};
+use crate::{
+    auth::{self, AuthError},
+    error::{AppError, Result},
+    state::AppState,
+};
+
+const HOUSEHOLD_COOKIE: &str = "lister_admin_household";
+
+/// The household the console works in, chosen on `/admin/households`.
+/// Defaults to the oldest household.
+pub struct ConsoleHousehold {
+    pub id: i32,
+    pub name: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ConsoleHousehold {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        let selected =
+            auth::cookie(&parts.headers, HOUSEHOLD_COOKIE).and_then(|id| id.parse::<i32>().ok());
+
+        let (id, name) = sqlx::query_as::<_, (i32, String)>(
+            r#"
+            SELECT id, name
+            FROM households
+            ORDER BY id IS NOT DISTINCT FROM $1 DESC, id ASC
+            LIMIT 1
+            "#,
+        )
+        .bind(selected)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or(AuthError::NoHousehold)?;
+
+        Ok(ConsoleHousehold { id, name })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct HouseholdUsage {
+    id: i32,
+    name: String,
+    members: i64,
+    lists: i64,
+}
+
+/// GET /admin/households - Pick the household to work in
+pub async fn index(
+    State(state): State<AppState>,
+    current: ConsoleHousehold,
+    Extension(csrf): Extension<CsrfToken>,
+) -> Result<Markup> {
+    let households = sqlx::query_as::<_, HouseholdUsage>(
+        r#"
+        SELECT h.id, h.name,
+               (SELECT COUNT(*) FROM household_members WHERE household_id = h.id) as members,
+               (SELECT COUNT(*) FROM lists WHERE household_id = h.id) as lists
+        FROM households h
+        ORDER BY h.name ASC
+        "#,
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(layout(
+        "Households",
+        Some(&csrf),
+        html! {
+            table {
+                thead {
+                    tr { th { "Household" } th { "Members" } th { "Lists" } th {} }
+                }
+                tbody {
+                    @for household in &households {
+                        tr {
+                            td { (household.name) }
+                            td { (household.members) }
+                            td { (household.lists) }
+                            td {
+                                @if household.id == current.id {
+                                    span.muted { "current" }
+                                } @else {
+                                    form.inline method="post" action={ "/admin/households/" (household.id) "/select" } {
+                                        (csrf)
+                                        button type="submit" { "Switch" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// POST /admin/households/:id/select - Work in another household
+pub async fn select(State(state): State<AppState>, Path(id): Path<i32>) -> Response {
+    let mut cookie = format!("{HOUSEHOLD_COOKIE}={id}; Path=/admin; HttpOnly; SameSite=Strict");
+    if state.config.cookie_secure {
+        cookie.push_str("; Secure");
+    }
+
+    (
+        [(header::SET_COOKIE, cookie)],
+        Redirect::to("/admin/names"),
+    )
+        .into_response()
+}
@@ -0,0 +1,297 @@
+//! Server-rendered admin console for cleaning up names and categories.
+//!
+//! Plain HTML forms and redirects, no JavaScript required. The console works in
+//! one household at a time, see [`households::ConsoleHousehold`].
+//!
+//! Admins sign in like they do in the app, with a browser session; users of
+//! passkeys or single sign-on sign in to the app first. Changes are audited as
+//! theirs.
+
+use std::net::SocketAddr;
+
+use axum::{
+    body::{self, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{request::Parts, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use maud::{html, Markup, Render, DOCTYPE};
+use serde::Deserialize;
+
+use crate::{
+    audit::{self, Context},
+    auth::{self, AuthError, Principal, CSRF_COOKIE, CSRF_HEADER},
+    error::AppError,
+    extract::ValidJson,
+    handlers,
+    i18n::Lang,
+    models::LoginRequest,
+    state::AppState,
+    tls::ClientCertificate,
+    validation::Validate,
+};
+
+mod categories;
+mod households;
+mod names;
+
+/// Form field carrying the CSRF token, see [`CsrfToken`]
+const CSRF_FIELD: &str = "csrf_token";
+/// Largest form the console reads to find the CSRF token
+const MAX_FORM_SIZE: usize = 64 * 1024;
+
+pub fn router(state: AppState) -> Router {
+    let protected = Router::new()
+        .route("/", get(|| async { Redirect::to("/admin/names") }))
+        .route("/names", get(names::index))
+        .route("/names/:id", post(names::update))
+        .route("/names/:id/merge", post(names::merge))
+        .route("/names/:id/delete", post(names::delete))
+        .route("/categories", get(categories::index))
+        .route("/categories/:id", post(categories::rename))
+        .route("/categories/:id/merge", post(categories::merge))
+        .route("/categories/:id/delete", post(categories::delete))
+        .route("/households", get(households::index))
+        .route("/households/:id/select", post(households::select))
+        .route("/logout", post(logout))
+        .layer(middleware::from_fn_with_state(state.clone(), require_admin));
+
+    Router::new()
+        .merge(protected)
+        .route("/login", get(login_form).post(login))
+        .with_state(state)
+}
+
+/// The session's CSRF token. HTML forms cannot send the `X-CSRF-Token`
+/// header, so the console's forms carry it in a hidden field instead.
+#[derive(Clone, Default)]
+pub struct CsrfToken(String);
+
+impl Render for CsrfToken {
+    fn render(&self) -> Markup {
+        html! { input type="hidden" name=(CSRF_FIELD) value=(self.0); }
+    }
+}
+
+/// Authenticates like the API and lets admins through. Without credentials,
+/// redirects to the login form.
+async fn require_admin(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let Some(body) = csrf_from_form(&mut parts, body).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+
+    let peer = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let certificate = parts.extensions.get::<ClientCertificate>();
+    let csrf = CsrfToken(
+        auth::cookie(&parts.headers, CSRF_COOKIE)
+            .unwrap_or_default()
+            .to_string(),
+    );
+
+    let principal =
+        match auth::authenticate(&state, &parts.headers, &parts.method, peer, certificate).await {
+            Ok(principal) if principal.is_admin() => principal,
+            Ok(_) => return denied(AuthError::Forbidden.into(), Some(&csrf)),
+            Err(AppError::Auth(AuthError::MissingToken | AuthError::InvalidToken)) => {
+                return Redirect::to("/admin/login").into_response()
+            }
+            Err(error) => return denied(error, None),
+        };
+
+    let context = Context::current().principal(&principal);
+    parts.extensions.insert(principal);
+    parts.extensions.insert(csrf);
+    audit::scope(context, next.run(Request::from_parts(parts, body))).await
+}
+
+/// Copies the CSRF token of a submitted form into the `X-CSRF-Token` header.
+/// `None` if the form is too large.
+async fn csrf_from_form(parts: &mut Parts, body: Body) -> Option<Body> {
+    if parts.method.is_safe() {
+        return Some(body);
+    }
+
+    let bytes = body::to_bytes(body, MAX_FORM_SIZE).await.ok()?;
+    let token = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|(key, _)| key == CSRF_FIELD)
+        .and_then(|(_, value)| HeaderValue::try_from(value).ok());
+    if let Some(token) = token {
+        parts.headers.insert(CSRF_HEADER, token);
+    }
+
+    Some(Body::from(bytes))
+}
+
+/// Error page for requests the console turns away. Signed-in users get to log out.
+fn denied(error: AppError, csrf: Option<&CsrfToken>) -> Response {
+    if let AppError::Database(ref e) = error {
+        tracing::error!("Database error: {:?}", e);
+    }
+    let message = error.message().render(Lang::En);
+    let page = layout("Access denied", csrf, html! { p.error { (message) } });
+
+    (error.status(), page).into_response()
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    username: String,
+    password: String,
+}
+
+async fn login_form() -> Markup {
+    login_page(None)
+}
+
+/// Signs in with a browser session, like `POST /api/auth/login`
+async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let mut request = LoginRequest {
+        username: form.username,
+        password: form.password,
+        passkey: None,
+    };
+    let result = match request.validated() {
+        Ok(()) => handlers::auth::login(State(state), headers, ValidJson(request)).await,
+        Err(error) => Err(error),
+    };
+
+    match result {
+        Ok((cookies, _)) => (cookies, Redirect::to("/admin/names")).into_response(),
+        Err(error) => login_page(Some(&error.message().render(Lang::En))).into_response(),
+    }
+}
+
+/// Ends the session, like `POST /api/auth/logout`
+async fn logout(State(state): State<AppState>, principal: Principal) -> Response {
+    match handlers::auth::logout(State(state), principal).await {
+        Ok((_, cookies)) => (cookies, Redirect::to("/admin/login")).into_response(),
+        Err(error) => denied(error, None),
+    }
+}
+
+fn login_page(error: Option<&str>) -> Markup {
+    layout(
+        "Login",
+        None,
+        html! {
+            @if let Some(error) = error {
+                p.error { (error) }
+            }
+            form method="post" action="/admin/login" {
+                label { "Username " input name="username" autocomplete="username" required autofocus; }
+                " "
+                label { "Password " input type="password" name="password" autocomplete="current-password" required; }
+                " "
+                button type="submit" { "Log in" }
+            }
+            p.muted { "Using a passkey or single sign-on? Sign in to the app, then come back." }
+        },
+    )
+}
+
+/// Status message passed along a redirect after a form submission
+#[derive(Default, Deserialize)]
+struct Flash {
+    notice: Option<String>,
+    error: Option<String>,
+}
+
+impl Flash {
+    fn render(&self) -> Markup {
+        html! {
+            @if let Some(notice) = &self.notice {
+                p.notice { (notice) }
+            }
+            @if let Some(error) = &self.error {
+                p.error { (error) }
+            }
+        }
+    }
+}
+
+/// Redirect back to `back` (if it points into the console) with a status message
+fn redirect_with(
+    back: Option<&str>,
+    fallback: &str,
+    outcome: Result<String, AppError>,
+) -> Redirect {
+    let target = back
+        .filter(|b| b.starts_with("/admin/") && !b.starts_with("//"))
+        .unwrap_or(fallback);
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut params: Vec<(String, String)> =
+        serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(key, _)| key != "notice" && key != "error")
+            .collect();
+    match outcome {
+        Ok(notice) => params.push(("notice".to_string(), notice)),
+        Err(e) => params.push(("error".to_string(), error_message(e))),
+    }
+
+    let query = serde_urlencoded::to_string(&params).unwrap_or_default();
+    Redirect::to(&format!("{path}?{query}"))
+}
+
+fn error_message(error: AppError) -> String {
+    if let AppError::Database(ref e) = error {
+        tracing::error!("Database error: {:?}", e);
+    }
+    error.detail()
+}
+
+/// Page with the navigation for signed-in admins, if `csrf` is given
+fn layout(title: &str, csrf: Option<&CsrfToken>, content: Markup) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { (title) " · Lister Admin" }
+                style { (STYLE) }
+            }
+            body {
+                @if let Some(csrf) = csrf {
+                    nav {
+                        a href="/admin/names" { "Names" }
+                        a href="/admin/categories" { "Categories" }
+                        a href="/admin/households" { "Households" }
+                        form.inline method="post" action="/admin/logout" {
+                            (csrf)
+                            button type="submit" { "Log out" }
+                        }
+                    }
+                }
+                h1 { (title) }
+                (content)
+            }
+        }
+    }
+}
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 1rem 2rem; }
+nav { display: flex; gap: 1rem; align-items: center; margin-bottom: 1rem; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ddd; padding: .3rem .5rem; text-align: left; vertical-align: top; }
+form.inline { display: inline; }
+.notice { color: #155724; background: #d4edda; padding: .5rem; }
+.error { color: #721c24; background: #f8d7da; padding: .5rem; }
+.muted { color: #777; }
+"#;
@@ -0,0 +1,274 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    response::Redirect,
+    Extension, Form,
+};
+use maud::{html, Markup};
+use serde::Deserialize;
+
+use super::{households::ConsoleHousehold, layout, redirect_with, CsrfToken, Flash};
+use crate::{
+    error::{AppError, Result},
+    i18n::Message,
+    models::{Name, UpdateNameRequest},
+    services,
+    state::AppState,
+    validation::Validate,
+};
+
+const PAGE_SIZE: i64 = 100;
+
+#[derive(Default, Deserialize)]
+pub struct NameFilter {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    uncategorized: Option<String>,
+    #[serde(default)]
+    page: Option<i64>,
+    #[serde(flatten)]
+    flash: Flash,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateForm {
+    name: String,
+    #[serde(default)]
+    category: String,
+    back: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeForm {
+    target: String,
+    back: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteForm {
+    back: Option<String>,
+}
+
+/// GET /admin/names - Browse and filter names
+pub async fn index(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Extension(csrf): Extension<CsrfToken>,
+    OriginalUri(uri): OriginalUri,
+    Query(filter): Query<NameFilter>,
+) -> Result<Markup> {
+    let page = filter.page.unwrap_or(1).max(1);
+    let uncategorized = filter.uncategorized.is_some();
+
+    let names = sqlx::query_as::<_, Name>(
+        r#"
+        SELECT id, name, count, category, version
+        FROM names
+        WHERE household_id = $6
+          AND ($1 = '' OR name ILIKE '%' || $1 || '%')
+          AND ($2 = '' OR category = $2)
+          AND (NOT $3 OR category IS NULL)
+        ORDER BY count DESC, name ASC
+        LIMIT $4 OFFSET $5
+        "#,
+    )
+    .bind(filter.q.trim())
+    .bind(&filter.category)
+    .bind(uncategorized)
+    .bind(PAGE_SIZE + 1)
+    // Far-off pages are just empty
+    .bind((page - 1).saturating_mul(PAGE_SIZE))
+    .bind(household.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let categories = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT name
+        FROM categories
+        WHERE household_id = $1
+        ORDER BY name ASC
+        "#,
+    )
+    .bind(household.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let has_next = names.len() as i64 > PAGE_SIZE;
+    let back = uri.to_string();
+    let page_link = |page: i64| {
+        let query = serde_urlencoded::to_string([
+            ("q", filter.q.as_str()),
+            ("category", filter.category.as_str()),
+            ("uncategorized", if uncategorized { "1" } else { "" }),
+            ("page", &page.to_string()),
+        ])
+        .unwrap_or_default();
+        format!("/admin/names?{query}")
+    };
+
+    Ok(layout(
+        &format!("Names · {}", household.name),
+        Some(&csrf),
+        html! {
+            (filter.flash.render())
+            form method="get" action="/admin/names" {
+                input type="search" name="q" value=(filter.q) placeholder="Filter by name";
+                " "
+                select name="category" {
+                    option value="" { "All categories" }
+                    @for category in &categories {
+                        option value=(category) selected[*category == filter.category] { (category) }
+                    }
+                }
+                " "
+                label { input type="checkbox" name="uncategorized" value="1" checked[uncategorized]; " without category" }
+                " "
+                button type="submit" { "Filter" }
+            }
+            datalist #categories {
+                @for category in &categories {
+                    option value=(category) {}
+                }
+            }
+            table {
+                thead {
+                    tr { th { "Name / category" } th { "Used" } th { "Merge into" } th {} }
+                }
+                tbody {
+                    @for name in names.iter().take(PAGE_SIZE as usize) {
+                        tr {
+                            td {
+                                form.inline method="post" action={ "/admin/names/" (name.id) } {
+                                    (csrf)
+                                    input type="hidden" name="back" value=(back);
+                                    input name="name" value=(name.name) required;
+                                    " "
+                                    input name="category" list="categories" value=[name.category.as_deref()] placeholder="no category";
+                                    " "
+                                    button type="submit" { "Save" }
+                                }
+                            }
+                            td { (name.count.unwrap_or(0)) }
+                            td {
+                                form.inline method="post" action={ "/admin/names/" (name.id) "/merge" } {
+                                    (csrf)
+                                    input type="hidden" name="back" value=(back);
+                                    input name="target" placeholder="existing name" required;
+                                    " "
+                                    button type="submit" { "Merge" }
+                                }
+                            }
+                            td {
+                                form.inline method="post" action={ "/admin/names/" (name.id) "/delete" } {
+                                    (csrf)
+                                    input type="hidden" name="back" value=(back);
+                                    button type="submit" { "Delete" }
+                                }
+                            }
+                        }
+                    }
+                    @if names.is_empty() {
+                        tr { td.muted colspan="4" { "No names match the filter." } }
+                    }
+                }
+            }
+            p {
+                @if page > 1 {
+                    a href=(page_link(page - 1)) { "« Previous" }
+                    " "
+                }
+                @if has_next {
+                    a href=(page_link(page + 1)) { "Next »" }
+                }
+            }
+        },
+    ))
+}
+
+/// POST /admin/names/:id - Rename and re-categorize a name
+pub async fn update(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Path(id): Path<i32>,
+    Form(form): Form<UpdateForm>,
+) -> Redirect {
+    let outcome = async {
+        // Same rules as the JSON API; an empty category clears it
+        let mut request = UpdateNameRequest {
+            name: Some(form.name),
+            category: Some(Some(form.category)),
+        };
+        request.validated()?;
+
+        let mut tx = state.pool.begin().await?;
+        let limits = services::quotas::limits(&mut tx, &state.config.quotas, household.id).await?;
+        let updated = services::names::update(
+            &mut tx,
+            &limits,
+            household.id,
+            id,
+            request.name.as_deref(),
+            request.category.as_ref().map(Option::as_deref),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(format!("Saved \"{}\"", updated.name))
+    }
+    .await;
+
+    redirect_with(form.back.as_deref(), "/admin/names", outcome)
+}
+
+/// POST /admin/names/:id/merge - Merge a name into another one
+pub async fn merge(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Path(id): Path<i32>,
+    Form(form): Form<MergeForm>,
+) -> Redirect {
+    let outcome = async {
+        let mut tx = state.pool.begin().await?;
+
+        let target_id = sqlx::query_scalar::<_, i32>(
+            r#"
+            SELECT id FROM names WHERE name = $1 AND household_id = $2
+            "#,
+        )
+        .bind(form.target.trim())
+        .bind(household.id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::BadRequest(Message::new("error.unknown_name").arg("name", form.target.trim()))
+        })?;
+
+        let merged = services::names::merge(&mut tx, household.id, id, target_id).await?;
+        tx.commit().await?;
+
+        Ok(format!("Merged into \"{}\"", merged.name))
+    }
+    .await;
+
+    redirect_with(form.back.as_deref(), "/admin/names", outcome)
+}
+
+/// POST /admin/names/:id/delete - Delete a name
+pub async fn delete(
+    State(state): State<AppState>,
+    household: ConsoleHousehold,
+    Path(id): Path<i32>,
+    Form(form): Form<DeleteForm>,
+) -> Redirect {
+    let outcome = async {
+        let mut conn = state.pool.acquire().await?;
+        services::names::delete(&mut conn, household.id, id).await?;
+        Ok("Name deleted".to_string())
+    }
+    .await;
+
+    redirect_with(form.back.as_deref(), "/admin/names", outcome)
+}
@@ -0,0 +1,112 @@
+//! Audit log of all changes to the data.
+//!
+//! Triggers on the audited tables (see the `audit_log` migration) record every
+//! created, updated and deleted row with its state before and after, so that
+//! cascades like renaming a category on all its items are logged row by row.
+//! Who made a change is read from the `lister.audit_context` setting of the
+//! connection. The pool sets it from the [`Context`] of the task that acquires
+//! the connection, see [`apply`].
+
+use std::future::Future;
+
+use axum::{extract::Request, middleware::Next, response::Response};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+use crate::{auth::Principal, problem::REQUEST_ID_HEADER};
+
+tokio::task_local! {
+    static CONTEXT: Context;
+}
+
+/// Who is changing data, and in which request
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Context {
+    /// `user`, `token`, `system`, `job` or `cli`; `None` for anonymous requests
+    pub actor: Option<&'static str>,
+    pub actor_name: Option<String>,
+    pub user_id: Option<i32>,
+    pub token_id: Option<i32>,
+    pub request_id: Option<String>,
+}
+
+impl Context {
+    /// The context of the current task, empty outside of [`scope`]
+    pub fn current() -> Context {
+        CONTEXT.try_with(Context::clone).unwrap_or_default()
+    }
+
+    pub fn principal(self, principal: &Principal) -> Context {
+        match principal {
+            Principal::System => Context {
+                actor: Some("system"),
+                ..self
+            },
+            Principal::User(user) => Context {
+                actor: Some("user"),
+                actor_name: Some(user.username.clone()),
+                user_id: Some(user.id),
+                ..self
+            },
+            Principal::Token(token) => Context {
+                actor: Some("token"),
+                actor_name: Some(token.name.clone()),
+                token_id: Some(token.id),
+                ..self
+            },
+        }
+    }
+
+    pub fn job(name: &str) -> Context {
+        Context {
+            actor: Some("job"),
+            actor_name: Some(name.to_string()),
+            ..Context::default()
+        }
+    }
+
+    pub fn cli() -> Context {
+        Context {
+            actor: Some("cli"),
+            ..Context::default()
+        }
+    }
+}
+
+/// Run `future` with changes attributed to `context`
+pub async fn scope<F: Future>(context: Context, future: F) -> F::Output {
+    CONTEXT.scope(context, future).await
+}
+
+/// Attributes changes to the request ID. [`crate::auth::auth_middleware`]
+/// adds the principal once it is known.
+pub async fn audit_middleware(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string);
+    let context = Context {
+        request_id,
+        ..Context::default()
+    };
+
+    scope(context, next.run(request)).await
+}
+
+/// Set the audit context of a connection to that of the current task. Runs
+/// whenever the pool connects or hands out a connection, so the setting never
+/// leaks from one task to the next.
+pub async fn apply(conn: &mut PgConnection) -> sqlx::Result<()> {
+    let context = CONTEXT
+        .try_with(|context| serde_json::to_string(context).unwrap_or_default())
+        .unwrap_or_default();
+
+    sqlx::query("SELECT set_config('lister.audit_context', $1, false)")
+        .bind(context)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
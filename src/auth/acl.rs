@@ -0,0 +1,101 @@
+//! Roles on single lists.
+//!
+//! Lists are shared with the whole household unless they are private. Owners
+//! and users or groups granted a role keep access either way; the rules live in
+//! the `list_role` database function. API tokens are limited by their scopes
+//! instead, and the shared `AUTH_TOKEN` may access every list.
+
+use std::fmt;
+
+use sqlx::PgExecutor;
+
+use super::{Access, AuthError, Principal};
+use crate::error::{AppError, Result};
+
+/// A user's role on a list, ordered from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ListRole {
+    /// Read the list and its items
+    Viewer,
+    /// Also add, change and check off items
+    Editor,
+    /// Also rename, delete and share the list
+    Owner,
+}
+
+/// Roles that can be granted; there is only one owner
+pub const GRANTABLE_ROLES: &[&str] = &["viewer", "editor"];
+
+impl ListRole {
+    pub fn parse(s: &str) -> Option<ListRole> {
+        match s {
+            "viewer" => Some(ListRole::Viewer),
+            "editor" => Some(ListRole::Editor),
+            "owner" => Some(ListRole::Owner),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ListRole::Viewer => "viewer",
+            ListRole::Editor => "editor",
+            ListRole::Owner => "owner",
+        }
+    }
+
+    /// What an API token needs in place of the role
+    pub fn access(self) -> Access {
+        match self {
+            ListRole::Viewer => Access::Read,
+            ListRole::Editor | ListRole::Owner => Access::Write,
+        }
+    }
+}
+
+impl fmt::Display for ListRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Principal {
+    /// Check that the principal has at least `role` on a list of the household.
+    /// Lists a user cannot see are reported as not found. Takes the pool, or
+    /// the transaction that goes on to change the list.
+    pub async fn require_list_role<'e>(
+        &self,
+        executor: impl PgExecutor<'e>,
+        household_id: i32,
+        list_id: i32,
+        role: ListRole,
+    ) -> Result<()> {
+        let user = match self {
+            Principal::System => return Ok(()),
+            Principal::Token(_) => return self.require_list(list_id, role.access()),
+            Principal::User(user) => user,
+        };
+
+        let current = sqlx::query_scalar::<_, Option<String>>(
+            r#"
+            SELECT list_role(id, $2)::text
+            FROM lists
+            WHERE id = $1 AND household_id = $3
+            "#,
+        )
+        .bind(list_id)
+        .bind(user.id)
+        .bind(household_id)
+        .fetch_optional(executor)
+        .await?
+        .flatten()
+        .and_then(|role| ListRole::parse(&role))
+        .ok_or(AppError::NotFound)?;
+
+        if current >= role {
+            Ok(())
+        } else {
+            Err(AuthError::InsufficientRole.into())
+        }
+    }
+}
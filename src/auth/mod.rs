@@ -0,0 +1,401 @@
+//! Authentication of API requests.
+//!
+//! Callers authenticate with the shared `AUTH_TOKEN` (the bootstrap admin), a
+//! scoped API token, a user's access token, a JWT from the OpenID Connect
+//! provider, a client certificate bound to an API token ([`crate::tls`]),
+//! headers set by a trusted reverse proxy ([`proxy`]), or a browser
+//! session cookie. Sessions and access tokens are
+//! issued for a password, a passkey ([`webauthn`]) or single sign-on.
+//! [`auth_middleware`] resolves them into a [`Principal`] in the request
+//! extensions, which handlers take as an extractor (or [`CurrentUser`], if they
+//! need a user account) and ask for the access they need.
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    audit,
+    error::{AppError, Result},
+    i18n::{Lang, Message},
+    problem::Problem,
+    state::AppState,
+    tls::ClientCertificate,
+};
+
+pub mod acl;
+pub mod oidc;
+pub mod password;
+pub mod proxy;
+pub mod session;
+pub mod tenant;
+pub mod tokens;
+pub mod webauthn;
+
+pub use acl::ListRole;
+pub use tenant::Tenant;
+pub use tokens::{Access, Scope, ScopedToken};
+
+pub const SESSION_COOKIE: &str = "lister_session";
+/// Readable by scripts, so browser apps can echo it in [`CSRF_HEADER`]
+pub const CSRF_COOKIE: &str = "lister_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// A signed-in user
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub id: i32,
+    pub username: String,
+    pub is_admin: bool,
+    pub language: Option<Lang>,
+    /// The session the request was authenticated with
+    pub session_id: Option<i64>,
+}
+
+/// Whoever sent the request
+#[derive(Debug, Clone)]
+pub enum Principal {
+    /// The shared `AUTH_TOKEN`, or anyone while authentication is not set up
+    System,
+    User(CurrentUser),
+    /// An API token, limited to its scopes
+    Token(ScopedToken),
+}
+
+impl Principal {
+    pub fn is_admin(&self) -> bool {
+        match self {
+            Principal::System => true,
+            Principal::User(user) => user.is_admin,
+            Principal::Token(token) => token.has(Scope::Admin),
+        }
+    }
+
+    pub fn user(&self) -> Option<&CurrentUser> {
+        match self {
+            Principal::User(user) => Some(user),
+            Principal::System | Principal::Token(_) => None,
+        }
+    }
+
+    /// Whether the principal may read or change all lists and the shared
+    /// names and categories
+    pub fn can(&self, access: Access) -> bool {
+        match self {
+            Principal::System | Principal::User(_) => true,
+            Principal::Token(token) => token.scopes.iter().any(|scope| scope.grants(access)),
+        }
+    }
+
+    pub fn can_list(&self, list_id: i32, access: Access) -> bool {
+        match self {
+            Principal::System | Principal::User(_) => true,
+            Principal::Token(token) => token
+                .scopes
+                .iter()
+                .any(|scope| scope.grants_list(list_id, access)),
+        }
+    }
+
+    /// The lists the principal is limited to, or `None` if it may access all lists
+    pub fn list_ids(&self, access: Access) -> Option<Vec<i32>> {
+        match self {
+            Principal::Token(token) if !self.can(access) => Some(
+                token
+                    .scopes
+                    .iter()
+                    .filter_map(|scope| match scope {
+                        Scope::List { id, .. } if scope.grants_list(*id, access) => Some(*id),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    pub fn require(&self, access: Access) -> Result<()> {
+        if self.can(access) {
+            Ok(())
+        } else {
+            Err(AuthError::InsufficientScope.into())
+        }
+    }
+
+    pub fn require_list(&self, list_id: i32, access: Access) -> Result<()> {
+        if self.can_list(list_id, access) {
+            Ok(())
+        } else {
+            Err(AuthError::InsufficientScope.into())
+        }
+    }
+
+    pub fn require_admin(&self) -> Result<()> {
+        match self {
+            _ if self.is_admin() => Ok(()),
+            Principal::Token(_) => Err(AuthError::InsufficientScope.into()),
+            _ => Err(AuthError::Forbidden.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .ok_or(AuthError::MissingToken.into())
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        match Principal::from_request_parts(parts, state).await? {
+            Principal::User(user) => Ok(user),
+            Principal::System | Principal::Token(_) => Err(AuthError::UserRequired.into()),
+        }
+    }
+}
+
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let certificate = request.extensions().get::<ClientCertificate>();
+    let principal = authenticate(
+        &state,
+        request.headers(),
+        request.method(),
+        peer,
+        certificate,
+    )
+    .await?;
+    let language = principal.user().and_then(|user| user.language);
+    let context = audit::Context::current().principal(&principal);
+    request.extensions_mut().insert(principal);
+
+    let mut response = audit::scope(context, next.run(request)).await;
+    // The user's preference beats Accept-Language for error messages
+    if let Some(language) = language {
+        response.extensions_mut().insert(language);
+    }
+    Ok(response)
+}
+
+/// Resolve the credentials of a request. Cookie sessions need the CSRF token
+/// unless `method` is safe.
+pub async fn authenticate(
+    state: &AppState,
+    headers: &HeaderMap,
+    method: &Method,
+    peer: Option<IpAddr>,
+    certificate: Option<&ClientCertificate>,
+) -> Result<Principal> {
+    if let Some(auth_header) = headers.get(header::AUTHORIZATION) {
+        let token = auth_header
+            .to_str()
+            .ok()
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or(AuthError::InvalidFormat)?;
+
+        if let Some(expected_token) = &state.config.auth_token {
+            if bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())) {
+                return Ok(Principal::System);
+            }
+        }
+
+        if token.starts_with(tokens::TOKEN_PREFIX) {
+            let token = tokens::authenticate(&state.pool, token)
+                .await?
+                .ok_or(AuthError::InvalidToken)?;
+            tracing::debug!(token_id = token.id, token_name = %token.name, "API token");
+            return Ok(Principal::Token(token));
+        }
+
+        if let Some(provider) = state.oidc.as_deref().filter(|_| oidc::is_jwt(token)) {
+            let user = oidc::authenticate(&state.pool, provider, token)
+                .await?
+                .ok_or(AuthError::InvalidToken)?;
+            return Ok(Principal::User(user));
+        }
+
+        let (user, _) = session::authenticate(&state.pool, "app", token)
+            .await?
+            .ok_or(AuthError::InvalidToken)?;
+        return Ok(Principal::User(user));
+    }
+
+    // Devices without a token of their own, e.g. on the LAN. Other certificates
+    // from the client CAs do not sign anyone in.
+    if let Some(certificate) = certificate {
+        if let Some(token) =
+            tokens::authenticate_certificate(&state.pool, &certificate.identities).await?
+        {
+            tracing::debug!(token_id = token.id, token_name = %token.name, "Client certificate");
+            return Ok(Principal::Token(token));
+        }
+    }
+
+    // API clients behind the proxy keep using bearer tokens, handled above
+    if let Some(config) = &state.config.proxy_auth {
+        if let Some(user) = proxy::authenticate(&state.pool, config, peer, headers, method).await? {
+            return Ok(Principal::User(user));
+        }
+    }
+
+    if let Some(token) = cookie(headers, SESSION_COOKIE) {
+        let (user, csrf_token) = session::authenticate(&state.pool, "browser", token)
+            .await?
+            .ok_or(AuthError::InvalidToken)?;
+
+        // Cookies are sent automatically, so state-changing requests must prove
+        // that they come from our own frontend
+        if !method.is_safe() {
+            let sent = headers.get(CSRF_HEADER).map(HeaderValue::as_bytes);
+            let valid = match (sent, csrf_token) {
+                (Some(sent), Some(expected)) => bool::from(sent.ct_eq(expected.as_bytes())),
+                _ => false,
+            };
+            if !valid {
+                return Err(AuthError::CsrfFailed.into());
+            }
+        }
+        return Ok(Principal::User(user));
+    }
+
+    // Without AUTH_TOKEN the API stays open until the first user or API token
+    // is created
+    if state.config.auth_token.is_none() && !credentials_exist(&state.pool).await? {
+        return Ok(Principal::System);
+    }
+
+    Err(AuthError::MissingToken.into())
+}
+
+/// Whether any user or API token exists. Until then, and without an
+/// `AUTH_TOKEN`, the API is open.
+pub async fn credentials_exist(pool: &PgPool) -> Result<bool> {
+    Ok(sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM users) OR EXISTS (SELECT 1 FROM api_tokens)",
+    )
+    .fetch_one(pool)
+    .await?)
+}
+
+pub fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    InvalidFormat,
+    InvalidToken,
+    InvalidCredentials,
+    InvalidInvitation,
+    CsrfFailed,
+    /// Authenticated, but not an admin
+    Forbidden,
+    /// Authenticated with the shared token where a user account is needed
+    UserRequired,
+    /// The API token lacks the scope for this request
+    InsufficientScope,
+    /// The requested household does not exist or the caller is not a member
+    NoHousehold,
+    /// The user's role on the list does not allow this
+    InsufficientRole,
+    /// The OpenID Connect login was declined, expired or came back invalid
+    SsoFailed,
+    /// The OpenID Connect provider could not be reached
+    SsoUnavailable,
+    /// The passkey assertion is unknown, expired or has a bad signature
+    InvalidPasskey,
+    /// The password was right, but the user also requires a passkey
+    PasskeyRequired,
+}
+
+impl AuthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::MissingToken
+            | AuthError::InvalidFormat
+            | AuthError::InvalidToken
+            | AuthError::InvalidCredentials
+            | AuthError::SsoFailed
+            | AuthError::InvalidPasskey
+            | AuthError::PasskeyRequired => StatusCode::UNAUTHORIZED,
+            AuthError::InvalidInvitation => StatusCode::BAD_REQUEST,
+            AuthError::CsrfFailed
+            | AuthError::Forbidden
+            | AuthError::UserRequired
+            | AuthError::InsufficientScope
+            | AuthError::NoHousehold
+            | AuthError::InsufficientRole => StatusCode::FORBIDDEN,
+            AuthError::SsoUnavailable => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::MissingToken => "missing_token",
+            AuthError::InvalidFormat => "invalid_auth_format",
+            AuthError::InvalidToken => "invalid_token",
+            AuthError::InvalidCredentials => "invalid_credentials",
+            AuthError::InvalidInvitation => "invalid_invitation",
+            AuthError::CsrfFailed => "csrf_failed",
+            AuthError::Forbidden => "forbidden",
+            AuthError::UserRequired => "user_required",
+            AuthError::InsufficientScope => "insufficient_scope",
+            AuthError::NoHousehold => "no_household",
+            AuthError::InsufficientRole => "insufficient_role",
+            AuthError::SsoFailed => "sso_failed",
+            AuthError::SsoUnavailable => "sso_unavailable",
+            AuthError::InvalidPasskey => "invalid_passkey",
+            AuthError::PasskeyRequired => "passkey_required",
+        }
+    }
+
+    pub fn message(&self) -> Message {
+        Message::new(format!("detail.{}", self.code()))
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let mut response = Problem::new(status, self.code(), self.message()).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
@@ -0,0 +1,716 @@
+//! Single sign-on with an OpenID Connect provider.
+//!
+//! Browsers sign in with the authorization code flow and PKCE and then get a
+//! regular session; apps may instead send tokens issued by the provider as
+//! bearer JWTs. The provider's discovery document and signing keys are fetched
+//! on first use and cached. Users are provisioned from the token claims, see
+//! [`services::identities`].
+
+use std::time::{Duration as StdDuration, Instant};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use tokio::sync::RwLock;
+
+use super::{session, AuthError, CurrentUser};
+use crate::{
+    config::OidcConfig,
+    error::{AppError, Result},
+    i18n::Lang,
+    services::{self, identities::Identity},
+};
+
+/// Holds the `state` of a login in the browser that started it
+pub const STATE_COOKIE: &str = "lister_oidc";
+/// How long a login may take at the provider
+pub const LOGIN_TTL_MINUTES: i64 = 10;
+
+/// Signing keys are refetched after this long, and for unknown key IDs at most
+/// this often
+const KEYS_TTL: StdDuration = StdDuration::from_secs(60 * 60);
+const KEYS_MIN_REFRESH: StdDuration = StdDuration::from_secs(60);
+const HTTP_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+
+#[derive(Clone, Deserialize)]
+struct Metadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+struct Keys {
+    set: JwkSet,
+    fetched_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// A login waiting for the provider's callback
+pub struct PendingLogin {
+    pub state: String,
+    pub authorization_url: String,
+}
+
+pub struct Provider {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<Metadata>>,
+    keys: RwLock<Option<Keys>>,
+}
+
+impl Provider {
+    pub fn new(config: OidcConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            config,
+            http: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+            metadata: RwLock::new(None),
+            keys: RwLock::new(None),
+        })
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Start a login: remember state, nonce and PKCE verifier, and build the
+    /// provider URL to send the browser to
+    pub async fn begin_login(
+        &self,
+        conn: &mut PgConnection,
+        return_to: &str,
+    ) -> Result<PendingLogin> {
+        let metadata = self.metadata().await?;
+        let state = session::generate_token();
+        let nonce = session::generate_token();
+        let code_verifier = session::generate_token();
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        sqlx::query(
+            r#"
+            INSERT INTO oidc_logins (state_hash, nonce, code_verifier, return_to, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(session::hash_token(&state))
+        .bind(&nonce)
+        .bind(&code_verifier)
+        .bind(return_to)
+        .bind(Utc::now() + Duration::minutes(LOGIN_TTL_MINUTES))
+        .execute(&mut *conn)
+        .await?;
+
+        let query = serde_urlencoded::to_string([
+            ("response_type", "code"),
+            ("client_id", &self.config.client_id),
+            ("redirect_uri", &self.config.redirect_url),
+            ("scope", &self.config.scopes),
+            ("state", &state),
+            ("nonce", &nonce),
+            ("code_challenge", &code_challenge),
+            ("code_challenge_method", "S256"),
+        ])
+        .map_err(|_| AppError::Internal)?;
+        let separator = if metadata.authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+
+        Ok(PendingLogin {
+            authorization_url: format!("{}{separator}{query}", metadata.authorization_endpoint),
+            state,
+        })
+    }
+
+    /// Complete a login with the code from the provider's callback. Returns the
+    /// identity and the path the browser came from.
+    pub async fn finish_login(
+        &self,
+        conn: &mut PgConnection,
+        state: &str,
+        code: &str,
+    ) -> Result<(Identity, String)> {
+        // Each login can be completed once
+        let (nonce, code_verifier, return_to) = sqlx::query_as::<_, (String, String, String)>(
+            r#"
+            DELETE FROM oidc_logins
+            WHERE state_hash = $1 AND expires_at > now()
+            RETURNING nonce, code_verifier, return_to
+            "#,
+        )
+        .bind(session::hash_token(state))
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(AuthError::SsoFailed)?;
+
+        let metadata = self.metadata().await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.config.redirect_url),
+            ("code_verifier", &code_verifier),
+        ];
+        let mut request = self.http.post(&metadata.token_endpoint);
+        match &self.config.client_secret {
+            Some(secret) => request = request.basic_auth(&self.config.client_id, Some(secret)),
+            None => form.push(("client_id", &self.config.client_id)),
+        }
+
+        let response = request.form(&form).send().await.map_err(unavailable)?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!(%status, body, "OpenID Connect provider rejected the authorization code");
+            return Err(AuthError::SsoFailed.into());
+        }
+        let tokens: TokenResponse = response.json().await.map_err(unavailable)?;
+
+        let claims = self
+            .verify(
+                &tokens.id_token,
+                std::slice::from_ref(&self.config.client_id),
+            )
+            .await?
+            .ok_or(AuthError::SsoFailed)?;
+        if claims.get("nonce").and_then(Value::as_str) != Some(nonce.as_str()) {
+            return Err(AuthError::SsoFailed.into());
+        }
+
+        let identity = self.identity(&claims).ok_or(AuthError::SsoFailed)?;
+        Ok((identity, return_to))
+    }
+
+    /// The claims of a token signed by the provider for one of `audiences`, or
+    /// `None` if it is invalid or expired
+    async fn verify(
+        &self,
+        token: &str,
+        audiences: &[String],
+    ) -> Result<Option<Map<String, Value>>> {
+        let Ok(header) = jsonwebtoken::decode_header(token) else {
+            return Ok(None);
+        };
+        // Keys come from the provider's public key set; a shared secret would
+        // let anyone who knows the client secret sign tokens
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Ok(None);
+        }
+        let Some(key) = self.decoding_key(header.kid.as_deref()).await? else {
+            return Ok(None);
+        };
+
+        let metadata = self.metadata().await?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(audiences);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        match jsonwebtoken::decode::<Map<String, Value>>(token, &key, &validation) {
+            Ok(data) => Ok(Some(data.claims)),
+            Err(error) => {
+                tracing::debug!(%error, "Rejected OpenID Connect token");
+                Ok(None)
+            }
+        }
+    }
+
+    fn identity(&self, claims: &Map<String, Value>) -> Option<Identity> {
+        let claim = |name: &str| claims.get(name).and_then(Value::as_str).map(str::to_string);
+        let subject = claim("sub")?;
+
+        let groups = claims
+            .get(&self.config.groups_claim)
+            .map(|value| match value {
+                Value::Array(groups) => groups
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect(),
+                Value::String(group) => vec![group.clone()],
+                _ => Vec::new(),
+            });
+
+        Some(Identity {
+            issuer: claim("iss")?,
+            username: claim(&self.config.username_claim).unwrap_or_else(|| subject.clone()),
+            display_name: claim("name"),
+            groups,
+            subject,
+            // The provider may be shared with others, so its usernames are not ours
+            link_by_username: false,
+        })
+    }
+
+    async fn metadata(&self) -> Result<Metadata> {
+        if let Some(metadata) = self.metadata.read().await.as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+        let metadata: Metadata = self.get_json(&url).await?;
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer {
+            tracing::warn!(
+                issuer = metadata.issuer,
+                "OpenID Connect discovery returned a different issuer than OIDC_ISSUER"
+            );
+            return Err(AuthError::SsoUnavailable.into());
+        }
+
+        *self.metadata.write().await = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    async fn decoding_key(&self, kid: Option<&str>) -> Result<Option<DecodingKey>> {
+        if let Some(keys) = self.keys.read().await.as_ref() {
+            let age = keys.fetched_at.elapsed();
+            if age < KEYS_TTL {
+                if let Some(jwk) = find_key(&keys.set, kid) {
+                    return Ok(DecodingKey::from_jwk(jwk).ok());
+                }
+                if age < KEYS_MIN_REFRESH {
+                    return Ok(None);
+                }
+            }
+        }
+
+        // Expired, or an unknown key: the provider may have rotated its keys
+        let metadata = self.metadata().await?;
+        let set: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+        let key = find_key(&set, kid).and_then(|jwk| DecodingKey::from_jwk(jwk).ok());
+        *self.keys.write().await = Some(Keys {
+            set,
+            fetched_at: Instant::now(),
+        });
+
+        Ok(key)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(unavailable)?
+            .json()
+            .await
+            .map_err(unavailable)
+    }
+}
+
+/// Tokens without a key ID are accepted if the provider has a single key
+fn find_key<'a>(set: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => set.find(kid),
+        None if set.keys.len() == 1 => set.keys.first(),
+        None => None,
+    }
+}
+
+fn unavailable(error: reqwest::Error) -> AppError {
+    tracing::warn!(%error, "OpenID Connect provider request failed");
+    AuthError::SsoUnavailable.into()
+}
+
+/// Our own tokens are plain base64; JWTs are three base64 parts of a JSON header
+pub fn is_jwt(token: &str) -> bool {
+    token.starts_with("eyJ") && token.split('.').count() == 3
+}
+
+/// Resolve a bearer JWT issued by the provider, provisioning its user on first use
+pub async fn authenticate(
+    pool: &PgPool,
+    provider: &Provider,
+    token: &str,
+) -> Result<Option<CurrentUser>> {
+    let Some(claims) = provider.verify(token, &provider.config.audiences).await? else {
+        return Ok(None);
+    };
+    let Some(identity) = provider.identity(&claims) else {
+        return Ok(None);
+    };
+
+    let mut tx = pool.begin().await?;
+    let user =
+        services::identities::sign_in(&mut tx, &provider.config.groups, &identity, false).await?;
+    tx.commit().await?;
+
+    if user.disabled {
+        return Ok(None);
+    }
+    Ok(Some(CurrentUser {
+        id: user.id,
+        username: user.username,
+        is_admin: user.is_admin,
+        language: user.language.as_deref().and_then(Lang::parse),
+        session_id: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use axum::{extract::State, routing::get, Json, Router};
+    use jsonwebtoken::{EncodingKey, Header};
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::{config::GroupMapping, services::identities};
+
+    /// An identity provider on a local port that counts how often its
+    /// discovery document and keys are fetched
+    #[derive(Clone)]
+    struct MockProvider {
+        issuer: String,
+        advertised_issuer: String,
+        keys: Arc<Mutex<Vec<Value>>>,
+        discovery_requests: Arc<AtomicUsize>,
+        keys_requests: Arc<AtomicUsize>,
+    }
+
+    impl MockProvider {
+        async fn start(keys: &[&SigningKey]) -> Self {
+            Self::start_as(keys, |issuer| issuer.to_string()).await
+        }
+
+        /// Start a provider whose discovery document names `advertise(issuer)`
+        /// as its issuer
+        async fn start_as(keys: &[&SigningKey], advertise: fn(&str) -> String) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+            let mock = MockProvider {
+                advertised_issuer: advertise(&issuer),
+                issuer,
+                keys: Arc::new(Mutex::new(keys.iter().map(|key| key.jwk.clone()).collect())),
+                discovery_requests: Arc::default(),
+                keys_requests: Arc::default(),
+            };
+
+            let app = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/keys", get(key_set))
+                .with_state(mock.clone());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            mock
+        }
+
+        fn provider(&self) -> Provider {
+            Provider::new(OidcConfig {
+                issuer: self.issuer.clone(),
+                client_id: "lister".to_string(),
+                client_secret: None,
+                redirect_url: "http://localhost/api/auth/oidc/callback".to_string(),
+                scopes: "openid profile".to_string(),
+                audiences: vec!["lister".to_string()],
+                username_claim: "preferred_username".to_string(),
+                groups_claim: "groups".to_string(),
+                groups: GroupMapping {
+                    admin_groups: vec!["admins".to_string()],
+                    household_groups: vec![
+                        ("family".to_string(), "Home".to_string()),
+                        ("cabin".to_string(), "Cabin".to_string()),
+                    ],
+                },
+            })
+            .unwrap()
+        }
+
+        fn claims(&self) -> Value {
+            json!({
+                "iss": self.advertised_issuer,
+                "sub": "3f1c",
+                "aud": "lister",
+                "exp": Utc::now().timestamp() + 300,
+                "preferred_username": "alice",
+                "name": "Alice",
+                "groups": ["family", "admins"],
+            })
+        }
+    }
+
+    async fn discovery(State(mock): State<MockProvider>) -> Json<Value> {
+        mock.discovery_requests.fetch_add(1, Ordering::SeqCst);
+        Json(json!({
+            "issuer": mock.advertised_issuer,
+            "authorization_endpoint": format!("{}/authorize", mock.issuer),
+            "token_endpoint": format!("{}/token", mock.issuer),
+            "jwks_uri": format!("{}/keys", mock.issuer),
+        }))
+    }
+
+    async fn key_set(State(mock): State<MockProvider>) -> Json<Value> {
+        mock.keys_requests.fetch_add(1, Ordering::SeqCst);
+        Json(json!({ "keys": *mock.keys.lock().unwrap() }))
+    }
+
+    struct SigningKey {
+        kid: String,
+        algorithm: Algorithm,
+        encoding: EncodingKey,
+        jwk: Value,
+    }
+
+    impl SigningKey {
+        fn es256(kid: &str) -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+            let pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            // An uncompressed point: 0x04, then x and y
+            let point = pair.public_key().as_ref();
+            SigningKey {
+                kid: kid.to_string(),
+                algorithm: Algorithm::ES256,
+                encoding: EncodingKey::from_ec_der(pkcs8.as_ref()),
+                jwk: json!({
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "kid": kid,
+                    "alg": "ES256",
+                    "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+                }),
+            }
+        }
+
+        fn ed25519(kid: &str) -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+            SigningKey {
+                kid: kid.to_string(),
+                algorithm: Algorithm::EdDSA,
+                encoding: EncodingKey::from_ed_der(pkcs8.as_ref()),
+                jwk: json!({
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "kid": kid,
+                    "alg": "EdDSA",
+                    "x": URL_SAFE_NO_PAD.encode(pair.public_key().as_ref()),
+                }),
+            }
+        }
+
+        fn sign(&self, claims: &Value) -> String {
+            let mut header = Header::new(self.algorithm);
+            header.kid = Some(self.kid.clone());
+            jsonwebtoken::encode(&header, claims, &self.encoding).unwrap()
+        }
+    }
+
+    async fn verify(provider: &Provider, token: &str) -> Option<Map<String, Value>> {
+        provider
+            .verify(token, &provider.config.audiences)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn accepts_es256_and_eddsa_tokens() {
+        let (ec, ed) = (SigningKey::es256("ec"), SigningKey::ed25519("ed"));
+        let mock = MockProvider::start(&[&ec, &ed]).await;
+        let provider = mock.provider();
+
+        for key in [&ec, &ed] {
+            let claims = verify(&provider, &key.sign(&mock.claims())).await.unwrap();
+            assert_eq!(claims["sub"], "3f1c");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_issuer_audience_and_expired_tokens() {
+        let key = SigningKey::es256("ec");
+        let mock = MockProvider::start(&[&key]).await;
+        let provider = mock.provider();
+
+        let mut claims = mock.claims();
+        claims["iss"] = json!("https://elsewhere.example");
+        assert!(verify(&provider, &key.sign(&claims)).await.is_none());
+
+        let mut claims = mock.claims();
+        claims["aud"] = json!("another-app");
+        assert!(verify(&provider, &key.sign(&claims)).await.is_none());
+
+        let mut claims = mock.claims();
+        claims["exp"] = json!(Utc::now().timestamp() - 3600);
+        assert!(verify(&provider, &key.sign(&claims)).await.is_none());
+
+        let mut claims = mock.claims();
+        claims.as_object_mut().unwrap().remove("exp");
+        assert!(verify(&provider, &key.sign(&claims)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_shared_secret_and_unsigned_tokens() {
+        let key = SigningKey::es256("ec");
+        let mock = MockProvider::start(&[&key]).await;
+        let provider = mock.provider();
+
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(key.kid.clone());
+        let secret = EncodingKey::from_secret(b"client secret");
+        let token = jsonwebtoken::encode(&header, &mock.claims(), &secret).unwrap();
+        assert!(verify(&provider, &token).await.is_none());
+
+        // Header `{"alg":"none"}` and no signature
+        let claims = URL_SAFE_NO_PAD.encode(mock.claims().to_string());
+        let token = format!("eyJhbGciOiJub25lIn0.{claims}.");
+        assert!(verify(&provider, &token).await.is_none());
+
+        // A signature by another key under a known key ID
+        let forged = SigningKey {
+            kid: key.kid.clone(),
+            ..SigningKey::es256("forged")
+        };
+        assert!(verify(&provider, &forged.sign(&mock.claims()))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn caches_discovery_and_keys() {
+        let key = SigningKey::es256("ec");
+        let mock = MockProvider::start(&[&key]).await;
+        let provider = mock.provider();
+
+        for _ in 0..3 {
+            assert!(verify(&provider, &key.sign(&mock.claims())).await.is_some());
+        }
+        assert_eq!(mock.discovery_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.keys_requests.load(Ordering::SeqCst), 1);
+
+        // Unknown key IDs do not refetch right after a fetch
+        let unknown = SigningKey::es256("unknown");
+        assert!(verify(&provider, &unknown.sign(&mock.claims()))
+            .await
+            .is_none());
+        assert_eq!(mock.keys_requests.load(Ordering::SeqCst), 1);
+
+        // Keys are refetched once they expire
+        provider.keys.write().await.as_mut().unwrap().fetched_at -= KEYS_TTL;
+        assert!(verify(&provider, &key.sign(&mock.claims())).await.is_some());
+        assert_eq!(mock.keys_requests.load(Ordering::SeqCst), 2);
+        assert_eq!(mock.discovery_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn picks_up_rotated_keys() {
+        let old = SigningKey::es256("old");
+        let mock = MockProvider::start(&[&old]).await;
+        let provider = mock.provider();
+        assert!(verify(&provider, &old.sign(&mock.claims())).await.is_some());
+
+        let new = SigningKey::ed25519("new");
+        *mock.keys.lock().unwrap() = vec![new.jwk.clone()];
+        provider.keys.write().await.as_mut().unwrap().fetched_at -= KEYS_MIN_REFRESH;
+
+        assert!(verify(&provider, &new.sign(&mock.claims())).await.is_some());
+        assert_eq!(mock.keys_requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn key_id_may_be_left_out_with_a_single_key() {
+        let key = SigningKey::es256("ec");
+        let mock = MockProvider::start(&[&key]).await;
+        let provider = mock.provider();
+
+        let token =
+            jsonwebtoken::encode(&Header::new(key.algorithm), &mock.claims(), &key.encoding)
+                .unwrap();
+        assert!(verify(&provider, &token).await.is_some());
+
+        *mock.keys.lock().unwrap() = vec![key.jwk.clone(), SigningKey::es256("other").jwk];
+        let provider = mock.provider();
+        assert!(verify(&provider, &token).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_discovery_for_another_issuer() {
+        let key = SigningKey::es256("ec");
+        let mock =
+            MockProvider::start_as(&[&key], |_| "https://elsewhere.example".to_string()).await;
+        let provider = mock.provider();
+
+        let token = key.sign(&mock.claims());
+        let result = provider.verify(&token, &provider.config.audiences).await;
+        assert!(matches!(
+            result,
+            Err(AppError::Auth(AuthError::SsoUnavailable))
+        ));
+        assert_eq!(mock.keys_requests.load(Ordering::SeqCst), 0);
+
+        // Up to a trailing slash, which tokens then carry too
+        let mock = MockProvider::start_as(&[&key], |issuer| format!("{issuer}/")).await;
+        assert!(verify(&mock.provider(), &key.sign(&mock.claims()))
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn maps_claims_to_an_identity_and_households() {
+        let key = SigningKey::es256("ec");
+        let mock = MockProvider::start(&[&key]).await;
+        let provider = mock.provider();
+        let mapping = &provider.config.groups;
+
+        let claims = verify(&provider, &key.sign(&mock.claims())).await.unwrap();
+        let identity = provider.identity(&claims).unwrap();
+        assert_eq!(identity.issuer, mock.issuer);
+        assert_eq!(identity.subject, "3f1c");
+        assert_eq!(identity.username, "alice");
+        assert_eq!(identity.display_name.as_deref(), Some("Alice"));
+        assert!(!identity.link_by_username);
+
+        let groups = identity.groups.unwrap();
+        assert_eq!(identities::is_admin(mapping, &groups), Some(true));
+        assert_eq!(
+            identities::memberships(mapping, &groups),
+            [("Home", true), ("Cabin", false)]
+        );
+
+        // A single group may be sent as a string
+        let mut claims = mock.claims();
+        claims["groups"] = json!("cabin");
+        let claims = verify(&provider, &key.sign(&claims)).await.unwrap();
+        let groups = provider.identity(&claims).unwrap().groups.unwrap();
+        assert_eq!(identities::is_admin(mapping, &groups), Some(false));
+        assert_eq!(
+            identities::memberships(mapping, &groups),
+            [("Home", false), ("Cabin", true)]
+        );
+
+        // Without groups roles are left alone, and the subject is the username
+        let mut claims = mock.claims();
+        let object = claims.as_object_mut().unwrap();
+        object.remove("groups");
+        object.remove("preferred_username");
+        let claims = verify(&provider, &key.sign(&claims)).await.unwrap();
+        let identity = provider.identity(&claims).unwrap();
+        assert_eq!(identity.groups, None);
+        assert_eq!(identity.username, "3f1c");
+    }
+}
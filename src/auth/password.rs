@@ -0,0 +1,58 @@
+//! Argon2id password hashing.
+//!
+//! Hashing is deliberately slow, so it runs on the blocking thread pool.
+
+use std::sync::OnceLock;
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::rngs::OsRng;
+
+use crate::error::{AppError, Result};
+
+/// Hash a password into a PHC string (`$argon2id$...`)
+pub async fn hash(password: String) -> Result<String> {
+    tokio::task::spawn_blocking(move || hash_blocking(&password))
+        .await
+        .map_err(|e| {
+            tracing::error!("Password hashing task failed: {e}");
+            AppError::Internal
+        })?
+}
+
+fn hash_blocking(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| {
+            tracing::error!("Failed to hash password: {e}");
+            AppError::Internal
+        })
+}
+
+/// Check a password against a stored hash. Without a hash (unknown user) a dummy
+/// hash is checked anyway, so both cases take equally long to reject.
+pub async fn verify(password: String, hash: Option<String>) -> bool {
+    let found = hash.is_some();
+
+    let matches = tokio::task::spawn_blocking(move || {
+        let hash = hash.unwrap_or_else(|| dummy_hash().to_string());
+        PasswordHash::new(&hash).is_ok_and(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+    })
+    .await
+    .unwrap_or(false);
+
+    found && matches
+}
+
+fn dummy_hash() -> &'static str {
+    static DUMMY: OnceLock<String> = OnceLock::new();
+    DUMMY.get_or_init(|| hash_blocking("dummy password").unwrap_or_default())
+}
@@ -0,0 +1,214 @@
+//! Authentication by a trusted reverse proxy.
+//!
+//! Proxies like Authelia or oauth2-proxy sign users in themselves and forward
+//! who they are in `Remote-User`, `Remote-Groups` and `Remote-Name`. Anyone can
+//! send these headers, so they only count on connections from the configured
+//! networks. Users are matched by username and created on first use, see
+//! [`services::identities`].
+
+use std::net::IpAddr;
+
+use axum::http::{header, HeaderMap, Method};
+use ipnet::IpNet;
+use sqlx::PgPool;
+
+use super::{AuthError, CurrentUser};
+use crate::{
+    config::ProxyAuthConfig,
+    error::Result,
+    i18n::Lang,
+    services::{self, identities::Identity},
+};
+
+/// Stored as the issuer of identities asserted by the proxy
+const ISSUER: &str = "proxy";
+
+/// The user the proxy vouches for, or `None` if the request did not come
+/// through the proxy or carries no user header
+pub async fn authenticate(
+    pool: &PgPool,
+    config: &ProxyAuthConfig,
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    method: &Method,
+) -> Result<Option<CurrentUser>> {
+    let Some(username) = headers
+        .get(&config.user_header)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|username| !username.is_empty())
+    else {
+        return Ok(None);
+    };
+    if !trusted(&config.trusted_networks, peer) {
+        tracing::debug!(
+            ?peer,
+            "Ignored proxy authentication header from untrusted address"
+        );
+        return Ok(None);
+    }
+
+    // The proxy's login is carried by a cookie, so state-changing requests must
+    // come from a page on the same site, like with our own sessions
+    if !method.is_safe() && !same_origin(headers) {
+        return Err(AuthError::CsrfFailed.into());
+    }
+
+    let text = |name| {
+        headers
+            .get(name)
+            .and_then(|value| std::str::from_utf8(value.as_bytes()).ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let identity = Identity {
+        issuer: ISSUER.to_string(),
+        subject: username.to_lowercase(),
+        username: username.to_string(),
+        display_name: text(&config.name_header).map(str::to_string),
+        groups: text(&config.groups_header).map(|groups| {
+            groups
+                .split(',')
+                .map(str::trim)
+                .filter(|group| !group.is_empty())
+                .map(str::to_string)
+                .collect()
+        }),
+        link_by_username: true,
+    };
+
+    let mut tx = pool.begin().await?;
+    let user = services::identities::sign_in(&mut tx, &config.groups, &identity, false).await?;
+    tx.commit().await?;
+
+    if user.disabled {
+        return Err(AuthError::InvalidToken.into());
+    }
+    Ok(Some(CurrentUser {
+        id: user.id,
+        username: user.username,
+        is_admin: user.is_admin,
+        language: user.language.as_deref().and_then(Lang::parse),
+        session_id: None,
+    }))
+}
+
+/// Whether the connection comes from one of the proxy's networks. IPv4 peers
+/// on a dual-stack socket show up as IPv4-mapped IPv6 addresses.
+fn trusted(networks: &[IpNet], peer: Option<IpAddr>) -> bool {
+    peer.is_some_and(|peer| {
+        let peer = peer.to_canonical();
+        networks.iter().any(|network| network.contains(&peer))
+    })
+}
+
+/// Whether a browser sent the request from a page of this site, going by
+/// `Sec-Fetch-Site`, else `Origin`, else `Referer`. Requests without any of
+/// them do not come from a browser.
+fn same_origin(headers: &HeaderMap) -> bool {
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return site == "same-origin" || site == "none";
+    }
+    let Some(source) = headers
+        .get(header::ORIGIN)
+        .or_else(|| headers.get(header::REFERER))
+    else {
+        return true;
+    };
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    let source_host = source
+        .to_str()
+        .ok()
+        .and_then(|source| source.split_once("://"))
+        .map(|(_, rest)| rest.split('/').next().unwrap_or(rest));
+
+    host.is_some() && source_host == host
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn trusts_only_peers_in_the_networks() {
+        let networks: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()];
+        let cases = [
+            (Some("10.1.2.3"), true),
+            (Some("::1"), true),
+            (Some("11.0.0.1"), false),
+            (Some("192.168.1.1"), false),
+            (Some("::2"), false),
+            (None, false),
+        ];
+        for (peer, expected) in cases {
+            let peer = peer.map(|peer| peer.parse().unwrap());
+            assert_eq!(trusted(&networks, peer), expected, "{peer:?}");
+        }
+        assert!(!trusted(&[], Some("10.1.2.3".parse().unwrap())));
+    }
+
+    #[test]
+    fn trusts_ipv4_mapped_peers_like_ipv4() {
+        let networks: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap()];
+        assert!(trusted(&networks, Some("::ffff:10.1.2.3".parse().unwrap())));
+        assert!(!trusted(
+            &networks,
+            Some("::ffff:11.0.0.1".parse().unwrap())
+        ));
+    }
+
+    #[test]
+    fn accepts_requests_from_this_site() {
+        let host = ("host", "lists.example.com");
+        assert!(same_origin(&headers(&[("sec-fetch-site", "same-origin")])));
+        assert!(same_origin(&headers(&[("sec-fetch-site", "none")])));
+        assert!(same_origin(&headers(&[
+            host,
+            ("origin", "https://lists.example.com")
+        ])));
+        assert!(same_origin(&headers(&[
+            host,
+            ("referer", "https://lists.example.com/lists/3")
+        ])));
+    }
+
+    #[test]
+    fn rejects_requests_from_other_sites() {
+        let host = ("host", "lists.example.com");
+        assert!(!same_origin(&headers(&[("sec-fetch-site", "cross-site")])));
+        assert!(!same_origin(&headers(&[
+            host,
+            ("origin", "https://evil.example")
+        ])));
+        assert!(!same_origin(&headers(&[host, ("origin", "null")])));
+        assert!(!same_origin(&headers(&[
+            host,
+            ("referer", "https://evil.example/lists.example.com")
+        ])));
+        // Origin wins over Referer
+        assert!(!same_origin(&headers(&[
+            host,
+            ("origin", "https://evil.example"),
+            ("referer", "https://lists.example.com/")
+        ])));
+        assert!(!same_origin(&headers(&[(
+            "origin",
+            "https://lists.example.com"
+        )])));
+    }
+
+    #[test]
+    fn accepts_requests_without_origin() {
+        assert!(same_origin(&headers(&[])));
+        assert!(same_origin(&headers(&[("host", "lists.example.com")])));
+    }
+}
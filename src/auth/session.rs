@@ -0,0 +1,230 @@
+//! Login sessions.
+//!
+//! Browser sessions are identified by a cookie and carry a CSRF token; app
+//! sessions by a short-lived access token that is renewed with a refresh token.
+//! Tokens are random and only their SHA-256 hashes are stored.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+
+use super::CurrentUser;
+use crate::error::Result;
+
+/// A new browser session; `token` goes into the session cookie
+pub struct BrowserSession {
+    pub token: String,
+    pub csrf_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A new or renewed pair of app tokens
+pub struct AppTokens {
+    pub user_id: i32,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 256 random bits, URL-safe base64
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub async fn create_browser(
+    conn: &mut PgConnection,
+    user_id: i32,
+    user_agent: Option<&str>,
+    ttl: Duration,
+) -> Result<BrowserSession> {
+    let session = BrowserSession {
+        token: generate_token(),
+        csrf_token: generate_token(),
+        expires_at: Utc::now() + ttl,
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (user_id, kind, token_hash, expires_at, csrf_token, user_agent)
+        VALUES ($1, 'browser', $2, $3, $4, $5)
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&session.token))
+    .bind(session.expires_at)
+    .bind(&session.csrf_token)
+    .bind(user_agent)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(session)
+}
+
+pub async fn create_app(
+    conn: &mut PgConnection,
+    user_id: i32,
+    user_agent: Option<&str>,
+    access_ttl: Duration,
+    refresh_ttl: Duration,
+) -> Result<AppTokens> {
+    let tokens = new_app_tokens(user_id, access_ttl);
+
+    sqlx::query(
+        r#"
+        INSERT INTO sessions
+            (user_id, kind, token_hash, expires_at, refresh_hash, refresh_expires_at, user_agent)
+        VALUES ($1, 'app', $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&tokens.access_token))
+    .bind(tokens.expires_at)
+    .bind(hash_token(&tokens.refresh_token))
+    .bind(Utc::now() + refresh_ttl)
+    .bind(user_agent)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(tokens)
+}
+
+/// Exchange a refresh token for a new token pair. Both old tokens stop working.
+pub async fn refresh(
+    conn: &mut PgConnection,
+    refresh_token: &str,
+    access_ttl: Duration,
+    refresh_ttl: Duration,
+) -> Result<Option<AppTokens>> {
+    let Some(user_id) = sqlx::query_scalar::<_, i32>(
+        r#"
+        SELECT s.user_id
+        FROM sessions s
+        JOIN users u ON u.id = s.user_id
+        WHERE s.refresh_hash = $1
+          AND s.refresh_expires_at > now()
+          AND NOT u.disabled
+        "#,
+    )
+    .bind(hash_token(refresh_token))
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let tokens = new_app_tokens(user_id, access_ttl);
+
+    sqlx::query(
+        r#"
+        UPDATE sessions
+        SET token_hash = $1, expires_at = $2, refresh_hash = $3, refresh_expires_at = $4,
+            last_used_at = now()
+        WHERE refresh_hash = $5
+        "#,
+    )
+    .bind(hash_token(&tokens.access_token))
+    .bind(tokens.expires_at)
+    .bind(hash_token(&tokens.refresh_token))
+    .bind(Utc::now() + refresh_ttl)
+    .bind(hash_token(refresh_token))
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Some(tokens))
+}
+
+fn new_app_tokens(user_id: i32, access_ttl: Duration) -> AppTokens {
+    AppTokens {
+        user_id,
+        access_token: generate_token(),
+        refresh_token: generate_token(),
+        expires_at: Utc::now() + access_ttl,
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SessionUser {
+    session_id: i64,
+    user_id: i32,
+    username: String,
+    is_admin: bool,
+    language: Option<String>,
+    csrf_token: Option<String>,
+}
+
+/// Resolve a session token of the given kind; also records the session as used.
+/// Returns the user and, for browser sessions, the CSRF token.
+pub async fn authenticate(
+    pool: &PgPool,
+    kind: &str,
+    token: &str,
+) -> Result<Option<(CurrentUser, Option<String>)>> {
+    let row = sqlx::query_as::<_, SessionUser>(
+        r#"
+        UPDATE sessions s
+        SET last_used_at = now()
+        FROM users u
+        WHERE u.id = s.user_id
+          AND s.token_hash = $1
+          AND s.kind = $2
+          AND s.expires_at > now()
+          AND NOT u.disabled
+        RETURNING s.id AS session_id, u.id AS user_id, u.username, u.is_admin, u.language,
+                  s.csrf_token
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(kind)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| {
+        let user = CurrentUser {
+            id: row.user_id,
+            username: row.username,
+            is_admin: row.is_admin,
+            language: row.language.as_deref().and_then(crate::i18n::Lang::parse),
+            session_id: Some(row.session_id),
+        };
+        (user, row.csrf_token)
+    }))
+}
+
+pub async fn delete(conn: &mut PgConnection, session_id: i64) -> Result<()> {
+    sqlx::query(
+        r#"
+        DELETE FROM sessions
+        WHERE id = $1
+        "#,
+    )
+    .bind(session_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Sign a user out everywhere, optionally keeping the current session
+pub async fn delete_all(conn: &mut PgConnection, user_id: i32, except: Option<i64>) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM sessions
+        WHERE user_id = $1
+          AND id IS DISTINCT FROM $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(except)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
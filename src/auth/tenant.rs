@@ -0,0 +1,151 @@
+//! The household a request works in.
+//!
+//! Users pick one of their households with the `X-Household-ID` header and
+//! otherwise work in the one they joined first. API tokens belong to a single
+//! household; the shared `AUTH_TOKEN` may pick any and defaults to the oldest.
+//! Members are either plain members or managers, who may invite people.
+//! Requests that work in a household count against its daily request quota.
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use sqlx::PgPool;
+
+use super::{AuthError, Principal};
+use crate::{
+    error::{AppError, Result},
+    state::AppState,
+};
+
+pub const HOUSEHOLD_HEADER: &str = "x-household-id";
+
+/// Roles of household members, see [`HouseholdRole`]
+pub const HOUSEHOLD_ROLES: &[&str] = &["member", "manager"];
+
+/// A user's role in a household, ordered from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HouseholdRole {
+    /// Works with the household's lists
+    Member,
+    /// Also invites people to the household and sees its members
+    Manager,
+}
+
+impl HouseholdRole {
+    pub fn parse(s: &str) -> Option<HouseholdRole> {
+        match s {
+            "member" => Some(HouseholdRole::Member),
+            "manager" => Some(HouseholdRole::Manager),
+            _ => None,
+        }
+    }
+}
+
+impl Principal {
+    /// Check that the principal may invite people to a household: admins and
+    /// the household's managers
+    pub async fn require_household_manager(&self, pool: &PgPool, household_id: i32) -> Result<()> {
+        let user = match self {
+            _ if self.is_admin() => return Ok(()),
+            Principal::User(user) => user,
+            Principal::System | Principal::Token(_) => return self.require_admin(),
+        };
+
+        let role = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT role
+            FROM household_members
+            WHERE household_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(household_id)
+        .bind(user.id)
+        .fetch_optional(pool)
+        .await?;
+
+        match role.as_deref().and_then(HouseholdRole::parse) {
+            Some(HouseholdRole::Manager) => Ok(()),
+            _ => Err(AuthError::Forbidden.into()),
+        }
+    }
+}
+
+/// ID of the caller's household; every list, category and name query is scoped to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tenant(pub i32);
+
+#[async_trait]
+impl FromRequestParts<AppState> for Tenant {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        if let Some(tenant) = parts.extensions.get::<Tenant>() {
+            return Ok(*tenant);
+        }
+
+        let principal = Principal::from_request_parts(parts, state).await?;
+        let requested = match parts.headers.get(HOUSEHOLD_HEADER) {
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(|v| v.trim().parse::<i32>().ok())
+                    .ok_or(AuthError::NoHousehold)?,
+            ),
+            None => None,
+        };
+
+        let tenant = resolve(state, &principal, requested).await?;
+
+        // Counted once per request, however often the household is extracted
+        state
+            .requests
+            .count(&state.pool, &state.config.quotas, tenant.0)
+            .await?;
+        parts.extensions.insert(tenant);
+
+        Ok(tenant)
+    }
+}
+
+/// The household the principal works in: `requested`, if they may, or their default
+pub async fn resolve(
+    state: &AppState,
+    principal: &Principal,
+    requested: Option<i32>,
+) -> Result<Tenant> {
+    let household_id = match principal {
+        Principal::Token(token) => {
+            Some(token.household_id).filter(|id| requested.is_none_or(|r| r == *id))
+        }
+        Principal::User(user) => {
+            sqlx::query_scalar::<_, i32>(
+                r#"
+                    SELECT household_id
+                    FROM household_members
+                    WHERE user_id = $1 AND ($2::int IS NULL OR household_id = $2)
+                    ORDER BY created_at, household_id
+                    LIMIT 1
+                    "#,
+            )
+            .bind(user.id)
+            .bind(requested)
+            .fetch_optional(&state.pool)
+            .await?
+        }
+        Principal::System => {
+            sqlx::query_scalar::<_, i32>(
+                r#"
+                    SELECT id
+                    FROM households
+                    WHERE $1::int IS NULL OR id = $1
+                    ORDER BY id
+                    LIMIT 1
+                    "#,
+            )
+            .bind(requested)
+            .fetch_optional(&state.pool)
+            .await?
+        }
+    };
+
+    Ok(household_id.map(Tenant).ok_or(AuthError::NoHousehold)?)
+}
@@ -0,0 +1,267 @@
+//! Named API tokens with scopes, for devices and scripts.
+//!
+//! Tokens carry a recognizable prefix, so they can be told apart from session
+//! tokens without a database lookup. Like sessions, only their hashes are stored.
+//! Tokens bound to a client certificate have no secret; the TLS handshake
+//! proves them instead, see [`crate::tls`].
+
+use std::fmt;
+
+use sqlx::PgPool;
+
+use super::session::{generate_token, hash_token};
+use crate::error::Result;
+
+/// Prefix of every API token
+pub const TOKEN_PREFIX: &str = "lst_";
+
+/// What a request wants to do with a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// A permission granted to an API token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Read all lists, items, names and categories
+    Read,
+    /// Read and change all lists, items, names and categories
+    Write,
+    /// Everything, including user, token and job management
+    Admin,
+    /// Access to a single list and its items
+    List { id: i32, access: Access },
+}
+
+impl Scope {
+    pub fn parse(s: &str) -> Option<Scope> {
+        match s {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            "admin" => Some(Scope::Admin),
+            _ => {
+                let (id, access) = s.strip_prefix("list:")?.split_once(':')?;
+                let access = match access {
+                    "read" => Access::Read,
+                    "write" => Access::Write,
+                    _ => return None,
+                };
+                Some(Scope::List {
+                    id: id.parse().ok().filter(|id| *id > 0)?,
+                    access,
+                })
+            }
+        }
+    }
+
+    pub fn is_valid(s: &str) -> bool {
+        Scope::parse(s).is_some()
+    }
+
+    /// Whether the scope allows `access` to all lists
+    pub fn grants(&self, access: Access) -> bool {
+        match self {
+            Scope::Read => access == Access::Read,
+            Scope::Write | Scope::Admin => true,
+            Scope::List { .. } => false,
+        }
+    }
+
+    /// Whether the scope allows `access` to the list `list_id`
+    pub fn grants_list(&self, list_id: i32, access: Access) -> bool {
+        match self {
+            Scope::List {
+                id,
+                access: granted,
+            } => *id == list_id && (*granted == Access::Write || access == Access::Read),
+            _ => self.grants(access),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Read => f.write_str("read"),
+            Scope::Write => f.write_str("write"),
+            Scope::Admin => f.write_str("admin"),
+            Scope::List {
+                id,
+                access: Access::Read,
+            } => write!(f, "list:{id}:read"),
+            Scope::List {
+                id,
+                access: Access::Write,
+            } => write!(f, "list:{id}:write"),
+        }
+    }
+}
+
+/// A request authenticated with an API token
+#[derive(Debug, Clone)]
+pub struct ScopedToken {
+    pub id: i32,
+    pub name: String,
+    pub household_id: i32,
+    pub scopes: Vec<Scope>,
+}
+
+impl ScopedToken {
+    pub fn has(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// A new random API token
+pub fn generate() -> String {
+    format!("{TOKEN_PREFIX}{}", generate_token())
+}
+
+/// Resolve an unrevoked, unexpired API token; also records it as used
+pub async fn authenticate(pool: &PgPool, token: &str) -> Result<Option<ScopedToken>> {
+    let row = sqlx::query_as::<_, (i32, String, i32, Vec<String>)>(
+        r#"
+        UPDATE api_tokens
+        SET last_used_at = now()
+        WHERE token_hash = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > now())
+        RETURNING id, name, household_id, scopes
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(scoped_token))
+}
+
+/// Resolve the unrevoked, unexpired token bound to one of the identities of a
+/// verified client certificate, preferring earlier identities; also records it
+/// as used
+pub async fn authenticate_certificate(
+    pool: &PgPool,
+    identities: &[String],
+) -> Result<Option<ScopedToken>> {
+    let row = sqlx::query_as::<_, (i32, String, i32, Vec<String>)>(
+        r#"
+        UPDATE api_tokens
+        SET last_used_at = now()
+        WHERE id = (
+            SELECT id FROM api_tokens
+            WHERE client_certificate = ANY($1)
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > now())
+            ORDER BY array_position($1, client_certificate)
+            LIMIT 1
+        )
+        RETURNING id, name, household_id, scopes
+        "#,
+    )
+    .bind(identities)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(scoped_token))
+}
+
+fn scoped_token((id, name, household_id, scopes): (i32, String, i32, Vec<String>)) -> ScopedToken {
+    ScopedToken {
+        id,
+        name,
+        household_id,
+        scopes: scopes.iter().filter_map(|s| Scope::parse(s)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_scopes() {
+        for scope in [
+            "read",
+            "write",
+            "admin",
+            "list:7:read",
+            "list:2147483647:write",
+        ] {
+            assert_eq!(Scope::parse(scope).unwrap().to_string(), scope);
+        }
+        assert_eq!(
+            Scope::parse("list:7:write"),
+            Some(Scope::List {
+                id: 7,
+                access: Access::Write
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_scopes() {
+        for scope in [
+            "",
+            "Read",
+            "list",
+            "list:7",
+            "list:7:admin",
+            "list:0:read",
+            "list:-1:read",
+            "list:x:read",
+            "list::read",
+            "list:2147483648:read",
+            "list:7:read:extra",
+            "lists:7:read",
+        ] {
+            assert!(!Scope::is_valid(scope), "{scope}");
+        }
+    }
+
+    #[test]
+    fn global_scopes_grant_every_list() {
+        assert!(Scope::Read.grants(Access::Read));
+        assert!(!Scope::Read.grants(Access::Write));
+        assert!(Scope::Write.grants(Access::Write));
+        assert!(Scope::Admin.grants(Access::Write));
+
+        assert!(Scope::Read.grants_list(3, Access::Read));
+        assert!(!Scope::Read.grants_list(3, Access::Write));
+        assert!(Scope::Write.grants_list(3, Access::Write));
+    }
+
+    #[test]
+    fn list_scopes_grant_only_their_list() {
+        let read = Scope::parse("list:3:read").unwrap();
+        let write = Scope::parse("list:3:write").unwrap();
+
+        assert!(!read.grants(Access::Read));
+        assert!(!write.grants(Access::Read));
+
+        assert!(read.grants_list(3, Access::Read));
+        assert!(!read.grants_list(3, Access::Write));
+        assert!(write.grants_list(3, Access::Read));
+        assert!(write.grants_list(3, Access::Write));
+        assert!(!write.grants_list(4, Access::Read));
+    }
+
+    #[test]
+    fn skips_unknown_stored_scopes() {
+        let token = scoped_token((
+            1,
+            "Fridge".to_string(),
+            2,
+            vec!["list:3:read".to_string(), "delete".to_string()],
+        ));
+        assert_eq!(
+            token.scopes,
+            [Scope::List {
+                id: 3,
+                access: Access::Read
+            }]
+        );
+        assert!(!token.has(Scope::Read));
+    }
+}
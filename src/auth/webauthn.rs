@@ -0,0 +1,841 @@
+//! Passkeys: the WebAuthn registration and login ceremonies.
+//!
+//! The API is the relying party; browsers talk to the authenticator through
+//! `navigator.credentials` and send us what it returns. Challenges are stored
+//! hashed and can be used once. Credentials with ES256, EdDSA and RS256 keys
+//! are accepted; attestation is not requested, so any authenticator works.
+//! Stored passkeys are managed by [`services::passkeys`].
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use ciborium::Value as Cbor;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgConnection;
+
+use super::{session, AuthError};
+use crate::{
+    config::WebauthnConfig,
+    error::{AppError, Result},
+    i18n::Message,
+    models::{AssertionCredential, PasskeyOptions, RegistrationCredential, User},
+    services,
+};
+
+/// How long the user has to respond to the authenticator's prompt
+pub const CHALLENGE_TTL_MINUTES: i64 = 5;
+
+/// COSE algorithms in order of preference
+const ES256: i64 = -7;
+const EDDSA: i64 = -8;
+const RS256: i64 = -257;
+const ALGORITHMS: [i64; 3] = [ES256, EDDSA, RS256];
+
+/// Authenticator data flags
+const USER_PRESENT: u8 = 0x01;
+const USER_VERIFIED: u8 = 0x04;
+const ATTESTED_CREDENTIAL: u8 = 0x40;
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+    #[serde(rename = "crossOrigin", default)]
+    cross_origin: bool,
+}
+
+/// A credential that passed the registration ceremony, ready to be stored
+pub struct NewCredential {
+    pub credential_id: Vec<u8>,
+    /// COSE_Key as sent by the authenticator
+    pub public_key: Vec<u8>,
+    pub algorithm: i32,
+    pub sign_count: i64,
+    pub transports: Vec<String>,
+}
+
+/// The stored passkey an assertion was made with
+pub struct VerifiedLogin {
+    pub user_id: i32,
+    /// The authenticator checked a PIN or biometrics, not just presence
+    pub user_verified: bool,
+}
+
+struct AuthenticatorData<'a> {
+    flags: u8,
+    sign_count: u32,
+    /// Credential ID and COSE key, only present during registration
+    attested: Option<(&'a [u8], &'a [u8])>,
+}
+
+/// Options for `navigator.credentials.create()` to add a passkey for `user`
+pub async fn registration_options(
+    conn: &mut PgConnection,
+    config: &WebauthnConfig,
+    user: &User,
+) -> Result<PasskeyOptions> {
+    let challenge = create_challenge(conn, "register", Some(user.id)).await?;
+    let exclude: Vec<_> = services::passkeys::credential_ids(conn, user.id)
+        .await?
+        .iter()
+        .map(|id| json!({ "type": "public-key", "id": URL_SAFE_NO_PAD.encode(id) }))
+        .collect();
+    let params: Vec<_> = ALGORITHMS
+        .iter()
+        .map(|alg| json!({ "type": "public-key", "alg": alg }))
+        .collect();
+
+    Ok(PasskeyOptions {
+        public_key: json!({
+            "challenge": challenge,
+            "rp": { "id": config.rp_id, "name": config.rp_name },
+            "user": {
+                "id": URL_SAFE_NO_PAD.encode(user_handle(user.id)),
+                "name": user.username,
+                "displayName": user.display_name.as_deref().unwrap_or(&user.username),
+            },
+            "pubKeyCredParams": params,
+            "excludeCredentials": exclude,
+            "authenticatorSelection": {
+                "residentKey": "preferred",
+                "userVerification": "preferred",
+            },
+            "attestation": "none",
+            "timeout": CHALLENGE_TTL_MINUTES * 60 * 1000,
+        }),
+    })
+}
+
+/// Check a new credential created for `user_id` with [`registration_options`]
+pub async fn verify_registration(
+    conn: &mut PgConnection,
+    config: &WebauthnConfig,
+    user_id: i32,
+    credential: &RegistrationCredential,
+) -> Result<NewCredential> {
+    let invalid = || AppError::BadRequest(Message::new("error.invalid_passkey_registration"));
+
+    let client_data_json = decode(&credential.response.client_data_json).ok_or_else(invalid)?;
+    let challenge_user = check_client_data(conn, config, &client_data_json, "webauthn.create")
+        .await?
+        .ok_or_else(invalid)?;
+    if challenge_user != Some(user_id) {
+        return Err(invalid());
+    }
+
+    let attestation = decode(&credential.response.attestation_object).ok_or_else(invalid)?;
+    let attestation: Cbor = ciborium::from_reader(attestation.as_slice()).map_err(|_| invalid())?;
+    let auth_data = map_get(&attestation, |key| key.as_text() == Some("authData"))
+        .and_then(Cbor::as_bytes)
+        .ok_or_else(invalid)?;
+    let data = parse_authenticator_data(config, auth_data).ok_or_else(invalid)?;
+    if data.flags & USER_PRESENT == 0 {
+        return Err(invalid());
+    }
+    let (credential_id, public_key) = data.attested.ok_or_else(invalid)?;
+    if decode(&credential.id).as_deref() != Some(credential_id) {
+        return Err(invalid());
+    }
+    let algorithm = cose_algorithm(public_key).ok_or_else(invalid)?;
+
+    Ok(NewCredential {
+        credential_id: credential_id.to_vec(),
+        public_key: public_key.to_vec(),
+        algorithm: algorithm as i32,
+        sign_count: i64::from(data.sign_count),
+        transports: credential.response.transports.clone(),
+    })
+}
+
+/// Options for `navigator.credentials.get()`. With a user, only their passkeys
+/// are allowed; without one the authenticator offers the passkeys it stores.
+pub async fn login_options(
+    conn: &mut PgConnection,
+    config: &WebauthnConfig,
+    user_id: Option<i32>,
+) -> Result<PasskeyOptions> {
+    let challenge = create_challenge(conn, "login", user_id).await?;
+    let allow: Vec<_> = match user_id {
+        Some(user_id) => services::passkeys::credential_ids(conn, user_id)
+            .await?
+            .iter()
+            .map(|id| json!({ "type": "public-key", "id": URL_SAFE_NO_PAD.encode(id) }))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(PasskeyOptions {
+        public_key: json!({
+            "challenge": challenge,
+            "rpId": config.rp_id,
+            "allowCredentials": allow,
+            "userVerification": "preferred",
+            "timeout": CHALLENGE_TTL_MINUTES * 60 * 1000,
+        }),
+    })
+}
+
+/// Check an assertion made with [`login_options`] and record the passkey's use
+pub async fn verify_login(
+    conn: &mut PgConnection,
+    config: &WebauthnConfig,
+    credential: &AssertionCredential,
+) -> Result<VerifiedLogin> {
+    let response = &credential.response;
+    let (Some(credential_id), Some(client_data_json), Some(auth_data), Some(signature)) = (
+        decode(&credential.id),
+        decode(&response.client_data_json),
+        decode(&response.authenticator_data),
+        decode(&response.signature),
+    ) else {
+        return Err(AuthError::InvalidPasskey.into());
+    };
+
+    let challenge_user = check_client_data(conn, config, &client_data_json, "webauthn.get")
+        .await?
+        .ok_or(AuthError::InvalidPasskey)?;
+    let passkey = services::passkeys::find_by_credential(conn, &credential_id)
+        .await?
+        .ok_or(AuthError::InvalidPasskey)?;
+    if challenge_user.is_some_and(|user_id| user_id != passkey.user_id) {
+        return Err(AuthError::InvalidPasskey.into());
+    }
+    if let Some(handle) = &response.user_handle {
+        if decode(handle) != Some(user_handle(passkey.user_id)) {
+            return Err(AuthError::InvalidPasskey.into());
+        }
+    }
+
+    let data = parse_authenticator_data(config, &auth_data).ok_or(AuthError::InvalidPasskey)?;
+    if data.flags & USER_PRESENT == 0 {
+        return Err(AuthError::InvalidPasskey.into());
+    }
+
+    let mut message = auth_data.clone();
+    message.extend_from_slice(&Sha256::digest(&client_data_json));
+    if !verify_signature(&passkey.public_key, &message, &signature) {
+        return Err(AuthError::InvalidPasskey.into());
+    }
+
+    let sign_count = i64::from(data.sign_count);
+    if !sign_count_advanced(passkey.sign_count, sign_count) {
+        tracing::warn!(
+            passkey_id = passkey.id,
+            user_id = passkey.user_id,
+            "Rejected passkey with a signature counter that went backwards"
+        );
+        return Err(AuthError::InvalidPasskey.into());
+    }
+    services::passkeys::record_use(conn, passkey.id, sign_count).await?;
+
+    Ok(VerifiedLogin {
+        user_id: passkey.user_id,
+        user_verified: data.flags & USER_VERIFIED != 0,
+    })
+}
+
+/// Authenticators that count signatures must count up; a lower count means
+/// the key was cloned. Synced passkeys always report zero.
+fn sign_count_advanced(stored: i64, reported: i64) -> bool {
+    reported > stored || (reported == 0 && stored == 0)
+}
+
+async fn create_challenge(
+    conn: &mut PgConnection,
+    purpose: &str,
+    user_id: Option<i32>,
+) -> Result<String> {
+    let challenge = session::generate_token();
+
+    sqlx::query(
+        r#"
+        INSERT INTO webauthn_challenges (challenge_hash, purpose, user_id, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(session::hash_token(&challenge))
+    .bind(purpose)
+    .bind(user_id)
+    .bind(Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES))
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(challenge)
+}
+
+/// Check type and origin of the client data and use up its challenge. Returns
+/// the user the challenge was issued for, or `None` if the data is invalid.
+async fn check_client_data(
+    conn: &mut PgConnection,
+    config: &WebauthnConfig,
+    client_data_json: &[u8],
+    kind: &str,
+) -> Result<Option<Option<i32>>> {
+    let Ok(client_data) = serde_json::from_slice::<ClientData>(client_data_json) else {
+        return Ok(None);
+    };
+    if client_data.kind != kind
+        || client_data.cross_origin
+        || !config.origins.contains(&client_data.origin)
+    {
+        return Ok(None);
+    }
+
+    let purpose = if kind == "webauthn.create" {
+        "register"
+    } else {
+        "login"
+    };
+    let challenge_user = sqlx::query_scalar::<_, Option<i32>>(
+        r#"
+        DELETE FROM webauthn_challenges
+        WHERE challenge_hash = $1 AND purpose = $2 AND expires_at > now()
+        RETURNING user_id
+        "#,
+    )
+    .bind(session::hash_token(&client_data.challenge))
+    .bind(purpose)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(challenge_user)
+}
+
+/// Parse authenticator data made for our relying party ID
+fn parse_authenticator_data<'a>(
+    config: &WebauthnConfig,
+    data: &'a [u8],
+) -> Option<AuthenticatorData<'a>> {
+    if data.len() < 37 || data[..32] != Sha256::digest(config.rp_id.as_bytes())[..] {
+        return None;
+    }
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes(data[33..37].try_into().ok()?);
+
+    let attested = if flags & ATTESTED_CREDENTIAL != 0 {
+        // AAGUID, then the length-prefixed credential ID and the COSE key
+        let rest = data.get(37 + 16..)?;
+        let id_len = usize::from(u16::from_be_bytes(rest.get(..2)?.try_into().ok()?));
+        let credential_id = rest.get(2..2 + id_len)?;
+        let key_start = rest.get(2 + id_len..)?;
+
+        // The key is followed by extensions, if any; it ends where its CBOR does
+        let mut remaining = key_start;
+        ciborium::from_reader::<Cbor, _>(&mut remaining).ok()?;
+        let key_len = key_start.len() - remaining.len();
+        Some((credential_id, &key_start[..key_len]))
+    } else {
+        None
+    };
+
+    Some(AuthenticatorData {
+        flags,
+        sign_count,
+        attested,
+    })
+}
+
+/// The algorithm of a COSE key, if it is one we can verify signatures with
+fn cose_algorithm(key: &[u8]) -> Option<i64> {
+    let key: Cbor = ciborium::from_reader(key).ok()?;
+    let algorithm = cose_int(&key, 3)?;
+    (ALGORITHMS.contains(&algorithm) && public_key(&key, algorithm).is_some()).then_some(algorithm)
+}
+
+enum PublicKey {
+    Ec(Vec<u8>),
+    Ed25519(Vec<u8>),
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+}
+
+fn public_key(key: &Cbor, algorithm: i64) -> Option<PublicKey> {
+    let bytes = |label| cose_bytes(key, label).map(<[u8]>::to_vec);
+    match (algorithm, cose_int(key, 1)?) {
+        // EC2 on P-256, as an uncompressed point
+        (ES256, 2) if cose_int(key, -1)? == 1 => {
+            let (x, y) = (bytes(-2)?, bytes(-3)?);
+            if x.len() != 32 || y.len() != 32 {
+                return None;
+            }
+            Some(PublicKey::Ec([&[0x04], &x[..], &y[..]].concat()))
+        }
+        // OKP on Ed25519
+        (EDDSA, 1) if cose_int(key, -1)? == 6 => Some(PublicKey::Ed25519(bytes(-2)?)),
+        (RS256, 3) => Some(PublicKey::Rsa {
+            n: bytes(-1)?,
+            e: bytes(-2)?,
+        }),
+        _ => None,
+    }
+}
+
+fn verify_signature(cose_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let Ok(key) = ciborium::from_reader::<Cbor, _>(cose_key) else {
+        return false;
+    };
+    let Some(public_key) = cose_int(&key, 3).and_then(|alg| public_key(&key, alg)) else {
+        return false;
+    };
+
+    match public_key {
+        PublicKey::Ec(point) => UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+            .verify(message, signature)
+            .is_ok(),
+        PublicKey::Ed25519(key) => UnparsedPublicKey::new(&signature::ED25519, key)
+            .verify(message, signature)
+            .is_ok(),
+        PublicKey::Rsa { n, e } => RsaPublicKeyComponents { n, e }
+            .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+            .is_ok(),
+    }
+}
+
+fn map_get(map: &Cbor, key: impl Fn(&Cbor) -> bool) -> Option<&Cbor> {
+    map.as_map()?
+        .iter()
+        .find(|(k, _)| key(k))
+        .map(|(_, value)| value)
+}
+
+fn cose_label(label: i64) -> impl Fn(&Cbor) -> bool {
+    move |key| key.as_integer().and_then(|k| i64::try_from(k).ok()) == Some(label)
+}
+
+fn cose_int(key: &Cbor, label: i64) -> Option<i64> {
+    map_get(key, cose_label(label))?
+        .as_integer()
+        .and_then(|value| i64::try_from(value).ok())
+}
+
+fn cose_bytes(key: &Cbor, label: i64) -> Option<&[u8]> {
+    map_get(key, cose_label(label))?
+        .as_bytes()
+        .map(Vec::as_slice)
+}
+
+/// The WebAuthn user handle; it must not contain the username
+fn user_handle(user_id: i32) -> Vec<u8> {
+    user_id.to_string().into_bytes()
+}
+
+/// Browsers send base64url without padding, some libraries add it
+fn decode(value: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+    };
+    use sqlx::{PgPool, Postgres, Transaction};
+
+    use super::*;
+    use crate::models::passkey::{AssertionResponse, AttestationResponse};
+
+    const RP_ID: &str = "lister.example.com";
+    const ORIGIN: &str = "https://lister.example.com";
+
+    fn config() -> WebauthnConfig {
+        WebauthnConfig {
+            rp_id: RP_ID.to_string(),
+            rp_name: "Lister".to_string(),
+            origins: vec![ORIGIN.to_string()],
+        }
+    }
+
+    fn to_cbor(value: &Cbor) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn cose_key(entries: &[(i64, Cbor)]) -> Vec<u8> {
+        to_cbor(&Cbor::Map(
+            entries
+                .iter()
+                .map(|(label, value)| (Cbor::Integer((*label).into()), value.clone()))
+                .collect(),
+        ))
+    }
+
+    fn int(value: i64) -> Cbor {
+        Cbor::Integer(value.into())
+    }
+
+    fn text(value: &str) -> Cbor {
+        Cbor::Text(value.to_string())
+    }
+
+    fn client_data(kind: &str, challenge: &str) -> Vec<u8> {
+        json!({ "type": kind, "challenge": challenge, "origin": ORIGIN, "crossOrigin": false })
+            .to_string()
+            .into_bytes()
+    }
+
+    enum Signer {
+        Ec(EcdsaKeyPair),
+        Ed25519(Ed25519KeyPair),
+    }
+
+    /// A software authenticator holding one credential
+    struct Authenticator {
+        credential_id: Vec<u8>,
+        signer: Signer,
+        sign_count: u32,
+    }
+
+    impl Authenticator {
+        fn es256() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            let pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            Authenticator {
+                credential_id: session::generate_token().into_bytes(),
+                signer: Signer::Ec(pair),
+                sign_count: 0,
+            }
+        }
+
+        fn ed25519() -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            Authenticator {
+                credential_id: session::generate_token().into_bytes(),
+                signer: Signer::Ed25519(Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()),
+                sign_count: 0,
+            }
+        }
+
+        fn algorithm(&self) -> i64 {
+            match self.signer {
+                Signer::Ec(_) => ES256,
+                Signer::Ed25519(_) => EDDSA,
+            }
+        }
+
+        fn public_key(&self) -> Vec<u8> {
+            match &self.signer {
+                Signer::Ec(pair) => {
+                    let point = pair.public_key().as_ref();
+                    cose_key(&[
+                        (1, int(2)),
+                        (3, int(ES256)),
+                        (-1, int(1)),
+                        (-2, Cbor::Bytes(point[1..33].to_vec())),
+                        (-3, Cbor::Bytes(point[33..].to_vec())),
+                    ])
+                }
+                Signer::Ed25519(pair) => cose_key(&[
+                    (1, int(1)),
+                    (3, int(EDDSA)),
+                    (-1, int(6)),
+                    (-2, Cbor::Bytes(pair.public_key().as_ref().to_vec())),
+                ]),
+            }
+        }
+
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            match &self.signer {
+                Signer::Ec(pair) => pair
+                    .sign(&SystemRandom::new(), message)
+                    .unwrap()
+                    .as_ref()
+                    .to_vec(),
+                Signer::Ed25519(pair) => pair.sign(message).as_ref().to_vec(),
+            }
+        }
+
+        /// Authenticator data for `rp_id`, with the credential if `flags` say so
+        fn authenticator_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+            let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+            data.push(flags);
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            if flags & ATTESTED_CREDENTIAL != 0 {
+                data.extend_from_slice(&[0; 16]);
+                let id_len = u16::try_from(self.credential_id.len()).unwrap();
+                data.extend_from_slice(&id_len.to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                data.extend_from_slice(&self.public_key());
+            }
+            data
+        }
+
+        /// What `navigator.credentials.create()` returns
+        fn create(&self, challenge: &str) -> RegistrationCredential {
+            let auth_data = self.authenticator_data(RP_ID, USER_PRESENT | ATTESTED_CREDENTIAL);
+            let attestation = Cbor::Map(vec![
+                (text("fmt"), text("none")),
+                (text("attStmt"), Cbor::Map(Vec::new())),
+                (text("authData"), Cbor::Bytes(auth_data)),
+            ]);
+
+            RegistrationCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: AttestationResponse {
+                    client_data_json: URL_SAFE_NO_PAD
+                        .encode(client_data("webauthn.create", challenge)),
+                    attestation_object: URL_SAFE_NO_PAD.encode(to_cbor(&attestation)),
+                    transports: vec!["internal".to_string()],
+                },
+            }
+        }
+
+        /// What `navigator.credentials.get()` returns, counting the signature
+        fn get(&mut self, challenge: &str, user_id: i32) -> AssertionCredential {
+            self.sign_count += 1;
+            let client_data_json = client_data("webauthn.get", challenge);
+            let auth_data = self.authenticator_data(RP_ID, USER_PRESENT | USER_VERIFIED);
+            let message = [&auth_data[..], &Sha256::digest(&client_data_json)].concat();
+
+            AssertionCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: AssertionResponse {
+                    client_data_json: URL_SAFE_NO_PAD.encode(client_data_json),
+                    authenticator_data: URL_SAFE_NO_PAD.encode(auth_data),
+                    signature: URL_SAFE_NO_PAD.encode(self.sign(&message)),
+                    user_handle: Some(URL_SAFE_NO_PAD.encode(user_handle(user_id))),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn parses_attested_credentials() {
+        let authenticator = Authenticator::es256();
+        let flags = USER_PRESENT | ATTESTED_CREDENTIAL | 0x80;
+        let mut data = authenticator.authenticator_data(RP_ID, flags);
+        // Extensions follow the key
+        data.extend_from_slice(&to_cbor(&Cbor::Map(vec![(text("credProtect"), int(1))])));
+
+        let parsed = parse_authenticator_data(&config(), &data).unwrap();
+        assert_eq!(parsed.flags, flags);
+        assert_eq!(parsed.sign_count, 0);
+        let (credential_id, public_key) = parsed.attested.unwrap();
+        assert_eq!(credential_id, authenticator.credential_id);
+        assert_eq!(public_key, authenticator.public_key());
+    }
+
+    #[test]
+    fn parses_assertion_data() {
+        let mut authenticator = Authenticator::ed25519();
+        authenticator.sign_count = 0x0102_0304;
+        let data = authenticator.authenticator_data(RP_ID, USER_PRESENT);
+
+        let parsed = parse_authenticator_data(&config(), &data).unwrap();
+        assert_eq!(parsed.flags, USER_PRESENT);
+        assert_eq!(parsed.sign_count, 0x0102_0304);
+        assert!(parsed.attested.is_none());
+    }
+
+    #[test]
+    fn rejects_data_for_another_relying_party() {
+        let authenticator = Authenticator::es256();
+        let data = authenticator.authenticator_data("lister.example.org", USER_PRESENT);
+        assert!(parse_authenticator_data(&config(), &data).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let authenticator = Authenticator::es256();
+        let data = authenticator.authenticator_data(RP_ID, USER_PRESENT);
+        assert!(parse_authenticator_data(&config(), &data[..36]).is_none());
+
+        let data = authenticator.authenticator_data(RP_ID, USER_PRESENT | ATTESTED_CREDENTIAL);
+        // Inside the credential ID, then inside the key
+        let id_end = 37 + 16 + 2 + authenticator.credential_id.len();
+        assert!(parse_authenticator_data(&config(), &data[..id_end - 1]).is_none());
+        assert!(parse_authenticator_data(&config(), &data[..data.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn verifies_es256_and_eddsa_signatures() {
+        for authenticator in [Authenticator::es256(), Authenticator::ed25519()] {
+            let key = authenticator.public_key();
+            let signature = authenticator.sign(b"message");
+            assert_eq!(cose_algorithm(&key), Some(authenticator.algorithm()));
+            assert!(verify_signature(&key, b"message", &signature));
+            assert!(!verify_signature(&key, b"massage", &signature));
+
+            let other = Authenticator::es256().public_key();
+            assert!(!verify_signature(&other, b"message", &signature));
+            assert!(!verify_signature(b"not cbor", b"message", &signature));
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_keys() {
+        let authenticator = Authenticator::es256();
+        let Signer::Ec(pair) = &authenticator.signer else {
+            unreachable!()
+        };
+        let point = pair.public_key().as_ref();
+        let x = Cbor::Bytes(point[1..33].to_vec());
+        let y = Cbor::Bytes(point[33..].to_vec());
+
+        // P-384 and a short coordinate
+        let p384 = cose_key(&[
+            (1, int(2)),
+            (3, int(ES256)),
+            (-1, int(2)),
+            (-2, x.clone()),
+            (-3, y.clone()),
+        ]);
+        assert_eq!(cose_algorithm(&p384), None);
+        let short = cose_key(&[
+            (1, int(2)),
+            (3, int(ES256)),
+            (-1, int(1)),
+            (-2, Cbor::Bytes(point[2..33].to_vec())),
+            (-3, y.clone()),
+        ]);
+        assert_eq!(cose_algorithm(&short), None);
+        // ES384
+        let es384 = cose_key(&[(1, int(2)), (3, int(-35)), (-1, int(1)), (-2, x), (-3, y)]);
+        assert_eq!(cose_algorithm(&es384), None);
+    }
+
+    #[test]
+    fn sign_counts_must_go_up() {
+        assert!(sign_count_advanced(0, 0));
+        assert!(sign_count_advanced(0, 1));
+        assert!(sign_count_advanced(5, 6));
+        assert!(!sign_count_advanced(5, 5));
+        assert!(!sign_count_advanced(5, 3));
+        assert!(!sign_count_advanced(5, 0));
+    }
+
+    /// A transaction that is rolled back when dropped, with a new user
+    async fn database(username: &str) -> (Transaction<'static, Postgres>, User) {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&url).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let user = services::users::create(&mut tx, username, "password123", None, false)
+            .await
+            .unwrap();
+        (tx, user)
+    }
+
+    fn challenge_of(options: PasskeyOptions) -> String {
+        options.public_key["challenge"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    async fn register(
+        conn: &mut PgConnection,
+        user: &User,
+        authenticator: &Authenticator,
+    ) -> NewCredential {
+        let challenge = challenge_of(registration_options(conn, &config(), user).await.unwrap());
+        let credential =
+            verify_registration(conn, &config(), user.id, &authenticator.create(&challenge))
+                .await
+                .unwrap();
+        services::passkeys::create(conn, user.id, "Laptop", &credential)
+            .await
+            .unwrap();
+        credential
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn registers_and_signs_in_with_passkeys() {
+        let (mut tx, user) = database("passkey-ceremony").await;
+
+        for mut authenticator in [Authenticator::es256(), Authenticator::ed25519()] {
+            let credential = register(&mut tx, &user, &authenticator).await;
+            assert_eq!(credential.credential_id, authenticator.credential_id);
+            assert_eq!(credential.public_key, authenticator.public_key());
+            assert_eq!(i64::from(credential.algorithm), authenticator.algorithm());
+
+            for user_id in [None, Some(user.id)] {
+                let challenge =
+                    challenge_of(login_options(&mut tx, &config(), user_id).await.unwrap());
+                let login =
+                    verify_login(&mut tx, &config(), &authenticator.get(&challenge, user.id))
+                        .await
+                        .unwrap();
+                assert_eq!(login.user_id, user.id);
+                assert!(login.user_verified);
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn challenges_are_used_once() {
+        let (mut tx, user) = database("passkey-challenge").await;
+        let mut authenticator = Authenticator::es256();
+
+        let challenge = challenge_of(
+            registration_options(&mut tx, &config(), &user)
+                .await
+                .unwrap(),
+        );
+        let credential = authenticator.create(&challenge);
+        assert!(
+            verify_registration(&mut tx, &config(), user.id, &credential)
+                .await
+                .is_ok()
+        );
+        assert!(
+            verify_registration(&mut tx, &config(), user.id, &credential)
+                .await
+                .is_err()
+        );
+        register(&mut tx, &user, &authenticator).await;
+
+        let challenge = challenge_of(login_options(&mut tx, &config(), None).await.unwrap());
+        let assertion = authenticator.get(&challenge, user.id);
+        assert!(verify_login(&mut tx, &config(), &assertion).await.is_ok());
+        // A new signature, which would pass the counter check, on the same challenge
+        let assertion = authenticator.get(&challenge, user.id);
+        assert!(verify_login(&mut tx, &config(), &assertion).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn rejects_sign_counts_that_go_back() {
+        let (mut tx, user) = database("passkey-counter").await;
+        let mut authenticator = Authenticator::es256();
+        register(&mut tx, &user, &authenticator).await;
+
+        authenticator.sign_count = 41;
+        let challenge = challenge_of(login_options(&mut tx, &config(), None).await.unwrap());
+        let assertion = authenticator.get(&challenge, user.id);
+        assert!(verify_login(&mut tx, &config(), &assertion).await.is_ok());
+
+        // A clone of the key that signed less often
+        authenticator.sign_count = 40;
+        let challenge = challenge_of(login_options(&mut tx, &config(), None).await.unwrap());
+        let assertion = authenticator.get(&challenge, user.id);
+        assert!(verify_login(&mut tx, &config(), &assertion).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn rejects_assertions_for_another_relying_party() {
+        let (mut tx, user) = database("passkey-relying-party").await;
+        let mut authenticator = Authenticator::ed25519();
+        register(&mut tx, &user, &authenticator).await;
+
+        let challenge = challenge_of(login_options(&mut tx, &config(), None).await.unwrap());
+        let mut assertion = authenticator.get(&challenge, user.id);
+        // Signed over data for another relying party, so only the hash is wrong
+        let client_data_json = decode(&assertion.response.client_data_json).unwrap();
+        let auth_data = authenticator.authenticator_data("lister.example.org", USER_PRESENT);
+        let message = [&auth_data[..], &Sha256::digest(&client_data_json)].concat();
+        assertion.response.authenticator_data = URL_SAFE_NO_PAD.encode(auth_data);
+        assertion.response.signature = URL_SAFE_NO_PAD.encode(authenticator.sign(&message));
+        assert!(verify_login(&mut tx, &config(), &assertion).await.is_err());
+    }
+}
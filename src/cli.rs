@@ -0,0 +1,178 @@
+//! Management commands, run instead of the server when arguments are given:
+//!
+//! ```text
+//! ultimatelister-api tokens list
+//! ultimatelister-api tokens create <name> --scope <scope>... [--expires-in-days <days>] [--household <id>]
+//!                                   [--certificate <identity>]
+//! ultimatelister-api tokens revoke <id>
+//! ```
+
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    error::AppError,
+    models::{ApiToken, CreateApiTokenRequest},
+    services,
+    validation::Validate,
+};
+
+const USAGE: &str = "\
+Usage:
+  ultimatelister-api                      Run the server
+  ultimatelister-api tokens list          List API tokens
+  ultimatelister-api tokens create <name> --scope <scope>... [--expires-in-days <days>]
+                                   [--household <id>] [--certificate <identity>]
+                                          Create an API token and print it
+  ultimatelister-api tokens revoke <id>   Revoke an API token
+
+Scopes: read, write, admin, list:<id>:read, list:<id>:write
+Tokens belong to the oldest household unless --household is given
+Certificate identities: CN=<common name>, DNS:<name>, URI:<uri>";
+
+pub async fn run(pool: &PgPool, args: &[String]) -> anyhow::Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["tokens", "list"] => list_tokens(pool).await,
+        ["tokens", "create", name, options @ ..] => create_token(pool, name, options).await,
+        ["tokens", "revoke", id] => {
+            let id = id
+                .parse()
+                .with_context(|| format!("Invalid token ID: {id}"))?;
+            let mut conn = pool.acquire().await?;
+            let api_token = services::api_tokens::revoke(&mut conn, id)
+                .await
+                .map_err(app_error)?;
+            println!("Revoked token {} ({})", api_token.id, api_token.name);
+            Ok(())
+        }
+        ["help" | "--help" | "-h"] => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => bail!("Unknown command\n\n{USAGE}"),
+    }
+}
+
+async fn list_tokens(pool: &PgPool) -> anyhow::Result<()> {
+    let mut conn = pool.acquire().await?;
+    let api_tokens = services::api_tokens::list(&mut conn)
+        .await
+        .map_err(app_error)?;
+
+    println!(
+        "{:>4}  {:<24}  {:<30}  {:<10}  {:<10}  STATUS",
+        "ID", "NAME", "SCOPES", "EXPIRES", "LAST USED"
+    );
+    for api_token in &api_tokens {
+        println!(
+            "{:>4}  {:<24}  {:<30}  {:<10}  {:<10}  {}",
+            api_token.id,
+            api_token.name,
+            api_token.scopes.join(","),
+            date(api_token.expires_at),
+            date(api_token.last_used_at),
+            status(api_token),
+        );
+    }
+    Ok(())
+}
+
+async fn create_token(pool: &PgPool, name: &str, options: &[&str]) -> anyhow::Result<()> {
+    let mut request = CreateApiTokenRequest {
+        name: name.to_string(),
+        scopes: Vec::new(),
+        expires_in_days: None,
+        household_id: None,
+        client_certificate: None,
+    };
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or_else(|| anyhow!("Missing value for {option}"))?;
+        match *option {
+            "--scope" => request.scopes.push(value.to_string()),
+            "--expires-in-days" => {
+                request.expires_in_days = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Invalid number of days: {value}"))?,
+                );
+            }
+            "--household" => {
+                request.household_id = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Invalid household ID: {value}"))?,
+                );
+            }
+            "--certificate" => request.client_certificate = Some(value.to_string()),
+            _ => bail!("Unknown option {option}\n\n{USAGE}"),
+        }
+    }
+    request.validated().map_err(app_error)?;
+
+    let expires_at = request
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(days));
+
+    let mut conn = pool.acquire().await?;
+    let household_id = match request.household_id {
+        Some(id) => services::households::find(&mut conn, id)
+            .await
+            .map_err(|error| match error {
+                AppError::NotFound => anyhow!("Household {id} does not exist"),
+                error => app_error(error),
+            })?
+            .id,
+        None => sqlx::query_scalar::<_, Option<i32>>("SELECT min(id) FROM households")
+            .fetch_one(&mut *conn)
+            .await?
+            .ok_or_else(|| anyhow!("No household exists"))?,
+    };
+    let created = services::api_tokens::create(
+        &mut conn,
+        household_id,
+        &request.name,
+        &request.scopes,
+        request.client_certificate.as_deref(),
+        None,
+        expires_at,
+    )
+    .await
+    .map_err(app_error)?;
+
+    println!(
+        "Created token {} ({})",
+        created.api_token.id, created.api_token.name
+    );
+    if let Some(token) = &created.token {
+        println!("{token}");
+        eprintln!("Store the token now; it cannot be shown again.");
+    } else if let Some(identity) = &created.api_token.client_certificate {
+        println!("Used by clients presenting a certificate for {identity}");
+    }
+    Ok(())
+}
+
+fn date(value: Option<DateTime<Utc>>) -> String {
+    value.map_or("-".to_string(), |d| d.format("%Y-%m-%d").to_string())
+}
+
+fn status(api_token: &ApiToken) -> &'static str {
+    if api_token.revoked_at.is_some() {
+        "revoked"
+    } else if api_token.expires_at.is_some_and(|at| at <= Utc::now()) {
+        "expired"
+    } else {
+        "active"
+    }
+}
+
+fn app_error(error: AppError) -> anyhow::Error {
+    anyhow!(error.detail())
+}
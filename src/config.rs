@@ -1,4 +1,10 @@
-use std::env;
+use std::{env, net::IpAddr};
+
+use anyhow::Context;
+use http::HeaderName;
+use ipnet::IpNet;
+
+use crate::models::Quotas;
 
 #[derive(Clone)]
 pub struct Config {
@@ -6,10 +12,99 @@ pub struct Config {
     pub host: String,
     pub port: u16,
     pub auth_token: Option<String>,
+    pub request_timeout_secs: u64,
+    pub jobs: Vec<JobConfig>,
+    /// Lifetime of browser sessions and refresh tokens
+    pub session_ttl_days: i64,
+    pub access_token_ttl_minutes: i64,
+    /// How long the `purge_audit_log` job keeps audit log entries
+    pub audit_retention_days: i32,
+    /// How long the `purge_tombstones` job keeps tombstones of deleted rows
+    pub tombstone_retention_days: i32,
+    /// Default quotas of all households, unlimited unless set
+    pub quotas: Quotas,
+    /// Mark session cookies `Secure` (requires HTTPS), always when serving TLS
+    pub cookie_secure: bool,
+    /// Single sign-on, enabled by `OIDC_ISSUER`
+    pub oidc: Option<OidcConfig>,
+    /// Passkeys, enabled by `WEBAUTHN_ORIGIN`
+    pub webauthn: Option<WebauthnConfig>,
+    /// Users authenticated by a reverse proxy, enabled by `PROXY_AUTH_TRUSTED_NETWORKS`
+    pub proxy_auth: Option<ProxyAuthConfig>,
+    /// HTTPS, enabled by `TLS_CERT` and `TLS_KEY`
+    pub tls: Option<TlsConfig>,
+}
+
+/// A background job enabled via `JOBS`, optionally with a custom schedule
+#[derive(Clone)]
+pub struct JobConfig {
+    pub name: String,
+    pub schedule: Option<String>,
+}
+
+/// An OpenID Connect provider that users can sign in with
+#[derive(Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// Without a secret the API signs in as a public client, relying on PKCE
+    pub client_secret: Option<String>,
+    /// Our callback URL, `.../api/auth/oidc/callback`, as registered at the provider
+    pub redirect_url: String,
+    pub scopes: String,
+    /// Accepted `aud` values of bearer JWTs; defaults to the client ID
+    pub audiences: Vec<String>,
+    pub username_claim: String,
+    pub groups_claim: String,
+    pub groups: GroupMapping,
+}
+
+/// How groups of an external identity translate into roles here
+#[derive(Clone)]
+pub struct GroupMapping {
+    /// Members of these groups are admins; admin rights are left alone if empty
+    pub admin_groups: Vec<String>,
+    /// Group name and the name of the household its members join
+    pub household_groups: Vec<(String, String)>,
+}
+
+/// A reverse proxy such as Authelia or oauth2-proxy that authenticates users
+/// and forwards who they are in request headers
+#[derive(Clone)]
+pub struct ProxyAuthConfig {
+    /// Only requests from these networks may set the headers
+    pub trusted_networks: Vec<IpNet>,
+    pub user_header: HeaderName,
+    pub groups_header: HeaderName,
+    pub name_header: HeaderName,
+    pub groups: GroupMapping,
+}
+
+/// PEM files of the server certificate and of the CAs that client certificates
+/// are checked against
+#[derive(Clone)]
+pub struct TlsConfig {
+    /// Certificate chain, starting with the server's own certificate
+    pub cert_path: String,
+    pub key_path: String,
+    /// Clients are asked for a certificate signed by one of these CAs; they
+    /// may still connect without one
+    pub client_ca_path: Option<String>,
+}
+
+/// The relying party that passkeys are registered for
+#[derive(Clone)]
+pub struct WebauthnConfig {
+    /// Domain the passkeys are bound to, e.g. `lister.example.com`
+    pub rp_id: String,
+    pub rp_name: String,
+    /// Origins the frontend is served from, e.g. `https://lister.example.com`
+    pub origins: Vec<String>,
 }
 
 impl Config {
     pub fn from_env() -> anyhow::Result<Self> {
+        let tls = tls_from_env()?;
         Ok(Self {
             database_url: env::var("DATABASE_URL")
                 .unwrap_or_else(|_| {
@@ -21,7 +116,206 @@ impl Config {
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()?,
             auth_token: env::var("AUTH_TOKEN").ok(),
+            request_timeout_secs: env::var("REQUEST_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()?,
+            jobs: jobs_from_env(),
+            session_ttl_days: env::var("SESSION_TTL_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()?,
+            access_token_ttl_minutes: env::var("ACCESS_TOKEN_TTL_MINUTES")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()?,
+            audit_retention_days: env::var("AUDIT_RETENTION_DAYS")
+                .unwrap_or_else(|_| "365".to_string())
+                .parse()?,
+            tombstone_retention_days: env::var("TOMBSTONE_RETENTION_DAYS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()?,
+            quotas: quotas_from_env()?,
+            // Serving HTTPS ourselves, cookies must never go out over plain HTTP
+            cookie_secure: tls.is_some()
+                || env::var("COOKIE_SECURE").is_ok_and(|v| v == "true" || v == "1"),
+            oidc: oidc_from_env()?,
+            webauthn: webauthn_from_env()?,
+            proxy_auth: proxy_auth_from_env()?,
+            tls,
+        })
+    }
+}
+
+/// `OIDC_ISSUER` enables single sign-on and requires `OIDC_CLIENT_ID` and `OIDC_REDIRECT_URL`
+fn oidc_from_env() -> anyhow::Result<Option<OidcConfig>> {
+    let Ok(issuer) = env::var("OIDC_ISSUER") else {
+        return Ok(None);
+    };
+    let client_id = env::var("OIDC_CLIENT_ID").context("OIDC_ISSUER requires OIDC_CLIENT_ID")?;
+
+    let audiences = match list_from_env("OIDC_AUDIENCE") {
+        audiences if audiences.is_empty() => vec![client_id.clone()],
+        audiences => audiences,
+    };
+
+    Ok(Some(OidcConfig {
+        issuer: issuer.trim_end_matches('/').to_string(),
+        redirect_url: env::var("OIDC_REDIRECT_URL")
+            .context("OIDC_ISSUER requires OIDC_REDIRECT_URL")?,
+        client_secret: env::var("OIDC_CLIENT_SECRET").ok(),
+        scopes: env::var("OIDC_SCOPES")
+            .unwrap_or_else(|_| "openid profile email groups".to_string()),
+        audiences,
+        username_claim: env::var("OIDC_USERNAME_CLAIM")
+            .unwrap_or_else(|_| "preferred_username".to_string()),
+        groups_claim: env::var("OIDC_GROUPS_CLAIM").unwrap_or_else(|_| "groups".to_string()),
+        groups: group_mapping_from_env("OIDC")?,
+        client_id,
+    }))
+}
+
+/// `<PREFIX>_ADMIN_GROUPS` and `<PREFIX>_HOUSEHOLD_GROUPS="family=Home,cabin=Cabin"`
+fn group_mapping_from_env(prefix: &str) -> anyhow::Result<GroupMapping> {
+    let household_groups = list_from_env(&format!("{prefix}_HOUSEHOLD_GROUPS"))
+        .into_iter()
+        .map(|pair| match pair.split_once('=') {
+            Some((group, household)) => {
+                Ok((group.trim().to_string(), household.trim().to_string()))
+            }
+            None => {
+                anyhow::bail!("{prefix}_HOUSEHOLD_GROUPS entries must look like group=Household")
+            }
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(GroupMapping {
+        admin_groups: list_from_env(&format!("{prefix}_ADMIN_GROUPS")),
+        household_groups,
+    })
+}
+
+/// `PROXY_AUTH_TRUSTED_NETWORKS=10.0.0.0/8,127.0.0.1` enables authentication by a reverse proxy;
+/// single addresses are accepted as well as networks
+fn proxy_auth_from_env() -> anyhow::Result<Option<ProxyAuthConfig>> {
+    let trusted_networks: Vec<IpNet> = list_from_env("PROXY_AUTH_TRUSTED_NETWORKS")
+        .iter()
+        .map(|network| {
+            network
+                .parse()
+                .or_else(|_| network.parse::<IpAddr>().map(IpNet::from))
+                .with_context(|| {
+                    format!("Invalid network in PROXY_AUTH_TRUSTED_NETWORKS: {network}")
+                })
         })
+        .collect::<anyhow::Result<_>>()?;
+    if trusted_networks.is_empty() {
+        return Ok(None);
     }
+
+    let header = |name: &str, default: &str| -> anyhow::Result<HeaderName> {
+        let value = env::var(name).unwrap_or_else(|_| default.to_string());
+        value
+            .parse()
+            .with_context(|| format!("Invalid header name in {name}: {value}"))
+    };
+
+    Ok(Some(ProxyAuthConfig {
+        trusted_networks,
+        user_header: header("PROXY_AUTH_USER_HEADER", "Remote-User")?,
+        groups_header: header("PROXY_AUTH_GROUPS_HEADER", "Remote-Groups")?,
+        name_header: header("PROXY_AUTH_NAME_HEADER", "Remote-Name")?,
+        groups: group_mapping_from_env("PROXY_AUTH")?,
+    }))
+}
+
+/// `TLS_CERT` and `TLS_KEY` enable HTTPS, `TLS_CLIENT_CA` client certificates on top
+fn tls_from_env() -> anyhow::Result<Option<TlsConfig>> {
+    let client_ca_path = env::var("TLS_CLIENT_CA").ok();
+    let Ok(cert_path) = env::var("TLS_CERT") else {
+        anyhow::ensure!(client_ca_path.is_none(), "TLS_CLIENT_CA requires TLS_CERT");
+        return Ok(None);
+    };
+
+    Ok(Some(TlsConfig {
+        cert_path,
+        key_path: env::var("TLS_KEY").context("TLS_CERT requires TLS_KEY")?,
+        client_ca_path,
+    }))
+}
+
+/// `WEBAUTHN_ORIGIN=https://lister.example.com` enables passkeys; the relying party ID
+/// defaults to the host of the first origin
+fn webauthn_from_env() -> anyhow::Result<Option<WebauthnConfig>> {
+    let origins: Vec<String> = list_from_env("WEBAUTHN_ORIGIN")
+        .into_iter()
+        .map(|origin| origin.trim_end_matches('/').to_string())
+        .collect();
+    let Some(first) = origins.first() else {
+        return Ok(None);
+    };
+
+    let rp_id = match env::var("WEBAUTHN_RP_ID") {
+        Ok(rp_id) => rp_id,
+        Err(_) => first
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .and_then(|rest| rest.split([':', '/']).next())
+            .filter(|host| !host.is_empty())
+            .context("WEBAUTHN_ORIGIN must look like https://host[:port]")?
+            .to_string(),
+    };
+
+    Ok(Some(WebauthnConfig {
+        rp_id,
+        rp_name: env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "Ultimate Lister".to_string()),
+        origins,
+    }))
+}
+
+/// `QUOTA_LISTS=20`, `QUOTA_ITEMS_PER_LIST`, `QUOTA_NAMES`, `QUOTA_CATEGORIES` and
+/// `QUOTA_REQUESTS_PER_DAY` limit every household unless overridden for it
+fn quotas_from_env() -> anyhow::Result<Quotas> {
+    let quota = |name: &str| -> anyhow::Result<Option<i64>> {
+        env::var(name)
+            .ok()
+            .map(|value| {
+                value
+                    .parse::<u32>()
+                    .map(i64::from)
+                    .with_context(|| format!("{name} must be a non-negative number"))
+            })
+            .transpose()
+    };
+
+    Ok(Quotas {
+        lists: quota("QUOTA_LISTS")?,
+        items_per_list: quota("QUOTA_ITEMS_PER_LIST")?,
+        names: quota("QUOTA_NAMES")?,
+        categories: quota("QUOTA_CATEGORIES")?,
+        requests_per_day: quota("QUOTA_REQUESTS_PER_DAY")?,
+    })
+}
+
+fn list_from_env(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `JOBS=recount_names,prune_names` enables jobs, `JOB_SCHEDULE_PRUNE_NAMES="0 0 4 * * Sun"`
+/// overrides the default schedule of a job
+fn jobs_from_env() -> Vec<JobConfig> {
+    env::var("JOBS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| JobConfig {
+            name: name.to_string(),
+            schedule: env::var(format!("JOB_SCHEDULE_{}", name.to_uppercase())).ok(),
+        })
+        .collect()
 }
 
@@ -1,44 +1,457 @@
 use axum::{
-    http::StatusCode,
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
-    Json,
 };
-use serde_json::json;
+use sqlx::{error::ErrorKind, postgres::PgDatabaseError};
 
+use crate::{auth::AuthError, i18n::Message, problem::Problem, validation::FieldError};
+
+/// Display renders the English message; clients get it in their language
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("Database error: {0}")]
-    Database(#[from] sqlx::Error),
+    Database(sqlx::Error),
 
-    #[error("Not found")]
+    #[error("{}", self.message())]
     NotFound,
 
-    #[error("Bad request: {0}")]
-    BadRequest(String),
+    #[error("{}", self.message())]
+    BadRequest(Message),
+
+    /// One entry per invalid field
+    #[error("{}", self.message())]
+    Validation(Vec<FieldError>),
+
+    /// Unique violation
+    #[error("{}", self.message())]
+    AlreadyExists {
+        /// Catalog key of the resource name, e.g. `resource.category`
+        resource: &'static str,
+        field: String,
+    },
+
+    /// Foreign key violation on insert/update: the referenced parent does not exist
+    #[error("{}", self.message())]
+    ParentNotFound {
+        parent: &'static str,
+        field: String,
+        /// The parent ID came from the URL rather than the request body
+        in_path: bool,
+    },
+
+    /// Foreign key violation on delete: the row is still referenced
+    #[error("{}", self.message())]
+    StillReferenced {
+        resource: &'static str,
+        referenced_by: &'static str,
+    },
+
+    /// Check or not-null violation
+    #[error("{}", self.message())]
+    ConstraintViolation { field: String },
+
+    #[error("{}", self.message())]
+    Conflict(Message),
+
+    /// `If-Match` does not match the row anymore, see [`crate::etag`]
+    #[error("{}", self.message())]
+    PreconditionFailed {
+        etag: String,
+        /// The row as it is now
+        current: serde_json::Value,
+    },
+
+    /// A household quota is used up, see [`crate::services::quotas`]
+    #[error("{}", self.message())]
+    QuotaExceeded {
+        /// `lists`, `itemsPerList`, `names`, `categories` or `requestsPerDay`
+        quota: &'static str,
+        limit: i64,
+    },
+
+    #[error("Invalid request body: {0}")]
+    InvalidBody(#[from] JsonRejection),
+
+    #[error("Invalid path parameter: {0}")]
+    InvalidPath(#[from] PathRejection),
+
+    #[error("Invalid query parameter: {0}")]
+    InvalidQuery(#[from] QueryRejection),
+
+    #[error("{}", self.message())]
+    Auth(AuthError),
 
     #[error("Internal server error")]
     Internal,
 }
 
+impl From<AuthError> for AppError {
+    fn from(error: AuthError) -> Self {
+        AppError::Auth(error)
+    }
+}
+
+/// Foreign keys whose value is taken from the request path,
+/// so a missing parent means the addressed resource does not exist
+const PATH_FOREIGN_KEYS: &[(&str, &str)] = &[
+    ("items", "list"),
+    ("household_members", "household_id"),
+    ("household_members", "user_id"),
+    ("household_quotas", "household_id"),
+    ("group_members", "group_id"),
+    ("group_members", "user_id"),
+    ("list_permissions", "list_id"),
+    ("list_permissions", "user_id"),
+    ("list_permissions", "group_id"),
+];
+
+impl From<sqlx::Error> for AppError {
+    /// Translates constraint violations into client errors, everything else stays a 500
+    fn from(error: sqlx::Error) -> Self {
+        let sqlx::Error::Database(ref db_err) = error else {
+            return AppError::Database(error);
+        };
+
+        let table = db_err.table().unwrap_or_default();
+        let pg_err = db_err.try_downcast_ref::<PgDatabaseError>();
+        let detail = pg_err.and_then(|e| e.detail()).unwrap_or_default();
+        let key_column = key_columns(detail)
+            .or_else(|| pg_err.and_then(|e| e.column()).map(str::to_string))
+            .unwrap_or_else(|| "value".to_string());
+
+        match db_err.kind() {
+            ErrorKind::UniqueViolation => AppError::AlreadyExists {
+                resource: resource_name(table),
+                field: key_column,
+            },
+            // "update or delete on table ... violates foreign key constraint"
+            ErrorKind::ForeignKeyViolation if db_err.message().starts_with("update or delete") => {
+                AppError::StillReferenced {
+                    resource: referenced_table(detail).map_or("resource.other", resource_name),
+                    referenced_by: resource_name(table),
+                }
+            }
+            ErrorKind::ForeignKeyViolation => AppError::ParentNotFound {
+                parent: referenced_table(detail).map_or("resource.referenced", resource_name),
+                in_path: PATH_FOREIGN_KEYS.contains(&(table, key_column.as_str())),
+                field: key_column,
+            },
+            ErrorKind::NotNullViolation => AppError::ConstraintViolation { field: key_column },
+            ErrorKind::CheckViolation => AppError::ConstraintViolation {
+                field: db_err
+                    .constraint()
+                    .map_or(key_column, |c| check_column(table, c).to_string()),
+            },
+            _ => AppError::Database(error),
+        }
+    }
+}
+
+/// Extracts `name` from a detail like `Key (name)=(Milch) already exists.`
+/// The household is left out of per-household keys like `(household_id, name)`.
+fn key_columns(detail: &str) -> Option<String> {
+    let rest = detail.strip_prefix("Key (")?;
+    let end = rest.find(")=")?;
+    let columns = &rest[..end];
+    Some(
+        columns
+            .strip_prefix("household_id, ")
+            .unwrap_or(columns)
+            .to_string(),
+    )
+}
+
+/// Extracts `lists` from a detail like `... is not present in table "lists".`
+fn referenced_table(detail: &str) -> Option<&str> {
+    let start = detail.find("table \"")? + "table \"".len();
+    let end = detail[start..].find('"')?;
+    Some(&detail[start..start + end])
+}
+
+/// Postgres names column checks `<table>_<column>_check`, fall back to the constraint name
+fn check_column<'a>(table: &str, constraint: &'a str) -> &'a str {
+    constraint
+        .strip_prefix(table)
+        .and_then(|c| c.strip_prefix('_'))
+        .and_then(|c| c.strip_suffix("_check"))
+        .unwrap_or(constraint)
+}
+
+/// Catalog key of the resource stored in `table`
+fn resource_name(table: &str) -> &'static str {
+    match table {
+        "lists" => "resource.list",
+        "items" => "resource.item",
+        "categories" => "resource.category",
+        "names" => "resource.name",
+        "users" => "resource.user",
+        "invitations" => "resource.invitation",
+        "api_tokens" => "resource.api_token",
+        "households" => "resource.household",
+        "groups" => "resource.group",
+        "passkeys" => "resource.passkey",
+        _ => "resource.other",
+    }
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Database(_) | AppError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::BadRequest(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::AlreadyExists { .. }
+            | AppError::StillReferenced { .. }
+            | AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+            AppError::ParentNotFound { in_path: true, .. } => StatusCode::NOT_FOUND,
+            // A full list cannot take the item, the household may still add others
+            AppError::QuotaExceeded {
+                quota: "itemsPerList",
+                ..
+            } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::QuotaExceeded { .. } => StatusCode::FORBIDDEN,
+            AppError::ParentNotFound { in_path: false, .. }
+            | AppError::ConstraintViolation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InvalidBody(rejection) => rejection.status(),
+            AppError::InvalidPath(rejection) => rejection.status(),
+            AppError::InvalidQuery(rejection) => rejection.status(),
+            AppError::Auth(error) => error.status(),
+        }
+    }
+
+    /// Stable, machine-readable error code (documented in openapi.yaml)
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "database_error",
+            AppError::NotFound => "not_found",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Validation(_) => "validation_failed",
+            AppError::AlreadyExists { .. } => "already_exists",
+            AppError::ParentNotFound { .. } => "parent_not_found",
+            AppError::StillReferenced { .. } => "still_referenced",
+            AppError::ConstraintViolation { .. } => "constraint_violation",
+            AppError::Conflict(_) => "conflict",
+            AppError::PreconditionFailed { .. } => "precondition_failed",
+            AppError::QuotaExceeded { .. } => "quota_exceeded",
+            AppError::InvalidBody(JsonRejection::MissingJsonContentType(_)) => {
+                "unsupported_media_type"
+            }
+            AppError::InvalidBody(JsonRejection::JsonSyntaxError(_)) => "malformed_json",
+            AppError::InvalidBody(_) => "invalid_body",
+            AppError::InvalidPath(_) => "invalid_path",
+            AppError::InvalidQuery(_) => "invalid_query",
+            AppError::Auth(error) => error.code(),
+            AppError::Internal => "internal_error",
+        }
+    }
+
+    /// Untranslated detail message
+    pub fn message(&self) -> Message {
+        match self {
+            // Never leak database internals to clients
+            AppError::Database(_) => Message::new("detail.database_error"),
+            AppError::NotFound => Message::new("detail.not_found"),
+            AppError::BadRequest(message) | AppError::Conflict(message) => message.clone(),
+            AppError::Validation(errors) => {
+                let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+                Message::new("detail.validation_failed").arg("fields", fields.join(", "))
+            }
+            AppError::AlreadyExists { resource, field } => Message::new("detail.already_exists")
+                .arg_key("resource", resource)
+                .arg("field", field),
+            AppError::ParentNotFound { parent, .. } => {
+                Message::new("detail.parent_not_found").arg_key("parent", parent)
+            }
+            AppError::StillReferenced {
+                resource,
+                referenced_by,
+            } => Message::new("detail.still_referenced")
+                .arg_key("resource", resource)
+                .arg_key("referenced_by", referenced_by),
+            AppError::ConstraintViolation { field } => {
+                Message::new("detail.constraint_violation").arg("field", field)
+            }
+            AppError::PreconditionFailed { .. } => Message::new("detail.precondition_failed"),
+            AppError::QuotaExceeded { quota, limit } => {
+                Message::new(format!("detail.quota_exceeded.{quota}")).arg("limit", limit)
+            }
+            AppError::InvalidBody(JsonRejection::MissingJsonContentType(_)) => {
+                Message::new("detail.unsupported_media_type")
+            }
+            AppError::InvalidBody(rejection) => Message::new(format!("detail.{}", self.code()))
+                .arg("reason", rejection_reason(&rejection.body_text())),
+            AppError::InvalidPath(rejection) => Message::new("detail.invalid_path")
+                .arg("reason", rejection_reason(&rejection.body_text())),
+            AppError::InvalidQuery(rejection) => Message::new("detail.invalid_query")
+                .arg("reason", rejection_reason(&rejection.body_text())),
+            AppError::Auth(error) => error.message(),
+            AppError::Internal => Message::new("detail.internal_error"),
+        }
+    }
+
+    /// Detail in English, for logs and the admin console
+    pub fn detail(&self) -> String {
+        match self {
+            AppError::Validation(errors) => errors
+                .iter()
+                .map(|e| format!("{}: {}", e.field, e.message))
+                .collect::<Vec<_>>()
+                .join("; "),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Drops axum's generic prefix, e.g. `Failed to parse the request body as JSON: `
+fn rejection_reason(body_text: &str) -> &str {
+    body_text
+        .split_once(": ")
+        .map_or(body_text, |(_, reason)| reason)
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Database(ref e) => {
-                tracing::error!("Database error: {:?}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database error")
-            }
-            AppError::NotFound => (StatusCode::NOT_FOUND, "Resource not found"),
-            AppError::BadRequest(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
-            AppError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
-        };
+        if let AppError::Database(ref e) = self {
+            tracing::error!("Database error: {:?}", e);
+        }
+        // Adds WWW-Authenticate where needed
+        if let AppError::Auth(error) = self {
+            return error.into_response();
+        }
+        // Clients can retry with the current ETag
+        if let AppError::PreconditionFailed { ref etag, .. } = self {
+            let etag = HeaderValue::try_from(etag.as_str()).ok();
+            let mut response = self.problem().into_response();
+            if let Some(etag) = etag {
+                response.headers_mut().insert(header::ETAG, etag);
+            }
+            return response;
+        }
+
+        self.problem().into_response()
+    }
+}
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+impl AppError {
+    /// The problem reported to the client, in English
+    pub fn problem(self) -> Problem {
+        let mut problem = Problem::new(self.status(), self.code(), self.message());
+        match self {
+            AppError::AlreadyExists { field, .. }
+            | AppError::ParentNotFound { field, .. }
+            | AppError::ConstraintViolation { field } => {
+                problem = problem.with_extension("field", field);
+            }
+            AppError::Validation(errors) => {
+                problem = problem.with_field_errors(errors);
+            }
+            AppError::QuotaExceeded { quota, limit } => {
+                problem = problem
+                    .with_extension("quota", quota)
+                    .with_extension("limit", limit);
+            }
+            AppError::PreconditionFailed { current, .. } => {
+                problem = problem.with_extension("current", current);
+            }
+            _ => {}
+        }
 
-        (status, body).into_response()
+        problem
     }
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_columns_leave_out_the_household() {
+        let cases = [
+            // categories_household_id_name_key
+            (
+                "Key (household_id, name)=(1, Dup) already exists.",
+                Some("name"),
+            ),
+            // households_name_key
+            ("Key (name)=(Home) already exists.", Some("name")),
+            // list_permissions_list_id_user_id_key
+            (
+                "Key (list_id, user_id)=(3, 7) already exists.",
+                Some("list_id, user_id"),
+            ),
+            // items_list_fkey
+            (
+                "Key (list)=(999999) is not present in table \"lists\".",
+                Some("list"),
+            ),
+            (
+                "Failing row contains (1, kitchen, null, {read}, null).",
+                None,
+            ),
+            ("", None),
+        ];
+        for (detail, expected) in cases {
+            assert_eq!(key_columns(detail).as_deref(), expected, "{detail}");
+        }
+    }
+
+    #[test]
+    fn referenced_tables_come_from_the_detail() {
+        let cases = [
+            // items_list_fkey
+            (
+                "Key (list)=(999999) is not present in table \"lists\".",
+                Some("lists"),
+            ),
+            // items_list_fkey, changing a list's ID
+            (
+                "Key (id)=(1) is still referenced from table \"items\".",
+                Some("items"),
+            ),
+            ("Key (name)=(Home) already exists.", None),
+            ("table \"unterminated", None),
+        ];
+        for (detail, expected) in cases {
+            assert_eq!(referenced_table(detail), expected, "{detail}");
+        }
+    }
+
+    #[test]
+    fn check_columns_fall_back_to_the_constraint() {
+        let cases = [
+            ("household_quotas", "household_quotas_lists_check", "lists"),
+            (
+                "household_quotas",
+                "household_quotas_items_per_list_check",
+                "items_per_list",
+            ),
+            ("item_events", "item_events_kind_check", "kind"),
+            ("list_permissions", "list_permissions_role_check", "role"),
+            ("api_tokens", "api_tokens_credential_check", "credential"),
+            // Table checks over several columns are named `<table>_check`
+            (
+                "list_permissions",
+                "list_permissions_check",
+                "list_permissions_check",
+            ),
+            (
+                "data_requests",
+                "data_requests_check",
+                "data_requests_check",
+            ),
+            // Another table's constraint, e.g. through a trigger
+            (
+                "items",
+                "household_quotas_lists_check",
+                "household_quotas_lists_check",
+            ),
+        ];
+        for (table, constraint, expected) in cases {
+            assert_eq!(check_column(table, constraint), expected, "{constraint}");
+        }
+    }
+}
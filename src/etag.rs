@@ -0,0 +1,162 @@
+//! Entity tags and `If-Match` for optimistic concurrency control.
+//!
+//! Lists, items, categories and names carry the `version` of their last
+//! change (see the `sync_touch` trigger), which doubles as their strong
+//! `ETag`. Clients send it back in `If-Match` when changing or deleting a
+//! row; if the row has changed since, the request fails with
+//! `412 Precondition Failed` and the current row.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::{
+    error::{AppError, Result},
+    models::{Category, Item, List, Name},
+};
+
+/// A row with a version
+pub trait Versioned {
+    fn version(&self) -> i64;
+
+    fn etag(&self) -> String {
+        format!("\"{}\"", self.version())
+    }
+}
+
+impl Versioned for List {
+    fn version(&self) -> i64 {
+        self.version
+    }
+}
+
+impl Versioned for Item {
+    fn version(&self) -> i64 {
+        self.version
+    }
+}
+
+impl Versioned for Category {
+    fn version(&self) -> i64 {
+        self.version
+    }
+}
+
+impl Versioned for Name {
+    fn version(&self) -> i64 {
+        self.version
+    }
+}
+
+/// The `If-Match` header; requests without one are unconditional
+pub struct IfMatch(pub Option<String>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        let values: Vec<_> = parts
+            .headers
+            .get_all(header::IF_MATCH)
+            .iter()
+            // Unreadable values match nothing
+            .map(|value| value.to_str().unwrap_or_default())
+            .collect();
+
+        Ok(IfMatch((!values.is_empty()).then(|| values.join(","))))
+    }
+}
+
+impl IfMatch {
+    /// Fails unless the header is missing, `*` or lists the ETag of `current`.
+    /// Weak tags never match, as `If-Match` compares strongly.
+    pub fn check<T: Versioned + Serialize>(&self, current: &T) -> Result<()> {
+        let Some(ref tags) = self.0 else {
+            return Ok(());
+        };
+
+        let etag = current.etag();
+        if tags
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag == etag)
+        {
+            return Ok(());
+        }
+
+        Err(AppError::PreconditionFailed {
+            etag,
+            current: serde_json::to_value(current).map_err(|_| AppError::Internal)?,
+        })
+    }
+}
+
+/// A JSON response with the row's `ETag`
+pub struct Tagged<T>(pub T);
+
+impl<T: Versioned + Serialize> IntoResponse for Tagged<T> {
+    fn into_response(self) -> Response {
+        ([(header::ETAG, self.0.etag())], axum::Json(self.0)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Row {
+        version: i64,
+    }
+
+    impl Versioned for Row {
+        fn version(&self) -> i64 {
+            self.version
+        }
+    }
+
+    const ROW: Row = Row { version: 5 };
+
+    async fn if_match(values: &[&str]) -> IfMatch {
+        let mut request = Request::builder();
+        for value in values {
+            request = request.header(header::IF_MATCH, *value);
+        }
+        let (mut parts, ()) = request.body(()).unwrap().into_parts();
+        IfMatch::from_request_parts(&mut parts, &()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn missing_header_is_unconditional() {
+        assert!(if_match(&[]).await.check(&ROW).is_ok());
+    }
+
+    #[tokio::test]
+    async fn matches_the_current_tag_or_any() {
+        for value in ["\"5\"", "*", " \"5\" ", "\"4\", \"5\"", "\"4\",*"] {
+            assert!(if_match(&[value]).await.check(&ROW).is_ok(), "{value}");
+        }
+        // Repeated headers count as one list
+        assert!(if_match(&["\"4\"", "\"5\""]).await.check(&ROW).is_ok());
+    }
+
+    #[tokio::test]
+    async fn fails_with_the_current_row() {
+        for value in ["\"4\"", "W/\"5\"", "5", "\"4\", W/\"5\"", ""] {
+            match if_match(&[value]).await.check(&ROW) {
+                Err(AppError::PreconditionFailed { etag, current }) => {
+                    assert_eq!(etag, "\"5\"");
+                    assert_eq!(current, serde_json::json!({ "version": 5 }));
+                }
+                result => panic!("{value}: {result:?}"),
+            }
+        }
+    }
+}
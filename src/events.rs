@@ -0,0 +1,310 @@
+//! Live item changes, streamed as Server-Sent Events or over the WebSocket
+//! API ([`crate::ws`]).
+//!
+//! The `item_events` trigger records every change to an item and sends it on
+//! the `item_events` channel, so every instance hears about changes made
+//! through the others. Each instance listens on that channel and hands the
+//! events to its [`Hub`], which fans them out to the open streams. Streams
+//! that fall behind, or miss events while the listener reconnects, catch up
+//! from the table, as do clients resuming with `Last-Event-ID`.
+//!
+//! Event IDs follow the order of inserts, not of commits, so catching up goes
+//! by transaction instead: a stream keeps a horizon before which all
+//! transactions have finished and been seen, and reads the events of the
+//! transactions since. Resuming after an event starts from the horizon when
+//! it was recorded, so events committed around it may come again; they carry
+//! the item's `version`.
+//!
+//! Which lists the principal may see is looked up once per list and looked up
+//! again when the `list_access` channel announces a change.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::response::sse::Event;
+use futures_util::stream::{self, Stream};
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    auth::{ListRole, Principal},
+    error::{AppError, Result},
+    models::ItemEvent,
+    services,
+};
+
+pub const CHANNEL: &str = "item_events";
+/// Announces changes to who may see which list
+pub const ACCESS_CHANNEL: &str = "list_access";
+
+/// Events buffered per stream before it has to catch up from the table
+const CAPACITY: usize = 1024;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How often open streams and WebSocket connections check that their
+/// credentials still pass
+pub const RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+enum Notice {
+    Event(Arc<ItemEvent>),
+    AccessChanged,
+    /// Notifications may have been lost
+    Gap,
+}
+
+/// Fans the events of this instance's listener out to the open streams
+#[derive(Clone)]
+pub struct Hub {
+    sender: broadcast::Sender<Notice>,
+}
+
+impl Default for Hub {
+    fn default() -> Hub {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Hub { sender }
+    }
+}
+
+impl Hub {
+    /// Listen for events in the background, reconnecting as needed
+    pub fn listen(&self, pool: PgPool) {
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = forward(&pool, &sender).await {
+                    tracing::error!("Listening for item events failed: {:?}", e);
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+}
+
+async fn forward(pool: &PgPool, sender: &broadcast::Sender<Notice>) -> sqlx::Result<()> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen_all([CHANNEL, ACCESS_CHANNEL]).await?;
+    // Streams opened while the listener was down have missed events
+    let _ = sender.send(Notice::Gap);
+
+    loop {
+        match listener.try_recv().await? {
+            Some(notification) if notification.channel() == ACCESS_CHANNEL => {
+                let _ = sender.send(Notice::AccessChanged);
+            }
+            Some(notification) => match serde_json::from_str(notification.payload()) {
+                Ok(event) => {
+                    let _ = sender.send(Notice::Event(Arc::new(event)));
+                }
+                Err(e) => tracing::warn!("Ignoring malformed item event: {}", e),
+            },
+            None => {
+                // Reconnect (the listener listens again by itself) before
+                // the streams catch up, so nothing falls in between
+                sqlx::query("SELECT 1").execute(&mut listener).await?;
+                let _ = sender.send(Notice::Gap);
+            }
+        }
+    }
+}
+
+/// What a subscription passes on to its client
+#[derive(Debug, Clone)]
+pub enum Delivery {
+    Event(Arc<ItemEvent>),
+    /// The events since the client's last one are gone; it has to reload and
+    /// resume from `last_id`
+    Reset { last_id: i64 },
+}
+
+pub type SseEvent = std::result::Result<Event, Infallible>;
+
+/// The events of a household, or of one of its lists, that the principal may see
+pub async fn subscribe(
+    hub: &Hub,
+    pool: PgPool,
+    principal: Principal,
+    household_id: i32,
+    list_id: Option<i32>,
+    last_event_id: Option<i64>,
+) -> Result<impl Stream<Item = Delivery>> {
+    // Subscribe first, so no event falls between the catch-up and the live ones
+    let receiver = hub.sender.subscribe();
+    let mut conn = pool.acquire().await?;
+    let position = match last_event_id {
+        // 0 is sent by `reset` while there are no events at all
+        Some(0) => Some((0, 0)),
+        Some(id) => services::item_events::position(&mut conn, id).await?,
+        None => None,
+    };
+
+    let mut subscription = Subscription {
+        receiver,
+        pool,
+        principal,
+        household_id,
+        list_id,
+        horizon: 0,
+        seen: HashMap::new(),
+        pending: VecDeque::new(),
+        access: HashMap::new(),
+        reset: None,
+    };
+    match (last_event_id, position) {
+        (Some(id), Some((xid, horizon))) => {
+            subscription.horizon = horizon;
+            subscription.seen.insert(id, xid);
+            drop(conn);
+            subscription.catch_up().await?;
+        }
+        // Events since then are gone, the client has to reload
+        (Some(_), None) => {
+            subscription.horizon = services::item_events::horizon(&mut conn).await?;
+            subscription.reset = Some(services::item_events::latest_id(&mut conn).await?);
+        }
+        (None, _) => subscription.horizon = services::item_events::horizon(&mut conn).await?,
+    }
+
+    Ok(stream::unfold(
+        subscription,
+        |mut subscription| async move {
+            let delivery = subscription.next().await?;
+            Some((delivery, subscription))
+        },
+    ))
+}
+
+struct Subscription {
+    receiver: broadcast::Receiver<Notice>,
+    pool: PgPool,
+    principal: Principal,
+    household_id: i32,
+    list_id: Option<i32>,
+    /// Transactions before it have finished, their events were passed on (or skipped)
+    horizon: i64,
+    /// Events of transactions from `horizon` on that were passed on (or
+    /// skipped), by ID, with their transaction
+    seen: HashMap<i64, i64>,
+    pending: VecDeque<ItemEvent>,
+    /// Whether the principal may see a list, by list ID
+    access: HashMap<i32, bool>,
+    /// Tell the client to reload and resume from this event before anything else
+    reset: Option<i64>,
+}
+
+impl Subscription {
+    /// The next event for the client, `None` once the hub is gone
+    async fn next(&mut self) -> Option<Delivery> {
+        if let Some(last_id) = self.reset.take() {
+            return Some(Delivery::Reset { last_id });
+        }
+
+        loop {
+            let event = match self.pending.pop_front() {
+                Some(event) => Arc::new(event),
+                None => match self.receiver.recv().await {
+                    Ok(Notice::Event(event)) if !self.is_new(&event) => continue,
+                    Ok(Notice::Event(event)) => {
+                        self.seen.insert(event.id, event.xid);
+                        event
+                    }
+                    Ok(Notice::AccessChanged) => {
+                        self.access.clear();
+                        continue;
+                    }
+                    Ok(Notice::Gap) | Err(RecvError::Lagged(_)) => {
+                        self.access.clear();
+                        if let Err(e) = self.catch_up().await {
+                            tracing::error!("Catching up on item events failed: {:?}", e);
+                        }
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+
+            if self.visible(&event).await {
+                return Some(Delivery::Event(event));
+            }
+        }
+    }
+
+    fn is_new(&self, event: &ItemEvent) -> bool {
+        event.xid >= self.horizon && !self.seen.contains_key(&event.id)
+    }
+
+    /// Queue the stored events the client has not seen yet
+    async fn catch_up(&mut self) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        // Before reading, so every transaction before it is in what is read
+        let horizon = services::item_events::horizon(&mut conn).await?;
+        let events =
+            services::item_events::since(&mut conn, self.household_id, self.list_id, self.horizon)
+                .await?;
+
+        for event in events {
+            if self.is_new(&event) {
+                self.seen.insert(event.id, event.xid);
+                self.pending.push_back(event);
+            }
+        }
+        self.horizon = self.horizon.max(horizon);
+        let horizon = self.horizon;
+        self.seen.retain(|_, xid| *xid >= horizon);
+
+        Ok(())
+    }
+
+    async fn visible(&mut self, event: &ItemEvent) -> bool {
+        if event.household_id != self.household_id
+            || self.list_id.is_some_and(|id| id != event.list_id)
+        {
+            return false;
+        }
+        if let Some(&visible) = self.access.get(&event.list_id) {
+            return visible;
+        }
+
+        let visible = match self
+            .principal
+            .require_list_role(
+                &self.pool,
+                self.household_id,
+                event.list_id,
+                ListRole::Viewer,
+            )
+            .await
+        {
+            Ok(()) => true,
+            Err(AppError::Auth(_) | AppError::NotFound) => false,
+            Err(e) => {
+                // Not remembered, the next event looks again
+                tracing::error!("Checking access to list {} failed: {:?}", event.list_id, e);
+                return false;
+            }
+        };
+        self.access.insert(event.list_id, visible);
+
+        visible
+    }
+}
+
+/// `id` is the event ID to resume from, `event` the kind and `data` the item
+pub fn to_sse(delivery: Delivery) -> SseEvent {
+    Ok(match delivery {
+        Delivery::Event(event) => Event::default()
+            .id(event.id.to_string())
+            .event(&event.kind)
+            .json_data(&event.item)
+            .unwrap_or_else(|_| Event::default().comment("unserializable item")),
+        Delivery::Reset { last_id } => Event::default()
+            .id(last_id.to_string())
+            .event("reset")
+            .data("{}"),
+    })
+}
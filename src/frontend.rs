@@ -0,0 +1,228 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+
+/// Static assets of the web frontend, embedded at compile time.
+///
+/// Build the SPA into `frontend/dist` before compiling with
+/// `--features embed-frontend`. Precompressed `.br` / `.gz` siblings
+/// (e.g. `app.js.br`) are picked up automatically.
+#[derive(RustEmbed)]
+#[folder = "frontend/dist/"]
+struct Assets;
+
+const INDEX: &str = "index.html";
+
+/// Long-lived caching for fingerprinted build output, revalidation for everything else
+const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const CACHE_REVALIDATE: &str = "no-cache";
+
+/// Fallback handler serving the embedded frontend with SPA history fallback
+pub async fn serve(method: Method, uri: Uri, headers: HeaderMap) -> Response {
+    if method != Method::GET && method != Method::HEAD {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+
+    let path = uri.path().trim_start_matches('/');
+
+    // Unknown API routes must not be answered with the SPA shell
+    if path == "api" || path.starts_with("api/") {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let path = if path.is_empty() { INDEX } else { path };
+
+    if let Some(response) = serve_asset(path, &method, &headers) {
+        return response;
+    }
+
+    // Client-side routes (no file extension) get the SPA shell,
+    // missing files stay a 404
+    let is_file = path
+        .rsplit('/')
+        .next()
+        .is_some_and(|segment| segment.contains('.'));
+    if is_file {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    serve_asset(INDEX, &method, &headers).unwrap_or_else(|| StatusCode::NOT_FOUND.into_response())
+}
+
+fn serve_asset(path: &str, method: &Method, headers: &HeaderMap) -> Option<Response> {
+    let file = Assets::get(path)?;
+
+    // Prefer a precompressed variant if the client accepts it
+    let accepted = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default();
+    let (data, encoding) = [("br", "br"), ("gzip", "gz")]
+        .into_iter()
+        .filter(|(encoding, _)| accepts_encoding(accepted, encoding))
+        .find_map(|(encoding, ext)| {
+            Assets::get(&format!("{path}.{ext}"))
+                .map(|variant| (variant.data, Some((encoding, ext))))
+        })
+        .unwrap_or((file.data, None));
+
+    let etag = entity_tag(
+        &hex_encode(&file.metadata.sha256_hash()),
+        encoding.map(|(_, ext)| ext),
+    );
+    let cache_control = if is_fingerprinted(path) {
+        CACHE_IMMUTABLE
+    } else {
+        CACHE_REVALIDATE
+    };
+
+    let mut response = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::VARY, "Accept-Encoding");
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|value| lists_tag(value, &etag));
+    if not_modified {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .ok();
+    }
+
+    if let Some((encoding, _)) = encoding {
+        response = response.header(header::CONTENT_ENCODING, encoding);
+    }
+
+    let content_type = HeaderValue::from_str(file.metadata.mimetype())
+        .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream"));
+
+    let content_length = data.len();
+    let body = if method == Method::HEAD {
+        Body::empty()
+    } else {
+        Body::from(data.into_owned())
+    };
+
+    response
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, content_length)
+        .body(body)
+        .ok()
+}
+
+/// The strong entity tag of a representation: each encoding is a different
+/// byte sequence, so it gets a tag of its own
+fn entity_tag(hash: &str, extension: Option<&str>) -> String {
+    match extension {
+        Some(extension) => format!("\"{hash}-{extension}\""),
+        None => format!("\"{hash}\""),
+    }
+}
+
+/// Whether an If-None-Match header lists the tag of the negotiated representation
+fn lists_tag(header: &str, etag: &str) -> bool {
+    header.split(',').any(|tag| tag.trim() == etag)
+}
+
+/// Whether the Accept-Encoding header allows the given coding (q=0 excluded).
+/// An entry for the coding itself takes precedence over `*`.
+fn accepts_encoding(header: &str, encoding: &str) -> bool {
+    let mut wildcard = false;
+    for part in header.split(',') {
+        let mut params = part.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        let accepted = !params.any(|p| {
+            p.strip_prefix("q=")
+                .or_else(|| p.strip_prefix("Q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        if name.eq_ignore_ascii_case(encoding) {
+            return accepted;
+        }
+        if name == "*" {
+            wildcard = accepted;
+        }
+    }
+    wildcard
+}
+
+/// Bundlers emit hashed file names like `assets/index-4f3a9c1b.js`
+fn is_fingerprinted(path: &str) -> bool {
+    if path.starts_with("assets/") {
+        return true;
+    }
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    stem.rsplit_once('-')
+        .is_some_and(|(_, hash)| hash.len() >= 8 && hash.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_listed_encodings() {
+        assert!(accepts_encoding("gzip, deflate, br", "br"));
+        assert!(accepts_encoding("gzip, deflate, br", "gzip"));
+        assert!(accepts_encoding("BR;q=0.5", "br"));
+        assert!(!accepts_encoding("gzip", "br"));
+        assert!(!accepts_encoding("", "gzip"));
+    }
+
+    #[test]
+    fn excludes_encodings_with_zero_quality() {
+        assert!(!accepts_encoding("br;q=0, gzip", "br"));
+        assert!(accepts_encoding("br;q=0, gzip", "gzip"));
+        assert!(!accepts_encoding("gzip; q=0.000", "gzip"));
+        assert!(!accepts_encoding("gzip;Q=0", "gzip"));
+        assert!(accepts_encoding("gzip;q=0.001", "gzip"));
+    }
+
+    #[test]
+    fn wildcard_covers_unlisted_encodings() {
+        assert!(accepts_encoding("*", "br"));
+        assert!(accepts_encoding("gzip;q=0, *", "br"));
+        assert!(!accepts_encoding("gzip;q=0, *", "gzip"));
+        assert!(!accepts_encoding("*;q=0, gzip", "br"));
+        assert!(accepts_encoding("*;q=0, gzip", "gzip"));
+    }
+
+    #[test]
+    fn tags_each_encoding_separately() {
+        assert_eq!(entity_tag("ab12", None), "\"ab12\"");
+        assert_eq!(entity_tag("ab12", Some("br")), "\"ab12-br\"");
+        assert_eq!(entity_tag("ab12", Some("gz")), "\"ab12-gz\"");
+    }
+
+    #[test]
+    fn matches_only_the_negotiated_tag() {
+        let br = entity_tag("ab12", Some("br"));
+        assert!(lists_tag("\"ab12-br\"", &br));
+        assert!(lists_tag("\"ab12-gz\", \"ab12-br\"", &br));
+        assert!(!lists_tag("\"ab12-gz\"", &br));
+        assert!(!lists_tag("\"ab12\"", &br));
+        assert!(!lists_tag("\"ab12-br\"", &entity_tag("ab12", None)));
+    }
+
+    #[test]
+    fn recognizes_fingerprinted_files() {
+        assert!(is_fingerprinted("assets/logo.svg"));
+        assert!(is_fingerprinted("index-4f3a9c1b.js"));
+        assert!(is_fingerprinted("fonts/inter-Bq7x2kPa.woff2"));
+        assert!(!is_fingerprinted("index.html"));
+        assert!(!is_fingerprinted("service-worker.js"));
+        assert!(!is_fingerprinted("manifest.webmanifest"));
+    }
+}
@@ -0,0 +1,26 @@
+use axum::extract::State;
+
+use crate::{
+    auth::Principal,
+    error::Result,
+    extract::{Json, Query},
+    models::{AuditEntry, AuditQuery},
+    services,
+    state::AppState,
+    validation::Validate,
+};
+
+/// GET /api/admin/audit - Get audit log entries, newest first (admin)
+pub async fn get_audit_log(
+    State(state): State<AppState>,
+    principal: Principal,
+    Query(mut query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>> {
+    principal.require_admin()?;
+    query.validated()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let entries = services::audit::list(&mut conn, &query).await?;
+
+    Ok(Json(entries))
+}
@@ -0,0 +1,432 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{AppendHeaders, Redirect},
+};
+use chrono::Duration;
+use serde::Deserialize;
+use sqlx::PgConnection;
+
+use crate::{
+    auth::{
+        self, oidc,
+        session::{self, BrowserSession},
+        webauthn, AuthError, CurrentUser, Principal, CSRF_COOKIE, SESSION_COOKIE,
+    },
+    config::{Config, WebauthnConfig},
+    error::{AppError, Result},
+    extract::{Json, Query, ValidJson},
+    models::{
+        AssertionCredential, ChangePasswordRequest, Household, JoinHouseholdRequest, LoginRequest,
+        PasskeyLoginOptionsRequest, PasskeyLoginRequest, PasskeyOptions, RegisterRequest,
+        SessionResponse, TokenRequest, TokenResponse, UpdateProfileRequest, User,
+    },
+    services,
+    state::AppState,
+};
+
+use super::passkeys::webauthn_config;
+
+type SetCookies = AppendHeaders<[(HeaderName, String); 2]>;
+
+#[derive(Deserialize)]
+pub struct OidcLoginQuery {
+    /// Path to return to after signing in
+    #[serde(rename = "returnTo")]
+    return_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// POST /api/auth/login - Sign in with a browser session cookie
+pub async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<LoginRequest>,
+) -> Result<(SetCookies, Json<SessionResponse>)> {
+    let ttl = Duration::days(state.config.session_ttl_days);
+    let mut tx = state.pool.begin().await?;
+
+    let user = services::users::verify_login(&mut tx, &payload.username, &payload.password)
+        .await?
+        .ok_or(AuthError::InvalidCredentials)?;
+    check_second_factor(&mut tx, &state, &user, payload.passkey.as_ref()).await?;
+    let session = session::create_browser(&mut tx, user.id, user_agent(&headers), ttl).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        session_cookies(&state.config, &session, ttl),
+        Json(SessionResponse {
+            user,
+            csrf_token: session.csrf_token,
+            expires_at: session.expires_at,
+        }),
+    ))
+}
+
+/// POST /api/auth/login/passkey/options - Start signing in with a passkey
+pub async fn passkey_login_options(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<PasskeyLoginOptionsRequest>,
+) -> Result<Json<PasskeyOptions>> {
+    let config = webauthn_config(&state)?;
+    let mut conn = state.pool.acquire().await?;
+
+    // Unknown users get options anyway, so they cannot be told apart
+    let user_id = match &payload.username {
+        Some(username) => services::users::find_by_username(&mut conn, username)
+            .await?
+            .map(|user| user.id),
+        None => None,
+    };
+    let options = webauthn::login_options(&mut conn, config, user_id).await?;
+
+    Ok(Json(options))
+}
+
+/// POST /api/auth/login/passkey - Sign in with a passkey and a browser session cookie
+pub async fn passkey_login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<PasskeyLoginRequest>,
+) -> Result<(SetCookies, Json<SessionResponse>)> {
+    let config = webauthn_config(&state)?;
+    let ttl = Duration::days(state.config.session_ttl_days);
+    let mut tx = state.pool.begin().await?;
+
+    let user = passkey_user(&mut tx, config, &payload.credential).await?;
+    let session = session::create_browser(&mut tx, user.id, user_agent(&headers), ttl).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        session_cookies(&state.config, &session, ttl),
+        Json(SessionResponse {
+            user,
+            csrf_token: session.csrf_token,
+            expires_at: session.expires_at,
+        }),
+    ))
+}
+
+/// POST /api/auth/token - Get app tokens with a password, a passkey or a refresh token
+pub async fn token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<TokenRequest>,
+) -> Result<Json<TokenResponse>> {
+    let access_ttl = Duration::minutes(state.config.access_token_ttl_minutes);
+    let refresh_ttl = Duration::days(state.config.session_ttl_days);
+    let mut tx = state.pool.begin().await?;
+
+    let tokens = match payload {
+        TokenRequest::Password {
+            username,
+            password,
+            passkey,
+        } => {
+            let user = services::users::verify_login(&mut tx, &username, &password)
+                .await?
+                .ok_or(AuthError::InvalidCredentials)?;
+            check_second_factor(&mut tx, &state, &user, passkey.as_ref()).await?;
+            session::create_app(
+                &mut tx,
+                user.id,
+                user_agent(&headers),
+                access_ttl,
+                refresh_ttl,
+            )
+            .await?
+        }
+        TokenRequest::Passkey { credential } => {
+            let config = webauthn_config(&state)?;
+            let user = passkey_user(&mut tx, config, &credential).await?;
+            session::create_app(
+                &mut tx,
+                user.id,
+                user_agent(&headers),
+                access_ttl,
+                refresh_ttl,
+            )
+            .await?
+        }
+        TokenRequest::RefreshToken { refresh_token } => {
+            session::refresh(&mut tx, &refresh_token, access_ttl, refresh_ttl)
+                .await?
+                .ok_or(AuthError::InvalidToken)?
+        }
+    };
+    let user = services::users::find(&mut tx, tokens.user_id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(TokenResponse {
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        token_type: "Bearer",
+        expires_in: access_ttl.num_seconds(),
+        user,
+    }))
+}
+
+/// POST /api/auth/register - Create an account with an invitation
+pub async fn register(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<RegisterRequest>,
+) -> Result<(StatusCode, Json<User>)> {
+    let mut tx = state.pool.begin().await?;
+    let user = services::invitations::redeem(
+        &mut tx,
+        &payload.invitation,
+        &payload.username,
+        &payload.password,
+        payload.display_name.as_deref(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+/// POST /api/auth/join - Join a household with an invitation
+pub async fn join_household(
+    State(state): State<AppState>,
+    current: CurrentUser,
+    ValidJson(payload): ValidJson<JoinHouseholdRequest>,
+) -> Result<Json<Household>> {
+    let mut tx = state.pool.begin().await?;
+    let household = services::invitations::join(&mut tx, &payload.invitation, current.id).await?;
+    tx.commit().await?;
+
+    Ok(Json(household))
+}
+
+/// GET /api/auth/oidc/login - Sign in with the OpenID Connect provider
+pub async fn oidc_login(
+    State(state): State<AppState>,
+    Query(query): Query<OidcLoginQuery>,
+) -> Result<(AppendHeaders<[(HeaderName, String); 1]>, Redirect)> {
+    let provider = state.oidc.as_deref().ok_or(AppError::NotFound)?;
+    // Only paths on this server, so the login cannot be used to redirect elsewhere
+    let return_to = query
+        .return_to
+        .filter(|path| path.starts_with('/') && !path.starts_with("//") && !path.contains('\\'))
+        .unwrap_or_else(|| "/".to_string());
+
+    let mut conn = state.pool.acquire().await?;
+    let login = provider.begin_login(&mut conn, &return_to).await?;
+
+    let max_age = oidc::LOGIN_TTL_MINUTES * 60;
+    let cookies = AppendHeaders([(
+        header::SET_COOKIE,
+        cookie(
+            &state.config,
+            oidc::STATE_COOKIE,
+            &login.state,
+            max_age,
+            true,
+        ),
+    )]);
+
+    Ok((cookies, Redirect::to(&login.authorization_url)))
+}
+
+/// GET /api/auth/oidc/callback - Finish signing in with the OpenID Connect provider
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<(AppendHeaders<[(HeaderName, String); 3]>, Redirect)> {
+    let provider = state.oidc.as_deref().ok_or(AppError::NotFound)?;
+    if let Some(error) = &query.error {
+        tracing::info!(
+            error,
+            description = query.error_description,
+            "OpenID Connect provider declined the login"
+        );
+        return Err(AuthError::SsoFailed.into());
+    }
+    let (Some(code), Some(login_state)) = (&query.code, &query.state) else {
+        return Err(AuthError::SsoFailed.into());
+    };
+    // The login must finish in the browser that started it
+    if auth::cookie(&headers, oidc::STATE_COOKIE) != Some(login_state.as_str()) {
+        return Err(AuthError::SsoFailed.into());
+    }
+
+    let ttl = Duration::days(state.config.session_ttl_days);
+    let mut tx = state.pool.begin().await?;
+
+    let (identity, return_to) = provider.finish_login(&mut tx, login_state, code).await?;
+    let user =
+        services::identities::sign_in(&mut tx, &provider.config().groups, &identity, true).await?;
+    if user.disabled {
+        return Err(AuthError::SsoFailed.into());
+    }
+    let session = session::create_browser(&mut tx, user.id, user_agent(&headers), ttl).await?;
+
+    tx.commit().await?;
+
+    let max_age = ttl.num_seconds();
+    let cookies = AppendHeaders([
+        (
+            header::SET_COOKIE,
+            cookie(&state.config, SESSION_COOKIE, &session.token, max_age, true),
+        ),
+        (
+            header::SET_COOKIE,
+            cookie(
+                &state.config,
+                CSRF_COOKIE,
+                &session.csrf_token,
+                max_age,
+                false,
+            ),
+        ),
+        (
+            header::SET_COOKIE,
+            cookie(&state.config, oidc::STATE_COOKIE, "", 0, true),
+        ),
+    ]);
+
+    Ok((cookies, Redirect::to(&return_to)))
+}
+
+/// POST /api/auth/logout - End the current session
+pub async fn logout(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<(StatusCode, SetCookies)> {
+    if let Some(session_id) = principal.user().and_then(|user| user.session_id) {
+        let mut conn = state.pool.acquire().await?;
+        session::delete(&mut conn, session_id).await?;
+    }
+
+    let cookies = AppendHeaders([
+        (
+            header::SET_COOKIE,
+            cookie(&state.config, SESSION_COOKIE, "", 0, true),
+        ),
+        (
+            header::SET_COOKIE,
+            cookie(&state.config, CSRF_COOKIE, "", 0, false),
+        ),
+    ]);
+
+    Ok((StatusCode::NO_CONTENT, cookies))
+}
+
+/// GET /api/auth/me - Get the signed-in user
+pub async fn get_me(State(state): State<AppState>, current: CurrentUser) -> Result<Json<User>> {
+    let mut conn = state.pool.acquire().await?;
+    let user = services::users::find(&mut conn, current.id).await?;
+
+    Ok(Json(user))
+}
+
+/// PUT /api/auth/me - Update display name and language
+pub async fn update_me(
+    State(state): State<AppState>,
+    current: CurrentUser,
+    ValidJson(payload): ValidJson<UpdateProfileRequest>,
+) -> Result<Json<User>> {
+    let mut conn = state.pool.acquire().await?;
+    let user = services::users::update_profile(&mut conn, current.id, &payload).await?;
+
+    Ok(Json(user))
+}
+
+/// PUT /api/auth/me/password - Change the password, ending all other sessions
+pub async fn change_password(
+    State(state): State<AppState>,
+    current: CurrentUser,
+    ValidJson(payload): ValidJson<ChangePasswordRequest>,
+) -> Result<StatusCode> {
+    let mut tx = state.pool.begin().await?;
+
+    services::users::verify_login(&mut tx, &current.username, &payload.current_password)
+        .await?
+        .ok_or(AuthError::InvalidCredentials)?;
+    services::users::set_password(&mut tx, current.id, &payload.new_password).await?;
+    session::delete_all(&mut tx, current.id, current.session_id).await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Users who require a passkey confirm password logins with one of theirs. The
+/// requirement is ignored while passkeys are turned off, so nobody is locked out.
+async fn check_second_factor(
+    conn: &mut PgConnection,
+    state: &AppState,
+    user: &User,
+    passkey: Option<&AssertionCredential>,
+) -> Result<()> {
+    let Some(config) = state
+        .config
+        .webauthn
+        .as_ref()
+        .filter(|_| user.passkey_required)
+    else {
+        return Ok(());
+    };
+    let passkey = passkey.ok_or(AuthError::PasskeyRequired)?;
+
+    let login = webauthn::verify_login(conn, config, passkey).await?;
+    if login.user_id != user.id {
+        return Err(AuthError::InvalidPasskey.into());
+    }
+    Ok(())
+}
+
+/// The user signing in with a passkey alone, which must have verified them
+async fn passkey_user(
+    conn: &mut PgConnection,
+    config: &WebauthnConfig,
+    credential: &AssertionCredential,
+) -> Result<User> {
+    let login = webauthn::verify_login(conn, config, credential).await?;
+    if !login.user_verified {
+        return Err(AuthError::InvalidPasskey.into());
+    }
+    services::users::find(conn, login.user_id).await
+}
+
+fn session_cookies(config: &Config, session: &BrowserSession, ttl: Duration) -> SetCookies {
+    let max_age = ttl.num_seconds();
+    AppendHeaders([
+        (
+            header::SET_COOKIE,
+            cookie(config, SESSION_COOKIE, &session.token, max_age, true),
+        ),
+        (
+            header::SET_COOKIE,
+            cookie(config, CSRF_COOKIE, &session.csrf_token, max_age, false),
+        ),
+    ])
+}
+
+fn user_agent(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+}
+
+fn cookie(config: &Config, name: &str, value: &str, max_age: i64, http_only: bool) -> String {
+    let mut cookie = format!("{name}={value}; Path=/; Max-Age={max_age}; SameSite=Lax");
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if config.cookie_secure {
+        cookie.push_str("; Secure");
+    }
+    cookie
+}
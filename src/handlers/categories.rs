@@ -1,35 +1,29 @@
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
-};
+use axum::{extract::State, http::StatusCode};
 
 use crate::{
+    auth::{Access, Principal, Tenant},
     error::{AppError, Result},
-    models::Category,
+    etag::{IfMatch, Tagged},
+    extract::{Json, Path, ValidJson},
+    models::{Category, CreateCategoryRequest, UpdateCategoryRequest},
+    services::{self, quotas::Quota},
     state::AppState,
-    validation,
 };
 
-#[derive(serde::Deserialize)]
-pub struct CreateCategoryRequest {
-    pub name: String,
-}
-
-#[derive(serde::Deserialize)]
-pub struct UpdateCategoryRequest {
-    pub name: String,
-}
-
 /// GET /api/categories - Get all categories
-pub async fn get_all_categories(State(state): State<AppState>) -> Result<Json<Vec<Category>>> {
+pub async fn get_all_categories(
+    State(state): State<AppState>,
+    Tenant(household_id): Tenant,
+) -> Result<Json<Vec<Category>>> {
     let categories = sqlx::query_as::<_, Category>(
         r#"
-        SELECT id, name
+        SELECT id, name, version
         FROM categories
+        WHERE household_id = $1
         ORDER BY name ASC
         "#,
     )
+    .bind(household_id)
     .fetch_all(&state.pool)
     .await?;
 
@@ -39,200 +33,88 @@ pub async fn get_all_categories(State(state): State<AppState>) -> Result<Json<Ve
 /// GET /api/categories/:id - Get a single category
 pub async fn get_category(
     State(state): State<AppState>,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
-) -> Result<Json<Category>> {
+) -> Result<Tagged<Category>> {
     let category = sqlx::query_as::<_, Category>(
         r#"
-        SELECT id, name
+        SELECT id, name, version
         FROM categories
-        WHERE id = $1
+        WHERE id = $1 AND household_id = $2
         "#,
     )
     .bind(id)
+    .bind(household_id)
     .fetch_optional(&state.pool)
     .await?
     .ok_or(AppError::NotFound)?;
 
-    Ok(Json(category))
+    Ok(Tagged(category))
 }
 
 /// POST /api/categories - Create a new category
 pub async fn create_category(
     State(state): State<AppState>,
-    Json(payload): Json<CreateCategoryRequest>,
-) -> Result<(StatusCode, Json<Category>)> {
-    // Validate input
-    validation::validate_string(&payload.name, "Category name")?;
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    ValidJson(payload): ValidJson<CreateCategoryRequest>,
+) -> Result<(StatusCode, Tagged<Category>)> {
+    principal.require(Access::Write)?;
+
+    let mut tx = state.pool.begin().await?;
+    let limits = services::quotas::limits(&mut tx, &state.config.quotas, household_id).await?;
+    services::quotas::reserve(&mut tx, &limits, household_id, Quota::Categories).await?;
 
     let category = sqlx::query_as::<_, Category>(
         r#"
-        INSERT INTO categories (name)
-        VALUES ($1)
-        RETURNING id, name
+        INSERT INTO categories (household_id, name)
+        VALUES ($1, $2)
+        RETURNING id, name, version
         "#,
     )
+    .bind(household_id)
     .bind(&payload.name)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|e| {
-        if let sqlx::Error::Database(ref db_err) = e {
-            if db_err.is_unique_violation() {
-                return AppError::BadRequest("Category already exists".to_string());
-            }
-        }
-        AppError::Database(e)
-    })?;
-
-    Ok((StatusCode::CREATED, Json(category)))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Tagged(category)))
 }
 
 /// PUT /api/categories/:id - Update a category
 pub async fn update_category(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateCategoryRequest>,
-) -> Result<Json<Category>> {
-    // Validate input
-    validation::validate_string(&payload.name, "Category name")?;
+    if_match: IfMatch,
+    ValidJson(payload): ValidJson<UpdateCategoryRequest>,
+) -> Result<Tagged<Category>> {
+    principal.require(Access::Write)?;
 
-    // Start transaction
     let mut tx = state.pool.begin().await?;
-
-    // Get the old category to know its name
-    let old_category = sqlx::query_as::<_, Category>(
-        r#"
-        SELECT id, name
-        FROM categories
-        WHERE id = $1
-        "#,
-    )
-    .bind(id)
-    .fetch_optional(&mut *tx)
-    .await?
-    .ok_or(AppError::NotFound)?;
-
-    // Create the new category (will fail if name already exists due to unique constraint)
-    let new_category = sqlx::query_as::<_, Category>(
-        r#"
-        INSERT INTO categories (name)
-        VALUES ($1)
-        RETURNING id, name
-        "#,
-    )
-    .bind(&payload.name)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| {
-        if let sqlx::Error::Database(ref db_err) = e {
-            if db_err.is_unique_violation() {
-                return AppError::BadRequest("Category already exists".to_string());
-            }
-        }
-        AppError::Database(e)
-    })?;
-
-    // Update all items that reference the old category name to use the new name
-    sqlx::query(
-        r#"
-        UPDATE items
-        SET category = $1
-        WHERE category = $2
-        "#,
-    )
-    .bind(&payload.name)
-    .bind(&old_category.name)
-    .execute(&mut *tx)
-    .await?;
-
-    // Update all names that reference the old category name to use the new name
-    sqlx::query(
-        r#"
-        UPDATE names
-        SET category = $1
-        WHERE category = $2
-        "#,
-    )
-    .bind(&payload.name)
-    .bind(&old_category.name)
-    .execute(&mut *tx)
-    .await?;
-
-    // Delete the old category
-    sqlx::query(
-        r#"
-        DELETE FROM categories
-        WHERE id = $1
-        "#,
-    )
-    .bind(id)
-    .execute(&mut *tx)
-    .await?;
-
-    // Commit transaction
+    if_match.check(&services::categories::lock(&mut tx, household_id, id).await?)?;
+    let new_category = services::categories::rename(&mut tx, household_id, id, &payload.name).await?;
     tx.commit().await?;
 
-    Ok(Json(new_category))
+    Ok(Tagged(new_category))
 }
 
 /// DELETE /api/categories/:id - Delete a category
 pub async fn delete_category(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
+    if_match: IfMatch,
 ) -> Result<StatusCode> {
-    // Start transaction
-    let mut tx = state.pool.begin().await?;
-
-    // Get the category to know its name
-    let category = sqlx::query_as::<_, Category>(
-        r#"
-        SELECT id, name
-        FROM categories
-        WHERE id = $1
-        "#,
-    )
-    .bind(id)
-    .fetch_optional(&mut *tx)
-    .await?
-    .ok_or(AppError::NotFound)?;
+    principal.require(Access::Write)?;
 
-    // Set category to NULL for all items that reference this category
-    sqlx::query(
-        r#"
-        UPDATE items
-        SET category = NULL
-        WHERE category = $1
-        "#,
-    )
-    .bind(&category.name)
-    .execute(&mut *tx)
-    .await?;
-
-    // Set category to NULL for all names that reference this category
-    sqlx::query(
-        r#"
-        UPDATE names
-        SET category = NULL
-        WHERE category = $1
-        "#,
-    )
-    .bind(&category.name)
-    .execute(&mut *tx)
-    .await?;
-
-    // Delete the category
-    sqlx::query(
-        r#"
-        DELETE FROM categories
-        WHERE id = $1
-        "#,
-    )
-    .bind(id)
-    .execute(&mut *tx)
-    .await?;
-
-    // Commit transaction
+    let mut tx = state.pool.begin().await?;
+    if_match.check(&services::categories::lock(&mut tx, household_id, id).await?)?;
+    services::categories::delete(&mut tx, household_id, id).await?;
     tx.commit().await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
-
@@ -0,0 +1,154 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+
+use crate::{
+    auth::{AuthError, CurrentUser, Principal},
+    error::{AppError, Result},
+    extract::{Json, Path, ValidJson},
+    jobs,
+    models::{DataRequest, DeleteAccountRequest},
+    services,
+    state::AppState,
+};
+
+/// GET /api/data-requests - Get all export and deletion requests (admin), or
+/// those about or by the signed-in user
+pub async fn get_all_data_requests(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<Vec<DataRequest>>> {
+    let user_id = match &principal {
+        _ if principal.is_admin() => None,
+        Principal::User(user) => Some(user.id),
+        Principal::System | Principal::Token(_) => return Err(AuthError::UserRequired.into()),
+    };
+
+    let mut conn = state.pool.acquire().await?;
+    let requests = services::data_requests::list(&mut conn, user_id).await?;
+
+    Ok(Json(requests))
+}
+
+/// GET /api/data-requests/:id - Get the status of an export or deletion
+pub async fn get_data_request(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i64>,
+) -> Result<Json<DataRequest>> {
+    let mut conn = state.pool.acquire().await?;
+    let request = services::data_requests::find(&mut conn, id).await?;
+    require_visible(&principal, &request)?;
+
+    Ok(Json(request))
+}
+
+/// GET /api/data-requests/:id/export - Download a finished export
+pub async fn download_export(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse> {
+    let mut conn = state.pool.acquire().await?;
+    let request = services::data_requests::find(&mut conn, id).await?;
+    require_visible(&principal, &request)?;
+
+    let document = services::data_requests::export_document(&mut conn, id).await?;
+    let disposition = format!(
+        "attachment; filename=\"lister-export-{}-{}.json\"",
+        request.username, request.id
+    );
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(document)))
+}
+
+/// POST /api/users/:id/export - Export everything stored about a user (admin)
+pub async fn export_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, Json<DataRequest>)> {
+    principal.require_admin()?;
+
+    request_export(&state, id, requested_by(&principal)).await
+}
+
+/// POST /api/users/:id/deletion - Delete a user and handle their lists (admin)
+pub async fn delete_user_account(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<DeleteAccountRequest>,
+) -> Result<(StatusCode, Json<DataRequest>)> {
+    principal.require_admin()?;
+
+    request_deletion(&state, id, &payload, requested_by(&principal)).await
+}
+
+/// POST /api/auth/me/export - Export everything stored about the signed-in user
+pub async fn export_me(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<(StatusCode, Json<DataRequest>)> {
+    request_export(&state, user.id, Some(user.id)).await
+}
+
+/// POST /api/auth/me/deletion - Delete the signed-in user's account
+pub async fn delete_me(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<DeleteAccountRequest>,
+) -> Result<(StatusCode, Json<DataRequest>)> {
+    request_deletion(&state, user.id, &payload, Some(user.id)).await
+}
+
+async fn request_export(
+    state: &AppState,
+    user_id: i32,
+    requested_by: Option<i32>,
+) -> Result<(StatusCode, Json<DataRequest>)> {
+    let mut conn = state.pool.acquire().await?;
+    let user = services::users::find(&mut conn, user_id).await?;
+    let request = services::data_requests::create_export(&mut conn, &user, requested_by).await?;
+
+    jobs::data_requests::spawn(state.pool.clone(), request.id);
+
+    Ok((StatusCode::ACCEPTED, Json(request)))
+}
+
+async fn request_deletion(
+    state: &AppState,
+    user_id: i32,
+    payload: &DeleteAccountRequest,
+    requested_by: Option<i32>,
+) -> Result<(StatusCode, Json<DataRequest>)> {
+    let mut tx = state.pool.begin().await?;
+    let user = services::users::find(&mut tx, user_id).await?;
+    let request =
+        services::data_requests::create_deletion(&mut tx, &user, payload, requested_by).await?;
+    tx.commit().await?;
+
+    jobs::data_requests::spawn(state.pool.clone(), request.id);
+
+    Ok((StatusCode::ACCEPTED, Json(request)))
+}
+
+fn requested_by(principal: &Principal) -> Option<i32> {
+    principal.user().map(|user| user.id)
+}
+
+/// Admins see all requests, users those about or by them
+fn require_visible(principal: &Principal, request: &DataRequest) -> Result<()> {
+    let visible = principal.is_admin()
+        || principal.user().is_some_and(|user| {
+            user.id == request.user_id || request.requested_by == Some(user.id)
+        });
+
+    if visible {
+        Ok(())
+    } else {
+        Err(AppError::NotFound)
+    }
+}
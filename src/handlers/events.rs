@@ -0,0 +1,145 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{Extensions, HeaderMap, Method},
+    response::sse::{KeepAlive, Sse},
+};
+use futures_util::{Stream, StreamExt};
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::{
+    auth::{self, tenant, ListRole, Principal, Tenant},
+    error::{AppError, Result},
+    events,
+    extract::Path,
+    state::AppState,
+    tls::ClientCertificate,
+};
+
+/// GET /api/events - Stream the item changes of the household (Server-Sent Events)
+pub async fn get_household_events(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    headers: HeaderMap,
+    extensions: Extensions,
+) -> Result<Sse<impl Stream<Item = events::SseEvent>>> {
+    let stream = events::subscribe(
+        &state.events,
+        state.pool.clone(),
+        principal,
+        household_id,
+        None,
+        last_event_id(&headers),
+    )
+    .await?;
+
+    let opener = Opener::new(headers, &extensions, household_id, None);
+    let stream = stream.take_until(revoked(state, opener));
+    Ok(Sse::new(stream.map(events::to_sse)).keep_alive(KeepAlive::default()))
+}
+
+/// GET /api/lists/:list_id/events - Stream the item changes of a list (Server-Sent Events)
+pub async fn get_list_events(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(list_id): Path<i32>,
+    headers: HeaderMap,
+    extensions: Extensions,
+) -> Result<Sse<impl Stream<Item = events::SseEvent>>> {
+    principal
+        .require_list_role(&state.pool, household_id, list_id, ListRole::Viewer)
+        .await?;
+
+    let stream = events::subscribe(
+        &state.events,
+        state.pool.clone(),
+        principal,
+        household_id,
+        Some(list_id),
+        last_event_id(&headers),
+    )
+    .await?;
+
+    let opener = Opener::new(headers, &extensions, household_id, Some(list_id));
+    let stream = stream.take_until(revoked(state, opener));
+    Ok(Sse::new(stream.map(events::to_sse)).keep_alive(KeepAlive::default()))
+}
+
+/// Sent by `EventSource` when it reconnects; anything else starts afresh
+fn last_event_id(headers: &HeaderMap) -> Option<i64> {
+    headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// What is needed to check the request that opened a stream again
+struct Opener {
+    headers: HeaderMap,
+    peer: Option<IpAddr>,
+    certificate: Option<ClientCertificate>,
+    household_id: i32,
+    list_id: Option<i32>,
+}
+
+impl Opener {
+    fn new(
+        headers: HeaderMap,
+        extensions: &Extensions,
+        household_id: i32,
+        list_id: Option<i32>,
+    ) -> Opener {
+        Opener {
+            headers,
+            peer: extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip()),
+            certificate: extensions.get::<ClientCertificate>().cloned(),
+            household_id,
+            list_id,
+        }
+    }
+
+    async fn check(&self, state: &AppState) -> Result<()> {
+        let principal = auth::authenticate(
+            state,
+            &self.headers,
+            &Method::GET,
+            self.peer,
+            self.certificate.as_ref(),
+        )
+        .await?;
+        let Tenant(household_id) =
+            tenant::resolve(state, &principal, Some(self.household_id)).await?;
+        if let Some(list_id) = self.list_id {
+            principal
+                .require_list_role(&state.pool, household_id, list_id, ListRole::Viewer)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Completes once the stream's credentials, or its role on the list, stop
+/// passing, which ends the stream. A reconnecting `EventSource` then gets the
+/// error.
+async fn revoked(state: AppState, opener: Opener) {
+    let mut recheck = tokio::time::interval_at(
+        Instant::now() + events::RECHECK_INTERVAL,
+        events::RECHECK_INTERVAL,
+    );
+    recheck.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        recheck.tick().await;
+        match opener.check(&state).await {
+            Ok(()) => {}
+            Err(AppError::Auth(_) | AppError::NotFound) => return,
+            // Not the client's fault, the next tick looks again
+            Err(e) => tracing::error!("Checking event stream credentials failed: {:?}", e),
+        }
+    }
+}
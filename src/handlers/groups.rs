@@ -0,0 +1,121 @@
+use axum::{extract::State, http::StatusCode};
+
+use crate::{
+    auth::{Principal, Tenant},
+    error::Result,
+    extract::{Json, Path, ValidJson},
+    models::{CreateGroupRequest, Group, UpdateGroupRequest, User},
+    services,
+    state::AppState,
+};
+
+/// GET /api/groups - Get all groups of the household
+pub async fn get_all_groups(
+    State(state): State<AppState>,
+    Tenant(household_id): Tenant,
+) -> Result<Json<Vec<Group>>> {
+    let mut conn = state.pool.acquire().await?;
+    let groups = services::groups::list(&mut conn, household_id).await?;
+
+    Ok(Json(groups))
+}
+
+/// GET /api/groups/:id - Get a single group
+pub async fn get_group(
+    State(state): State<AppState>,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+) -> Result<Json<Group>> {
+    let mut conn = state.pool.acquire().await?;
+    let group = services::groups::find(&mut conn, household_id, id).await?;
+
+    Ok(Json(group))
+}
+
+/// POST /api/groups - Create a group (admin)
+pub async fn create_group(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    ValidJson(payload): ValidJson<CreateGroupRequest>,
+) -> Result<(StatusCode, Json<Group>)> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let group = services::groups::create(&mut conn, household_id, &payload.name).await?;
+
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+/// PUT /api/groups/:id - Rename a group (admin)
+pub async fn update_group(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<UpdateGroupRequest>,
+) -> Result<Json<Group>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let group = services::groups::rename(&mut conn, household_id, id, &payload.name).await?;
+
+    Ok(Json(group))
+}
+
+/// DELETE /api/groups/:id - Delete a group and the roles granted to it (admin)
+pub async fn delete_group(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::groups::delete(&mut conn, household_id, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/groups/:id/members - Get the members of a group
+pub async fn get_group_members(
+    State(state): State<AppState>,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<User>>> {
+    let mut conn = state.pool.acquire().await?;
+    let members = services::groups::members(&mut conn, household_id, id).await?;
+
+    Ok(Json(members))
+}
+
+/// PUT /api/groups/:id/members/:user_id - Add a household member to a group (admin)
+pub async fn add_group_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::groups::add_member(&mut conn, household_id, id, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/groups/:id/members/:user_id - Remove a user from a group (admin)
+pub async fn remove_group_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::groups::remove_member(&mut conn, household_id, id, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
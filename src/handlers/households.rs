@@ -0,0 +1,237 @@
+use axum::{extract::State, http::StatusCode};
+use sqlx::PgConnection;
+
+use crate::{
+    auth::{AuthError, Principal, Tenant},
+    error::{AppError, Result},
+    extract::{Json, Path, ValidJson},
+    models::{
+        CreateHouseholdRequest, Household, HouseholdMember, HouseholdUsage, Quotas,
+        UpdateHouseholdMemberRequest, UpdateHouseholdRequest,
+    },
+    services,
+    state::AppState,
+};
+
+/// GET /api/households - Get all households (admin), or the caller's own
+pub async fn get_all_households(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<Vec<Household>>> {
+    let mut conn = state.pool.acquire().await?;
+
+    let households = match &principal {
+        Principal::User(user) if !user.is_admin => {
+            services::households::list(&mut conn, Some(user.id)).await?
+        }
+        Principal::Token(token) if !principal.is_admin() => {
+            vec![services::households::find(&mut conn, token.household_id).await?]
+        }
+        _ => services::households::list(&mut conn, None).await?,
+    };
+
+    Ok(Json(households))
+}
+
+/// GET /api/households/:id - Get a single household (admin or member)
+pub async fn get_household(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<Json<Household>> {
+    let mut conn = state.pool.acquire().await?;
+    require_member(&mut conn, &principal, id).await?;
+
+    let household = services::households::find(&mut conn, id).await?;
+
+    Ok(Json(household))
+}
+
+/// POST /api/households - Create a household (admin)
+pub async fn create_household(
+    State(state): State<AppState>,
+    principal: Principal,
+    ValidJson(payload): ValidJson<CreateHouseholdRequest>,
+) -> Result<(StatusCode, Json<Household>)> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let household = services::households::create(&mut conn, &payload.name).await?;
+
+    Ok((StatusCode::CREATED, Json(household)))
+}
+
+/// PUT /api/households/:id - Rename a household (admin)
+pub async fn update_household(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<UpdateHouseholdRequest>,
+) -> Result<Json<Household>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let household = services::households::rename(&mut conn, id, &payload.name).await?;
+
+    Ok(Json(household))
+}
+
+/// DELETE /api/households/:id - Delete a household with all its data (admin)
+pub async fn delete_household(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::households::delete(&mut conn, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/households/:id/members - Get the members of a household with their
+/// roles (admin or manager)
+pub async fn get_household_members(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<HouseholdMember>>> {
+    principal.require_household_manager(&state.pool, id).await?;
+
+    let mut conn = state.pool.acquire().await?;
+    let members = services::households::members(&mut conn, id).await?;
+
+    Ok(Json(members))
+}
+
+/// PUT /api/households/:id/members/:user_id - Add a user to a household (admin)
+pub async fn add_household_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::households::add_member(&mut conn, id, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/households/:id/members/:user_id/role - Change a member's role (admin)
+pub async fn update_household_member_role(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path((id, user_id)): Path<(i32, i32)>,
+    ValidJson(payload): ValidJson<UpdateHouseholdMemberRequest>,
+) -> Result<Json<HouseholdMember>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let member = services::households::set_role(&mut conn, id, user_id, &payload.role).await?;
+
+    Ok(Json(member))
+}
+
+/// DELETE /api/households/:id/members/:user_id - Remove a user from a household (admin)
+pub async fn remove_household_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut tx = state.pool.begin().await?;
+    services::households::remove_member(&mut tx, id, user_id).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/households/:id/usage - Get the usage and limits of the household's
+/// quotas (admin or member)
+pub async fn get_household_usage(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<Json<HouseholdUsage>> {
+    let mut conn = state.pool.acquire().await?;
+    require_member(&mut conn, &principal, id).await?;
+    services::households::find(&mut conn, id).await?;
+
+    let usage = services::quotas::usage(&mut conn, &state.config.quotas, id).await?;
+
+    Ok(Json(usage))
+}
+
+/// GET /api/households/:id/quotas - Get the household's quota overrides (admin)
+pub async fn get_household_quotas(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<Json<Quotas>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::households::find(&mut conn, id).await?;
+    let quotas = services::quotas::overrides(&mut conn, id).await?;
+
+    Ok(Json(quotas))
+}
+
+/// PUT /api/households/:id/quotas - Replace the household's quota overrides;
+/// `null` uses the server default (admin)
+pub async fn update_household_quotas(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<Quotas>,
+) -> Result<Json<Quotas>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let quotas = services::quotas::set_overrides(&mut conn, id, &payload).await?;
+
+    Ok(Json(quotas))
+}
+
+/// Admins see all households, users and API tokens only their own
+async fn require_member(conn: &mut PgConnection, principal: &Principal, id: i32) -> Result<()> {
+    let allowed = match principal {
+        Principal::User(user) if !user.is_admin => {
+            services::households::is_member(conn, id, user.id).await?
+        }
+        Principal::Token(token) if !principal.is_admin() => token.household_id == id,
+        _ => true,
+    };
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(AuthError::NoHousehold.into())
+    }
+}
+
+/// The household named in a request body, or the caller's own if none is given
+pub(crate) async fn target_household(
+    conn: &mut PgConnection,
+    requested: Option<i32>,
+    tenant: Option<Tenant>,
+) -> Result<i32> {
+    let Some(id) = requested else {
+        return tenant
+            .map(|Tenant(id)| id)
+            .ok_or(AuthError::NoHousehold.into());
+    };
+
+    match services::households::find(conn, id).await {
+        Ok(_) => Ok(id),
+        Err(AppError::NotFound) => Err(AppError::ParentNotFound {
+            parent: "resource.household",
+            field: "householdId".to_string(),
+            in_path: false,
+        }),
+        Err(error) => Err(error),
+    }
+}
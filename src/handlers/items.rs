@@ -1,30 +1,37 @@
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
-};
+use axum::{extract::State, http::StatusCode};
 
 use crate::{
+    auth::{ListRole, Principal, Tenant},
     error::{AppError, Result},
+    etag::{IfMatch, Tagged},
+    extract::{Json, Path, ValidJson},
     models::{CreateItemRequest, Item, UpdateItemRequest},
+    services::{self, items::ITEM_COLUMNS},
     state::AppState,
-    validation,
 };
 
 /// GET /api/lists/:list_id/items - Get all items in a list
 pub async fn get_list_items(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(list_id): Path<i32>,
 ) -> Result<Json<Vec<Item>>> {
-    let items = sqlx::query_as::<_, Item>(
+    principal
+        .require_list_role(&state.pool, household_id, list_id, ListRole::Viewer)
+        .await?;
+
+    let items = sqlx::query_as::<_, Item>(&format!(
         r#"
-        SELECT id, name, amount, "amountUnit", "inCart", list, category
+        SELECT {ITEM_COLUMNS}
         FROM items
         WHERE list = $1
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
         ORDER BY id ASC
-        "#,
-    )
+        "#
+    ))
     .bind(list_id)
+    .bind(household_id)
     .fetch_all(&state.pool)
     .await?;
 
@@ -32,251 +39,182 @@ pub async fn get_list_items(
 }
 
 /// GET /api/items/:id - Get a single item
-pub async fn get_item(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Item>> {
-    let item = sqlx::query_as::<_, Item>(
+pub async fn get_item(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+) -> Result<Tagged<Item>> {
+    authorize_item(&state, &principal, household_id, id, ListRole::Viewer).await?;
+
+    let item = sqlx::query_as::<_, Item>(&format!(
         r#"
-        SELECT id, name, amount, "amountUnit", "inCart", list, category
+        SELECT {ITEM_COLUMNS}
         FROM items
         WHERE id = $1
-        "#,
-    )
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
+        "#
+    ))
     .bind(id)
+    .bind(household_id)
     .fetch_optional(&state.pool)
     .await?
     .ok_or(AppError::NotFound)?;
 
-    Ok(Json(item))
+    Ok(Tagged(item))
 }
 
 /// POST /api/lists/:list_id/items - Create a new item
 pub async fn create_item(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(list_id): Path<i32>,
-    Json(payload): Json<CreateItemRequest>,
-) -> Result<(StatusCode, Json<Item>)> {
-    // Validate input
-    validation::validate_string(&payload.name, "Item name")?;
-    validation::validate_optional_string(&payload.amount_unit, "Amount unit")?;
-    validation::validate_optional_string(&payload.category, "Category")?;
+    ValidJson(payload): ValidJson<CreateItemRequest>,
+) -> Result<(StatusCode, Tagged<Item>)> {
+    // Lists of other households and private lists the user cannot see are
+    // reported like lists that do not exist
+    let list_not_found = || AppError::ParentNotFound {
+        parent: "resource.list",
+        field: "list".to_string(),
+        in_path: true,
+    };
+
+    match principal
+        .require_list_role(&state.pool, household_id, list_id, ListRole::Editor)
+        .await
+    {
+        Err(AppError::NotFound) => return Err(list_not_found()),
+        result => result?,
+    }
 
     // Start transaction
     let mut tx = state.pool.begin().await?;
 
-    // Insert category if provided and doesn't exist
-    if let Some(ref category) = payload.category {
-        sqlx::query(
-            r#"
-            INSERT INTO categories (name)
-            VALUES ($1)
-            ON CONFLICT (name) DO NOTHING
-            "#,
-        )
-        .bind(category)
-        .execute(&mut *tx)
-        .await?;
-    }
-
-    // Insert or update name entry for autocomplete
-    let existing_name = sqlx::query_scalar::<_, i32>(
+    let list_exists = sqlx::query_scalar::<_, bool>(
         r#"
-        SELECT id FROM names WHERE name = $1
+        SELECT EXISTS (SELECT 1 FROM lists WHERE id = $1 AND household_id = $2)
         "#,
     )
-    .bind(&payload.name)
-    .fetch_optional(&mut *tx)
+    .bind(list_id)
+    .bind(household_id)
+    .fetch_one(&mut *tx)
     .await?;
 
-    if existing_name.is_some() {
-        // Update count
-        sqlx::query(
-            r#"
-            UPDATE names
-            SET count = count + 1, category = COALESCE($2, category)
-            WHERE name = $1
-            "#,
-        )
-        .bind(&payload.name)
-        .bind(&payload.category)
-        .execute(&mut *tx)
-        .await?;
-    } else {
-        // Insert new name
-        sqlx::query(
-            r#"
-            INSERT INTO names (name, category, count)
-            VALUES ($1, $2, 1)
-            "#,
-        )
-        .bind(&payload.name)
-        .bind(&payload.category)
-        .execute(&mut *tx)
-        .await?;
+    if !list_exists {
+        return Err(list_not_found());
     }
 
-    // Insert item
-    let item = sqlx::query_as::<_, Item>(
-        r#"
-        INSERT INTO items (name, amount, "amountUnit", list, category, "inCart")
-        VALUES ($1, $2, $3, $4, $5, false)
-        RETURNING id, name, amount, "amountUnit", "inCart", list, category
-        "#,
-    )
-    .bind(&payload.name)
-    .bind(payload.amount)
-    .bind(&payload.amount_unit)
-    .bind(list_id)
-    .bind(&payload.category)
-    .fetch_one(&mut *tx)
-    .await?;
+    let limits = services::quotas::limits(&mut tx, &state.config.quotas, household_id).await?;
+    let item = services::items::create(&mut tx, &limits, household_id, list_id, &payload).await?;
 
     tx.commit().await?;
 
-    Ok((StatusCode::CREATED, Json(item)))
+    Ok((StatusCode::CREATED, Tagged(item)))
 }
 
 /// PUT /api/items/:id - Update an item
 pub async fn update_item(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateItemRequest>,
-) -> Result<Json<Item>> {
-    // Validate input
-    if let Some(ref name) = payload.name {
-        validation::validate_string(name, "Item name")?;
-    }
-    if let Some(Some(ref unit)) = payload.amount_unit {
-        validation::validate_string(unit, "Amount unit")?;
-    }
-    if let Some(Some(ref cat)) = payload.category {
-        validation::validate_string(cat, "Category")?;
-    }
+    if_match: IfMatch,
+    ValidJson(payload): ValidJson<UpdateItemRequest>,
+) -> Result<Tagged<Item>> {
+    authorize_item(&state, &principal, household_id, id, ListRole::Editor).await?;
 
     // Start transaction
     let mut tx = state.pool.begin().await?;
+    if_match.check(&services::items::lock(&mut tx, household_id, id).await?)?;
 
-    // Get current item
-    let current_item = sqlx::query_as::<_, Item>(
-        r#"
-        SELECT id, name, amount, "amountUnit", "inCart", list, category
-        FROM items
-        WHERE id = $1
-        "#,
-    )
-    .bind(id)
-    .fetch_optional(&mut *tx)
-    .await?
-    .ok_or(AppError::NotFound)?;
-
-    // Determine new values using Option<Option<T>>:
-    // - None = field not in request, keep current value
-    // - Some(None) = field is explicitly null, set to NULL
-    // - Some(Some(value)) = field has a value, use it
-    let new_name = payload.name.as_ref().unwrap_or(&current_item.name);
-    let new_amount = match payload.amount {
-        Some(inner) => inner,           // Field present: use it (even if None)
-        None => current_item.amount,    // Field missing: keep current
-    };
-    let new_amount_unit = match payload.amount_unit {
-        Some(inner) => inner,
-        None => current_item.amount_unit.clone(),
-    };
-    let new_category = match payload.category {
-        Some(inner) => inner,
-        None => current_item.category.clone(),
-    };
-
-    // Insert category if provided and doesn't exist
-    if let Some(ref category) = new_category {
-        sqlx::query(
-            r#"
-            INSERT INTO categories (name)
-            VALUES ($1)
-            ON CONFLICT (name) DO NOTHING
-            "#,
-        )
-        .bind(category)
-        .execute(&mut *tx)
-        .await?;
-    }
-
-    // Update names table category association
-    sqlx::query(
-        r#"
-        UPDATE names
-        SET category = $2
-        WHERE name = $1
-        "#,
-    )
-    .bind(new_name)
-    .bind(&new_category)
-    .execute(&mut *tx)
-    .await?;
-
-    // Update item
-    let item = sqlx::query_as::<_, Item>(
-        r#"
-        UPDATE items
-        SET name = $1,
-            amount = $2,
-            "amountUnit" = $3,
-            category = $4
-        WHERE id = $5
-        RETURNING id, name, amount, "amountUnit", "inCart", list, category
-        "#,
-    )
-    .bind(new_name)
-    .bind(&new_amount)
-    .bind(&new_amount_unit)
-    .bind(&new_category)
-    .bind(id)
-    .fetch_one(&mut *tx)
-    .await?;
+    let limits = services::quotas::limits(&mut tx, &state.config.quotas, household_id).await?;
+    let item = services::items::update(&mut tx, &limits, household_id, id, &payload).await?;
 
     tx.commit().await?;
 
-    Ok(Json(item))
+    Ok(Tagged(item))
 }
 
 /// PATCH /api/items/:id/toggle - Toggle item in cart status
 pub async fn toggle_item(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
-) -> Result<Json<Item>> {
-    let item = sqlx::query_as::<_, Item>(
+    if_match: IfMatch,
+) -> Result<Tagged<Item>> {
+    authorize_item(&state, &principal, household_id, id, ListRole::Editor).await?;
+
+    let mut tx = state.pool.begin().await?;
+    if_match.check(&services::items::lock(&mut tx, household_id, id).await?)?;
+
+    let item = sqlx::query_as::<_, Item>(&format!(
         r#"
         UPDATE items
         SET "inCart" = NOT "inCart"
         WHERE id = $1
-        RETURNING id, name, amount, "amountUnit", "inCart", list, category
-        "#,
-    )
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
+        RETURNING {ITEM_COLUMNS}
+        "#
+    ))
     .bind(id)
-    .fetch_optional(&state.pool)
+    .bind(household_id)
+    .fetch_optional(&mut *tx)
     .await?
     .ok_or(AppError::NotFound)?;
 
-    Ok(Json(item))
+    tx.commit().await?;
+
+    Ok(Tagged(item))
 }
 
 /// DELETE /api/items/:id - Delete an item
 pub async fn delete_item(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
+    if_match: IfMatch,
 ) -> Result<StatusCode> {
-    let result = sqlx::query(
+    authorize_item(&state, &principal, household_id, id, ListRole::Editor).await?;
+
+    let mut tx = state.pool.begin().await?;
+    if_match.check(&services::items::lock(&mut tx, household_id, id).await?)?;
+    services::items::delete(&mut tx, household_id, id).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Check the role on the list an item belongs to. Only looks the list up for
+/// users and principals limited to single lists.
+async fn authorize_item(
+    state: &AppState,
+    principal: &Principal,
+    household_id: i32,
+    id: i32,
+    role: ListRole,
+) -> Result<()> {
+    if principal.user().is_none() && principal.can(role.access()) {
+        return Ok(());
+    }
+
+    let list_id = sqlx::query_scalar::<_, i32>(
         r#"
-        DELETE FROM items
+        SELECT list
+        FROM items
         WHERE id = $1
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
         "#,
     )
     .bind(id)
-    .execute(&state.pool)
-    .await?;
-
-    if result.rows_affected() == 0 {
-        return Err(AppError::NotFound);
-    }
+    .bind(household_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
 
-    Ok(StatusCode::NO_CONTENT)
+    principal
+        .require_list_role(&state.pool, household_id, list_id, role)
+        .await
 }
-
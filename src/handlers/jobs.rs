@@ -0,0 +1,71 @@
+use axum::extract::State;
+
+use crate::{
+    auth::Principal,
+    error::{AppError, Result},
+    extract::{Json, Path},
+    i18n::Message,
+    jobs::Trigger,
+    models::{JobDetails, JobRun, JobStatus},
+    state::AppState,
+};
+
+const HISTORY_LIMIT: i64 = 50;
+
+/// GET /api/admin/jobs - Get all background jobs with their last run
+pub async fn get_all_jobs(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<Vec<JobStatus>>> {
+    principal.require_admin()?;
+
+    let mut jobs = Vec::new();
+    for scheduled in state.scheduler.jobs() {
+        jobs.push(state.scheduler.status(scheduled).await?);
+    }
+
+    Ok(Json(jobs))
+}
+
+/// GET /api/admin/jobs/:name - Get a job with its run history
+pub async fn get_job(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(name): Path<String>,
+) -> Result<Json<JobDetails>> {
+    principal.require_admin()?;
+
+    let scheduled = state.scheduler.find(&name).ok_or(AppError::NotFound)?;
+
+    let status = state.scheduler.status(scheduled).await?;
+    let runs = state
+        .scheduler
+        .runs(scheduled.job.name, false, HISTORY_LIMIT)
+        .await?;
+
+    Ok(Json(JobDetails { status, runs }))
+}
+
+/// POST /api/admin/jobs/:name/run - Run a job now
+pub async fn run_job(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(name): Path<String>,
+) -> Result<Json<JobRun>> {
+    principal.require_admin()?;
+
+    let scheduled = state.scheduler.find(&name).ok_or(AppError::NotFound)?;
+
+    // The run goes on if the request times out or the client goes away
+    let scheduler = state.scheduler.clone();
+    let job = scheduled.job;
+    let run = tokio::spawn(async move { scheduler.run(job, Trigger::Manual).await })
+        .await
+        .map_err(|error| {
+            tracing::error!("Job {} panicked: {}", job.name, error);
+            AppError::Internal
+        })??
+        .ok_or_else(|| AppError::Conflict(Message::new("error.job_running")))?;
+
+    Ok(Json(run))
+}
@@ -0,0 +1,132 @@
+use axum::{extract::State, http::StatusCode};
+
+use crate::{
+    auth::{ListRole, Principal, Tenant},
+    error::Result,
+    etag::{IfMatch, Tagged},
+    extract::{Json, Path, ValidJson},
+    models::{GrantListRoleRequest, List, ListPermission, TransferListRequest},
+    services,
+    state::AppState,
+};
+
+/// GET /api/lists/:id/permissions - Get the roles granted on a list
+pub async fn get_list_permissions(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<ListPermission>>> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Viewer)
+        .await?;
+
+    let mut conn = state.pool.acquire().await?;
+    let permissions = services::list_permissions::list(&mut conn, id).await?;
+
+    Ok(Json(permissions))
+}
+
+/// PUT /api/lists/:id/permissions/users/:user_id - Grant a user a role (owner)
+pub async fn grant_list_role_to_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path((id, user_id)): Path<(i32, i32)>,
+    ValidJson(payload): ValidJson<GrantListRoleRequest>,
+) -> Result<Json<ListPermission>> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
+
+    let mut conn = state.pool.acquire().await?;
+    let permission =
+        services::list_permissions::grant_user(&mut conn, household_id, id, user_id, &payload.role)
+            .await?;
+
+    Ok(Json(permission))
+}
+
+/// DELETE /api/lists/:id/permissions/users/:user_id - Revoke a user's role (owner)
+pub async fn revoke_list_role_from_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::list_permissions::revoke_user(&mut conn, id, user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/lists/:id/permissions/groups/:group_id - Grant a group a role (owner)
+pub async fn grant_list_role_to_group(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path((id, group_id)): Path<(i32, i32)>,
+    ValidJson(payload): ValidJson<GrantListRoleRequest>,
+) -> Result<Json<ListPermission>> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
+
+    let mut conn = state.pool.acquire().await?;
+    let permission = services::list_permissions::grant_group(
+        &mut conn,
+        household_id,
+        id,
+        group_id,
+        &payload.role,
+    )
+    .await?;
+
+    Ok(Json(permission))
+}
+
+/// DELETE /api/lists/:id/permissions/groups/:group_id - Revoke a group's role (owner)
+pub async fn revoke_list_role_from_group(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path((id, group_id)): Path<(i32, i32)>,
+) -> Result<StatusCode> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::list_permissions::revoke_group(&mut conn, id, group_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/lists/:id/owner - Transfer ownership of a list (owner or admin)
+/// Admins may take over lists whose owner left.
+pub async fn transfer_list(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+    if_match: IfMatch,
+    Json(payload): Json<TransferListRequest>,
+) -> Result<Tagged<List>> {
+    if !principal.is_admin() {
+        principal
+            .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+            .await?;
+    }
+
+    let mut tx = state.pool.begin().await?;
+    if_match.check(&services::lists::lock(&mut tx, household_id, id).await?)?;
+    let list =
+        services::list_permissions::transfer(&mut tx, household_id, id, payload.user_id).await?;
+    tx.commit().await?;
+
+    Ok(Tagged(list))
+}
@@ -0,0 +1,155 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use maud::{html, Markup, DOCTYPE};
+
+use crate::{
+    auth::{ListRole, Principal, Tenant},
+    error::Result,
+    extract::{Json, Path, ValidJson},
+    i18n::{Lang, Message},
+    models::{CreateListShareRequest, CreatedListShare, ListShare, SharedList},
+    services,
+    state::AppState,
+};
+
+/// GET /api/lists/:id/shares - Get the share links of a list (owner)
+pub async fn get_list_shares(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<ListShare>>> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
+
+    let mut conn = state.pool.acquire().await?;
+    let shares = services::list_shares::list(&mut conn, household_id, id).await?;
+
+    Ok(Json(shares))
+}
+
+/// POST /api/lists/:id/shares - Create a read-only share link (owner)
+pub async fn create_list_share(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<CreateListShareRequest>,
+) -> Result<(StatusCode, Json<CreatedListShare>)> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
+
+    let expires_at = payload
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(days));
+    let created_by = principal.user().map(|user| user.id);
+
+    let mut conn = state.pool.acquire().await?;
+    let share =
+        services::list_shares::create(&mut conn, household_id, id, created_by, expires_at).await?;
+
+    Ok((StatusCode::CREATED, Json(share)))
+}
+
+/// DELETE /api/lists/:id/shares/:share_id - Revoke a share link (owner)
+pub async fn revoke_list_share(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path((id, share_id)): Path<(i32, i32)>,
+) -> Result<StatusCode> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::list_shares::revoke(&mut conn, household_id, id, share_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/shared/:token - Read-only view of a shared list (no authentication)
+/// Browsers get an HTML page, other clients JSON.
+pub async fn get_shared_list(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(token): Path<String>,
+) -> Result<Response> {
+    let mut conn = state.pool.acquire().await?;
+    let shared = services::list_shares::open(&mut conn, &token).await?;
+
+    let wants_html = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    let body = if wants_html {
+        render_shared_list(&shared, Lang::from_headers(&headers)).into_response()
+    } else {
+        Json(shared).into_response()
+    };
+
+    // Keep the token out of referrers and search engines
+    Ok((
+        [
+            (header::REFERRER_POLICY, "no-referrer"),
+            (header::HeaderName::from_static("x-robots-tag"), "noindex"),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+fn render_shared_list(shared: &SharedList, lang: Lang) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(lang.code()) {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { (shared.name) }
+                style { (STYLE) }
+            }
+            body {
+                h1 { (shared.name) }
+                @if shared.items.is_empty() {
+                    p.muted { (Message::new("shared.empty").render(lang)) }
+                }
+                ul {
+                    @for item in &shared.items {
+                        li.done[item.in_cart] {
+                            (item.name)
+                            @if let Some(amount) = item.amount {
+                                " · " (amount.normalize())
+                                @if let Some(unit) = &item.amount_unit { " " (unit) }
+                            }
+                            @if let Some(category) = &item.category {
+                                span.muted { " " (category) }
+                            }
+                        }
+                    }
+                }
+                @if let Some(expires_at) = shared.expires_at {
+                    p.muted {
+                        (Message::new("shared.expires")
+                            .arg("date", expires_at.format("%Y-%m-%d"))
+                            .render(lang))
+                    }
+                }
+            }
+        }
+    }
+}
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 1rem auto; max-width: 40rem; padding: 0 1rem; }
+ul { list-style: none; padding: 0; }
+li { border-bottom: 1px solid #ddd; padding: .5rem 0; }
+li.done { text-decoration: line-through; color: #777; }
+.muted { color: #777; font-size: .9em; }
+"#;
@@ -1,25 +1,40 @@
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
-};
+use axum::{extract::State, http::StatusCode};
 
 use crate::{
+    auth::{Access, ListRole, Principal, Tenant},
     error::{AppError, Result},
+    etag::{IfMatch, Tagged},
+    extract::{Json, Path, ValidJson},
     models::{CreateListRequest, List, ListWithCount, UpdateListRequest},
+    services::{self, lists::LIST_COLUMNS},
     state::AppState,
-    validation,
 };
 
 /// GET /api/lists - Get all lists with item counts
-pub async fn get_all_lists(State(state): State<AppState>) -> Result<Json<Vec<ListWithCount>>> {
+/// Users only see the lists they have a role on; API tokens scoped to single
+/// lists only see those lists.
+pub async fn get_all_lists(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+) -> Result<Json<Vec<ListWithCount>>> {
+    let user_id = principal.user().map(|user| user.id);
+
     let lists = sqlx::query_as::<_, ListWithCount>(
         r#"
-        SELECT l.id, l.name, (SELECT COUNT(*) FROM items WHERE "list" = l.id) as count
+        SELECT l.id, l.uuid, l.name, l.owner_id, l.private, l.version, r.role,
+               (SELECT COUNT(*) FROM items WHERE "list" = l.id) as count
         FROM lists l
+        CROSS JOIN LATERAL (SELECT list_role(l.id, $3)::text AS role) r
+        WHERE l.household_id = $1
+          AND ($2::int[] IS NULL OR l.id = ANY($2))
+          AND ($3::int IS NULL OR r.role IS NOT NULL)
         ORDER BY id ASC
         "#,
     )
+    .bind(household_id)
+    .bind(principal.list_ids(Access::Read))
+    .bind(user_id)
     .fetch_all(&state.pool)
     .await?;
 
@@ -27,89 +42,89 @@ pub async fn get_all_lists(State(state): State<AppState>) -> Result<Json<Vec<Lis
 }
 
 /// GET /api/lists/:id - Get a single list
-pub async fn get_list(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<List>> {
-    let list = sqlx::query_as::<_, List>(
+pub async fn get_list(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Path(id): Path<i32>,
+) -> Result<Tagged<List>> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Viewer)
+        .await?;
+
+    let list = sqlx::query_as::<_, List>(&format!(
         r#"
-        SELECT id, name
+        SELECT {LIST_COLUMNS}
         FROM lists
-        WHERE id = $1
-        "#,
-    )
+        WHERE id = $1 AND household_id = $2
+        "#
+    ))
     .bind(id)
+    .bind(household_id)
     .fetch_optional(&state.pool)
     .await?
     .ok_or(AppError::NotFound)?;
 
-    Ok(Json(list))
+    Ok(Tagged(list))
 }
 
-/// POST /api/lists - Create a new list
+/// POST /api/lists - Create a new list, owned by the user creating it
 pub async fn create_list(
     State(state): State<AppState>,
-    Json(payload): Json<CreateListRequest>,
-) -> Result<(StatusCode, Json<List>)> {
-    // Validate input
-    validation::validate_string(&payload.name, "List name")?;
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    ValidJson(payload): ValidJson<CreateListRequest>,
+) -> Result<(StatusCode, Tagged<List>)> {
+    principal.require(Access::Write)?;
 
-    let list = sqlx::query_as::<_, List>(
-        r#"
-        INSERT INTO lists (name)
-        VALUES ($1)
-        RETURNING id, name
-        "#,
-    )
-    .bind(&payload.name)
-    .fetch_one(&state.pool)
-    .await?;
+    let mut tx = state.pool.begin().await?;
+    let limits = services::quotas::limits(&mut tx, &state.config.quotas, household_id).await?;
+    let owner_id = principal.user().map(|user| user.id);
+    let list = services::lists::create(&mut tx, &limits, household_id, owner_id, &payload).await?;
+
+    tx.commit().await?;
 
-    Ok((StatusCode::CREATED, Json(list)))
+    Ok((StatusCode::CREATED, Tagged(list)))
 }
 
-/// PUT /api/lists/:id - Update a list (rename)
+/// PUT /api/lists/:id - Update a list (rename, make private or shared) (owner)
 pub async fn update_list(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateListRequest>,
-) -> Result<Json<List>> {
-    // Validate input
-    validation::validate_string(&payload.name, "List name")?;
+    if_match: IfMatch,
+    ValidJson(payload): ValidJson<UpdateListRequest>,
+) -> Result<Tagged<List>> {
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
 
-    let list = sqlx::query_as::<_, List>(
-        r#"
-        UPDATE lists
-        SET name = $1
-        WHERE id = $2
-        RETURNING id, name
-        "#,
-    )
-    .bind(&payload.name)
-    .bind(id)
-    .fetch_optional(&state.pool)
-    .await?
-    .ok_or(AppError::NotFound)?;
+    let mut tx = state.pool.begin().await?;
+    if_match.check(&services::lists::lock(&mut tx, household_id, id).await?)?;
+    let name = Some(payload.name.as_str());
+    let list = services::lists::update(&mut tx, household_id, id, name, payload.private).await?;
+    tx.commit().await?;
 
-    Ok(Json(list))
+    Ok(Tagged(list))
 }
 
-/// DELETE /api/lists/:id - Delete a list
+/// DELETE /api/lists/:id - Delete a list (owner)
 pub async fn delete_list(
     State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
     Path(id): Path<i32>,
+    if_match: IfMatch,
 ) -> Result<StatusCode> {
-    let result = sqlx::query(
-        r#"
-        DELETE FROM lists
-        WHERE id = $1
-        "#,
-    )
-    .bind(id)
-    .execute(&state.pool)
-    .await?;
+    principal
+        .require_list_role(&state.pool, household_id, id, ListRole::Owner)
+        .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(AppError::NotFound);
-    }
+    let mut tx = state.pool.begin().await?;
+    if_match.check(&services::lists::lock(&mut tx, household_id, id).await?)?;
+    services::lists::delete(&mut tx, household_id, id).await?;
+    tx.commit().await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
-
@@ -1,12 +1,38 @@
+pub mod audit;
+pub mod auth;
 pub mod categories;
+pub mod data_requests;
+pub mod events;
+pub mod groups;
+pub mod households;
 pub mod items;
+pub mod jobs;
+pub mod list_permissions;
+pub mod list_shares;
 pub mod lists;
 pub mod names;
+pub mod passkeys;
 pub mod search;
+pub mod sync;
+pub mod tokens;
+pub mod users;
+pub mod ws;
 
+pub use audit::*;
 pub use categories::*;
+pub use data_requests::*;
+pub use events::*;
+pub use groups::*;
+pub use households::*;
 pub use items::*;
+pub use jobs::*;
+pub use list_permissions::*;
+pub use list_shares::*;
 pub use lists::*;
 pub use names::*;
 pub use search::*;
+pub use sync::*;
+pub use tokens::*;
+pub use users::*;
+pub use ws::*;
 
@@ -0,0 +1,92 @@
+use axum::{extract::State, http::StatusCode};
+
+use crate::{
+    auth::{webauthn, CurrentUser},
+    config::WebauthnConfig,
+    error::{AppError, Result},
+    extract::{Json, Path, ValidJson},
+    models::{Passkey, PasskeyOptions, RegisterPasskeyRequest, UpdatePasskeyRequest},
+    services,
+    state::AppState,
+};
+
+/// GET /api/auth/me/passkeys - Get the signed-in user's passkeys
+pub async fn get_all_passkeys(
+    State(state): State<AppState>,
+    current: CurrentUser,
+) -> Result<Json<Vec<Passkey>>> {
+    webauthn_config(&state)?;
+
+    let mut conn = state.pool.acquire().await?;
+    let passkeys = services::passkeys::list(&mut conn, current.id).await?;
+
+    Ok(Json(passkeys))
+}
+
+/// POST /api/auth/me/passkeys/options - Start registering a passkey
+pub async fn passkey_registration_options(
+    State(state): State<AppState>,
+    current: CurrentUser,
+) -> Result<Json<PasskeyOptions>> {
+    let config = webauthn_config(&state)?;
+
+    let mut conn = state.pool.acquire().await?;
+    let user = services::users::find(&mut conn, current.id).await?;
+    let options = webauthn::registration_options(&mut conn, config, &user).await?;
+
+    Ok(Json(options))
+}
+
+/// POST /api/auth/me/passkeys - Finish registering a passkey
+pub async fn create_passkey(
+    State(state): State<AppState>,
+    current: CurrentUser,
+    ValidJson(payload): ValidJson<RegisterPasskeyRequest>,
+) -> Result<(StatusCode, Json<Passkey>)> {
+    let config = webauthn_config(&state)?;
+    let mut tx = state.pool.begin().await?;
+
+    let credential =
+        webauthn::verify_registration(&mut tx, config, current.id, &payload.credential).await?;
+    let passkey =
+        services::passkeys::create(&mut tx, current.id, &payload.name, &credential).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(passkey)))
+}
+
+/// PUT /api/auth/me/passkeys/:id - Rename a passkey
+pub async fn update_passkey(
+    State(state): State<AppState>,
+    current: CurrentUser,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<UpdatePasskeyRequest>,
+) -> Result<Json<Passkey>> {
+    webauthn_config(&state)?;
+
+    let mut conn = state.pool.acquire().await?;
+    let passkey = services::passkeys::rename(&mut conn, current.id, id, &payload.name).await?;
+
+    Ok(Json(passkey))
+}
+
+/// DELETE /api/auth/me/passkeys/:id - Remove a passkey
+pub async fn delete_passkey(
+    State(state): State<AppState>,
+    current: CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    webauthn_config(&state)?;
+
+    let mut tx = state.pool.begin().await?;
+    services::passkeys::delete(&mut tx, current.id, id).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Passkey routes do not exist unless `WEBAUTHN_ORIGIN` is set
+pub(super) fn webauthn_config(state: &AppState) -> Result<&WebauthnConfig> {
+    state.config.webauthn.as_ref().ok_or(AppError::NotFound)
+}
@@ -1,28 +1,22 @@
-use axum::{extract::State, Json};
-use serde::Serialize;
+use axum::extract::State;
 use std::collections::HashMap;
 
-use crate::{error::Result, state::AppState};
-
-#[derive(Serialize)]
-pub struct SearchResponse {
-    pub names: Vec<String>,
-}
-
-#[derive(Serialize)]
-pub struct CategoryByProductResponse {
-    pub mappings: HashMap<String, Option<String>>,
-}
+use crate::{auth::Tenant, error::Result, extract::Json, state::AppState};
 
 /// GET /api/search - Get all known item names for autocomplete
-pub async fn search_names(State(state): State<AppState>) -> Result<Json<Vec<String>>> {
+pub async fn search_names(
+    State(state): State<AppState>,
+    Tenant(household_id): Tenant,
+) -> Result<Json<Vec<String>>> {
     let names = sqlx::query_scalar::<_, String>(
         r#"
         SELECT name
         FROM names
+        WHERE household_id = $1
         ORDER BY count DESC, name ASC
         "#,
     )
+    .bind(household_id)
     .fetch_all(&state.pool)
     .await?;
 
@@ -32,13 +26,16 @@ pub async fn search_names(State(state): State<AppState>) -> Result<Json<Vec<Stri
 /// GET /api/search/category-mappings - Get product name to category mappings
 pub async fn get_category_mappings(
     State(state): State<AppState>,
+    Tenant(household_id): Tenant,
 ) -> Result<Json<HashMap<String, Option<String>>>> {
     let rows = sqlx::query_as::<_, (String, Option<String>)>(
         r#"
         SELECT name, category
         FROM names
+        WHERE household_id = $1
         "#,
     )
+    .bind(household_id)
     .fetch_all(&state.pool)
     .await?;
 
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, http::HeaderMap};
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    auth::{Access, ListRole, Principal, Tenant},
+    error::{AppError, Result},
+    extract::{Json, Query, ValidJson},
+    i18n::{Lang, Message},
+    models::{
+        Change, CreateItemRequest, Item, List, ListChanges, Operation, OperationResult,
+        PushRequest, PushResult, SyncChanges, SyncCursor, SyncQuery, UpdateItemRequest,
+    },
+    problem::REQUEST_ID_HEADER,
+    services,
+    state::AppState,
+    validation::Validate,
+};
+
+/// GET /api/sync - Get the changes to lists, items, categories and names since a cursor
+pub async fn get_sync(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    Query(mut query): Query<SyncQuery>,
+) -> Result<Json<SyncChanges>> {
+    query.validated()?;
+    let since = query
+        .since
+        .as_deref()
+        .map(|since| {
+            SyncCursor::parse(since)
+                .ok_or_else(|| AppError::BadRequest(Message::new("error.invalid_sync_cursor")))
+        })
+        .transpose()?;
+
+    // The window and the changes in it are read from one snapshot
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let changes = services::sync::changes(
+        &mut tx,
+        household_id,
+        principal.list_ids(Access::Read).as_deref(),
+        principal.user().map(|user| user.id),
+        since,
+        query.limit(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(changes))
+}
+
+/// POST /api/sync - Replay changes made offline, in order
+/// Operations that fail are reported with a problem each and do not stop the
+/// ones after them.
+pub async fn push_changes(
+    State(state): State<AppState>,
+    principal: Principal,
+    Tenant(household_id): Tenant,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<PushRequest>,
+) -> Result<Json<PushResult>> {
+    let language = principal
+        .user()
+        .and_then(|user| user.language)
+        .unwrap_or_else(|| Lang::from_headers(&headers));
+    let request_id = headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string);
+
+    let mut results = Vec::with_capacity(payload.operations.len());
+    let mut list_uuids = Vec::new();
+    let mut item_uuids = Vec::new();
+    for operation in payload.operations {
+        let (list_uuid, item_uuid) = operation.change.targets();
+        list_uuids.extend(list_uuid);
+        item_uuids.extend(item_uuid);
+
+        let id = operation.id;
+        let result = match replay(&state, &principal, household_id, operation).await {
+            Ok(result) => result,
+            Err(error) => {
+                if let AppError::Database(ref e) = error {
+                    tracing::error!("Database error: {:?}", e);
+                }
+                let mut problem = error.problem();
+                problem.request_id = request_id.clone();
+                problem.localize(language);
+                OperationResult::failed(id, problem)
+            }
+        };
+        results.push(result);
+    }
+
+    let mut conn = state.pool.acquire().await?;
+    let (lists, items) = services::sync::rows(
+        &mut conn,
+        household_id,
+        principal.list_ids(Access::Read).as_deref(),
+        principal.user().map(|user| user.id),
+        &list_uuids,
+        &item_uuids,
+    )
+    .await?;
+
+    Ok(Json(PushResult {
+        results,
+        lists,
+        items,
+    }))
+}
+
+enum Outcome {
+    /// With the fields left alone because they changed later
+    Applied(Vec<&'static str>),
+    Skipped(&'static str, Vec<&'static str>),
+}
+
+/// Apply an operation in a transaction of its own. Skipped operations are
+/// recorded like applied ones; failed ones are not, so they can be sent again.
+///
+/// Conflicts are settled by these rules:
+/// - Deletes win: changes to deleted lists and items, and new items in deleted
+///   lists, are skipped. Deleting what is gone already is skipped as well.
+/// - The last change to a field wins: a field changed after the operation was
+///   made is left alone.
+/// - Creating a list or item that exists already changes it like an update.
+async fn replay(
+    state: &AppState,
+    principal: &Principal,
+    household_id: i32,
+    operation: Operation,
+) -> Result<OperationResult> {
+    let Operation { id, at, change } = operation;
+    let user_id = principal.user().map(|user| user.id);
+
+    let mut tx = state.pool.begin().await?;
+    if !services::sync::record_operation(&mut tx, household_id, id, user_id).await? {
+        return Ok(OperationResult::duplicate(id));
+    }
+    let at = services::sync::set_changed_at(&mut tx, at).await?;
+
+    let outcome = match change {
+        Change::CreateList { list } => {
+            principal.require(Access::Write)?;
+            let existing = match list.uuid {
+                Some(uuid) => services::lists::find_by_uuid(&mut tx, household_id, uuid).await?,
+                None => None,
+            };
+
+            match existing {
+                Some(existing) => {
+                    let changes = ListChanges {
+                        name: Some(list.name),
+                        private: Some(list.private),
+                    };
+                    update_list(principal, &mut tx, household_id, at, &existing, changes).await?
+                }
+                None if deleted(&mut tx, household_id, "lists", list.uuid).await? => {
+                    Outcome::Skipped("deleted", Vec::new())
+                }
+                None => {
+                    let limits =
+                        services::quotas::limits(&mut tx, &state.config.quotas, household_id)
+                            .await?;
+                    services::lists::create(&mut tx, &limits, household_id, user_id, &list).await?;
+                    Outcome::Applied(Vec::new())
+                }
+            }
+        }
+        Change::UpdateList {
+            uuid,
+            list: changes,
+        } => match services::lists::find_by_uuid(&mut tx, household_id, uuid).await? {
+            Some(list) => update_list(principal, &mut tx, household_id, at, &list, changes).await?,
+            None if deleted(&mut tx, household_id, "lists", Some(uuid)).await? => {
+                Outcome::Skipped("deleted", Vec::new())
+            }
+            None => return Err(AppError::NotFound),
+        },
+        Change::DeleteList { uuid } => {
+            match services::lists::find_by_uuid(&mut tx, household_id, uuid).await? {
+                Some(list) => {
+                    principal
+                        .require_list_role(&mut *tx, household_id, list.id, ListRole::Owner)
+                        .await?;
+                    services::lists::delete(&mut tx, household_id, list.id).await?;
+                    Outcome::Applied(Vec::new())
+                }
+                None => Outcome::Skipped("deleted", Vec::new()),
+            }
+        }
+        Change::CreateItem { list_uuid, item } => {
+            let existing = match item.uuid {
+                Some(uuid) => services::items::find_by_uuid(&mut tx, household_id, uuid).await?,
+                None => None,
+            };
+            let list = services::lists::find_by_uuid(&mut tx, household_id, list_uuid).await?;
+
+            match (existing, list) {
+                (Some(existing), _) => {
+                    let changes = UpdateItemRequest {
+                        name: Some(item.name),
+                        amount: Some(item.amount),
+                        amount_unit: Some(item.amount_unit),
+                        category: Some(item.category),
+                        in_cart: Some(item.in_cart),
+                    };
+                    update_item(
+                        state,
+                        principal,
+                        &mut tx,
+                        household_id,
+                        at,
+                        &existing,
+                        changes,
+                    )
+                    .await?
+                }
+                (None, _) if deleted(&mut tx, household_id, "items", item.uuid).await? => {
+                    Outcome::Skipped("deleted", Vec::new())
+                }
+                (None, Some(list)) => {
+                    create_item(state, principal, &mut tx, household_id, &list, &item).await?
+                }
+                (None, None)
+                    if deleted(&mut tx, household_id, "lists", Some(list_uuid)).await? =>
+                {
+                    Outcome::Skipped("deleted", Vec::new())
+                }
+                (None, None) => return Err(list_not_found()),
+            }
+        }
+        Change::UpdateItem {
+            uuid,
+            item: changes,
+        } => match services::items::find_by_uuid(&mut tx, household_id, uuid).await? {
+            Some(item) => {
+                update_item(state, principal, &mut tx, household_id, at, &item, changes).await?
+            }
+            None if deleted(&mut tx, household_id, "items", Some(uuid)).await? => {
+                Outcome::Skipped("deleted", Vec::new())
+            }
+            None => return Err(AppError::NotFound),
+        },
+        Change::DeleteItem { uuid } => {
+            match services::items::find_by_uuid(&mut tx, household_id, uuid).await? {
+                Some(item) => {
+                    principal
+                        .require_list_role(&mut *tx, household_id, item.list, ListRole::Editor)
+                        .await?;
+                    services::items::delete(&mut tx, household_id, item.id).await?;
+                    Outcome::Applied(Vec::new())
+                }
+                None => Outcome::Skipped("deleted", Vec::new()),
+            }
+        }
+    };
+
+    tx.commit().await?;
+
+    Ok(match outcome {
+        Outcome::Applied(outdated) => OperationResult::applied(id, outdated),
+        Outcome::Skipped(reason, outdated) => OperationResult::skipped(id, reason, outdated),
+    })
+}
+
+async fn update_list(
+    principal: &Principal,
+    conn: &mut PgConnection,
+    household_id: i32,
+    at: DateTime<Utc>,
+    list: &List,
+    changes: ListChanges,
+) -> Result<Outcome> {
+    principal
+        .require_list_role(&mut *conn, household_id, list.id, ListRole::Owner)
+        .await?;
+
+    let mut fields = Fields::load(conn, "lists", list.id, at).await?;
+    let name = fields.keep("name", changes.name);
+    let private = fields.keep("private", changes.private);
+    if name.is_none() && private.is_none() {
+        return Ok(fields.unchanged());
+    }
+
+    services::lists::update(conn, household_id, list.id, name.as_deref(), private).await?;
+    Ok(Outcome::Applied(fields.outdated))
+}
+
+async fn create_item(
+    state: &AppState,
+    principal: &Principal,
+    conn: &mut PgConnection,
+    household_id: i32,
+    list: &List,
+    item: &CreateItemRequest,
+) -> Result<Outcome> {
+    // Like in `POST /api/lists/:list_id/items`, lists the user cannot see
+    // are reported like lists that do not exist
+    match principal
+        .require_list_role(&mut *conn, household_id, list.id, ListRole::Editor)
+        .await
+    {
+        Err(AppError::NotFound) => return Err(list_not_found()),
+        result => result?,
+    }
+
+    let limits = services::quotas::limits(conn, &state.config.quotas, household_id).await?;
+    services::items::create(conn, &limits, household_id, list.id, item).await?;
+    Ok(Outcome::Applied(Vec::new()))
+}
+
+async fn update_item(
+    state: &AppState,
+    principal: &Principal,
+    conn: &mut PgConnection,
+    household_id: i32,
+    at: DateTime<Utc>,
+    item: &Item,
+    changes: UpdateItemRequest,
+) -> Result<Outcome> {
+    principal
+        .require_list_role(&mut *conn, household_id, item.list, ListRole::Editor)
+        .await?;
+
+    // Field names are the column names
+    let mut fields = Fields::load(conn, "items", item.id, at).await?;
+    let changes = UpdateItemRequest {
+        name: fields.keep("name", changes.name),
+        amount: fields.keep("amount", changes.amount),
+        amount_unit: fields.keep("amountUnit", changes.amount_unit),
+        category: fields.keep("category", changes.category),
+        in_cart: fields.keep("inCart", changes.in_cart),
+    };
+    if changes.name.is_none()
+        && changes.amount.is_none()
+        && changes.amount_unit.is_none()
+        && changes.category.is_none()
+        && changes.in_cart.is_none()
+    {
+        return Ok(fields.unchanged());
+    }
+
+    let limits = services::quotas::limits(conn, &state.config.quotas, household_id).await?;
+    services::items::update(conn, &limits, household_id, item.id, &changes).await?;
+    Ok(Outcome::Applied(fields.outdated))
+}
+
+/// The fields of a row an operation made at `at` may still change
+struct Fields {
+    updated_at: HashMap<String, DateTime<Utc>>,
+    at: DateTime<Utc>,
+    outdated: Vec<&'static str>,
+}
+
+impl Fields {
+    async fn load(
+        conn: &mut PgConnection,
+        table: &'static str,
+        id: i32,
+        at: DateTime<Utc>,
+    ) -> Result<Fields> {
+        Ok(Fields {
+            updated_at: services::sync::field_updated_at(conn, table, id).await?,
+            at,
+            outdated: Vec::new(),
+        })
+    }
+
+    /// The new value, unless the field changed after the operation
+    fn keep<T>(&mut self, field: &'static str, value: Option<T>) -> Option<T> {
+        let changed_later = self
+            .updated_at
+            .get(field)
+            .is_some_and(|updated_at| *updated_at > self.at);
+        if value.is_some() && changed_later {
+            self.outdated.push(field);
+            return None;
+        }
+        value
+    }
+
+    /// Nothing left to change: skipped if that is because of later changes
+    fn unchanged(self) -> Outcome {
+        if self.outdated.is_empty() {
+            Outcome::Applied(self.outdated)
+        } else {
+            Outcome::Skipped("outdated", self.outdated)
+        }
+    }
+}
+
+async fn deleted(
+    conn: &mut PgConnection,
+    household_id: i32,
+    entity: &str,
+    uuid: Option<Uuid>,
+) -> Result<bool> {
+    match uuid {
+        Some(uuid) => services::sync::is_deleted(conn, household_id, entity, uuid).await,
+        None => Ok(false),
+    }
+}
+
+fn list_not_found() -> AppError {
+    AppError::ParentNotFound {
+        parent: "resource.list",
+        field: "listUuid".to_string(),
+        in_path: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn fields(at: DateTime<Utc>, updated_at: &[(&str, DateTime<Utc>)]) -> Fields {
+        Fields {
+            updated_at: updated_at
+                .iter()
+                .map(|(field, at)| (field.to_string(), *at))
+                .collect(),
+            at,
+            outdated: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn later_server_changes_win() {
+        let at = Utc::now();
+        let mut fields = fields(at, &[("name", at + Duration::seconds(1))]);
+
+        assert_eq!(fields.keep("name", Some("Oat milk")), None);
+        assert_eq!(fields.outdated, ["name"]);
+        assert!(
+            matches!(fields.unchanged(), Outcome::Skipped("outdated", outdated) if outdated == ["name"])
+        );
+    }
+
+    #[test]
+    fn earlier_and_simultaneous_server_changes_lose() {
+        let at = Utc::now();
+        let mut fields = fields(at, &[("name", at - Duration::seconds(1)), ("amount", at)]);
+
+        assert_eq!(fields.keep("name", Some("Oat milk")), Some("Oat milk"));
+        assert_eq!(fields.keep("amount", Some(2)), Some(2));
+        assert!(fields.outdated.is_empty());
+    }
+
+    #[test]
+    fn fields_without_a_recorded_change_are_kept() {
+        let at = Utc::now();
+        let mut fields = fields(at, &[("name", at + Duration::seconds(1))]);
+
+        assert_eq!(fields.keep("inCart", Some(true)), Some(true));
+        assert!(fields.outdated.is_empty());
+    }
+
+    #[test]
+    fn fields_left_out_are_never_outdated() {
+        let at = Utc::now();
+        let mut fields = fields(at, &[("name", at + Duration::seconds(1))]);
+
+        assert_eq!(fields.keep::<String>("name", None), None);
+        assert!(fields.outdated.is_empty());
+        assert!(matches!(fields.unchanged(), Outcome::Applied(outdated) if outdated.is_empty()));
+    }
+
+    #[test]
+    fn partly_outdated_changes_apply_the_rest() {
+        let at = Utc::now();
+        let mut fields = fields(at, &[("category", at + Duration::minutes(5))]);
+
+        assert_eq!(fields.keep("name", Some("Oat milk")), Some("Oat milk"));
+        assert_eq!(fields.keep("category", Some("Dairy")), None);
+        assert_eq!(fields.outdated, ["category"]);
+    }
+}
@@ -0,0 +1,84 @@
+use axum::{extract::State, http::StatusCode};
+use chrono::{Duration, Utc};
+
+use crate::{
+    auth::{Principal, Tenant},
+    error::Result,
+    extract::{Json, Path, ValidJson},
+    models::{ApiToken, CreateApiTokenRequest, CreatedApiToken},
+    services,
+    state::AppState,
+};
+
+use super::households::target_household;
+
+/// GET /api/tokens - Get all API tokens, including revoked ones (admin)
+pub async fn get_all_api_tokens(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<Vec<ApiToken>>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let api_tokens = services::api_tokens::list(&mut conn).await?;
+
+    Ok(Json(api_tokens))
+}
+
+/// GET /api/tokens/:id - Get a single API token (admin)
+pub async fn get_api_token(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<Json<ApiToken>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let api_token = services::api_tokens::find(&mut conn, id).await?;
+
+    Ok(Json(api_token))
+}
+
+/// POST /api/tokens - Create an API token for a household (admin)
+pub async fn create_api_token(
+    State(state): State<AppState>,
+    principal: Principal,
+    tenant: Option<Tenant>,
+    ValidJson(payload): ValidJson<CreateApiTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedApiToken>)> {
+    principal.require_admin()?;
+
+    let expires_at = payload
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(days));
+    let created_by = principal.user().map(|user| user.id);
+
+    let mut conn = state.pool.acquire().await?;
+    let household_id = target_household(&mut conn, payload.household_id, tenant).await?;
+    let api_token = services::api_tokens::create(
+        &mut conn,
+        household_id,
+        &payload.name,
+        &payload.scopes,
+        payload.client_certificate.as_deref(),
+        created_by,
+        expires_at,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(api_token)))
+}
+
+/// DELETE /api/tokens/:id - Revoke an API token (admin)
+pub async fn revoke_api_token(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::api_tokens::revoke(&mut conn, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,186 @@
+use axum::{extract::State, http::StatusCode};
+use chrono::Duration;
+
+use crate::{
+    auth::{AuthError, Principal, Tenant},
+    error::Result,
+    extract::{Json, Path, Query, ValidJson},
+    models::{
+        CreateInvitationRequest, CreateUserRequest, CreatedInvitation, Invitation, InvitationQuery,
+        UpdateUserRequest, User,
+    },
+    services,
+    state::AppState,
+    validation::Validate,
+};
+
+use super::households::target_household;
+
+/// Invitations are valid for a week unless requested otherwise
+const DEFAULT_INVITATION_HOURS: i64 = 24 * 7;
+
+/// GET /api/users - Get all users (admin)
+pub async fn get_all_users(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Json<Vec<User>>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let users = services::users::list(&mut conn).await?;
+
+    Ok(Json(users))
+}
+
+/// GET /api/users/:id - Get a single user (admin)
+pub async fn get_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<Json<User>> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    let user = services::users::find(&mut conn, id).await?;
+
+    Ok(Json(user))
+}
+
+/// POST /api/users - Create a user in a household (admin)
+pub async fn create_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    tenant: Option<Tenant>,
+    ValidJson(payload): ValidJson<CreateUserRequest>,
+) -> Result<(StatusCode, Json<User>)> {
+    principal.require_admin()?;
+
+    let mut tx = state.pool.begin().await?;
+    let household_id = target_household(&mut tx, payload.household_id, tenant).await?;
+    let user = services::users::create(
+        &mut tx,
+        &payload.username,
+        &payload.password,
+        payload.display_name.as_deref(),
+        payload.is_admin,
+    )
+    .await?;
+    services::households::add_member(&mut tx, household_id, user.id).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+/// PUT /api/users/:id - Update a user (admin)
+pub async fn update_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<UpdateUserRequest>,
+) -> Result<Json<User>> {
+    principal.require_admin()?;
+
+    let mut tx = state.pool.begin().await?;
+    let user = services::users::update(&mut tx, id, &payload).await?;
+    tx.commit().await?;
+
+    Ok(Json(user))
+}
+
+/// DELETE /api/users/:id - Delete a user (admin)
+pub async fn delete_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    principal.require_admin()?;
+
+    let mut conn = state.pool.acquire().await?;
+    services::users::delete(&mut conn, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/invitations - Get all invitations (admin), or those of the
+/// household (manager)
+pub async fn get_all_invitations(
+    State(state): State<AppState>,
+    principal: Principal,
+    tenant: Option<Tenant>,
+    Query(mut query): Query<InvitationQuery>,
+) -> Result<Json<Vec<Invitation>>> {
+    query.validated()?;
+    let household_id = if principal.is_admin() {
+        None
+    } else {
+        let Tenant(household_id) = tenant.ok_or(AuthError::NoHousehold)?;
+        principal
+            .require_household_manager(&state.pool, household_id)
+            .await?;
+        Some(household_id)
+    };
+
+    let mut conn = state.pool.acquire().await?;
+    let invitations =
+        services::invitations::list(&mut conn, household_id, query.status.as_deref()).await?;
+
+    Ok(Json(invitations))
+}
+
+/// POST /api/invitations - Create an invitation to a household (admin or manager)
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    principal: Principal,
+    tenant: Option<Tenant>,
+    ValidJson(payload): ValidJson<CreateInvitationRequest>,
+) -> Result<(StatusCode, Json<CreatedInvitation>)> {
+    if payload.is_admin {
+        principal.require_admin()?;
+    }
+
+    let ttl = Duration::hours(payload.expires_in_hours.unwrap_or(DEFAULT_INVITATION_HOURS));
+    let created_by = principal.user().map(|user| user.id);
+    let role = payload.role.as_deref().unwrap_or("member");
+    // Single use unless requested otherwise
+    let max_uses = payload.max_uses.unwrap_or(Some(1));
+
+    let mut conn = state.pool.acquire().await?;
+    let household_id = target_household(&mut conn, payload.household_id, tenant).await?;
+    principal
+        .require_household_manager(&state.pool, household_id)
+        .await?;
+    let invitation = services::invitations::create(
+        &mut conn,
+        household_id,
+        created_by,
+        payload.is_admin,
+        role,
+        max_uses,
+        ttl,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+/// DELETE /api/invitations/:id - Revoke an invitation (admin or manager)
+pub async fn delete_invitation(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    let mut conn = state.pool.acquire().await?;
+    let invitation = services::invitations::find(&mut conn, id).await?;
+    match invitation.household_id {
+        Some(household_id) => {
+            principal
+                .require_household_manager(&state.pool, household_id)
+                .await?
+        }
+        None => principal.require_admin()?,
+    }
+
+    services::invitations::delete(&mut conn, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
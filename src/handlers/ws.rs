@@ -0,0 +1,41 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, State, WebSocketUpgrade},
+    http::{Extensions, HeaderMap},
+    response::Response,
+};
+
+use crate::{
+    audit,
+    error::{AppError, Result},
+    i18n::Message,
+    state::AppState,
+    tls::ClientCertificate,
+    ws,
+};
+
+/// GET /api/ws - Open a WebSocket connection, authenticated by its first message
+pub async fn open_websocket(
+    State(state): State<AppState>,
+    upgrade: Option<WebSocketUpgrade>,
+    headers: HeaderMap,
+    extensions: Extensions,
+) -> Result<Response> {
+    let upgrade_request = upgrade.ok_or(AppError::BadRequest(Message::new(
+        "error.ws_upgrade_required",
+    )))?;
+
+    let upgrade = ws::Upgrade {
+        headers,
+        peer: extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip()),
+        certificate: extensions.get::<ClientCertificate>().cloned(),
+        context: audit::Context::current(),
+    };
+
+    Ok(upgrade_request
+        .max_message_size(ws::MAX_MESSAGE_SIZE)
+        .on_upgrade(move |socket| ws::serve(socket, state, upgrade)))
+}
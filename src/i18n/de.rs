@@ -0,0 +1,239 @@
+pub const MESSAGES: &[(&str, &str)] = &[
+    // Problem titles, by error code
+    ("title.bad_request", "Ungültige Anfrage"),
+    ("title.validation_failed", "Validierung fehlgeschlagen"),
+    ("title.malformed_json", "Ungültiger Anfrageinhalt"),
+    ("title.invalid_body", "Ungültiger Anfrageinhalt"),
+    ("title.invalid_path", "Ungültiger Pfadparameter"),
+    ("title.invalid_query", "Ungültiger Abfrageparameter"),
+    ("title.unsupported_media_type", "Ungültiger Anfrageinhalt"),
+    ("title.missing_token", "Anmeldung erforderlich"),
+    ("title.invalid_auth_format", "Anmeldung erforderlich"),
+    ("title.invalid_token", "Anmeldung erforderlich"),
+    ("title.invalid_credentials", "Anmeldung fehlgeschlagen"),
+    ("title.invalid_invitation", "Ungültige Einladung"),
+    ("title.csrf_failed", "Zugriff verweigert"),
+    ("title.forbidden", "Zugriff verweigert"),
+    ("title.user_required", "Zugriff verweigert"),
+    ("title.insufficient_scope", "Zugriff verweigert"),
+    ("title.no_household", "Zugriff verweigert"),
+    ("title.insufficient_role", "Zugriff verweigert"),
+    ("title.sso_failed", "Anmeldung fehlgeschlagen"),
+    ("title.sso_unavailable", "Identitätsanbieter nicht erreichbar"),
+    ("title.invalid_passkey", "Anmeldung fehlgeschlagen"),
+    ("title.passkey_required", "Passkey erforderlich"),
+    ("title.not_found", "Ressource nicht gefunden"),
+    ("title.method_not_allowed", "Methode nicht erlaubt"),
+    ("title.timeout", "Zeitüberschreitung"),
+    ("title.payload_too_large", "Anfrage zu groß"),
+    ("title.already_exists", "Ressource existiert bereits"),
+    ("title.still_referenced", "Ressource wird noch verwendet"),
+    ("title.conflict", "Konflikt"),
+    ("title.precondition_failed", "Vorbedingung nicht erfüllt"),
+    ("title.quota_exceeded", "Kontingent erschöpft"),
+    (
+        "title.parent_not_found",
+        "Referenzierte Ressource nicht gefunden",
+    ),
+    ("title.constraint_violation", "Ungültiger Wert"),
+    ("title.database_error", "Datenbankfehler"),
+    ("title.internal_error", "Interner Serverfehler"),
+    // Problem details
+    (
+        "detail.bad_request",
+        "Die Anfrage konnte nicht verarbeitet werden",
+    ),
+    ("detail.validation_failed", "Ungültige Felder: {fields}"),
+    (
+        "detail.malformed_json",
+        "Der Anfrageinhalt ist kein gültiges JSON: {reason}",
+    ),
+    ("detail.invalid_body", "Ungültiger Anfrageinhalt: {reason}"),
+    ("detail.invalid_path", "Ungültiger Pfadparameter: {reason}"),
+    ("detail.invalid_query", "Ungültiger Abfrageparameter: {reason}"),
+    (
+        "detail.unsupported_media_type",
+        "Erwartet wird eine Anfrage mit `Content-Type: application/json`",
+    ),
+    ("detail.missing_token", "Authorization-Header fehlt"),
+    (
+        "detail.invalid_auth_format",
+        "Ungültiger Authorization-Header. Erwartet: Bearer <token>",
+    ),
+    ("detail.invalid_token", "Ungültiges Token"),
+    ("detail.invalid_credentials", "Benutzername oder Passwort falsch"),
+    (
+        "detail.invalid_invitation",
+        "Die Einladung ist ungültig, abgelaufen oder bereits verwendet",
+    ),
+    (
+        "detail.csrf_failed",
+        "X-CSRF-Token-Header fehlt oder passt nicht zur Sitzung",
+    ),
+    (
+        "detail.forbidden",
+        "Dazu fehlt dir die Berechtigung",
+    ),
+    ("detail.user_required", "Dafür ist ein Benutzerkonto erforderlich"),
+    ("detail.insufficient_scope", "Das API-Token hat nicht die nötige Berechtigung"),
+    ("detail.no_household", "Der Haushalt existiert nicht oder du bist kein Mitglied"),
+    ("detail.insufficient_role", "Deine Rolle in dieser Liste erlaubt das nicht"),
+    (
+        "detail.sso_failed",
+        "Die Anmeldung per Single Sign-on ist fehlgeschlagen, versuch es bitte erneut",
+    ),
+    (
+        "detail.sso_unavailable",
+        "Der Identitätsanbieter ist nicht erreichbar",
+    ),
+    ("detail.invalid_passkey", "Der Passkey konnte nicht geprüft werden"),
+    (
+        "detail.passkey_required",
+        "Bestätige die Anmeldung mit einem deiner Passkeys",
+    ),
+    ("detail.not_found", "Ressource nicht gefunden"),
+    (
+        "detail.route_not_found",
+        "Unter diesem Pfad gibt es keine Ressource",
+    ),
+    (
+        "detail.method_not_allowed",
+        "Die Methode ist für diese Ressource nicht erlaubt",
+    ),
+    (
+        "detail.timeout",
+        "Die Verarbeitung der Anfrage hat zu lange gedauert",
+    ),
+    ("detail.payload_too_large", "Der Anfrageinhalt ist zu groß"),
+    (
+        "detail.already_exists",
+        "{resource} mit diesem Wert für „{field}“ existiert bereits",
+    ),
+    (
+        "detail.still_referenced",
+        "{resource} wird noch verwendet von: {referenced_by}",
+    ),
+    ("detail.parent_not_found", "{parent} nicht gefunden"),
+    (
+        "detail.constraint_violation",
+        "Ungültiger Wert für „{field}“",
+    ),
+    (
+        "detail.precondition_failed",
+        "Die Ressource wurde zwischenzeitlich geändert",
+    ),
+    (
+        "detail.quota_exceeded.lists",
+        "Der Haushalt hat sein Limit von {limit} Listen erreicht",
+    ),
+    (
+        "detail.quota_exceeded.itemsPerList",
+        "Diese Liste hat ihr Limit von {limit} Einträgen erreicht",
+    ),
+    (
+        "detail.quota_exceeded.names",
+        "Der Haushalt hat sein Limit von {limit} Namen erreicht",
+    ),
+    (
+        "detail.quota_exceeded.categories",
+        "Der Haushalt hat sein Limit von {limit} Kategorien erreicht",
+    ),
+    (
+        "detail.quota_exceeded.requestsPerDay",
+        "Der Haushalt hat seine {limit} Anfragen für heute aufgebraucht",
+    ),
+    (
+        "detail.database_error",
+        "Ein Datenbankfehler ist aufgetreten",
+    ),
+    (
+        "detail.internal_error",
+        "Ein unerwarteter Fehler ist aufgetreten",
+    ),
+    // Specific errors
+    (
+        "error.merge_name_into_self",
+        "Ein Name kann nicht mit sich selbst zusammengeführt werden",
+    ),
+    (
+        "error.merge_category_into_self",
+        "Eine Kategorie kann nicht mit sich selbst zusammengeführt werden",
+    ),
+    ("error.unknown_name", "Unbekannter Name „{name}“"),
+    ("error.job_running", "Der Job läuft bereits"),
+    (
+        "error.invalid_passkey_registration",
+        "Die Passkey-Registrierung ist ungültig oder abgelaufen",
+    ),
+    (
+        "error.no_passkey",
+        "Registriere zuerst einen Passkey, bevor du ihn verlangst",
+    ),
+    ("error.already_member", "Du bist bereits Mitglied dieses Haushalts"),
+    ("error.export_not_ready", "Der Export ist noch nicht fertig"),
+    (
+        "error.deletion_pending",
+        "Die Löschung dieses Kontos läuft bereits",
+    ),
+    (
+        "error.transfer_target_required",
+        "Wähle einen Benutzer (transferTo), an den die Listen übertragen werden",
+    ),
+    (
+        "error.transfer_to_self",
+        "Listen können nicht an das zu löschende Konto übertragen werden",
+    ),
+    (
+        "error.ws_upgrade_required",
+        "Verbinde dich mit einem WebSocket-Client",
+    ),
+    (
+        "error.ws_auth_required",
+        "Melde dich zuerst mit einer `auth`-Nachricht an",
+    ),
+    (
+        "error.ws_authenticated",
+        "Die Verbindung ist bereits angemeldet",
+    ),
+    ("error.ws_invalid_message", "Ungültige Nachricht: {reason}"),
+    ("error.invalid_sync_cursor", "Ungültiger Synchronisationscursor"),
+    // Field validation
+    ("validation.required", "darf nicht leer sein"),
+    (
+        "validation.too_long",
+        "darf höchstens {max} Zeichen lang sein (angegeben: {len})",
+    ),
+    (
+        "validation.too_short",
+        "muss mindestens {min} Zeichen lang sein (angegeben: {len})",
+    ),
+    ("validation.invalid_chars", "darf nur {allowed} enthalten"),
+    ("validation.invalid_format", "muss das Format {expected} haben"),
+    ("validation.not_allowed", "muss einer der folgenden Werte sein: {options}"),
+    ("validation.too_small", "muss mindestens {min} sein"),
+    ("validation.too_large", "darf höchstens {max} sein"),
+    (
+        "validation.too_many",
+        "darf höchstens {max} Einträge haben (angegeben: {len})",
+    ),
+    (
+        "validation.too_precise",
+        "darf höchstens {scale} Nachkommastellen haben",
+    ),
+    // Shared list page
+    ("shared.empty", "Diese Liste ist leer."),
+    ("shared.expires", "Dieser Link gilt bis {date}."),
+    // Resource names, used as arguments
+    ("resource.list", "Liste"),
+    ("resource.item", "Eintrag"),
+    ("resource.category", "Kategorie"),
+    ("resource.name", "Name"),
+    ("resource.user", "Benutzer"),
+    ("resource.invitation", "Einladung"),
+    ("resource.api_token", "API-Token"),
+    ("resource.household", "Haushalt"),
+    ("resource.group", "Gruppe"),
+    ("resource.passkey", "Passkey"),
+    ("resource.other", "Ressource"),
+    ("resource.referenced", "Referenzierte Ressource"),
+];
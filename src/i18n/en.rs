@@ -0,0 +1,212 @@
+pub const MESSAGES: &[(&str, &str)] = &[
+    // Problem titles, by error code
+    ("title.bad_request", "Bad request"),
+    ("title.validation_failed", "Validation failed"),
+    ("title.malformed_json", "Invalid request body"),
+    ("title.invalid_body", "Invalid request body"),
+    ("title.invalid_path", "Invalid path parameter"),
+    ("title.invalid_query", "Invalid query parameter"),
+    ("title.unsupported_media_type", "Invalid request body"),
+    ("title.missing_token", "Authentication required"),
+    ("title.invalid_auth_format", "Authentication required"),
+    ("title.invalid_token", "Authentication required"),
+    ("title.invalid_credentials", "Authentication failed"),
+    ("title.invalid_invitation", "Invalid invitation"),
+    ("title.csrf_failed", "Access denied"),
+    ("title.forbidden", "Access denied"),
+    ("title.user_required", "Access denied"),
+    ("title.insufficient_scope", "Access denied"),
+    ("title.no_household", "Access denied"),
+    ("title.insufficient_role", "Access denied"),
+    ("title.sso_failed", "Authentication failed"),
+    ("title.sso_unavailable", "Identity provider unavailable"),
+    ("title.invalid_passkey", "Authentication failed"),
+    ("title.passkey_required", "Passkey required"),
+    ("title.not_found", "Resource not found"),
+    ("title.method_not_allowed", "Method not allowed"),
+    ("title.timeout", "Request timeout"),
+    ("title.payload_too_large", "Payload too large"),
+    ("title.already_exists", "Resource already exists"),
+    ("title.still_referenced", "Resource still referenced"),
+    ("title.conflict", "Conflict"),
+    ("title.precondition_failed", "Precondition failed"),
+    ("title.quota_exceeded", "Quota exceeded"),
+    ("title.parent_not_found", "Referenced resource not found"),
+    ("title.constraint_violation", "Constraint violation"),
+    ("title.database_error", "Database error"),
+    ("title.internal_error", "Internal server error"),
+    // Problem details
+    ("detail.bad_request", "The request could not be processed"),
+    ("detail.validation_failed", "Invalid fields: {fields}"),
+    (
+        "detail.malformed_json",
+        "The request body is not valid JSON: {reason}",
+    ),
+    ("detail.invalid_body", "Invalid request body: {reason}"),
+    ("detail.invalid_path", "Invalid path parameter: {reason}"),
+    ("detail.invalid_query", "Invalid query parameter: {reason}"),
+    (
+        "detail.unsupported_media_type",
+        "Expected request with `Content-Type: application/json`",
+    ),
+    ("detail.missing_token", "Missing authorization header"),
+    (
+        "detail.invalid_auth_format",
+        "Invalid authorization header format. Expected: Bearer <token>",
+    ),
+    ("detail.invalid_token", "Invalid authentication token"),
+    ("detail.invalid_credentials", "Invalid username or password"),
+    (
+        "detail.invalid_invitation",
+        "The invitation is invalid, expired or already used",
+    ),
+    (
+        "detail.csrf_failed",
+        "Missing or invalid X-CSRF-Token header for this session",
+    ),
+    (
+        "detail.forbidden",
+        "You do not have permission to perform this action",
+    ),
+    ("detail.user_required", "This action requires a user account"),
+    ("detail.insufficient_scope", "The API token does not have the required scope"),
+    ("detail.no_household", "The household does not exist or you are not a member"),
+    ("detail.insufficient_role", "Your role on this list does not allow this"),
+    ("detail.sso_failed", "Single sign-on failed, please try again"),
+    (
+        "detail.sso_unavailable",
+        "The identity provider could not be reached",
+    ),
+    ("detail.invalid_passkey", "The passkey could not be verified"),
+    (
+        "detail.passkey_required",
+        "Confirm the login with one of your passkeys",
+    ),
+    ("detail.not_found", "Resource not found"),
+    ("detail.route_not_found", "No resource exists at this path"),
+    (
+        "detail.method_not_allowed",
+        "The method is not allowed for this resource",
+    ),
+    ("detail.timeout", "The request took too long to process"),
+    ("detail.payload_too_large", "The request body is too large"),
+    (
+        "detail.already_exists",
+        "{resource} with this {field} already exists",
+    ),
+    (
+        "detail.still_referenced",
+        "{resource} is still referenced by {referenced_by}",
+    ),
+    ("detail.parent_not_found", "{parent} not found"),
+    ("detail.constraint_violation", "Invalid value for {field}"),
+    (
+        "detail.precondition_failed",
+        "The resource has been changed in the meantime",
+    ),
+    (
+        "detail.quota_exceeded.lists",
+        "The household has reached its limit of {limit} lists",
+    ),
+    (
+        "detail.quota_exceeded.itemsPerList",
+        "This list has reached its limit of {limit} items",
+    ),
+    (
+        "detail.quota_exceeded.names",
+        "The household has reached its limit of {limit} names",
+    ),
+    (
+        "detail.quota_exceeded.categories",
+        "The household has reached its limit of {limit} categories",
+    ),
+    (
+        "detail.quota_exceeded.requestsPerDay",
+        "The household has used up its {limit} requests for today",
+    ),
+    ("detail.database_error", "A database error occurred"),
+    ("detail.internal_error", "An unexpected error occurred"),
+    // Specific errors
+    (
+        "error.merge_name_into_self",
+        "Cannot merge a name into itself",
+    ),
+    (
+        "error.merge_category_into_self",
+        "Cannot merge a category into itself",
+    ),
+    ("error.unknown_name", "Unknown name \"{name}\""),
+    ("error.job_running", "Job is already running"),
+    (
+        "error.invalid_passkey_registration",
+        "The passkey registration is invalid or has expired",
+    ),
+    ("error.no_passkey", "Register a passkey before requiring one"),
+    ("error.already_member", "You are already a member of this household"),
+    ("error.export_not_ready", "The export is not ready yet"),
+    (
+        "error.deletion_pending",
+        "The deletion of this account is already in progress",
+    ),
+    (
+        "error.transfer_target_required",
+        "Choose a user (transferTo) to transfer the lists to",
+    ),
+    (
+        "error.transfer_to_self",
+        "Lists cannot be transferred to the account being deleted",
+    ),
+    (
+        "error.ws_upgrade_required",
+        "Connect with a WebSocket client",
+    ),
+    (
+        "error.ws_auth_required",
+        "Authenticate with an `auth` message first",
+    ),
+    (
+        "error.ws_authenticated",
+        "The connection is already authenticated",
+    ),
+    ("error.ws_invalid_message", "Invalid message: {reason}"),
+    ("error.invalid_sync_cursor", "Invalid sync cursor"),
+    // Field validation
+    ("validation.required", "must not be empty"),
+    (
+        "validation.too_long",
+        "must not exceed {max} characters (got {len})",
+    ),
+    (
+        "validation.too_short",
+        "must be at least {min} characters long (got {len})",
+    ),
+    ("validation.invalid_chars", "may only contain {allowed}"),
+    ("validation.invalid_format", "must have the format {expected}"),
+    ("validation.not_allowed", "must be one of: {options}"),
+    ("validation.too_small", "must be at least {min}"),
+    ("validation.too_large", "must not exceed {max}"),
+    (
+        "validation.too_many",
+        "must not have more than {max} entries (got {len})",
+    ),
+    (
+        "validation.too_precise",
+        "must not have more than {scale} decimal places",
+    ),
+    // Shared list page
+    ("shared.empty", "This list is empty."),
+    ("shared.expires", "This link works until {date}."),
+    // Resource names, used as arguments
+    ("resource.list", "List"),
+    ("resource.item", "Item"),
+    ("resource.category", "Category"),
+    ("resource.name", "Name"),
+    ("resource.user", "User"),
+    ("resource.invitation", "Invitation"),
+    ("resource.api_token", "API token"),
+    ("resource.household", "Household"),
+    ("resource.group", "Group"),
+    ("resource.passkey", "Passkey"),
+    ("resource.other", "Resource"),
+    ("resource.referenced", "Referenced resource"),
+];
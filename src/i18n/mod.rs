@@ -0,0 +1,181 @@
+//! Message catalogs for user-facing texts, mostly errors.
+//!
+//! Errors carry a [`Message`] (a catalog key plus arguments) instead of a
+//! formatted string; it is rendered once the client's [`Lang`] is known.
+//! Error codes stay language-independent.
+
+use std::{borrow::Cow, fmt};
+
+use axum::http::{header, HeaderMap};
+
+mod de;
+mod en;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+impl Lang {
+    /// Language tag, as sent in `Content-Language`
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+        }
+    }
+
+    /// Parse a language tag like `de`, `de-AT` or `en_US`
+    pub fn parse(tag: &str) -> Option<Lang> {
+        let primary = tag.split(['-', '_']).next()?.trim();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Lang::En),
+            "de" => Some(Lang::De),
+            _ => None,
+        }
+    }
+
+    /// Pick the best supported language from an `Accept-Language` header
+    pub fn negotiate(accept_language: &str) -> Option<Lang> {
+        accept_language
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let lang = Lang::parse(parts.next()?)?;
+                let quality = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (quality > 0.0).then_some((lang, quality))
+            })
+            // On equal quality the first entry wins
+            .fold(
+                None,
+                |best: Option<(Lang, f32)>, (lang, quality)| match best {
+                    Some((_, q)) if q >= quality => best,
+                    _ => Some((lang, quality)),
+                },
+            )
+            .map(|(lang, _)| lang)
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Lang {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|h| h.to_str().ok())
+            .and_then(Lang::negotiate)
+            .unwrap_or_default()
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => en::MESSAGES,
+            Lang::De => de::MESSAGES,
+        }
+    }
+
+    fn lookup(self, key: &str) -> Option<&'static str> {
+        self.catalog()
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, template)| *template)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Arg {
+    /// Inserted verbatim
+    Text(String),
+    /// Another catalog key, rendered in the same language
+    Key(&'static str),
+}
+
+/// A translatable message: catalog key plus `{name}` arguments
+#[derive(Debug, Clone)]
+pub struct Message {
+    key: Cow<'static, str>,
+    args: Vec<(&'static str, Arg)>,
+}
+
+impl Message {
+    pub fn new(key: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            key: key.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.args.push((name, Arg::Text(value.to_string())));
+        self
+    }
+
+    /// Argument that is itself translated, e.g. a resource name
+    pub fn arg_key(mut self, name: &'static str, key: &'static str) -> Self {
+        self.args.push((name, Arg::Key(key)));
+        self
+    }
+
+    /// Render in `lang`, falling back to English and then to the key itself
+    pub fn render(&self, lang: Lang) -> String {
+        let template = lang
+            .lookup(&self.key)
+            .or_else(|| Lang::En.lookup(&self.key))
+            .unwrap_or(&self.key);
+
+        self.args
+            .iter()
+            .fold(template.to_string(), |text, (name, arg)| {
+                let value = match arg {
+                    Arg::Text(value) => Cow::Borrowed(value.as_str()),
+                    Arg::Key(key) => Cow::Owned(Message::new(*key).render(lang)),
+                };
+                text.replace(&format!("{{{name}}}"), &value)
+            })
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(Lang::En))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_by_quality() {
+        assert_eq!(Lang::negotiate("en;q=0.5, de;q=0.8"), Some(Lang::De));
+        assert_eq!(
+            Lang::negotiate("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7"),
+            Some(Lang::En)
+        );
+        assert_eq!(Lang::negotiate("de-AT,en-US;q=0.9"), Some(Lang::De));
+        // Without a q-value an entry has quality 1
+        assert_eq!(Lang::negotiate("en;q=0.9, de_DE"), Some(Lang::De));
+    }
+
+    #[test]
+    fn first_entry_wins_on_equal_quality() {
+        assert_eq!(Lang::negotiate("de, en"), Some(Lang::De));
+        assert_eq!(Lang::negotiate("en;q=0.7, de;q=0.7"), Some(Lang::En));
+    }
+
+    #[test]
+    fn skips_refused_and_malformed_entries() {
+        assert_eq!(Lang::negotiate("de;q=0, en;q=0.1"), Some(Lang::En));
+        assert_eq!(Lang::negotiate("de;q=0.0"), None);
+        assert_eq!(Lang::negotiate("de;q=high, en;q=0.2"), Some(Lang::En));
+        assert_eq!(Lang::negotiate("de ; q = 0.9 , en;q=0.5"), Some(Lang::De));
+    }
+
+    #[test]
+    fn unsupported_languages_negotiate_nothing() {
+        assert_eq!(Lang::negotiate("fr, es;q=0.5"), None);
+        assert_eq!(Lang::negotiate("*"), None);
+        assert_eq!(Lang::negotiate(""), None);
+    }
+}
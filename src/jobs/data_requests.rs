@@ -0,0 +1,115 @@
+//! Background processing of personal data exports and account deletions.
+//!
+//! Requests are stored in `data_requests` and run right after they are made;
+//! callers poll the request for its status. Requests that were waiting or
+//! running when the server stopped are picked up again on startup.
+
+use serde_json::Value;
+use sqlx::{Connection, PgConnection, PgPool};
+
+use super::lock_key;
+use crate::{
+    audit,
+    error::{AppError, Result},
+    models::DataRequest,
+    services::data_requests,
+};
+
+/// Process a request in the background. Changes are attributed to the
+/// current audit context, i.e. whoever made the request.
+pub fn spawn(pool: PgPool, id: i64) {
+    let context = audit::Context::current();
+    tokio::spawn(audit::scope(context, async move {
+        if let Err(e) = process(&pool, id).await {
+            tracing::error!("Failed to process data request {}: {:?}", id, e);
+        }
+    }));
+}
+
+/// Process the requests left over from the last run
+pub async fn resume(pool: &PgPool) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    for id in data_requests::unfinished(&mut conn).await? {
+        spawn(pool.clone(), id);
+    }
+
+    Ok(())
+}
+
+async fn process(pool: &PgPool, id: i64) -> Result<()> {
+    // The lock is bound to this connection and keeps other instances from
+    // processing the same request; processing runs in its own task, so it is
+    // not dropped before the lock is released
+    let mut conn = pool.acquire().await?;
+    let key = lock_key(&format!("data_request:{id}"));
+
+    let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+        .bind(key)
+        .fetch_one(&mut *conn)
+        .await?;
+    if !locked {
+        return Ok(());
+    }
+
+    let result = process_locked(&mut conn, id).await;
+
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(key)
+        .execute(&mut *conn)
+        .await?;
+
+    result
+}
+
+async fn process_locked(conn: &mut PgConnection, id: i64) -> Result<()> {
+    // Holding the lock, a request still marked as running was interrupted
+    let Some(request) = data_requests::start(conn, id).await? else {
+        return Ok(());
+    };
+
+    tracing::info!(
+        "Processing {} of user {} (request {})",
+        request.kind,
+        request.username,
+        id
+    );
+
+    let outcome = match execute(conn, &request).await {
+        Ok(result) => {
+            tracing::info!("Data request {} succeeded", id);
+            Ok(result)
+        }
+        Err(e) => {
+            tracing::error!("Data request {} failed: {:?}", id, e);
+            Err(e.to_string())
+        }
+    };
+
+    data_requests::finish(conn, id, outcome).await
+}
+
+/// Run the export or deletion in one transaction; exports return their document
+async fn execute(conn: &mut PgConnection, request: &DataRequest) -> Result<Option<Value>> {
+    let mut tx = conn.begin().await?;
+
+    let result = match request.list_policy.as_deref() {
+        Some(list_policy) => {
+            data_requests::delete_account(
+                &mut tx,
+                request.user_id,
+                list_policy,
+                request.transfer_to,
+            )
+            .await?;
+            None
+        }
+        None => {
+            let export = data_requests::export(&mut tx, request.user_id).await?;
+            Some(serde_json::to_value(export).map_err(|_| AppError::Internal)?)
+        }
+    };
+
+    tx.commit().await?;
+
+    Ok(result)
+}
@@ -0,0 +1,343 @@
+//! In-process scheduler for maintenance jobs.
+//!
+//! Jobs are declared in [`JOBS`] and enabled via the `JOBS` environment
+//! variable. A Postgres advisory lock per job makes sure only one instance
+//! runs it at a time; every run is recorded in `job_runs`.
+
+use std::{future::Future, pin::Pin, str::FromStr, sync::Arc};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use sqlx::PgPool;
+
+use crate::{
+    audit,
+    config::Config,
+    error::Result,
+    models::{JobRun, JobStatus},
+};
+
+pub mod data_requests;
+mod tasks;
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<String>> + Send>>;
+
+/// A maintenance job. `run` returns a short summary of what it did.
+pub struct Job {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Cron expression with seconds, e.g. `0 30 3 * * *`
+    pub default_schedule: &'static str,
+    run: fn(PgPool, &Config) -> JobFuture,
+}
+
+pub static JOBS: &[Job] = &[
+    Job {
+        name: "recount_names",
+        description: "Raise names.count to at least the number of items using the name",
+        default_schedule: "0 0 3 * * *",
+        run: tasks::recount_names,
+    },
+    Job {
+        name: "prune_names",
+        description: "Delete names that were used only once and are not on any list",
+        default_schedule: "0 15 3 * * Sun",
+        run: tasks::prune_names,
+    },
+    Job {
+        name: "purge_orphaned_categories",
+        description: "Delete categories not used by any item or name",
+        default_schedule: "0 30 3 * * *",
+        run: tasks::purge_orphaned_categories,
+    },
+    Job {
+        name: "expire_job_runs",
+        description: "Delete job run history older than 30 days",
+        default_schedule: "0 45 3 * * *",
+        run: tasks::expire_job_runs,
+    },
+    Job {
+        name: "purge_sessions",
+        description: "Delete expired login sessions, invitations, single sign-on logins and passkey challenges",
+        default_schedule: "0 0 4 * * *",
+        run: tasks::purge_sessions,
+    },
+    Job {
+        name: "purge_audit_log",
+        description: "Delete audit log entries older than AUDIT_RETENTION_DAYS",
+        default_schedule: "0 15 4 * * *",
+        run: tasks::purge_audit_log,
+    },
+    Job {
+        name: "purge_data_requests",
+        description: "Delete finished personal data exports and deletion requests older than 7 days",
+        default_schedule: "0 30 4 * * *",
+        run: tasks::purge_data_requests,
+    },
+    Job {
+        name: "purge_request_counts",
+        description: "Delete daily request counts of households older than 31 days",
+        default_schedule: "0 45 4 * * *",
+        run: tasks::purge_request_counts,
+    },
+    Job {
+        name: "purge_item_events",
+        description: "Delete item events older than a day, streams resuming from them start over",
+        default_schedule: "0 0 * * * *",
+        run: tasks::purge_item_events,
+    },
+    Job {
+        name: "purge_tombstones",
+        description: "Delete tombstones and replayed operations older than TOMBSTONE_RETENTION_DAYS, syncs from before them start over",
+        default_schedule: "0 0 5 * * *",
+        run: tasks::purge_tombstones,
+    },
+];
+
+/// How a run was started, stored in `job_runs.trigger`
+#[derive(Debug, Clone, Copy)]
+pub enum Trigger {
+    Schedule,
+    Manual,
+}
+
+impl Trigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            Trigger::Schedule => "schedule",
+            Trigger::Manual => "manual",
+        }
+    }
+}
+
+pub struct ScheduledJob {
+    pub job: &'static Job,
+    pub schedule: Schedule,
+    /// Whether the job runs on its schedule. Disabled jobs can still be triggered manually.
+    pub enabled: bool,
+}
+
+impl ScheduledJob {
+    pub fn next_run(&self) -> Option<DateTime<Utc>> {
+        if !self.enabled {
+            return None;
+        }
+        self.schedule.upcoming(Utc).next()
+    }
+}
+
+pub struct Scheduler {
+    pool: PgPool,
+    config: Config,
+    jobs: Vec<ScheduledJob>,
+}
+
+impl Scheduler {
+    /// Resolve the configured jobs, failing on unknown names or invalid schedules
+    pub fn new(pool: PgPool, config: &Config) -> anyhow::Result<Self> {
+        if let Some(unknown) = config
+            .jobs
+            .iter()
+            .find(|c| !JOBS.iter().any(|job| job.name == c.name))
+        {
+            anyhow::bail!("Unknown job in JOBS: {}", unknown.name);
+        }
+
+        let jobs = JOBS
+            .iter()
+            .map(|job| {
+                let job_config = config.jobs.iter().find(|c| c.name == job.name);
+                let expression = job_config
+                    .and_then(|c| c.schedule.as_deref())
+                    .unwrap_or(job.default_schedule);
+                let schedule = Schedule::from_str(expression)
+                    .with_context(|| format!("Invalid schedule for job {}", job.name))?;
+
+                Ok(ScheduledJob {
+                    job,
+                    schedule,
+                    enabled: job_config.is_some(),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            pool,
+            config: config.clone(),
+            jobs,
+        })
+    }
+
+    pub fn jobs(&self) -> &[ScheduledJob] {
+        &self.jobs
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ScheduledJob> {
+        self.jobs.iter().find(|j| j.job.name == name)
+    }
+
+    /// Spawn a timer task for every enabled job
+    pub fn start(self: &Arc<Self>) {
+        for (index, scheduled) in self.jobs.iter().enumerate() {
+            if !scheduled.enabled {
+                continue;
+            }
+
+            tracing::info!(
+                "Scheduling job {} ({})",
+                scheduled.job.name,
+                scheduled.schedule
+            );
+
+            let scheduler = Arc::clone(self);
+            tokio::spawn(async move {
+                let scheduled = &scheduler.jobs[index];
+                while let Some(next) = scheduled.next_run() {
+                    let delay = (next - Utc::now()).to_std().unwrap_or_default();
+                    tokio::time::sleep(delay).await;
+
+                    if let Err(e) = scheduler.run(scheduled.job, Trigger::Schedule).await {
+                        tracing::error!("Failed to run job {}: {:?}", scheduled.job.name, e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Run a job now, unless another instance holds its lock.
+    ///
+    /// Returns `None` if the job is already running elsewhere.
+    pub async fn run(&self, job: &Job, trigger: Trigger) -> Result<Option<JobRun>> {
+        // The lock belongs to a transaction that stays open for the whole run.
+        // If the run is dropped halfway, the transaction is rolled back and the
+        // lock released with it, rather than staying on a pooled connection.
+        let mut lock = self.pool.begin().await?;
+        let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_xact_lock($1)")
+            .bind(lock_key(job.name))
+            .fetch_one(&mut *lock)
+            .await?;
+        if !locked {
+            tracing::debug!("Job {} is locked by another instance", job.name);
+            return Ok(None);
+        }
+
+        // Runs are recorded outside the lock's transaction, so they show up
+        // as running while the job works
+        let mut conn = self.pool.acquire().await?;
+        let run = self.run_locked(&mut conn, job, trigger).await?;
+
+        lock.commit().await?;
+        Ok(Some(run))
+    }
+
+    async fn run_locked(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        job: &Job,
+        trigger: Trigger,
+    ) -> Result<JobRun> {
+        // Holding the lock, any run still marked as running was interrupted
+        sqlx::query(
+            r#"
+            UPDATE job_runs
+            SET status = 'abandoned', finished_at = now()
+            WHERE job = $1 AND status = 'running'
+            "#,
+        )
+        .bind(job.name)
+        .execute(&mut *conn)
+        .await?;
+
+        let run_id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO job_runs (job, trigger)
+            VALUES ($1, $2)
+            RETURNING id
+            "#,
+        )
+        .bind(job.name)
+        .bind(trigger.as_str())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        tracing::info!("Running job {} ({})", job.name, trigger.as_str());
+
+        // Changes made by the job are attributed to it in the audit log
+        let run = audit::scope(
+            audit::Context::job(job.name),
+            (job.run)(self.pool.clone(), &self.config),
+        );
+        let (status, message, error) = match run.await {
+            Ok(message) => {
+                tracing::info!("Job {} succeeded: {}", job.name, message);
+                ("succeeded", Some(message), None)
+            }
+            Err(e) => {
+                tracing::error!("Job {} failed: {:?}", job.name, e);
+                ("failed", None, Some(e.to_string()))
+            }
+        };
+
+        let run = sqlx::query_as::<_, JobRun>(
+            r#"
+            UPDATE job_runs
+            SET status = $2, message = $3, error = $4, finished_at = now()
+            WHERE id = $1
+            RETURNING id, job, trigger, status, message, error, started_at, finished_at
+            "#,
+        )
+        .bind(run_id)
+        .bind(status)
+        .bind(message)
+        .bind(error)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(run)
+    }
+
+    /// Current state of a job including its most recent run and failure
+    pub async fn status(&self, scheduled: &ScheduledJob) -> Result<JobStatus> {
+        let last_run = self.runs(scheduled.job.name, false, 1).await?.pop();
+        let last_error = self.runs(scheduled.job.name, true, 1).await?.pop();
+
+        Ok(JobStatus {
+            name: scheduled.job.name,
+            description: scheduled.job.description,
+            schedule: scheduled.schedule.to_string(),
+            enabled: scheduled.enabled,
+            next_run: scheduled.next_run(),
+            last_run,
+            last_error,
+        })
+    }
+
+    /// Most recent runs of a job, newest first
+    pub async fn runs(&self, name: &str, failed_only: bool, limit: i64) -> Result<Vec<JobRun>> {
+        let runs = sqlx::query_as::<_, JobRun>(
+            r#"
+            SELECT id, job, trigger, status, message, error, started_at, finished_at
+            FROM job_runs
+            WHERE job = $1 AND (NOT $2 OR status = 'failed')
+            ORDER BY started_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(name)
+        .bind(failed_only)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+}
+
+/// Stable advisory lock key per job name (FNV-1a)
+fn lock_key(name: &str) -> i64 {
+    let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    hash as i64
+}
@@ -0,0 +1,249 @@
+use sqlx::PgPool;
+
+use super::JobFuture;
+use crate::config::Config;
+
+/// Counts only ever grow through `create_item`, so this repairs entries
+/// that fell behind the actual usage without erasing the learned history
+pub fn recount_names(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            UPDATE names n
+            SET count = usage.count
+            FROM (
+                SELECT l.household_id, i.name, COUNT(*) as count
+                FROM items i
+                JOIN lists l ON l.id = i.list
+                GROUP BY l.household_id, i.name
+            ) usage
+            WHERE usage.household_id = n.household_id
+              AND usage.name = n.name
+              AND COALESCE(n.count, 0) < usage.count
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!("Updated {} names", result.rows_affected()))
+    })
+}
+
+pub fn prune_names(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM names n
+            WHERE COALESCE(n.count, 0) <= 1
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM items i
+                  JOIN lists l ON l.id = i.list
+                  WHERE i.name = n.name AND l.household_id = n.household_id
+              )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!("Deleted {} names", result.rows_affected()))
+    })
+}
+
+pub fn purge_orphaned_categories(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM categories c
+            WHERE NOT EXISTS (
+                  SELECT 1
+                  FROM items i
+                  JOIN lists l ON l.id = i.list
+                  WHERE i.category = c.name AND l.household_id = c.household_id
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM names n
+                  WHERE n.category = c.name AND n.household_id = c.household_id
+              )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!("Deleted {} categories", result.rows_affected()))
+    })
+}
+
+pub fn expire_job_runs(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM job_runs
+            WHERE started_at < now() - interval '30 days'
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!("Deleted {} job runs", result.rows_affected()))
+    })
+}
+
+pub fn purge_sessions(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let sessions = sqlx::query(
+            r#"
+            DELETE FROM sessions
+            WHERE COALESCE(refresh_expires_at, expires_at) < now()
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let invitations = sqlx::query(
+            r#"
+            DELETE FROM invitations
+            WHERE used_at IS NULL AND expires_at < now()
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let logins = sqlx::query(
+            r#"
+            DELETE FROM oidc_logins
+            WHERE expires_at < now()
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let challenges = sqlx::query(
+            r#"
+            DELETE FROM webauthn_challenges
+            WHERE expires_at < now()
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!(
+            "Deleted {} sessions, {} invitations, {} unfinished single sign-on logins and {} passkey challenges",
+            sessions.rows_affected(),
+            invitations.rows_affected(),
+            logins.rows_affected(),
+            challenges.rows_affected()
+        ))
+    })
+}
+
+pub fn purge_audit_log(pool: PgPool, config: &Config) -> JobFuture {
+    let retention_days = config.audit_retention_days;
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM audit_log
+            WHERE occurred_at < now() - make_interval(days => $1)
+            "#,
+        )
+        .bind(retention_days)
+        .execute(&pool)
+        .await?;
+
+        Ok(format!(
+            "Deleted {} audit log entries",
+            result.rows_affected()
+        ))
+    })
+}
+
+/// Exports hold a copy of everything stored about a user, so they are not kept
+/// for long
+pub fn purge_data_requests(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM data_requests
+            WHERE finished_at < now() - interval '7 days'
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!("Deleted {} data requests", result.rows_affected()))
+    })
+}
+
+/// Only today's count is enforced; older days are kept a month for reference
+pub fn purge_request_counts(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM household_requests
+            WHERE day < (now() AT TIME ZONE 'UTC')::date - 31
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!("Deleted {} request counts", result.rows_affected()))
+    })
+}
+
+pub fn purge_item_events(pool: PgPool, _config: &Config) -> JobFuture {
+    Box::pin(async move {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM item_events
+            WHERE created_at < now() - interval '1 day'
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(format!("Deleted {} item events", result.rows_affected()))
+    })
+}
+
+/// Syncs from before the newest purged tombstone start over, see
+/// [`crate::services::sync`]
+pub fn purge_tombstones(pool: PgPool, config: &Config) -> JobFuture {
+    let retention_days = config.tombstone_retention_days;
+    Box::pin(async move {
+        let purged = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH purged AS (
+                DELETE FROM tombstones
+                WHERE deleted_at < now() - make_interval(days => $1)
+                RETURNING version_xid::text::bigint AS xid
+            ), horizon AS (
+                INSERT INTO sync_horizon (xid)
+                SELECT MAX(xid) + 1 FROM purged HAVING COUNT(*) > 0
+                ON CONFLICT (id) DO UPDATE
+                SET xid = GREATEST(sync_horizon.xid, EXCLUDED.xid)
+            )
+            SELECT COUNT(*) FROM purged
+            "#,
+        )
+        .bind(retention_days)
+        .fetch_one(&pool)
+        .await?;
+
+        // Clients offline for longer have to start over anyway
+        let operations = sqlx::query(
+            r#"
+            DELETE FROM sync_operations
+            WHERE applied_at < now() - make_interval(days => $1)
+            "#,
+        )
+        .bind(retention_days)
+        .execute(&pool)
+        .await?;
+
+        Ok(format!(
+            "Deleted {} tombstones and {} replayed operations",
+            purged,
+            operations.rows_affected()
+        ))
+    })
+}
@@ -1,15 +1,31 @@
+use std::{net::SocketAddr, sync::Arc};
+
 use anyhow::Context;
 use sqlx::postgres::PgPoolOptions;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod admin;
+mod audit;
 mod auth;
+mod cli;
 mod config;
 mod error;
+mod etag;
+mod events;
+mod extract;
+#[cfg(feature = "embed-frontend")]
+mod frontend;
 mod handlers;
+mod i18n;
+mod jobs;
 mod models;
+mod problem;
 mod routes;
+mod services;
 mod state;
+mod tls;
 mod validation;
+mod ws;
 
 use config::Config;
 use state::AppState;
@@ -30,28 +46,84 @@ async fn main() -> anyhow::Result<()> {
 
     // Load configuration
     let config = Config::from_env()?;
-    tracing::info!("Starting server on {}:{}", config.host, config.port);
 
     // Create database connection pool
     let pool = PgPoolOptions::new()
         .max_connections(5)
+        // Attribute changes in the audit log to the task using the connection
+        .after_connect(|conn, _| Box::pin(audit::apply(conn)))
+        .before_acquire(|conn, _| Box::pin(async move { audit::apply(conn).await.map(|()| true) }))
         .connect(&config.database_url)
         .await
         .context("Failed to connect to database")?;
 
     tracing::info!("Connected to database");
 
+    sqlx::migrate!()
+        .run(&pool)
+        .await
+        .context("Failed to run database migrations")?;
+
+    // Management commands, e.g. `tokens list`, run instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return audit::scope(audit::Context::cli(), cli::run(&pool, &args)).await;
+    }
+
+    tracing::info!("Starting server on {}:{}", config.host, config.port);
+
     // Log auth status
-    if config.auth_token.is_some() {
+    if config.auth_token.is_some() || auth::credentials_exist(&pool).await? {
         tracing::info!("Authentication enabled");
     } else {
-        tracing::info!("Authentication disabled - API is open");
+        tracing::warn!("No AUTH_TOKEN, users or API tokens yet - API is open until one exists");
+    }
+
+    let oidc = match config.oidc.clone() {
+        Some(oidc_config) => {
+            tracing::info!("Single sign-on enabled with {}", oidc_config.issuer);
+            Some(Arc::new(auth::oidc::Provider::new(oidc_config)?))
+        }
+        None => None,
+    };
+    if let Some(webauthn) = &config.webauthn {
+        tracing::info!("Passkeys enabled for {}", webauthn.rp_id);
+    }
+    if let Some(proxy_auth) = &config.proxy_auth {
+        tracing::info!(
+            "Trusting {} from {:?}",
+            proxy_auth.user_header,
+            proxy_auth.trusted_networks
+        );
     }
 
+    // Finish exports and account deletions interrupted by a restart
+    audit::scope(
+        audit::Context::job("data_requests"),
+        jobs::data_requests::resume(&pool),
+    )
+    .await?;
+
+    // Start background jobs
+    let scheduler = Arc::new(jobs::Scheduler::new(pool.clone(), &config)?);
+    scheduler.start();
+
+    // Forward item changes from all instances to event streams
+    let events = events::Hub::default();
+    events.listen(pool.clone());
+
+    // Count requests against the daily quotas, written every few seconds
+    let requests = services::quotas::RequestCounter::default();
+    requests.flush_periodically(pool.clone(), config.quotas.clone());
+
     // Create application state
     let state = AppState {
         pool,
         config: config.clone(),
+        scheduler,
+        events,
+        requests,
+        oidc,
     };
 
     // Build application router
@@ -64,9 +136,22 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("Server listening on {}", listener.local_addr()?);
 
-    axum::serve(listener, app)
+    match &config.tls {
+        Some(tls_config) => {
+            let server_config = tls::server_config(tls_config)?;
+            if let Some(client_ca_path) = &tls_config.client_ca_path {
+                tracing::info!("Accepting client certificates issued by {client_ca_path}");
+            }
+            tls::serve(listener, app, server_config).await?;
+        }
+        // Peer addresses decide whether proxy authentication headers are trusted
+        None => axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
         .await
-        .context("Server error")?;
+        .context("Server error")?,
+    }
 
     Ok(())
 }
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::{
+    auth::Scope,
+    validation::{Validate, Validator, MAX_LENGTH},
+};
+
+/// Longest accepted token lifetime (10 years)
+pub const MAX_EXPIRY_DAYS: i64 = 3650;
+
+const SCOPE_FORMAT: &str = "read, write, admin, list:<id>:read or list:<id>:write";
+const CERTIFICATE_FORMAT: &str = "CN=<common name>, DNS:<name> or URI:<uri>";
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<String>,
+    #[serde(rename = "householdId")]
+    pub household_id: i32,
+    /// Clients presenting a certificate with this identity use the token
+    /// without a secret
+    #[serde(rename = "clientCertificate")]
+    pub client_certificate: Option<String>,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<i32>,
+    /// Never expires if null
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    /// Never expires if omitted
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<i64>,
+    /// Defaults to the caller's household
+    #[serde(rename = "householdId")]
+    pub household_id: Option<i32>,
+    /// Bind the token to a client certificate instead of issuing a secret
+    #[serde(rename = "clientCertificate")]
+    pub client_certificate: Option<String>,
+}
+
+/// A new API token; the token itself is only ever shown here
+#[derive(Debug, Serialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+    /// Missing for tokens bound to a client certificate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Validate for CreateApiTokenRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+        v.not_empty_list("scopes", &self.scopes);
+        for (i, scope) in self.scopes.iter_mut().enumerate() {
+            v.text(&format!("scopes[{i}]"), scope)
+                .lowercase()
+                .not_empty()
+                .format(Scope::is_valid, SCOPE_FORMAT);
+        }
+        v.number("expiresInDays", &self.expires_in_days)
+            .min(1)
+            .max(MAX_EXPIRY_DAYS);
+        v.text("clientCertificate", &mut self.client_certificate)
+            .empty_as_null()
+            .format(is_certificate_identity, CERTIFICATE_FORMAT)
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+fn is_certificate_identity(s: &str) -> bool {
+    ["CN=", "DNS:", "URI:"]
+        .iter()
+        .any(|prefix| s.strip_prefix(prefix).is_some_and(|name| !name.is_empty()))
+}
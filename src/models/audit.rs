@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+use crate::validation::{Validate, Validator};
+
+/// Tables whose changes are recorded in the audit log
+pub const AUDITED_ENTITIES: &[&str] = &[
+    "lists",
+    "items",
+    "categories",
+    "names",
+    "users",
+    "households",
+    "household_members",
+    "household_quotas",
+    "groups",
+    "group_members",
+    "list_permissions",
+    "list_shares",
+    "api_tokens",
+    "invitations",
+    "passkeys",
+    "user_identities",
+];
+
+pub const AUDIT_ACTIONS: &[&str] = &["create", "update", "delete"];
+pub const AUDIT_ACTORS: &[&str] = &["user", "token", "system", "job", "cli"];
+
+const DEFAULT_AUDIT_LIMIT: i64 = 100;
+const MAX_AUDIT_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: DateTime<Utc>,
+    pub actor: Option<String>,
+    #[serde(rename = "actorName")]
+    pub actor_name: Option<String>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<i32>,
+    #[serde(rename = "tokenId")]
+    pub token_id: Option<i32>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    pub entity: String,
+    #[serde(rename = "entityId")]
+    pub entity_id: Option<String>,
+    pub action: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Filters for the audit log; entries match all given filters
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub entity: Option<String>,
+    #[serde(rename = "entityId")]
+    pub entity_id: Option<String>,
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<i32>,
+    #[serde(rename = "tokenId")]
+    pub token_id: Option<i32>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Only entries older than this one, for paging
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl AuditQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_AUDIT_LIMIT)
+    }
+}
+
+impl Validate for AuditQuery {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("entity", &mut self.entity)
+            .lowercase()
+            .one_of(AUDITED_ENTITIES);
+        v.text("action", &mut self.action)
+            .lowercase()
+            .one_of(AUDIT_ACTIONS);
+        v.text("actor", &mut self.actor)
+            .lowercase()
+            .one_of(AUDIT_ACTORS);
+        v.number("limit", &self.limit).min(1).max(MAX_AUDIT_LIMIT);
+    }
+}
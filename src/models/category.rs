@@ -1,10 +1,14 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use crate::validation::{Validate, Validator, MAX_LENGTH};
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Category {
     pub id: i32,
     pub name: String,
+    /// Changes with every update; the `ETag`
+    pub version: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,3 +21,18 @@ pub struct UpdateCategoryRequest {
     pub name: String,
 }
 
+impl Validate for CreateCategoryRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdateCategoryRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
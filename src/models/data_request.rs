@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::{
+    ApiToken, AuditEntry, Group, Household, Invitation, Item, List, ListShare, Name, Passkey, User,
+};
+use crate::validation::{Validate, Validator};
+
+/// What happens to the lists a deleted user owns
+pub const LIST_POLICIES: &[&str] = &["transfer", "release", "delete"];
+
+/// A personal data export or account deletion, processed in the background
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DataRequest {
+    pub id: i64,
+    /// `export` or `deletion`
+    pub kind: String,
+    #[serde(rename = "userId")]
+    pub user_id: i32,
+    pub username: String,
+    #[serde(rename = "listPolicy", skip_serializing_if = "Option::is_none")]
+    pub list_policy: Option<String>,
+    #[serde(rename = "transferTo", skip_serializing_if = "Option::is_none")]
+    pub transfer_to: Option<i32>,
+    /// `pending`, `running`, `succeeded` or `failed`
+    pub status: String,
+    pub error: Option<String>,
+    #[serde(rename = "requestedBy")]
+    pub requested_by: Option<i32>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    /// `transfer` owned lists to `transferTo`, `release` them to the household
+    /// (private lists are deleted), or `delete` them
+    pub lists: String,
+    #[serde(rename = "transferTo")]
+    pub transfer_to: Option<i32>,
+}
+
+/// Everything stored about a user
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    #[serde(rename = "exportedAt")]
+    pub exported_at: DateTime<Utc>,
+    pub user: User,
+    pub households: Vec<ExportedMembership>,
+    pub groups: Vec<ExportedGroup>,
+    /// Lists the user owns, with their items
+    pub lists: Vec<ExportedList>,
+    /// Roles granted to the user on other lists
+    #[serde(rename = "listRoles")]
+    pub list_roles: Vec<ExportedListRole>,
+    /// Item names the user added first, as recorded in the audit log
+    pub names: Vec<ExportedName>,
+    #[serde(rename = "apiTokens")]
+    pub api_tokens: Vec<ApiToken>,
+    #[serde(rename = "listShares")]
+    pub list_shares: Vec<ListShare>,
+    /// Invitations the user created or joined with
+    pub invitations: Vec<Invitation>,
+    pub sessions: Vec<ExportedSession>,
+    pub passkeys: Vec<Passkey>,
+    /// Accounts at single sign-on providers and reverse proxies
+    pub identities: Vec<ExportedIdentity>,
+    /// Changes made by the user and to their account
+    #[serde(rename = "auditLog")]
+    pub audit_log: Vec<AuditEntry>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExportedMembership {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub household: Household,
+    pub role: String,
+    #[serde(rename = "joinedAt")]
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExportedGroup {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub group: Group,
+    #[serde(rename = "householdId")]
+    pub household_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedList {
+    #[serde(flatten)]
+    pub list: List,
+    #[serde(rename = "householdId")]
+    pub household_id: i32,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExportedListRole {
+    #[serde(rename = "listId")]
+    pub list_id: i32,
+    #[serde(rename = "listName")]
+    pub list_name: String,
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExportedName {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub name: Name,
+    #[serde(rename = "householdId")]
+    pub household_id: i32,
+}
+
+/// A sign-in, without its tokens
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExportedSession {
+    pub id: i64,
+    /// `browser` or `app`
+    pub kind: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: DateTime<Utc>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExportedIdentity {
+    pub issuer: String,
+    pub subject: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "syncedAt")]
+    pub synced_at: DateTime<Utc>,
+}
+
+impl Validate for DeleteAccountRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("lists", &mut self.lists)
+            .lowercase()
+            .not_empty()
+            .one_of(LIST_POLICIES);
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::validation::{Validate, Validator, MAX_LENGTH};
+
+/// Members of a household that lists can be shared with together
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Group {
+    pub id: i32,
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGroupRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateGroupRequest {
+    pub name: String,
+}
+
+impl Validate for CreateGroupRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdateGroupRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::User;
+use crate::{
+    auth::tenant::HOUSEHOLD_ROLES,
+    validation::{Validate, Validator, MAX_LENGTH},
+};
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Household {
+    pub id: i32,
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user in a household, with their role there
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct HouseholdMember {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub user: User,
+    /// `member` or `manager`
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHouseholdRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateHouseholdRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateHouseholdMemberRequest {
+    pub role: String,
+}
+
+impl Validate for CreateHouseholdRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdateHouseholdRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdateHouseholdMemberRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("role", &mut self.role).one_of(HOUSEHOLD_ROLES);
+    }
+}
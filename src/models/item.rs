@@ -1,10 +1,15 @@
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::validation::{Validate, Validator, AMOUNT_SCALE, MAX_AMOUNT, MAX_LENGTH};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Item {
     pub id: i32,
+    /// Chosen by the client creating the item, or generated
+    pub uuid: Uuid,
     pub name: String,
     #[sqlx(rename = "amount")]
     pub amount: Option<Decimal>,
@@ -16,15 +21,21 @@ pub struct Item {
     pub in_cart: bool,
     pub list: i32,
     pub category: Option<String>,
+    /// Changes with every update; the `ETag`
+    pub version: i64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateItemRequest {
+    /// Generated unless the client chooses one
+    pub uuid: Option<Uuid>,
     pub name: String,
     pub amount: Option<Decimal>,
     #[serde(rename = "amountUnit")]
     pub amount_unit: Option<String>,
     pub category: Option<String>,
+    #[serde(rename = "inCart", default)]
+    pub in_cart: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -37,14 +48,52 @@ pub struct UpdateItemRequest {
     pub amount_unit: Option<Option<String>>,
     #[serde(default, deserialize_with = "deserialize_some")]
     pub category: Option<Option<String>>,
+    #[serde(rename = "inCart")]
+    pub in_cart: Option<bool>,
+}
+
+/// Empty units and categories mean "none", on create as well as on update
+impl Validate for CreateItemRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+        v.number("amount", &self.amount)
+            .min(Decimal::ZERO)
+            .max(MAX_AMOUNT)
+            .max_scale(AMOUNT_SCALE);
+        v.text("amountUnit", &mut self.amount_unit)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+        v.text("category", &mut self.category)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdateItemRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+        v.number("amount", &self.amount)
+            .min(Decimal::ZERO)
+            .max(MAX_AMOUNT)
+            .max_scale(AMOUNT_SCALE);
+        v.text("amountUnit", &mut self.amount_unit)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+        v.text("category", &mut self.category)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+    }
 }
 
 // Helper function to distinguish between missing field and explicit null
-fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+pub(super) fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
 where
     T: Deserialize<'de>,
     D: serde::Deserializer<'de>,
 {
     Deserialize::deserialize(deserializer).map(Some)
 }
-
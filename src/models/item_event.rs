@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+
+use super::Item;
+
+/// A change to an item, as recorded by the `item_events` trigger
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ItemEvent {
+    pub id: i64,
+    #[serde(rename = "householdId")]
+    pub household_id: i32,
+    #[serde(rename = "listId")]
+    pub list_id: i32,
+    /// `created`, `updated`, `toggled` or `deleted`
+    pub kind: String,
+    /// The item after the change, or before it for `deleted`
+    pub item: Json<Item>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    /// The transaction that recorded the event, see [`crate::events`]
+    #[serde(skip_serializing)]
+    pub xid: i64,
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct JobRun {
+    pub id: i64,
+    pub job: String,
+    pub trigger: String,
+    pub status: String,
+    pub message: Option<String>,
+    pub error: Option<String>,
+    #[serde(rename = "startedAt")]
+    pub started_at: DateTime<Utc>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobStatus {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub schedule: String,
+    pub enabled: bool,
+    #[serde(rename = "nextRun")]
+    pub next_run: Option<DateTime<Utc>>,
+    #[serde(rename = "lastRun")]
+    pub last_run: Option<JobRun>,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<JobRun>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobDetails {
+    #[serde(flatten)]
+    pub status: JobStatus,
+    pub runs: Vec<JobRun>,
+}
@@ -1,26 +1,101 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{
+    auth::acl::GRANTABLE_ROLES,
+    validation::{Validate, Validator, MAX_LENGTH},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct List {
     pub id: i32,
+    /// Chosen by the client creating the list, or generated
+    pub uuid: Uuid,
     pub name: String,
+    #[serde(rename = "ownerId")]
+    pub owner_id: Option<i32>,
+    /// Only visible to the owner and users or groups granted a role
+    pub private: bool,
+    /// Changes with every update; the `ETag`
+    pub version: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ListWithCount {
     pub id: i32,
+    pub uuid: Uuid,
     pub name: String,
+    #[serde(rename = "ownerId")]
+    pub owner_id: Option<i32>,
+    pub private: bool,
+    /// The caller's role; null for API tokens and the shared token
+    pub role: Option<String>,
     pub count: Option<i64>,
+    pub version: i64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateListRequest {
+    /// Generated unless the client chooses one
+    pub uuid: Option<Uuid>,
     pub name: String,
+    #[serde(default)]
+    pub private: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateListRequest {
     pub name: String,
+    pub private: Option<bool>,
+}
+
+/// A role on a list granted to a user or a group
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ListPermission {
+    pub id: i32,
+    #[serde(rename = "userId")]
+    pub user_id: Option<i32>,
+    #[serde(rename = "groupId")]
+    pub group_id: Option<i32>,
+    pub role: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GrantListRoleRequest {
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferListRequest {
+    #[serde(rename = "userId")]
+    pub user_id: i32,
+}
+
+impl Validate for CreateListRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdateListRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for GrantListRoleRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("role", &mut self.role)
+            .lowercase()
+            .not_empty()
+            .one_of(GRANTABLE_ROLES);
+    }
+}
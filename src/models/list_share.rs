@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::validation::{Validate, Validator};
+
+/// Longest accepted share link lifetime (1 year)
+pub const MAX_SHARE_DAYS: i64 = 365;
+
+/// A read-only link to a list
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ListShare {
+    pub id: i32,
+    #[serde(rename = "listId")]
+    pub list_id: i32,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<i32>,
+    /// Never expires if null
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateListShareRequest {
+    /// Never expires if omitted
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<i64>,
+}
+
+/// A new share link; the token is only ever shown here
+#[derive(Debug, Serialize)]
+pub struct CreatedListShare {
+    #[serde(flatten)]
+    pub share: ListShare,
+    pub token: String,
+    /// Path of the public page, relative to the server
+    pub url: String,
+}
+
+/// What a share link shows
+#[derive(Debug, Serialize)]
+pub struct SharedList {
+    pub name: String,
+    pub items: Vec<SharedItem>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// An item as a share link shows it, without IDs or versions
+#[derive(Debug, Serialize, FromRow)]
+pub struct SharedItem {
+    pub name: String,
+    pub amount: Option<Decimal>,
+    #[sqlx(rename = "amountUnit")]
+    #[serde(rename = "amountUnit")]
+    pub amount_unit: Option<String>,
+    #[sqlx(rename = "inCart")]
+    #[serde(rename = "inCart")]
+    pub in_cart: bool,
+    /// Category name
+    pub category: Option<String>,
+}
+
+impl Validate for CreateListShareRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.number("expiresInDays", &self.expires_in_days)
+            .min(1)
+            .max(MAX_SHARE_DAYS);
+    }
+}
@@ -1,10 +1,54 @@
+pub mod api_token;
+pub mod audit;
 pub mod category;
+pub mod data_request;
+pub mod group;
+pub mod household;
 pub mod item;
+pub mod item_event;
+pub mod job;
 pub mod list;
+pub mod list_share;
 pub mod name;
+pub mod passkey;
+pub mod quota;
+pub mod sync;
+pub mod user;
 
-pub use category::Category;
+pub use api_token::{ApiToken, CreateApiTokenRequest, CreatedApiToken};
+pub use audit::{AuditEntry, AuditQuery};
+pub use category::{Category, CreateCategoryRequest, UpdateCategoryRequest};
+pub use data_request::{
+    AccountExport, DataRequest, DeleteAccountRequest, ExportedGroup, ExportedIdentity,
+    ExportedList, ExportedListRole, ExportedMembership, ExportedName, ExportedSession,
+};
+pub use group::{CreateGroupRequest, Group, UpdateGroupRequest};
+pub use household::{
+    CreateHouseholdRequest, Household, HouseholdMember, UpdateHouseholdMemberRequest,
+    UpdateHouseholdRequest,
+};
 pub use item::{CreateItemRequest, Item, UpdateItemRequest};
-pub use list::{CreateListRequest, List, ListWithCount, UpdateListRequest};
-pub use name::Name;
-
+pub use item_event::ItemEvent;
+pub use job::{JobDetails, JobRun, JobStatus};
+pub use list::{
+    CreateListRequest, GrantListRoleRequest, List, ListPermission, ListWithCount,
+    TransferListRequest, UpdateListRequest,
+};
+pub use list_share::{
+    CreateListShareRequest, CreatedListShare, ListShare, SharedItem, SharedList,
+};
+pub use name::{Name, UpdateNameRequest};
+pub use passkey::{
+    AssertionCredential, Passkey, PasskeyLoginOptionsRequest, PasskeyLoginRequest, PasskeyOptions,
+    RegisterPasskeyRequest, RegistrationCredential, UpdatePasskeyRequest,
+};
+pub use quota::{HouseholdUsage, QuotaUsage, Quotas};
+pub use sync::{
+    Change, ListChanges, Operation, OperationResult, PushRequest, PushResult, SyncChanges,
+    SyncCursor, SyncQuery, Synced, Tombstone,
+};
+pub use user::{
+    ChangePasswordRequest, CreateInvitationRequest, CreateUserRequest, CreatedInvitation,
+    Invitation, InvitationQuery, JoinHouseholdRequest, LoginRequest, RegisterRequest, SessionResponse,
+    TokenRequest, TokenResponse, UpdateProfileRequest, UpdateUserRequest, User,
+};
@@ -1,11 +1,33 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use super::item::deserialize_some;
+use crate::validation::{Validate, Validator, MAX_LENGTH};
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Name {
     pub id: i32,
     pub name: String,
     pub count: Option<i64>,
     pub category: Option<String>,
+    /// Changes with every update; the `ETag`
+    pub version: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateNameRequest {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub category: Option<Option<String>>,
 }
 
+impl Validate for UpdateNameRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+        v.text("category", &mut self.category)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+use crate::validation::{Validate, Validator, MAX_LENGTH};
+
+/// A passkey registered for the signed-in user
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Passkey {
+    pub id: i32,
+    pub name: String,
+    /// How the authenticator can be reached, e.g. `internal`, `usb` or `hybrid`
+    pub transports: Vec<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Options for `navigator.credentials.create()` or `.get()`, binary values
+/// base64url-encoded as expected by `PublicKeyCredential.parse*OptionsFromJSON()`
+#[derive(Debug, Serialize)]
+pub struct PasskeyOptions {
+    #[serde(rename = "publicKey")]
+    pub public_key: Value,
+}
+
+/// `PublicKeyCredential` returned by `navigator.credentials.create()`, as
+/// produced by its `toJSON()`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+    #[serde(default)]
+    pub transports: Vec<String>,
+}
+
+/// `PublicKeyCredential` returned by `navigator.credentials.get()`, as
+/// produced by its `toJSON()`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterPasskeyRequest {
+    pub name: String,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePasskeyRequest {
+    pub name: String,
+}
+
+/// Login options for one user's passkeys, or without a username for the
+/// passkeys the authenticator discovers itself
+#[derive(Debug, Deserialize)]
+pub struct PasskeyLoginOptionsRequest {
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeyLoginRequest {
+    pub credential: AssertionCredential,
+}
+
+impl Validate for RegisterPasskeyRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdatePasskeyRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for PasskeyLoginOptionsRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("username", &mut self.username)
+            .empty_as_null()
+            .lowercase();
+    }
+}
+
+impl Validate for PasskeyLoginRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.verbatim("credential.id", &mut self.credential.id)
+            .not_empty();
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::validation::{Validate, Validator};
+
+/// Limits on what a household may create; `None` is unlimited, or in
+/// overrides, the server default
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct Quotas {
+    pub lists: Option<i64>,
+    #[serde(rename = "itemsPerList")]
+    pub items_per_list: Option<i64>,
+    pub names: Option<i64>,
+    pub categories: Option<i64>,
+    /// API requests working in the household per day (UTC)
+    #[serde(rename = "requestsPerDay")]
+    pub requests_per_day: Option<i64>,
+}
+
+impl Quotas {
+    /// These limits, falling back to `defaults` where unset
+    pub fn or(&self, defaults: &Quotas) -> Quotas {
+        Quotas {
+            lists: self.lists.or(defaults.lists),
+            items_per_list: self.items_per_list.or(defaults.items_per_list),
+            names: self.names.or(defaults.names),
+            categories: self.categories.or(defaults.categories),
+            requests_per_day: self.requests_per_day.or(defaults.requests_per_day),
+        }
+    }
+}
+
+/// How much of a quota is used
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaUsage {
+    pub used: i64,
+    /// `None` when unlimited
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HouseholdUsage {
+    #[serde(rename = "householdId")]
+    pub household_id: i32,
+    pub lists: QuotaUsage,
+    /// Items on the fullest list
+    #[serde(rename = "itemsPerList")]
+    pub items_per_list: QuotaUsage,
+    pub names: QuotaUsage,
+    pub categories: QuotaUsage,
+    /// Requests made today (UTC)
+    #[serde(rename = "requestsPerDay")]
+    pub requests_per_day: QuotaUsage,
+}
+
+impl Validate for Quotas {
+    fn validate(&mut self, v: &mut Validator) {
+        v.number("lists", &self.lists).min(0);
+        v.number("itemsPerList", &self.items_per_list).min(0);
+        v.number("names", &self.names).min(0);
+        v.number("categories", &self.categories).min(0);
+        v.number("requestsPerDay", &self.requests_per_day).min(0);
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgRow, FromRow, Row};
+use uuid::Uuid;
+
+use super::{Category, CreateItemRequest, CreateListRequest, Item, List, Name, UpdateItemRequest};
+use crate::{
+    problem::Problem,
+    validation::{Validate, Validator, MAX_LENGTH},
+};
+
+const DEFAULT_SYNC_LIMIT: i64 = 500;
+const MAX_SYNC_LIMIT: i64 = 1000;
+
+/// Most operations replayed by one request
+const MAX_OPERATIONS: usize = 500;
+
+/// A row changed since the client's cursor
+#[derive(Debug, Clone, Serialize)]
+pub struct Synced<T> {
+    #[serde(flatten)]
+    pub row: T,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'r, T: FromRow<'r, PgRow>> FromRow<'r, PgRow> for Synced<T> {
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        Ok(Synced {
+            row: T::from_row(row)?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// A deleted row
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Tombstone {
+    /// `lists`, `items`, `categories` or `names`
+    pub entity: String,
+    pub id: i32,
+    /// For lists and items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+    /// The list itself, or the item's list
+    #[serde(rename = "listId", skip_serializing_if = "Option::is_none")]
+    pub list_id: Option<i32>,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub version: i64,
+}
+
+/// One page of changes
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncChanges {
+    pub lists: Vec<Synced<List>>,
+    pub items: Vec<Synced<Item>>,
+    pub categories: Vec<Synced<Category>>,
+    pub names: Vec<Synced<Name>>,
+    pub deleted: Vec<Tombstone>,
+    /// `since` for the next request
+    pub cursor: String,
+    /// Whether the next page follows right away
+    #[serde(rename = "hasMore")]
+    pub has_more: bool,
+    /// The cursor was too old: this is a full sync, local data must be replaced
+    pub reset: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncQuery {
+    pub since: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl SyncQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_SYNC_LIMIT)
+    }
+}
+
+impl Validate for SyncQuery {
+    fn validate(&mut self, v: &mut Validator) {
+        v.number("limit", &self.limit).min(1).max(MAX_SYNC_LIMIT);
+    }
+}
+
+/// Position in the change feed. Changes are read in windows of transaction
+/// IDs: `from` up to (not including) `to`, which is the oldest transaction
+/// still running when the window was opened, so every change in it has been
+/// committed. Within a window, pages follow the change versions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncCursor {
+    pub from: i64,
+    /// Set while paging through a window
+    pub to: Option<i64>,
+    /// The last version on the previous page
+    pub after: i64,
+}
+
+impl SyncCursor {
+    /// `<from>` between windows, `<from>.<to>.<after>` within one
+    pub fn parse(s: &str) -> Option<SyncCursor> {
+        let mut parts = s.split('.').map(|part| part.parse::<i64>().ok());
+        let cursor = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(from), None, None, None) => SyncCursor {
+                from: from?,
+                to: None,
+                after: 0,
+            },
+            (Some(from), Some(to), Some(after), None) => SyncCursor {
+                from: from?,
+                to: Some(to?),
+                after: after?,
+            },
+            _ => return None,
+        };
+
+        Some(cursor).filter(|cursor| cursor.from >= 0 && cursor.to.is_none_or(|to| to >= 0))
+    }
+}
+
+/// Changes made offline, replayed in order
+#[derive(Debug, Deserialize)]
+pub struct PushRequest {
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Operation {
+    /// Chosen by the client; an operation is applied only once
+    pub id: Uuid,
+    /// When the change was made on the client
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+/// Lists and items are referred to by UUID, so new ones can be changed
+/// before they are synced
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Change {
+    /// `list.uuid` is required
+    CreateList {
+        list: CreateListRequest,
+    },
+    UpdateList {
+        uuid: Uuid,
+        list: ListChanges,
+    },
+    DeleteList {
+        uuid: Uuid,
+    },
+    /// `item.uuid` is required
+    CreateItem {
+        list_uuid: Uuid,
+        item: CreateItemRequest,
+    },
+    UpdateItem {
+        uuid: Uuid,
+        item: UpdateItemRequest,
+    },
+    DeleteItem {
+        uuid: Uuid,
+    },
+}
+
+impl Change {
+    /// The UUIDs of the list and the item the change refers to
+    pub fn targets(&self) -> (Option<Uuid>, Option<Uuid>) {
+        match self {
+            Change::CreateList { list } => (list.uuid, None),
+            Change::UpdateList { uuid, .. } | Change::DeleteList { uuid } => (Some(*uuid), None),
+            Change::CreateItem { list_uuid, item } => (Some(*list_uuid), item.uuid),
+            Change::UpdateItem { uuid, .. } | Change::DeleteItem { uuid } => (None, Some(*uuid)),
+        }
+    }
+}
+
+/// Like [`super::UpdateListRequest`], but every field is optional
+#[derive(Debug, Deserialize)]
+pub struct ListChanges {
+    pub name: Option<String>,
+    pub private: Option<bool>,
+}
+
+/// What became of an operation
+#[derive(Debug, Serialize)]
+pub struct OperationResult {
+    pub id: Uuid,
+    /// `applied`, `duplicate` (applied before), `skipped` or `failed`
+    pub status: &'static str,
+    /// Why the operation was skipped: `deleted` or `outdated`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    /// Fields left as they were because they changed later
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outdated: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Problem>,
+}
+
+impl OperationResult {
+    pub fn applied(id: Uuid, outdated: Vec<&'static str>) -> OperationResult {
+        OperationResult {
+            id,
+            status: "applied",
+            reason: None,
+            outdated,
+            error: None,
+        }
+    }
+
+    pub fn duplicate(id: Uuid) -> OperationResult {
+        OperationResult {
+            status: "duplicate",
+            ..OperationResult::applied(id, Vec::new())
+        }
+    }
+
+    pub fn skipped(id: Uuid, reason: &'static str, outdated: Vec<&'static str>) -> OperationResult {
+        OperationResult {
+            status: "skipped",
+            reason: Some(reason),
+            ..OperationResult::applied(id, outdated)
+        }
+    }
+
+    pub fn failed(id: Uuid, error: Problem) -> OperationResult {
+        OperationResult {
+            status: "failed",
+            error: Some(error),
+            ..OperationResult::applied(id, Vec::new())
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushResult {
+    pub results: Vec<OperationResult>,
+    /// The lists and items the operations refer to as they are now; those
+    /// missing are deleted
+    pub lists: Vec<Synced<List>>,
+    pub items: Vec<Synced<Item>>,
+}
+
+impl Validate for ListChanges {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("name", &mut self.name)
+            .not_empty()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for PushRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.max_entries("operations", &self.operations, MAX_OPERATIONS);
+        for (i, operation) in self.operations.iter_mut().enumerate() {
+            let field = format!("operations[{i}]");
+            match &mut operation.change {
+                Change::CreateList { list } => {
+                    v.required(&format!("{field}.list.uuid"), &list.uuid);
+                    v.nested(&format!("{field}.list"), list);
+                }
+                Change::UpdateList { list, .. } => v.nested(&format!("{field}.list"), list),
+                Change::CreateItem { item, .. } => {
+                    v.required(&format!("{field}.item.uuid"), &item.uuid);
+                    v.nested(&format!("{field}.item"), item);
+                }
+                Change::UpdateItem { item, .. } => v.nested(&format!("{field}.item"), item),
+                Change::DeleteList { .. } | Change::DeleteItem { .. } => {}
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyncCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to {
+            Some(to) => write!(f, "{}.{}.{}", self.from, to, self.after),
+            None => write!(f, "{}", self.from),
+        }
+    }
+}
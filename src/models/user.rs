@@ -0,0 +1,336 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::{item::deserialize_some, passkey::AssertionCredential};
+use crate::{
+    auth::tenant::HOUSEHOLD_ROLES,
+    validation::{Validate, Validator, MAX_LENGTH},
+};
+
+/// Languages a user can choose for messages
+pub const LANGUAGES: &[&str] = &["en", "de"];
+
+pub const USERNAME_MIN: usize = 3;
+pub const USERNAME_MAX: usize = 50;
+const PASSWORD_MIN: usize = 8;
+const PASSWORD_MAX: usize = 256;
+/// Most uses of a limited invitation
+const MAX_INVITATION_USES: i32 = 1000;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    #[serde(rename = "isAdmin")]
+    pub is_admin: bool,
+    pub disabled: bool,
+    pub language: Option<String>,
+    /// Password logins must also present a passkey
+    #[serde(rename = "passkeyRequired")]
+    pub passkey_required: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    #[serde(rename = "isAdmin", default)]
+    pub is_admin: bool,
+    /// Household the user joins; defaults to the caller's
+    #[serde(rename = "householdId")]
+    pub household_id: Option<i32>,
+}
+
+/// Changes made by an admin. All fields are optional.
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequest {
+    #[serde(rename = "displayName")]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub display_name: Option<Option<String>>,
+    #[serde(rename = "isAdmin")]
+    pub is_admin: Option<bool>,
+    pub disabled: Option<bool>,
+    /// Sets a new password and signs the user out everywhere
+    pub password: Option<String>,
+}
+
+/// Changes a user makes to their own account. All fields are optional.
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileRequest {
+    #[serde(rename = "displayName")]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub display_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub language: Option<Option<String>>,
+    /// Require a passkey in addition to the password; needs a registered passkey
+    #[serde(rename = "passkeyRequired")]
+    pub passkey_required: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+    /// Second factor for users who require a passkey
+    pub passkey: Option<AssertionCredential>,
+}
+
+/// OAuth-style token request: sign in with a password or a passkey, or rotate
+/// a refresh token
+#[derive(Debug, Deserialize)]
+#[serde(tag = "grantType", rename_all = "snake_case")]
+pub enum TokenRequest {
+    Password {
+        username: String,
+        password: String,
+        passkey: Option<AssertionCredential>,
+    },
+    Passkey {
+        credential: AssertionCredential,
+    },
+    RefreshToken {
+        #[serde(rename = "refreshToken")]
+        refresh_token: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub invitation: String,
+    pub username: String,
+    pub password: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+}
+
+/// Response to a browser login; the session itself is in an HttpOnly cookie
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub user: User,
+    /// Must be sent as `X-CSRF-Token` with every unsafe request
+    #[serde(rename = "csrfToken")]
+    pub csrf_token: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    #[serde(rename = "accessToken")]
+    pub access_token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[serde(rename = "tokenType")]
+    pub token_type: &'static str,
+    /// Lifetime of the access token in seconds
+    #[serde(rename = "expiresIn")]
+    pub expires_in: i64,
+    pub user: User,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Invitation {
+    pub id: i32,
+    #[serde(rename = "isAdmin")]
+    pub is_admin: bool,
+    /// Household the new user joins
+    #[serde(rename = "householdId")]
+    pub household_id: Option<i32>,
+    /// Role in the household: `member` or `manager`
+    pub role: String,
+    /// Unlimited if null
+    #[serde(rename = "maxUses")]
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    /// `pending`, `used` once all uses are taken, or `expired`
+    pub status: String,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<i32>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    /// The user of the latest use
+    #[serde(rename = "usedBy")]
+    pub used_by: Option<i32>,
+    #[serde(rename = "usedAt")]
+    pub used_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationRequest {
+    #[serde(rename = "isAdmin", default)]
+    pub is_admin: bool,
+    #[serde(rename = "expiresInHours")]
+    pub expires_in_hours: Option<i64>,
+    /// Defaults to the caller's household
+    #[serde(rename = "householdId")]
+    pub household_id: Option<i32>,
+    /// Defaults to `member`
+    pub role: Option<String>,
+    /// Defaults to 1; null for unlimited uses
+    #[serde(rename = "maxUses")]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub max_uses: Option<Option<i32>>,
+}
+
+/// A new invitation; the join code is only ever shown here
+#[derive(Debug, Serialize)]
+pub struct CreatedInvitation {
+    #[serde(flatten)]
+    pub invitation: Invitation,
+    /// Join code like `K7QF-9XMW-PT3H`
+    pub token: String,
+}
+
+/// Filter of `GET /api/invitations`
+#[derive(Debug, Deserialize)]
+pub struct InvitationQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinHouseholdRequest {
+    pub invitation: String,
+}
+
+pub fn username_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-')
+}
+
+fn validate_username(v: &mut Validator, username: &mut String) {
+    v.text("username", username)
+        .lowercase()
+        .not_empty()
+        .min_chars(USERNAME_MIN)
+        .max_chars(USERNAME_MAX)
+        .chars(username_char, "a-z 0-9 . _ -");
+}
+
+fn validate_password(v: &mut Validator, name: &str, password: &mut String) {
+    v.verbatim(name, password)
+        .not_empty()
+        .min_chars(PASSWORD_MIN)
+        .max_chars(PASSWORD_MAX);
+}
+
+impl Validate for CreateUserRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        validate_username(v, &mut self.username);
+        validate_password(v, "password", &mut self.password);
+        v.text("displayName", &mut self.display_name)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for UpdateUserRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("displayName", &mut self.display_name)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+        if let Some(password) = &mut self.password {
+            validate_password(v, "password", password);
+        }
+    }
+}
+
+impl Validate for UpdateProfileRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("displayName", &mut self.display_name)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+        v.text("language", &mut self.language)
+            .empty_as_null()
+            .lowercase()
+            .one_of(LANGUAGES);
+    }
+}
+
+impl Validate for ChangePasswordRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.verbatim("currentPassword", &mut self.current_password)
+            .not_empty();
+        validate_password(v, "newPassword", &mut self.new_password);
+    }
+}
+
+impl Validate for LoginRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("username", &mut self.username)
+            .lowercase()
+            .not_empty();
+        v.verbatim("password", &mut self.password).not_empty();
+    }
+}
+
+impl Validate for TokenRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        match self {
+            TokenRequest::Password {
+                username, password, ..
+            } => {
+                v.text("username", username).lowercase().not_empty();
+                v.verbatim("password", password).not_empty();
+            }
+            TokenRequest::Passkey { credential } => {
+                v.verbatim("credential.id", &mut credential.id).not_empty();
+            }
+            TokenRequest::RefreshToken { refresh_token } => {
+                v.text("refreshToken", refresh_token).not_empty();
+            }
+        }
+    }
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("invitation", &mut self.invitation).not_empty();
+        validate_username(v, &mut self.username);
+        validate_password(v, "password", &mut self.password);
+        v.text("displayName", &mut self.display_name)
+            .empty_as_null()
+            .max_chars(MAX_LENGTH);
+    }
+}
+
+impl Validate for CreateInvitationRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.number("expiresInHours", &self.expires_in_hours)
+            .min(1)
+            .max(24 * 30);
+        v.text("role", &mut self.role).one_of(HOUSEHOLD_ROLES);
+        v.number("maxUses", &self.max_uses)
+            .min(1)
+            .max(MAX_INVITATION_USES);
+    }
+}
+
+impl Validate for InvitationQuery {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("status", &mut self.status)
+            .lowercase()
+            .one_of(&["pending", "used", "expired"]);
+    }
+}
+
+impl Validate for JoinHouseholdRequest {
+    fn validate(&mut self, v: &mut Validator) {
+        v.text("invitation", &mut self.invitation).not_empty();
+    }
+}
@@ -0,0 +1,184 @@
+//! RFC 7807 `application/problem+json` error responses.
+//!
+//! Errors build a [`Problem`] without knowing the request; [`problem_middleware`]
+//! completes it with the request path and ID, renders its messages in the
+//! client's language, and turns bodyless error responses (unknown routes,
+//! timeouts, ...) into problems as well.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    i18n::{Lang, Message},
+    validation::FieldError,
+};
+
+pub const CONTENT_TYPE: &str = "application/problem+json";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub type_uri: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Stable, machine-readable error code
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Additional members specific to the problem type
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+    /// Untranslated `detail`
+    #[serde(skip)]
+    detail_message: Message,
+    /// Untranslated `errors` extension
+    #[serde(skip)]
+    field_errors: Vec<FieldError>,
+}
+
+impl Problem {
+    /// The title is looked up by `code`; everything starts out in English
+    pub fn new(status: StatusCode, code: &'static str, detail: Message) -> Self {
+        let mut problem = Self {
+            type_uri: format!("urn:lister:problem:{code}"),
+            title: String::new(),
+            status: status.as_u16(),
+            detail: String::new(),
+            code,
+            instance: None,
+            request_id: None,
+            extensions: Map::new(),
+            detail_message: detail,
+            field_errors: Vec::new(),
+        };
+        problem.localize(Lang::En);
+        problem
+    }
+
+    pub fn with_extension(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.extensions.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Adds the `errors` extension listing invalid fields
+    pub fn with_field_errors(mut self, errors: Vec<FieldError>) -> Self {
+        self.field_errors = errors;
+        self.localize(Lang::En);
+        self
+    }
+
+    /// Render title, detail and field errors in `lang`
+    pub fn localize(&mut self, lang: Lang) {
+        self.title = Message::new(format!("title.{}", self.code)).render(lang);
+        self.detail = self.detail_message.render(lang);
+        if !self.field_errors.is_empty() {
+            let errors = self
+                .field_errors
+                .iter()
+                .map(|error| error.to_json(lang))
+                .collect();
+            self.extensions
+                .insert("errors".to_string(), Value::Array(errors));
+        }
+    }
+
+    /// Problem for an error response that was produced without a body
+    fn from_status(status: StatusCode) -> Self {
+        let (code, detail) = match status {
+            StatusCode::NOT_FOUND => ("not_found", "detail.route_not_found"),
+            StatusCode::METHOD_NOT_ALLOWED => ("method_not_allowed", "detail.method_not_allowed"),
+            StatusCode::REQUEST_TIMEOUT => ("timeout", "detail.timeout"),
+            StatusCode::PAYLOAD_TOO_LARGE => ("payload_too_large", "detail.payload_too_large"),
+            s if s.is_server_error() => ("internal_error", "detail.internal_error"),
+            _ => ("bad_request", "detail.bad_request"),
+        };
+        Self::new(status, code, Message::new(detail))
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let body = serde_json::to_vec(&self).unwrap_or_default();
+        let mut response = (
+            self.status_code(),
+            [(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE))],
+            body,
+        )
+            .into_response();
+
+        // Keep the problem around so the middleware can complete it
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Adds `instance` and `requestId` to problem responses and translates them.
+///
+/// The language comes from `Accept-Language`, unless the response carries a
+/// [`Lang`] extension (e.g. the preference of the signed-in user).
+pub async fn problem_middleware(request: Request, next: Next) -> Response {
+    let instance = request.uri().path().to_string();
+    let accepted_lang = Lang::from_headers(request.headers());
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+
+    let problem = match response.extensions().get::<Problem>() {
+        Some(problem) => problem.clone(),
+        None if is_bodyless_error(&response) => Problem::from_status(response.status()),
+        None => return response,
+    };
+
+    let lang = response
+        .extensions()
+        .get::<Lang>()
+        .copied()
+        .unwrap_or(accepted_lang);
+    let mut problem = Problem {
+        instance: Some(instance),
+        request_id,
+        ..problem
+    };
+    problem.localize(lang);
+
+    let (mut parts, _) = response.into_parts();
+    let body = serde_json::to_vec(&problem).unwrap_or_default();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE));
+    parts.headers.insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(lang.code()),
+    );
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-language"));
+    parts.extensions.insert(problem);
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn is_bodyless_error(response: &Response) -> bool {
+    (response.status().is_client_error() || response.status().is_server_error())
+        && !response.headers().contains_key(header::CONTENT_TYPE)
+}
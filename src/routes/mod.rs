@@ -3,11 +3,21 @@ use axum::{
     routing::{delete, get, patch, post, put},
     Router,
 };
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use std::time::Duration;
 
-use crate::{auth, handlers, state::AppState};
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
+
+use crate::{admin, audit, auth, handlers, problem, state::AppState};
 
 pub fn create_router(state: AppState) -> Router {
+    let admin_routes = admin::router(state.clone());
+    let request_timeout = Duration::from_secs(state.config.request_timeout_secs);
+
     let api_routes = Router::new()
         // Lists routes
         .route("/lists", get(handlers::get_all_lists))
@@ -15,9 +25,33 @@ pub fn create_router(state: AppState) -> Router {
         .route("/lists/:id", get(handlers::get_list))
         .route("/lists/:id", put(handlers::update_list))
         .route("/lists/:id", delete(handlers::delete_list))
+        .route("/lists/:id/owner", put(handlers::transfer_list))
+        // List sharing routes
+        .route("/lists/:id/permissions", get(handlers::get_list_permissions))
+        .route(
+            "/lists/:id/permissions/users/:user_id",
+            put(handlers::grant_list_role_to_user),
+        )
+        .route(
+            "/lists/:id/permissions/users/:user_id",
+            delete(handlers::revoke_list_role_from_user),
+        )
+        .route(
+            "/lists/:id/permissions/groups/:group_id",
+            put(handlers::grant_list_role_to_group),
+        )
+        .route(
+            "/lists/:id/permissions/groups/:group_id",
+            delete(handlers::revoke_list_role_from_group),
+        )
+        .route("/lists/:id/shares", get(handlers::get_list_shares))
+        .route("/lists/:id/shares", post(handlers::create_list_share))
+        .route("/lists/:id/shares/:share_id", delete(handlers::revoke_list_share))
         // Items routes
         .route("/lists/:list_id/items", get(handlers::get_list_items))
         .route("/lists/:list_id/items", post(handlers::create_item))
+        .route("/lists/:list_id/events", get(handlers::get_list_events))
+        .route("/events", get(handlers::get_household_events))
         .route("/items/:id", get(handlers::get_item))
         .route("/items/:id", put(handlers::update_item))
         .route("/items/:id", delete(handlers::delete_item))
@@ -39,17 +73,133 @@ pub fn create_router(state: AppState) -> Router {
             "/search/category-mappings",
             get(handlers::get_category_mappings),
         )
+        // Sync routes
+        .route("/sync", get(handlers::get_sync))
+        .route("/sync", post(handlers::push_changes))
+        // Admin routes
+        .route("/admin/jobs", get(handlers::get_all_jobs))
+        .route("/admin/jobs/:name", get(handlers::get_job))
+        .route("/admin/jobs/:name/run", post(handlers::run_job))
+        .route("/admin/audit", get(handlers::get_audit_log))
+        // Account routes
+        .route("/auth/logout", post(handlers::auth::logout))
+        .route("/auth/me", get(handlers::auth::get_me))
+        .route("/auth/me", put(handlers::auth::update_me))
+        .route("/auth/me/password", put(handlers::auth::change_password))
+        .route("/auth/join", post(handlers::auth::join_household))
+        .route(
+            "/auth/me/passkeys",
+            get(handlers::passkeys::get_all_passkeys),
+        )
+        .route(
+            "/auth/me/passkeys",
+            post(handlers::passkeys::create_passkey),
+        )
+        .route(
+            "/auth/me/passkeys/options",
+            post(handlers::passkeys::passkey_registration_options),
+        )
+        .route(
+            "/auth/me/passkeys/:id",
+            put(handlers::passkeys::update_passkey),
+        )
+        .route(
+            "/auth/me/passkeys/:id",
+            delete(handlers::passkeys::delete_passkey),
+        )
+        .route("/auth/me/export", post(handlers::export_me))
+        .route("/auth/me/deletion", post(handlers::delete_me))
+        // User management routes (admin)
+        .route("/users", get(handlers::get_all_users))
+        .route("/users", post(handlers::create_user))
+        .route("/users/:id", get(handlers::get_user))
+        .route("/users/:id", put(handlers::update_user))
+        .route("/users/:id", delete(handlers::delete_user))
+        .route("/users/:id/export", post(handlers::export_user))
+        .route("/users/:id/deletion", post(handlers::delete_user_account))
+        .route("/invitations", get(handlers::get_all_invitations))
+        .route("/invitations", post(handlers::create_invitation))
+        .route("/invitations/:id", delete(handlers::delete_invitation))
+        // Personal data export and account deletion routes
+        .route("/data-requests", get(handlers::get_all_data_requests))
+        .route("/data-requests/:id", get(handlers::get_data_request))
+        .route("/data-requests/:id/export", get(handlers::download_export))
+        // Household routes
+        .route("/households", get(handlers::get_all_households))
+        .route("/households", post(handlers::create_household))
+        .route("/households/:id", get(handlers::get_household))
+        .route("/households/:id", put(handlers::update_household))
+        .route("/households/:id", delete(handlers::delete_household))
+        .route("/households/:id/members", get(handlers::get_household_members))
+        .route(
+            "/households/:id/members/:user_id",
+            put(handlers::add_household_member),
+        )
+        .route(
+            "/households/:id/members/:user_id",
+            delete(handlers::remove_household_member),
+        )
+        .route(
+            "/households/:id/members/:user_id/role",
+            put(handlers::update_household_member_role),
+        )
+        .route("/households/:id/usage", get(handlers::get_household_usage))
+        .route("/households/:id/quotas", get(handlers::get_household_quotas))
+        .route("/households/:id/quotas", put(handlers::update_household_quotas))
+        // Group routes
+        .route("/groups", get(handlers::get_all_groups))
+        .route("/groups", post(handlers::create_group))
+        .route("/groups/:id", get(handlers::get_group))
+        .route("/groups/:id", put(handlers::update_group))
+        .route("/groups/:id", delete(handlers::delete_group))
+        .route("/groups/:id/members", get(handlers::get_group_members))
+        .route("/groups/:id/members/:user_id", put(handlers::add_group_member))
+        .route(
+            "/groups/:id/members/:user_id",
+            delete(handlers::remove_group_member),
+        )
+        // API token routes (admin)
+        .route("/tokens", get(handlers::get_all_api_tokens))
+        .route("/tokens", post(handlers::create_api_token))
+        .route("/tokens/:id", get(handlers::get_api_token))
+        .route("/tokens/:id", delete(handlers::revoke_api_token))
         .layer(middleware::from_fn_with_state(
-            state.config.clone(),
+            state.clone(),
             auth::auth_middleware,
         ))
+        // Public routes (added after the auth layer, so it does not apply)
+        .route("/auth/login", post(handlers::auth::login))
+        .route(
+            "/auth/login/passkey/options",
+            post(handlers::auth::passkey_login_options),
+        )
+        .route("/auth/login/passkey", post(handlers::auth::passkey_login))
+        .route("/auth/token", post(handlers::auth::token))
+        .route("/auth/register", post(handlers::auth::register))
+        .route("/auth/oidc/login", get(handlers::auth::oidc_login))
+        .route("/auth/oidc/callback", get(handlers::auth::oidc_callback))
+        .route("/shared/:token", get(handlers::get_shared_list))
+        // Authenticated by its first message
+        .route("/ws", get(handlers::open_websocket))
         .with_state(state);
 
-    Router::new()
+    let router = Router::new()
         .nest("/api", api_routes)
-        .route("/health", get(health_check))
+        .nest("/admin", admin_routes)
+        .route("/health", get(health_check));
+
+    // Everything else is the embedded SPA, if compiled in
+    #[cfg(feature = "embed-frontend")]
+    let router = router.fallback(crate::frontend::serve);
+
+    router
+        .layer(middleware::from_fn(audit::audit_middleware))
+        .layer(TimeoutLayer::new(request_timeout))
+        .layer(middleware::from_fn(problem::problem_middleware))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
 }
 
 async fn health_check() -> &'static str {
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+use crate::{
+    auth::{session, tokens, Scope},
+    error::{AppError, Result},
+    models::{ApiToken, CreatedApiToken},
+};
+
+const API_TOKEN_COLUMNS: &str = "id, name, scopes, household_id, client_certificate, created_by, \
+     expires_at, last_used_at, revoked_at, created_at";
+
+pub async fn list(conn: &mut PgConnection) -> Result<Vec<ApiToken>> {
+    let api_tokens = sqlx::query_as::<_, ApiToken>(&format!(
+        "SELECT {API_TOKEN_COLUMNS} FROM api_tokens ORDER BY created_at DESC"
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(api_tokens)
+}
+
+/// Tokens a user created, including revoked and expired ones
+pub async fn created_by(conn: &mut PgConnection, user_id: i32) -> Result<Vec<ApiToken>> {
+    let api_tokens = sqlx::query_as::<_, ApiToken>(&format!(
+        "SELECT {API_TOKEN_COLUMNS} FROM api_tokens WHERE created_by = $1 ORDER BY created_at DESC"
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(api_tokens)
+}
+
+pub async fn find(conn: &mut PgConnection, id: i32) -> Result<ApiToken> {
+    sqlx::query_as::<_, ApiToken>(&format!(
+        "SELECT {API_TOKEN_COLUMNS} FROM api_tokens WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Create a token; the scopes must already be validated. They are stored in
+/// canonical form, e.g. `list:7:read` for `list:007:read`. Tokens bound to a
+/// client certificate get no secret.
+pub async fn create(
+    conn: &mut PgConnection,
+    household_id: i32,
+    name: &str,
+    scopes: &[String],
+    client_certificate: Option<&str>,
+    created_by: Option<i32>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<CreatedApiToken> {
+    let token = client_certificate.is_none().then(tokens::generate);
+    let scopes: Vec<String> = scopes
+        .iter()
+        .filter_map(|scope| Scope::parse(scope))
+        .map(|scope| scope.to_string())
+        .collect();
+
+    let api_token = sqlx::query_as::<_, ApiToken>(&format!(
+        r#"
+        INSERT INTO api_tokens (household_id, name, token_hash, client_certificate, scopes, created_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING {API_TOKEN_COLUMNS}
+        "#
+    ))
+    .bind(household_id)
+    .bind(name)
+    .bind(token.as_deref().map(session::hash_token))
+    .bind(client_certificate)
+    .bind(&scopes)
+    .bind(created_by)
+    .bind(expires_at)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(CreatedApiToken { api_token, token })
+}
+
+/// Revoke a token. Revoking it again keeps the original timestamp.
+pub async fn revoke(conn: &mut PgConnection, id: i32) -> Result<ApiToken> {
+    sqlx::query_as::<_, ApiToken>(&format!(
+        r#"
+        UPDATE api_tokens
+        SET revoked_at = COALESCE(revoked_at, now())
+        WHERE id = $1
+        RETURNING {API_TOKEN_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
@@ -0,0 +1,66 @@
+use sqlx::PgConnection;
+
+use crate::{
+    error::Result,
+    models::{AuditEntry, AuditQuery},
+};
+
+const AUDIT_COLUMNS: &str = "id, occurred_at, actor, actor_name, user_id, token_id, request_id, \
+     entity, entity_id, action, before, after";
+
+/// Audit log entries matching the query, newest first
+pub async fn list(conn: &mut PgConnection, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+    let entries = sqlx::query_as::<_, AuditEntry>(&format!(
+        r#"
+        SELECT {AUDIT_COLUMNS}
+        FROM audit_log
+        WHERE ($1::text IS NULL OR entity = $1)
+          AND ($2::text IS NULL OR entity_id = $2)
+          AND ($3::text IS NULL OR action = $3)
+          AND ($4::text IS NULL OR actor = $4)
+          AND ($5::int IS NULL OR user_id = $5)
+          AND ($6::int IS NULL OR token_id = $6)
+          AND ($7::text IS NULL OR request_id = $7)
+          AND ($8::timestamptz IS NULL OR occurred_at >= $8)
+          AND ($9::timestamptz IS NULL OR occurred_at < $9)
+          AND ($10::bigint IS NULL OR id < $10)
+        ORDER BY id DESC
+        LIMIT $11
+        "#
+    ))
+    .bind(query.entity.as_deref())
+    .bind(query.entity_id.as_deref())
+    .bind(query.action.as_deref())
+    .bind(query.actor.as_deref())
+    .bind(query.user_id)
+    .bind(query.token_id)
+    .bind(query.request_id.as_deref())
+    .bind(query.from)
+    .bind(query.until)
+    .bind(query.before)
+    .bind(query.limit())
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Changes a user made and changes to their account, memberships, roles and
+/// passkeys, oldest first
+pub async fn of_user(conn: &mut PgConnection, user_id: i32) -> Result<Vec<AuditEntry>> {
+    let entries = sqlx::query_as::<_, AuditEntry>(&format!(
+        r#"
+        SELECT {AUDIT_COLUMNS}
+        FROM audit_log
+        WHERE user_id = $1
+           OR (entity = 'users' AND entity_id = $1::text)
+           OR COALESCE(after, before) ->> 'user_id' = $1::text
+        ORDER BY id
+        "#
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(entries)
+}
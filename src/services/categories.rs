@@ -0,0 +1,197 @@
+use sqlx::PgConnection;
+
+use crate::{
+    error::{AppError, Result},
+    i18n::Message,
+    models::{Category, Quotas},
+    services::quotas::{self, Quota},
+};
+
+/// Load a category of the household by ID
+pub async fn find(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Category> {
+    sqlx::query_as::<_, Category>(
+        r#"
+        SELECT id, name, version
+        FROM categories
+        WHERE id = $1 AND household_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Load a category of the household by ID and lock it until the transaction ends
+pub async fn lock(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Category> {
+    sqlx::query_as::<_, Category>(
+        r#"
+        SELECT id, name, version
+        FROM categories
+        WHERE id = $1 AND household_id = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Insert a category unless it already exists, within the household's quota
+pub async fn ensure_exists(
+    conn: &mut PgConnection,
+    limits: &Quotas,
+    household_id: i32,
+    name: &str,
+) -> Result<()> {
+    if limits.categories.is_some() {
+        let exists = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM categories WHERE household_id = $1 AND name = $2)
+            "#,
+        )
+        .bind(household_id)
+        .bind(name)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if !exists {
+            quotas::reserve(conn, limits, household_id, Quota::Categories).await?;
+        }
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO categories (household_id, name)
+        VALUES ($1, $2)
+        ON CONFLICT (household_id, name) DO NOTHING
+        "#,
+    )
+    .bind(household_id)
+    .bind(name)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Rename a category, rewriting all items and names that use it.
+///
+/// The category gets a new ID, as the old row is replaced.
+pub async fn rename(
+    conn: &mut PgConnection,
+    household_id: i32,
+    id: i32,
+    name: &str,
+) -> Result<Category> {
+    // Get the old category to know its name
+    let old_category = find(conn, household_id, id).await?;
+
+    // Create the new category (fails with a conflict if the name already exists)
+    let new_category = sqlx::query_as::<_, Category>(
+        r#"
+        INSERT INTO categories (household_id, name)
+        VALUES ($1, $2)
+        RETURNING id, name, version
+        "#,
+    )
+    .bind(household_id)
+    .bind(name)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    reassign(conn, household_id, &old_category.name, Some(name)).await?;
+    delete_row(conn, id).await?;
+
+    Ok(new_category)
+}
+
+/// Merge the category `source_id` into `target_id`.
+///
+/// Items and names of the source category move to the target,
+/// then the source category is deleted.
+pub async fn merge(
+    conn: &mut PgConnection,
+    household_id: i32,
+    source_id: i32,
+    target_id: i32,
+) -> Result<Category> {
+    if source_id == target_id {
+        return Err(AppError::BadRequest(Message::new(
+            "error.merge_category_into_self",
+        )));
+    }
+
+    let source = find(conn, household_id, source_id).await?;
+    let target = find(conn, household_id, target_id).await?;
+
+    reassign(conn, household_id, &source.name, Some(&target.name)).await?;
+    delete_row(conn, source_id).await?;
+
+    Ok(target)
+}
+
+/// Delete a category, clearing it from all items and names
+pub async fn delete(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<()> {
+    // Get the category to know its name
+    let category = find(conn, household_id, id).await?;
+
+    reassign(conn, household_id, &category.name, None).await?;
+    delete_row(conn, id).await?;
+
+    Ok(())
+}
+
+/// Point all items and names of the household using category `from` to `to`
+async fn reassign(
+    conn: &mut PgConnection,
+    household_id: i32,
+    from: &str,
+    to: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE items
+        SET category = $1
+        WHERE category = $2
+          AND list IN (SELECT id FROM lists WHERE household_id = $3)
+        "#,
+    )
+    .bind(to)
+    .bind(from)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE names
+        SET category = $1
+        WHERE category = $2 AND household_id = $3
+        "#,
+    )
+    .bind(to)
+    .bind(from)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn delete_row(conn: &mut PgConnection, id: i32) -> Result<()> {
+    sqlx::query(
+        r#"
+        DELETE FROM categories
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
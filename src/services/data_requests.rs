@@ -0,0 +1,430 @@
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::{FromRow, PgConnection};
+
+use crate::{
+    error::{AppError, Result},
+    i18n::Message,
+    models::{
+        AccountExport, DataRequest, DeleteAccountRequest, ExportedGroup, ExportedIdentity,
+        ExportedList, ExportedListRole, ExportedMembership, ExportedName, ExportedSession, Item,
+        List, User,
+    },
+    services::{
+        api_tokens, audit, invitations, items::ITEM_COLUMNS, list_permissions, list_shares,
+        lists::LIST_COLUMNS, passkeys, users,
+    },
+};
+
+const DATA_REQUEST_COLUMNS: &str = "id, kind, user_id, username, list_policy, transfer_to, \
+     status, error, requested_by, created_at, started_at, finished_at";
+
+#[derive(FromRow)]
+struct OwnedList {
+    #[sqlx(flatten)]
+    list: List,
+    household_id: i32,
+}
+
+/// Requests, newest first; optionally only those about or by a user
+pub async fn list(conn: &mut PgConnection, user_id: Option<i32>) -> Result<Vec<DataRequest>> {
+    let requests = sqlx::query_as::<_, DataRequest>(&format!(
+        r#"
+        SELECT {DATA_REQUEST_COLUMNS}
+        FROM data_requests
+        WHERE $1::int IS NULL OR user_id = $1 OR requested_by = $1
+        ORDER BY created_at DESC
+        "#
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(requests)
+}
+
+pub async fn find(conn: &mut PgConnection, id: i64) -> Result<DataRequest> {
+    sqlx::query_as::<_, DataRequest>(&format!(
+        "SELECT {DATA_REQUEST_COLUMNS} FROM data_requests WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+pub async fn create_export(
+    conn: &mut PgConnection,
+    user: &User,
+    requested_by: Option<i32>,
+) -> Result<DataRequest> {
+    let request = sqlx::query_as::<_, DataRequest>(&format!(
+        r#"
+        INSERT INTO data_requests (kind, user_id, username, requested_by)
+        VALUES ('export', $1, $2, $3)
+        RETURNING {DATA_REQUEST_COLUMNS}
+        "#
+    ))
+    .bind(user.id)
+    .bind(&user.username)
+    .bind(requested_by)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(request)
+}
+
+/// Queue the deletion of an account. A transfer is checked up front, so that
+/// the request does not fail later for a reason the caller can fix now.
+pub async fn create_deletion(
+    conn: &mut PgConnection,
+    user: &User,
+    deletion: &DeleteAccountRequest,
+    requested_by: Option<i32>,
+) -> Result<DataRequest> {
+    let transfer_to = match deletion.lists.as_str() {
+        "transfer" => {
+            let transfer_to = deletion.transfer_to.ok_or_else(|| {
+                AppError::BadRequest(Message::new("error.transfer_target_required"))
+            })?;
+            check_transfer(conn, user.id, transfer_to).await?;
+            Some(transfer_to)
+        }
+        _ => None,
+    };
+
+    let pending = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM data_requests
+            WHERE kind = 'deletion' AND user_id = $1 AND status IN ('pending', 'running')
+        )
+        "#,
+    )
+    .bind(user.id)
+    .fetch_one(&mut *conn)
+    .await?;
+    if pending {
+        return Err(AppError::Conflict(Message::new("error.deletion_pending")));
+    }
+
+    let request = sqlx::query_as::<_, DataRequest>(&format!(
+        r#"
+        INSERT INTO data_requests (kind, user_id, username, list_policy, transfer_to, requested_by)
+        VALUES ('deletion', $1, $2, $3, $4, $5)
+        RETURNING {DATA_REQUEST_COLUMNS}
+        "#
+    ))
+    .bind(user.id)
+    .bind(&user.username)
+    .bind(&deletion.lists)
+    .bind(transfer_to)
+    .bind(requested_by)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(request)
+}
+
+/// Mark a pending request, or one whose run was interrupted, as running.
+/// Returns `None` if it already finished.
+pub async fn start(conn: &mut PgConnection, id: i64) -> Result<Option<DataRequest>> {
+    let request = sqlx::query_as::<_, DataRequest>(&format!(
+        r#"
+        UPDATE data_requests
+        SET status = 'running', started_at = now()
+        WHERE id = $1 AND status IN ('pending', 'running')
+        RETURNING {DATA_REQUEST_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(request)
+}
+
+/// Record the outcome of a run: the export document, if any, or an error
+pub async fn finish(
+    conn: &mut PgConnection,
+    id: i64,
+    outcome: std::result::Result<Option<Value>, String>,
+) -> Result<()> {
+    let (status, result, error) = match outcome {
+        Ok(result) => ("succeeded", result, None),
+        Err(error) => ("failed", None, Some(error)),
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE data_requests
+        SET status = $2, result = $3, error = $4, finished_at = now()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(result)
+    .bind(error)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// IDs of requests that are waiting or were interrupted
+pub async fn unfinished(conn: &mut PgConnection) -> Result<Vec<i64>> {
+    let ids = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT id
+        FROM data_requests
+        WHERE status IN ('pending', 'running')
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(ids)
+}
+
+/// The document of a finished export
+pub async fn export_document(conn: &mut PgConnection, id: i64) -> Result<Value> {
+    let request = find(conn, id).await?;
+    if request.kind != "export" {
+        return Err(AppError::NotFound);
+    }
+
+    sqlx::query_scalar::<_, Option<Value>>(
+        "SELECT result FROM data_requests WHERE id = $1 AND status = 'succeeded'",
+    )
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .flatten()
+    .ok_or_else(|| AppError::Conflict(Message::new("error.export_not_ready")))
+}
+
+/// Collect everything stored about a user
+pub async fn export(conn: &mut PgConnection, user_id: i32) -> Result<AccountExport> {
+    let user = users::find(conn, user_id).await?;
+
+    let households = sqlx::query_as::<_, ExportedMembership>(
+        r#"
+        SELECT h.id, h.name, h.created_at, m.role, m.created_at AS joined_at
+        FROM household_members m
+        JOIN households h ON h.id = m.household_id
+        WHERE m.user_id = $1
+        ORDER BY h.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let groups = sqlx::query_as::<_, ExportedGroup>(
+        r#"
+        SELECT g.id, g.name, g.created_at, g.household_id
+        FROM group_members gm
+        JOIN groups g ON g.id = gm.group_id
+        WHERE gm.user_id = $1
+        ORDER BY g.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let owned = sqlx::query_as::<_, OwnedList>(&format!(
+        r#"
+        SELECT {LIST_COLUMNS}, household_id
+        FROM lists
+        WHERE owner_id = $1
+        ORDER BY id
+        "#
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let list_ids: Vec<i32> = owned.iter().map(|owned| owned.list.id).collect();
+    let items = sqlx::query_as::<_, Item>(&format!(
+        r#"
+        SELECT {ITEM_COLUMNS}
+        FROM items
+        WHERE list = ANY($1)
+        ORDER BY id
+        "#
+    ))
+    .bind(&list_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let lists = owned
+        .into_iter()
+        .map(|owned| ExportedList {
+            items: items
+                .iter()
+                .filter(|item| item.list == owned.list.id)
+                .cloned()
+                .collect(),
+            list: owned.list,
+            household_id: owned.household_id,
+        })
+        .collect();
+
+    let list_roles = sqlx::query_as::<_, ExportedListRole>(
+        r#"
+        SELECT l.id AS list_id, l.name AS list_name, p.role::text AS role
+        FROM list_permissions p
+        JOIN lists l ON l.id = p.list_id
+        WHERE p.user_id = $1
+        ORDER BY l.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Names are shared by the household; the audit log knows who added them
+    let names = sqlx::query_as::<_, ExportedName>(
+        r#"
+        SELECT n.id, n.name, n.count, n.category, n.household_id
+        FROM names n
+        WHERE EXISTS (
+            SELECT 1 FROM audit_log a
+            WHERE a.entity = 'names' AND a.action = 'create'
+              AND a.entity_id = n.id::text AND a.user_id = $1
+        )
+        ORDER BY n.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let sessions = sqlx::query_as::<_, ExportedSession>(
+        r#"
+        SELECT id, kind, user_agent, created_at, last_used_at,
+               COALESCE(refresh_expires_at, expires_at) AS expires_at
+        FROM sessions
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let identities = sqlx::query_as::<_, ExportedIdentity>(
+        r#"
+        SELECT issuer, subject, created_at, synced_at
+        FROM user_identities
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(AccountExport {
+        exported_at: Utc::now(),
+        user,
+        households,
+        groups,
+        lists,
+        list_roles,
+        names,
+        api_tokens: api_tokens::created_by(conn, user_id).await?,
+        list_shares: list_shares::created_by(conn, user_id).await?,
+        invitations: invitations::of_user(conn, user_id).await?,
+        sessions,
+        passkeys: passkeys::list(conn, user_id).await?,
+        identities,
+        audit_log: audit::of_user(conn, user_id).await?,
+    })
+}
+
+/// Delete an account, after transferring, releasing or deleting the lists it
+/// owns. Released lists can be managed by every member of their household;
+/// private lists cannot be released, as nobody else may see them.
+pub async fn delete_account(
+    conn: &mut PgConnection,
+    user_id: i32,
+    list_policy: &str,
+    transfer_to: Option<i32>,
+) -> Result<()> {
+    match (list_policy, transfer_to) {
+        ("transfer", Some(transfer_to)) => {
+            let owned = sqlx::query_as::<_, (i32, i32)>(
+                "SELECT id, household_id FROM lists WHERE owner_id = $1 ORDER BY id",
+            )
+            .bind(user_id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+            for (list_id, household_id) in owned {
+                list_permissions::transfer(conn, household_id, list_id, transfer_to).await?;
+            }
+        }
+        ("transfer", None) => {
+            return Err(AppError::BadRequest(Message::new(
+                "error.transfer_target_required",
+            )));
+        }
+        ("release", _) => {
+            sqlx::query("DELETE FROM lists WHERE owner_id = $1 AND private")
+                .bind(user_id)
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query("UPDATE lists SET owner_id = NULL WHERE owner_id = $1")
+                .bind(user_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        _ => {
+            sqlx::query("DELETE FROM lists WHERE owner_id = $1")
+                .bind(user_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    users::delete(conn, user_id).await
+}
+
+/// The new owner must be another member of every household the user owns
+/// lists in
+async fn check_transfer(conn: &mut PgConnection, user_id: i32, transfer_to: i32) -> Result<()> {
+    if transfer_to == user_id {
+        return Err(AppError::BadRequest(Message::new("error.transfer_to_self")));
+    }
+
+    let missing = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM lists l
+            WHERE l.owner_id = $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM household_members m
+                  WHERE m.household_id = l.household_id AND m.user_id = $2
+              )
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(transfer_to)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if missing {
+        return Err(AppError::ParentNotFound {
+            parent: "resource.user",
+            field: "transferTo".to_string(),
+            in_path: false,
+        });
+    }
+
+    Ok(())
+}
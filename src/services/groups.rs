@@ -0,0 +1,168 @@
+use sqlx::PgConnection;
+
+use crate::{
+    error::{AppError, Result},
+    models::{Group, User},
+};
+
+const GROUP_COLUMNS: &str = "id, name, created_at";
+
+pub async fn find(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Group> {
+    sqlx::query_as::<_, Group>(&format!(
+        "SELECT {GROUP_COLUMNS} FROM groups WHERE id = $1 AND household_id = $2"
+    ))
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+pub async fn list(conn: &mut PgConnection, household_id: i32) -> Result<Vec<Group>> {
+    let groups = sqlx::query_as::<_, Group>(&format!(
+        "SELECT {GROUP_COLUMNS} FROM groups WHERE household_id = $1 ORDER BY name ASC"
+    ))
+    .bind(household_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(groups)
+}
+
+pub async fn create(conn: &mut PgConnection, household_id: i32, name: &str) -> Result<Group> {
+    let group = sqlx::query_as::<_, Group>(&format!(
+        r#"
+        INSERT INTO groups (household_id, name)
+        VALUES ($1, $2)
+        RETURNING {GROUP_COLUMNS}
+        "#
+    ))
+    .bind(household_id)
+    .bind(name)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(group)
+}
+
+pub async fn rename(
+    conn: &mut PgConnection,
+    household_id: i32,
+    id: i32,
+    name: &str,
+) -> Result<Group> {
+    sqlx::query_as::<_, Group>(&format!(
+        r#"
+        UPDATE groups
+        SET name = $1
+        WHERE id = $2 AND household_id = $3
+        RETURNING {GROUP_COLUMNS}
+        "#
+    ))
+    .bind(name)
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Delete a group; the roles granted to it go with it
+pub async fn delete(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM groups
+        WHERE id = $1 AND household_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn members(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Vec<User>> {
+    // Distinguish an empty group from a missing one
+    find(conn, household_id, id).await?;
+
+    let users = sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.id, u.username, u.display_name, u.is_admin, u.disabled, u.language,
+               u.passkey_required, u.created_at
+        FROM users u
+        JOIN group_members m ON m.user_id = u.id
+        WHERE m.group_id = $1
+        ORDER BY u.username
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(users)
+}
+
+/// Add a household member to a group; adding an existing member does nothing
+pub async fn add_member(
+    conn: &mut PgConnection,
+    household_id: i32,
+    id: i32,
+    user_id: i32,
+) -> Result<()> {
+    find(conn, household_id, id).await?;
+
+    let is_member = super::households::is_member(conn, household_id, user_id).await?;
+    if !is_member {
+        return Err(AppError::ParentNotFound {
+            parent: "resource.user",
+            field: "user_id".to_string(),
+            in_path: true,
+        });
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO group_members (group_id, user_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn remove_member(
+    conn: &mut PgConnection,
+    household_id: i32,
+    id: i32,
+    user_id: i32,
+) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM group_members
+        WHERE group_id = $1 AND user_id = $2
+          AND group_id IN (SELECT id FROM groups WHERE household_id = $3)
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use sqlx::PgConnection;
+
+use crate::{
+    error::{AppError, Result},
+    models::{Household, HouseholdMember},
+};
+
+const HOUSEHOLD_COLUMNS: &str = "id, name, created_at";
+
+pub async fn find(conn: &mut PgConnection, id: i32) -> Result<Household> {
+    sqlx::query_as::<_, Household>(&format!(
+        "SELECT {HOUSEHOLD_COLUMNS} FROM households WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// All households, or only those `user_id` is a member of
+pub async fn list(conn: &mut PgConnection, user_id: Option<i32>) -> Result<Vec<Household>> {
+    let households = sqlx::query_as::<_, Household>(&format!(
+        r#"
+        SELECT {HOUSEHOLD_COLUMNS}
+        FROM households
+        WHERE $1::int IS NULL
+           OR id IN (SELECT household_id FROM household_members WHERE user_id = $1)
+        ORDER BY name ASC
+        "#
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(households)
+}
+
+pub async fn create(conn: &mut PgConnection, name: &str) -> Result<Household> {
+    let household = sqlx::query_as::<_, Household>(&format!(
+        r#"
+        INSERT INTO households (name)
+        VALUES ($1)
+        RETURNING {HOUSEHOLD_COLUMNS}
+        "#
+    ))
+    .bind(name)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(household)
+}
+
+pub async fn rename(conn: &mut PgConnection, id: i32, name: &str) -> Result<Household> {
+    sqlx::query_as::<_, Household>(&format!(
+        r#"
+        UPDATE households
+        SET name = $1
+        WHERE id = $2
+        RETURNING {HOUSEHOLD_COLUMNS}
+        "#
+    ))
+    .bind(name)
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Delete a household with all its lists, items, categories, names and API tokens
+pub async fn delete(conn: &mut PgConnection, id: i32) -> Result<()> {
+    // Items reference lists, which cascade from the household
+    let result = sqlx::query(
+        r#"
+        DELETE FROM households
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn is_member(conn: &mut PgConnection, household_id: i32, user_id: i32) -> Result<bool> {
+    let member = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM household_members WHERE household_id = $1 AND user_id = $2
+        )
+        "#,
+    )
+    .bind(household_id)
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(member)
+}
+
+pub async fn members(conn: &mut PgConnection, household_id: i32) -> Result<Vec<HouseholdMember>> {
+    // Distinguish an empty household from a missing one
+    find(conn, household_id).await?;
+
+    let members = sqlx::query_as::<_, HouseholdMember>(
+        r#"
+        SELECT u.id, u.username, u.display_name, u.is_admin, u.disabled, u.language,
+               u.passkey_required, u.created_at, m.role
+        FROM users u
+        JOIN household_members m ON m.user_id = u.id
+        WHERE m.household_id = $1
+        ORDER BY u.username
+        "#,
+    )
+    .bind(household_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(members)
+}
+
+/// Add a user to a household; adding an existing member does nothing
+pub async fn add_member(conn: &mut PgConnection, household_id: i32, user_id: i32) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO household_members (household_id, user_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(household_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Add a user to a household with a role; returns false if they already are a
+/// member, whose role is then left alone
+pub async fn join(
+    conn: &mut PgConnection,
+    household_id: i32,
+    user_id: i32,
+    role: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO household_members (household_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(household_id)
+    .bind(user_id)
+    .bind(role)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Change a member's role in a household
+pub async fn set_role(
+    conn: &mut PgConnection,
+    household_id: i32,
+    user_id: i32,
+    role: &str,
+) -> Result<HouseholdMember> {
+    sqlx::query_as::<_, HouseholdMember>(
+        r#"
+        WITH m AS (
+            UPDATE household_members
+            SET role = $3
+            WHERE household_id = $1 AND user_id = $2
+            RETURNING user_id, role
+        )
+        SELECT u.id, u.username, u.display_name, u.is_admin, u.disabled, u.language,
+               u.passkey_required, u.created_at, m.role
+        FROM users u
+        JOIN m ON m.user_id = u.id
+        "#,
+    )
+    .bind(household_id)
+    .bind(user_id)
+    .bind(role)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Remove a user from a household and its groups
+pub async fn remove_member(conn: &mut PgConnection, household_id: i32, user_id: i32) -> Result<()> {
+    sqlx::query(
+        r#"
+        DELETE FROM group_members
+        WHERE user_id = $1
+          AND group_id IN (SELECT id FROM groups WHERE household_id = $2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let result = sqlx::query(
+        r#"
+        DELETE FROM household_members
+        WHERE household_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(household_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
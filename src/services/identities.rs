@@ -0,0 +1,228 @@
+use sqlx::PgConnection;
+
+use super::{households, users};
+use crate::{
+    auth::session,
+    config::GroupMapping,
+    error::{AppError, Result},
+    models::{
+        user::{username_char, USERNAME_MAX, USERNAME_MIN},
+        User,
+    },
+};
+
+/// Roles of users who are authenticated on every request, by bearer JWTs or a
+/// reverse proxy, are taken from their groups at most this often
+const SYNC_INTERVAL_MINUTES: i32 = 5;
+
+/// A user as described by an identity provider or a reverse proxy
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub issuer: String,
+    pub subject: String,
+    /// Suggested username; adjusted to our rules when the user is created
+    pub username: String,
+    pub display_name: Option<String>,
+    /// `None` if no groups were sent, which leaves roles alone
+    pub groups: Option<Vec<String>>,
+    /// Take over a local account with the same username rather than creating
+    /// one with a suffix; only for sources the admin controls
+    pub link_by_username: bool,
+}
+
+/// The user an identity belongs to, created on its first sign-in. Admin rights
+/// and households are taken from the groups when `sync` is set and otherwise
+/// every few minutes.
+pub async fn sign_in(
+    conn: &mut PgConnection,
+    mapping: &GroupMapping,
+    identity: &Identity,
+    sync: bool,
+) -> Result<User> {
+    let linked = sqlx::query_as::<_, (i32, bool)>(
+        r#"
+        SELECT user_id, synced_at < now() - make_interval(mins => $3)
+        FROM user_identities
+        WHERE issuer = $1 AND subject = $2
+        "#,
+    )
+    .bind(&identity.issuer)
+    .bind(&identity.subject)
+    .bind(SYNC_INTERVAL_MINUTES)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let (user_id, stale) = match linked {
+        Some(linked) => linked,
+        None => (link_user(conn, identity).await?, true),
+    };
+    if sync || stale {
+        sync_groups(conn, mapping, user_id, identity).await?;
+    }
+
+    users::find(conn, user_id).await
+}
+
+/// Link a new identity to a user, created without a password unless the
+/// identity may take over an existing account. A username that is already
+/// taken otherwise gets a suffix rather than the existing account.
+async fn link_user(conn: &mut PgConnection, identity: &Identity) -> Result<i32> {
+    // Disabled accounts are linked too, so they stay disabled
+    let existing = if identity.link_by_username {
+        sqlx::query_scalar::<_, i32>("SELECT id FROM users WHERE username = $1")
+            .bind(identity.username.to_lowercase())
+            .fetch_optional(&mut *conn)
+            .await?
+    } else {
+        None
+    };
+    let user_id = match existing {
+        Some(user_id) => user_id,
+        None => create_user(conn, identity).await?,
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_identities (user_id, issuer, subject)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(user_id)
+    .bind(&identity.issuer)
+    .bind(&identity.subject)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(user_id)
+}
+
+async fn create_user(conn: &mut PgConnection, identity: &Identity) -> Result<i32> {
+    let suffix = &session::hash_token(&format!("{}\n{}", identity.issuer, identity.subject))[..6];
+
+    let mut username: String = identity
+        .username
+        .to_lowercase()
+        .chars()
+        .map(|c| if username_char(c) { c } else { '-' })
+        .take(USERNAME_MAX)
+        .collect();
+    if username.chars().count() < USERNAME_MIN {
+        username = format!("user-{suffix}");
+    }
+
+    let taken =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM users WHERE username = $1)")
+            .bind(&username)
+            .fetch_one(&mut *conn)
+            .await?;
+    if taken {
+        let base: String = username
+            .chars()
+            .take(USERNAME_MAX - suffix.len() - 1)
+            .collect();
+        username = format!("{base}-{suffix}");
+    }
+
+    let user_id = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO users (username, display_name)
+        VALUES ($1, $2)
+        RETURNING id
+        "#,
+    )
+    .bind(&username)
+    .bind(&identity.display_name)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    tracing::info!(
+        user_id,
+        username,
+        issuer = identity.issuer,
+        "Provisioned externally authenticated user"
+    );
+    Ok(user_id)
+}
+
+/// Apply the admin and household groups. Households that are not mapped to a
+/// group keep their members; mapped ones are created on demand.
+async fn sync_groups(
+    conn: &mut PgConnection,
+    mapping: &GroupMapping,
+    user_id: i32,
+    identity: &Identity,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE user_identities
+        SET synced_at = now()
+        WHERE issuer = $1 AND subject = $2
+        "#,
+    )
+    .bind(&identity.issuer)
+    .bind(&identity.subject)
+    .execute(&mut *conn)
+    .await?;
+
+    let Some(groups) = &identity.groups else {
+        return Ok(());
+    };
+
+    if let Some(is_admin) = is_admin(mapping, groups) {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET is_admin = $1
+            WHERE id = $2 AND is_admin <> $1
+            "#,
+        )
+        .bind(is_admin)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    for (household, member) in memberships(mapping, groups) {
+        let existing = sqlx::query_scalar::<_, i32>("SELECT id FROM households WHERE name = $1")
+            .bind(household)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        if member {
+            let household_id = match existing {
+                Some(id) => id,
+                None => households::create(conn, household).await?.id,
+            };
+            households::add_member(conn, household_id, user_id).await?;
+        } else if let Some(household_id) = existing {
+            match households::remove_member(conn, household_id, user_id).await {
+                Ok(()) | Err(AppError::NotFound) => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether groups make their user an admin, or `None` if no admin groups are
+/// configured
+pub fn is_admin(mapping: &GroupMapping, groups: &[String]) -> Option<bool> {
+    if mapping.admin_groups.is_empty() {
+        return None;
+    }
+    Some(
+        groups
+            .iter()
+            .any(|group| mapping.admin_groups.contains(group)),
+    )
+}
+
+/// Each mapped household, and whether a user in these groups is a member
+pub fn memberships<'a>(mapping: &'a GroupMapping, groups: &[String]) -> Vec<(&'a str, bool)> {
+    mapping
+        .household_groups
+        .iter()
+        .map(|(group, household)| (household.as_str(), groups.contains(group)))
+        .collect()
+}
@@ -0,0 +1,245 @@
+use chrono::{Duration, Utc};
+use rand::{rngs::OsRng, Rng};
+use sqlx::PgConnection;
+
+use crate::{
+    auth::{session, AuthError},
+    error::{AppError, Result},
+    i18n::Message,
+    models::{CreatedInvitation, Household, Invitation, User},
+    services::{households, users},
+};
+
+const INVITATION_COLUMNS: &str = "id, is_admin, household_id, role, max_uses, uses, \
+     CASE WHEN uses >= max_uses THEN 'used' WHEN expires_at <= now() THEN 'expired' \
+     ELSE 'pending' END AS status, \
+     created_by, expires_at, used_by, used_at, created_at";
+
+/// Letters and digits that cannot be mistaken for each other
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// 12 characters of 5 bits each
+const CODE_LENGTH: usize = 12;
+
+/// An invitation with uses left, locked until the transaction ends
+#[derive(sqlx::FromRow)]
+struct PendingInvitation {
+    id: i32,
+    is_admin: bool,
+    household_id: Option<i32>,
+    role: String,
+}
+
+/// Invitations, newest first; optionally only those of a household or with a
+/// status (`pending`, `used` or `expired`)
+pub async fn list(
+    conn: &mut PgConnection,
+    household_id: Option<i32>,
+    status: Option<&str>,
+) -> Result<Vec<Invitation>> {
+    let invitations = sqlx::query_as::<_, Invitation>(&format!(
+        r#"
+        SELECT *
+        FROM (
+            SELECT {INVITATION_COLUMNS}
+            FROM invitations
+            WHERE $1::int IS NULL OR household_id = $1
+        ) i
+        WHERE $2::text IS NULL OR status = $2
+        ORDER BY created_at DESC
+        "#
+    ))
+    .bind(household_id)
+    .bind(status)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(invitations)
+}
+
+/// Invitations a user created or was the last to use
+pub async fn of_user(conn: &mut PgConnection, user_id: i32) -> Result<Vec<Invitation>> {
+    let invitations = sqlx::query_as::<_, Invitation>(&format!(
+        r#"
+        SELECT {INVITATION_COLUMNS}
+        FROM invitations
+        WHERE created_by = $1 OR used_by = $1
+        ORDER BY created_at DESC
+        "#
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(invitations)
+}
+
+pub async fn find(conn: &mut PgConnection, id: i32) -> Result<Invitation> {
+    sqlx::query_as::<_, Invitation>(&format!(
+        "SELECT {INVITATION_COLUMNS} FROM invitations WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Create an invitation with a new join code; `max_uses` is unlimited if `None`
+pub async fn create(
+    conn: &mut PgConnection,
+    household_id: i32,
+    created_by: Option<i32>,
+    is_admin: bool,
+    role: &str,
+    max_uses: Option<i32>,
+    ttl: Duration,
+) -> Result<CreatedInvitation> {
+    let code = generate_code();
+
+    let invitation = sqlx::query_as::<_, Invitation>(&format!(
+        r#"
+        INSERT INTO invitations (token_hash, is_admin, household_id, role, max_uses, created_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING {INVITATION_COLUMNS}
+        "#
+    ))
+    .bind(session::hash_token(&normalize_code(&code)))
+    .bind(is_admin)
+    .bind(household_id)
+    .bind(role)
+    .bind(max_uses)
+    .bind(created_by)
+    .bind(Utc::now() + ttl)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(CreatedInvitation {
+        invitation,
+        token: code,
+    })
+}
+
+/// Create a user from a pending invitation, add them to the invitation's
+/// household and count the use
+pub async fn redeem(
+    conn: &mut PgConnection,
+    code: &str,
+    username: &str,
+    password: &str,
+    display_name: Option<&str>,
+) -> Result<User> {
+    let invitation = claim(conn, code).await?;
+
+    let user = users::create(conn, username, password, display_name, invitation.is_admin).await?;
+    if let Some(household_id) = invitation.household_id {
+        households::join(conn, household_id, user.id, &invitation.role).await?;
+    }
+
+    record_use(conn, invitation.id, user.id).await?;
+
+    Ok(user)
+}
+
+/// Add an existing user to the household of a pending invitation and count
+/// the use. Members of the household keep their role and the invitation.
+pub async fn join(conn: &mut PgConnection, code: &str, user_id: i32) -> Result<Household> {
+    let invitation = claim(conn, code).await?;
+    let household_id = invitation
+        .household_id
+        .ok_or(AuthError::InvalidInvitation)?;
+
+    if !households::join(conn, household_id, user_id, &invitation.role).await? {
+        return Err(AppError::Conflict(Message::new("error.already_member")));
+    }
+    if invitation.is_admin {
+        sqlx::query("UPDATE users SET is_admin = true WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    record_use(conn, invitation.id, user_id).await?;
+
+    households::find(conn, household_id).await
+}
+
+pub async fn delete(conn: &mut PgConnection, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM invitations
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+/// Lock a pending invitation, so it cannot be used more often than allowed by
+/// concurrent requests
+async fn claim(conn: &mut PgConnection, code: &str) -> Result<PendingInvitation> {
+    // Invitations from before join codes were stored as sent
+    let hashes = [
+        session::hash_token(&normalize_code(code)),
+        session::hash_token(code),
+    ];
+
+    let invitation = sqlx::query_as::<_, PendingInvitation>(
+        r#"
+        SELECT id, is_admin, household_id, role
+        FROM invitations
+        WHERE token_hash = ANY($1)
+          AND (max_uses IS NULL OR uses < max_uses)
+          AND expires_at > now()
+        FOR UPDATE
+        "#,
+    )
+    .bind(&hashes[..])
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AuthError::InvalidInvitation)?;
+
+    Ok(invitation)
+}
+
+async fn record_use(conn: &mut PgConnection, id: i32, user_id: i32) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE invitations
+        SET uses = uses + 1, used_by = $1, used_at = now()
+        WHERE id = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// A new join code like `K7QF-9XMW-PT3H`
+fn generate_code() -> String {
+    let chars: Vec<char> = (0..CODE_LENGTH)
+        .map(|_| char::from(CODE_ALPHABET[OsRng.gen_range(0..CODE_ALPHABET.len())]))
+        .collect();
+
+    chars
+        .chunks(4)
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Codes are typed in, so case, spaces and dashes do not matter
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
@@ -0,0 +1,70 @@
+use sqlx::PgConnection;
+
+use crate::{error::Result, models::ItemEvent};
+
+const ITEM_EVENT_COLUMNS: &str =
+    "id, household_id, list_id, kind, item, created_at, xid::text::bigint AS xid";
+
+/// Events of the household (or one of its lists) recorded by transactions
+/// from `horizon` on, oldest first
+pub async fn since(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_id: Option<i32>,
+    horizon: i64,
+) -> Result<Vec<ItemEvent>> {
+    let events = sqlx::query_as::<_, ItemEvent>(&format!(
+        r#"
+        SELECT {ITEM_EVENT_COLUMNS}
+        FROM item_events
+        WHERE household_id = $1
+          AND ($2::int IS NULL OR list_id = $2)
+          AND xid >= $3::bigint::text::xid8
+        ORDER BY id
+        "#
+    ))
+    .bind(household_id)
+    .bind(list_id)
+    .bind(horizon)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(events)
+}
+
+/// The oldest transaction still running; all before it have finished
+pub async fn horizon(conn: &mut PgConnection) -> Result<i64> {
+    let xid = sqlx::query_scalar::<_, i64>(
+        "SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(xid)
+}
+
+/// ID of the newest event, 0 if there are none
+pub async fn latest_id(conn: &mut PgConnection) -> Result<i64> {
+    let id = sqlx::query_scalar::<_, i64>("SELECT COALESCE(MAX(id), 0) FROM item_events")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(id)
+}
+
+/// The transaction of a stored event and the horizon when it was recorded,
+/// to resume after it; `None` once it is gone
+pub async fn position(conn: &mut PgConnection, id: i64) -> Result<Option<(i64, i64)>> {
+    let position = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT xid::text::bigint, snapshot_xmin::text::bigint
+        FROM item_events
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(position)
+}
@@ -0,0 +1,245 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    models::{CreateItemRequest, Item, Quotas, UpdateItemRequest},
+    services::{
+        categories,
+        quotas::{self, Quota},
+    },
+};
+
+pub const ITEM_COLUMNS: &str =
+    r#"id, uuid, name, amount, "amountUnit", "inCart", list, category, version"#;
+
+/// Load an item of the household by ID
+pub async fn find(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Item> {
+    sqlx::query_as::<_, Item>(&format!(
+        r#"
+        SELECT {ITEM_COLUMNS}
+        FROM items
+        WHERE id = $1
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
+        "#
+    ))
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Load an item of the household by ID and lock it until the transaction ends
+pub async fn lock(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Item> {
+    sqlx::query_as::<_, Item>(&format!(
+        r#"
+        SELECT {ITEM_COLUMNS}
+        FROM items
+        WHERE id = $1
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
+        FOR UPDATE
+        "#
+    ))
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Load an item of the household by UUID
+pub async fn find_by_uuid(
+    conn: &mut PgConnection,
+    household_id: i32,
+    uuid: Uuid,
+) -> Result<Option<Item>> {
+    let item = sqlx::query_as::<_, Item>(&format!(
+        r#"
+        SELECT {ITEM_COLUMNS}
+        FROM items
+        WHERE uuid = $1
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
+        "#
+    ))
+    .bind(uuid)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(item)
+}
+
+/// Add an item to a list of the household, counting its name for
+/// autocomplete. The list must exist.
+pub async fn create(
+    conn: &mut PgConnection,
+    limits: &Quotas,
+    household_id: i32,
+    list_id: i32,
+    item: &CreateItemRequest,
+) -> Result<Item> {
+    quotas::reserve_item(conn, limits, list_id).await?;
+
+    // Insert category if provided and doesn't exist
+    if let Some(ref category) = item.category {
+        categories::ensure_exists(conn, limits, household_id, category).await?;
+    }
+
+    // Insert or update name entry for autocomplete
+    let existing_name = sqlx::query_scalar::<_, i32>(
+        r#"
+        SELECT id FROM names WHERE name = $1 AND household_id = $2
+        "#,
+    )
+    .bind(&item.name)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if existing_name.is_some() {
+        // Update count
+        sqlx::query(
+            r#"
+            UPDATE names
+            SET count = count + 1, category = COALESCE($2, category)
+            WHERE name = $1 AND household_id = $3
+            "#,
+        )
+        .bind(&item.name)
+        .bind(&item.category)
+        .bind(household_id)
+        .execute(&mut *conn)
+        .await?;
+    } else {
+        // Insert new name
+        quotas::reserve(conn, limits, household_id, Quota::Names).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO names (household_id, name, category, count)
+            VALUES ($1, $2, $3, 1)
+            "#,
+        )
+        .bind(household_id)
+        .bind(&item.name)
+        .bind(&item.category)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // Insert item
+    let item = sqlx::query_as::<_, Item>(&format!(
+        r#"
+        INSERT INTO items (uuid, name, amount, "amountUnit", list, category, "inCart")
+        VALUES (COALESCE($1, gen_random_uuid()), $2, $3, $4, $5, $6, $7)
+        RETURNING {ITEM_COLUMNS}
+        "#
+    ))
+    .bind(item.uuid)
+    .bind(&item.name)
+    .bind(item.amount)
+    .bind(&item.amount_unit)
+    .bind(list_id)
+    .bind(&item.category)
+    .bind(item.in_cart)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(item)
+}
+
+/// Change the fields of an item given in `changes`, keeping the category of
+/// its name entry in line
+pub async fn update(
+    conn: &mut PgConnection,
+    limits: &Quotas,
+    household_id: i32,
+    id: i32,
+    changes: &UpdateItemRequest,
+) -> Result<Item> {
+    // Get current item
+    let current_item = find(conn, household_id, id).await?;
+
+    // Determine new values using Option<Option<T>>:
+    // - None = field not in request, keep current value
+    // - Some(None) = field is explicitly null, set to NULL
+    // - Some(Some(value)) = field has a value, use it
+    let new_name = changes.name.as_ref().unwrap_or(&current_item.name);
+    let new_amount = match changes.amount {
+        Some(inner) => inner,        // Field present: use it (even if None)
+        None => current_item.amount, // Field missing: keep current
+    };
+    let new_amount_unit = match changes.amount_unit {
+        Some(ref inner) => inner.clone(),
+        None => current_item.amount_unit.clone(),
+    };
+    let new_category = match changes.category {
+        Some(ref inner) => inner.clone(),
+        None => current_item.category.clone(),
+    };
+    let new_in_cart = changes.in_cart.unwrap_or(current_item.in_cart);
+
+    // Insert category if provided and doesn't exist
+    if let Some(ref category) = new_category {
+        categories::ensure_exists(conn, limits, household_id, category).await?;
+    }
+
+    // Update names table category association
+    sqlx::query(
+        r#"
+        UPDATE names
+        SET category = $2
+        WHERE name = $1 AND household_id = $3
+        "#,
+    )
+    .bind(new_name)
+    .bind(&new_category)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    // Update item
+    let item = sqlx::query_as::<_, Item>(&format!(
+        r#"
+        UPDATE items
+        SET name = $1,
+            amount = $2,
+            "amountUnit" = $3,
+            category = $4,
+            "inCart" = $5
+        WHERE id = $6
+        RETURNING {ITEM_COLUMNS}
+        "#
+    ))
+    .bind(new_name)
+    .bind(new_amount)
+    .bind(&new_amount_unit)
+    .bind(&new_category)
+    .bind(new_in_cart)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(item)
+}
+
+/// Delete an item of the household
+pub async fn delete(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM items
+        WHERE id = $1
+          AND list IN (SELECT id FROM lists WHERE household_id = $2)
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use sqlx::PgConnection;
+
+use crate::{
+    auth::ListRole,
+    error::{AppError, Result},
+    models::{List, ListPermission},
+    services::{groups, households, lists::LIST_COLUMNS},
+};
+
+const PERMISSION_COLUMNS: &str = "id, user_id, group_id, role::text AS role, created_at";
+
+/// Roles granted on a list, user grants first
+pub async fn list(conn: &mut PgConnection, list_id: i32) -> Result<Vec<ListPermission>> {
+    let permissions = sqlx::query_as::<_, ListPermission>(&format!(
+        r#"
+        SELECT {PERMISSION_COLUMNS}
+        FROM list_permissions
+        WHERE list_id = $1
+        ORDER BY user_id ASC NULLS LAST, group_id ASC
+        "#
+    ))
+    .bind(list_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(permissions)
+}
+
+/// Grant a household member a role on a list, replacing any role they had.
+/// The role must be one of [`GRANTABLE_ROLES`](crate::auth::acl::GRANTABLE_ROLES).
+pub async fn grant_user(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_id: i32,
+    user_id: i32,
+    role: &str,
+) -> Result<ListPermission> {
+    if !households::is_member(conn, household_id, user_id).await? {
+        return Err(AppError::ParentNotFound {
+            parent: "resource.user",
+            field: "user_id".to_string(),
+            in_path: true,
+        });
+    }
+
+    let permission = sqlx::query_as::<_, ListPermission>(&format!(
+        r#"
+        INSERT INTO list_permissions (list_id, user_id, role)
+        VALUES ($1, $2, $3::list_role)
+        ON CONFLICT (list_id, user_id) DO UPDATE SET role = EXCLUDED.role
+        RETURNING {PERMISSION_COLUMNS}
+        "#
+    ))
+    .bind(list_id)
+    .bind(user_id)
+    .bind(role)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(permission)
+}
+
+/// Grant a group of the household a role on a list, replacing any role it had
+pub async fn grant_group(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_id: i32,
+    group_id: i32,
+    role: &str,
+) -> Result<ListPermission> {
+    match groups::find(conn, household_id, group_id).await {
+        Ok(_) => {}
+        Err(AppError::NotFound) => {
+            return Err(AppError::ParentNotFound {
+                parent: "resource.group",
+                field: "group_id".to_string(),
+                in_path: true,
+            })
+        }
+        Err(error) => return Err(error),
+    }
+
+    let permission = sqlx::query_as::<_, ListPermission>(&format!(
+        r#"
+        INSERT INTO list_permissions (list_id, group_id, role)
+        VALUES ($1, $2, $3::list_role)
+        ON CONFLICT (list_id, group_id) DO UPDATE SET role = EXCLUDED.role
+        RETURNING {PERMISSION_COLUMNS}
+        "#
+    ))
+    .bind(list_id)
+    .bind(group_id)
+    .bind(role)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(permission)
+}
+
+pub async fn revoke_user(conn: &mut PgConnection, list_id: i32, user_id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM list_permissions
+        WHERE list_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(list_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn revoke_group(conn: &mut PgConnection, list_id: i32, group_id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM list_permissions
+        WHERE list_id = $1 AND group_id = $2
+        "#,
+    )
+    .bind(list_id)
+    .bind(group_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+/// Make a household member the owner of a list. The previous owner stays on
+/// as an editor; a role the new owner was granted is dropped.
+pub async fn transfer(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_id: i32,
+    user_id: i32,
+) -> Result<List> {
+    if !households::is_member(conn, household_id, user_id).await? {
+        return Err(AppError::ParentNotFound {
+            parent: "resource.user",
+            field: "userId".to_string(),
+            in_path: false,
+        });
+    }
+
+    // Lock the list so concurrent transfers see each other's owner
+    let previous_owner = sqlx::query_scalar::<_, Option<i32>>(
+        r#"
+        SELECT owner_id
+        FROM lists
+        WHERE id = $1 AND household_id = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(list_id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    if let Some(previous_owner) = previous_owner.filter(|owner| *owner != user_id) {
+        // Former members of the household get no role
+        if households::is_member(conn, household_id, previous_owner).await? {
+            let role = ListRole::Editor.as_str();
+            grant_user(conn, household_id, list_id, previous_owner, role).await?;
+        }
+    }
+
+    sqlx::query(
+        r#"
+        DELETE FROM list_permissions
+        WHERE list_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(list_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let list = sqlx::query_as::<_, List>(&format!(
+        r#"
+        UPDATE lists
+        SET owner_id = $1
+        WHERE id = $2
+        RETURNING {LIST_COLUMNS}
+        "#
+    ))
+    .bind(user_id)
+    .bind(list_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(list)
+}
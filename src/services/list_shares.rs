@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+use crate::{
+    auth::session,
+    error::{AppError, Result},
+    models::{CreatedListShare, ListShare, SharedItem, SharedList},
+};
+
+const SHARE_COLUMNS: &str =
+    "id, list_id, created_by, expires_at, last_used_at, revoked_at, created_at";
+
+/// Where a share link is opened
+pub const SHARED_PATH: &str = "/api/shared";
+
+/// All share links of a list, including revoked and expired ones
+pub async fn list(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_id: i32,
+) -> Result<Vec<ListShare>> {
+    let shares = sqlx::query_as::<_, ListShare>(&format!(
+        r#"
+        SELECT {SHARE_COLUMNS}
+        FROM list_shares
+        WHERE list_id = $1
+          AND list_id IN (SELECT id FROM lists WHERE household_id = $2)
+        ORDER BY created_at DESC
+        "#
+    ))
+    .bind(list_id)
+    .bind(household_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(shares)
+}
+
+/// Share links a user created, on any list
+pub async fn created_by(conn: &mut PgConnection, user_id: i32) -> Result<Vec<ListShare>> {
+    let shares = sqlx::query_as::<_, ListShare>(&format!(
+        "SELECT {SHARE_COLUMNS} FROM list_shares WHERE created_by = $1 ORDER BY created_at DESC"
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(shares)
+}
+
+pub async fn create(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_id: i32,
+    created_by: Option<i32>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<CreatedListShare> {
+    let token = session::generate_token();
+
+    let share = sqlx::query_as::<_, ListShare>(&format!(
+        r#"
+        INSERT INTO list_shares (list_id, token_hash, created_by, expires_at)
+        SELECT id, $2, $3, $4
+        FROM lists
+        WHERE id = $1 AND household_id = $5
+        RETURNING {SHARE_COLUMNS}
+        "#
+    ))
+    .bind(list_id)
+    .bind(session::hash_token(&token))
+    .bind(created_by)
+    .bind(expires_at)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(CreatedListShare {
+        share,
+        url: format!("{SHARED_PATH}/{token}"),
+        token,
+    })
+}
+
+/// Revoke a share link of a list; revoking twice keeps the first time
+pub async fn revoke(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_id: i32,
+    id: i32,
+) -> Result<ListShare> {
+    sqlx::query_as::<_, ListShare>(&format!(
+        r#"
+        UPDATE list_shares
+        SET revoked_at = COALESCE(revoked_at, now())
+        WHERE id = $1 AND list_id = $2
+          AND list_id IN (SELECT id FROM lists WHERE household_id = $3)
+        RETURNING {SHARE_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(list_id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// The list behind a valid share token with its items. Unknown, revoked and
+/// expired tokens all look the same.
+pub async fn open(conn: &mut PgConnection, token: &str) -> Result<SharedList> {
+    let (list_id, name, expires_at) = sqlx::query_as::<_, (i32, String, Option<DateTime<Utc>>)>(
+        r#"
+            UPDATE list_shares s
+            SET last_used_at = now()
+            FROM lists l
+            WHERE l.id = s.list_id
+              AND s.token_hash = $1
+              AND s.revoked_at IS NULL
+              AND (s.expires_at IS NULL OR s.expires_at > now())
+            RETURNING l.id, l.name, s.expires_at
+            "#,
+    )
+    .bind(session::hash_token(token))
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    let items = sqlx::query_as::<_, SharedItem>(
+        r#"
+        SELECT name, amount, "amountUnit", "inCart", category
+        FROM items
+        WHERE list = $1
+        ORDER BY category ASC NULLS LAST, id ASC
+        "#,
+    )
+    .bind(list_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(SharedList {
+        name,
+        items,
+        expires_at,
+    })
+}
@@ -0,0 +1,121 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    models::{CreateListRequest, List, Quotas},
+    services::quotas::{self, Quota},
+};
+
+pub const LIST_COLUMNS: &str = "id, uuid, name, owner_id, private, version";
+
+/// Load a list of the household by ID and lock it until the transaction ends
+pub async fn lock(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<List> {
+    sqlx::query_as::<_, List>(&format!(
+        r#"
+        SELECT {LIST_COLUMNS}
+        FROM lists
+        WHERE id = $1 AND household_id = $2
+        FOR UPDATE
+        "#
+    ))
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Load a list of the household by UUID
+pub async fn find_by_uuid(
+    conn: &mut PgConnection,
+    household_id: i32,
+    uuid: Uuid,
+) -> Result<Option<List>> {
+    let list = sqlx::query_as::<_, List>(&format!(
+        r#"
+        SELECT {LIST_COLUMNS}
+        FROM lists
+        WHERE uuid = $1 AND household_id = $2
+        "#
+    ))
+    .bind(uuid)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(list)
+}
+
+/// Create a list in the household, owned by `owner_id`
+pub async fn create(
+    conn: &mut PgConnection,
+    limits: &Quotas,
+    household_id: i32,
+    owner_id: Option<i32>,
+    list: &CreateListRequest,
+) -> Result<List> {
+    quotas::reserve(conn, limits, household_id, Quota::Lists).await?;
+
+    let list = sqlx::query_as::<_, List>(&format!(
+        r#"
+        INSERT INTO lists (uuid, household_id, name, owner_id, private)
+        VALUES (COALESCE($1, gen_random_uuid()), $2, $3, $4, $5)
+        RETURNING {LIST_COLUMNS}
+        "#
+    ))
+    .bind(list.uuid)
+    .bind(household_id)
+    .bind(&list.name)
+    .bind(owner_id)
+    .bind(list.private)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(list)
+}
+
+/// Rename a list and/or make it private or shared; `None` keeps the current value
+pub async fn update(
+    conn: &mut PgConnection,
+    household_id: i32,
+    id: i32,
+    name: Option<&str>,
+    private: Option<bool>,
+) -> Result<List> {
+    sqlx::query_as::<_, List>(&format!(
+        r#"
+        UPDATE lists
+        SET name = COALESCE($1, name), private = COALESCE($2, private)
+        WHERE id = $3 AND household_id = $4
+        RETURNING {LIST_COLUMNS}
+        "#
+    ))
+    .bind(name)
+    .bind(private)
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Delete a list of the household with its items
+pub async fn delete(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM lists
+        WHERE id = $1 AND household_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
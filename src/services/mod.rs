@@ -0,0 +1,22 @@
+//! Business logic shared between the JSON API and the admin console.
+//!
+//! Functions take a connection so callers decide on the transaction scope.
+
+pub mod api_tokens;
+pub mod audit;
+pub mod categories;
+pub mod data_requests;
+pub mod groups;
+pub mod households;
+pub mod identities;
+pub mod invitations;
+pub mod item_events;
+pub mod items;
+pub mod list_permissions;
+pub mod list_shares;
+pub mod lists;
+pub mod names;
+pub mod passkeys;
+pub mod quotas;
+pub mod sync;
+pub mod users;
@@ -0,0 +1,215 @@
+use sqlx::PgConnection;
+
+use crate::{
+    error::{AppError, Result},
+    i18n::Message,
+    models::{Name, Quotas},
+    services::categories,
+};
+
+/// Load a name entry of the household by ID
+pub async fn find(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Name> {
+    sqlx::query_as::<_, Name>(
+        r#"
+        SELECT id, name, count, category, version
+        FROM names
+        WHERE id = $1 AND household_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Load a name entry of the household by ID and lock it until the transaction ends
+pub async fn lock(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<Name> {
+    sqlx::query_as::<_, Name>(
+        r#"
+        SELECT id, name, count, category, version
+        FROM names
+        WHERE id = $1 AND household_id = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+/// Rename and/or re-categorize a name entry, propagating the change to all
+/// items of the household.
+///
+/// For `category`, `None` keeps the current value and `Some(None)` clears it.
+pub async fn update(
+    conn: &mut PgConnection,
+    limits: &Quotas,
+    household_id: i32,
+    id: i32,
+    name: Option<&str>,
+    category: Option<Option<&str>>,
+) -> Result<Name> {
+    // Get current name entry
+    let current_name = find(conn, household_id, id).await?;
+
+    // Determine new values
+    let new_name = name.unwrap_or(&current_name.name);
+    let new_category = match category {
+        Some(inner) => inner,
+        None => current_name.category.as_deref(),
+    };
+
+    // Renaming onto another entry would leave two entries with the same name
+    if new_name != current_name.name {
+        let taken = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM names WHERE name = $1 AND id <> $2 AND household_id = $3
+            )
+            "#,
+        )
+        .bind(new_name)
+        .bind(id)
+        .bind(household_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if taken {
+            return Err(AppError::AlreadyExists {
+                resource: "resource.name",
+                field: "name".to_string(),
+            });
+        }
+    }
+
+    // If category is provided and not null, ensure it exists in categories table
+    if let Some(category) = new_category {
+        categories::ensure_exists(conn, limits, household_id, category).await?;
+    }
+
+    // If the name itself changed, update all items that use this name
+    if new_name != current_name.name {
+        sqlx::query(
+            r#"
+            UPDATE items
+            SET name = $1
+            WHERE name = $2
+              AND list IN (SELECT id FROM lists WHERE household_id = $3)
+            "#,
+        )
+        .bind(new_name)
+        .bind(&current_name.name)
+        .bind(household_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // If the category changed, update all items that use this name
+    if new_category != current_name.category.as_deref() {
+        sqlx::query(
+            r#"
+            UPDATE items
+            SET category = $1
+            WHERE name = $2
+              AND list IN (SELECT id FROM lists WHERE household_id = $3)
+            "#,
+        )
+        .bind(new_category)
+        .bind(new_name)
+        .bind(household_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // Update the name entry
+    let updated_name = sqlx::query_as::<_, Name>(
+        r#"
+        UPDATE names
+        SET name = $1, category = $2
+        WHERE id = $3
+        RETURNING id, name, count, category, version
+        "#,
+    )
+    .bind(new_name)
+    .bind(new_category)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(updated_name)
+}
+
+/// Merge the name entry `source_id` into `target_id`.
+///
+/// Items using the source name are renamed to the target (and take over its
+/// category, if it has one), usage counts are summed and the source is deleted.
+pub async fn merge(
+    conn: &mut PgConnection,
+    household_id: i32,
+    source_id: i32,
+    target_id: i32,
+) -> Result<Name> {
+    if source_id == target_id {
+        return Err(AppError::BadRequest(Message::new(
+            "error.merge_name_into_self",
+        )));
+    }
+
+    let source = find(conn, household_id, source_id).await?;
+    let target = find(conn, household_id, target_id).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE items
+        SET name = $1, category = COALESCE($2, category)
+        WHERE name = $3
+          AND list IN (SELECT id FROM lists WHERE household_id = $4)
+        "#,
+    )
+    .bind(&target.name)
+    .bind(&target.category)
+    .bind(&source.name)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    delete(conn, household_id, source_id).await?;
+
+    let merged = sqlx::query_as::<_, Name>(
+        r#"
+        UPDATE names
+        SET count = COALESCE(count, 0) + $1
+        WHERE id = $2
+        RETURNING id, name, count, category, version
+        "#,
+    )
+    .bind(source.count.unwrap_or(0))
+    .bind(target_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(merged)
+}
+
+/// Delete a name entry. Items using the name are left untouched.
+pub async fn delete(conn: &mut PgConnection, household_id: i32, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM names
+        WHERE id = $1 AND household_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(household_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
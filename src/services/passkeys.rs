@@ -0,0 +1,151 @@
+use sqlx::PgConnection;
+
+use crate::{
+    auth::webauthn::NewCredential,
+    error::{AppError, Result},
+    models::Passkey,
+};
+
+const PASSKEY_COLUMNS: &str = "id, name, transports, created_at, last_used_at";
+
+/// What a login is checked against
+#[derive(sqlx::FromRow)]
+pub struct StoredPasskey {
+    pub id: i32,
+    pub user_id: i32,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+}
+
+pub async fn list(conn: &mut PgConnection, user_id: i32) -> Result<Vec<Passkey>> {
+    let passkeys = sqlx::query_as::<_, Passkey>(&format!(
+        "SELECT {PASSKEY_COLUMNS} FROM passkeys WHERE user_id = $1 ORDER BY created_at"
+    ))
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(passkeys)
+}
+
+pub async fn credential_ids(conn: &mut PgConnection, user_id: i32) -> Result<Vec<Vec<u8>>> {
+    let ids = sqlx::query_scalar::<_, Vec<u8>>(
+        "SELECT credential_id FROM passkeys WHERE user_id = $1 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(ids)
+}
+
+pub async fn find_by_credential(
+    conn: &mut PgConnection,
+    credential_id: &[u8],
+) -> Result<Option<StoredPasskey>> {
+    let passkey = sqlx::query_as::<_, StoredPasskey>(
+        r#"
+        SELECT p.id, p.user_id, p.public_key, p.sign_count
+        FROM passkeys p
+        JOIN users u ON u.id = p.user_id
+        WHERE p.credential_id = $1 AND NOT u.disabled
+        "#,
+    )
+    .bind(credential_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(passkey)
+}
+
+/// Store a credential that passed the registration ceremony
+pub async fn create(
+    conn: &mut PgConnection,
+    user_id: i32,
+    name: &str,
+    credential: &NewCredential,
+) -> Result<Passkey> {
+    let passkey = sqlx::query_as::<_, Passkey>(&format!(
+        r#"
+        INSERT INTO passkeys (user_id, name, credential_id, public_key, algorithm, sign_count, transports)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING {PASSKEY_COLUMNS}
+        "#
+    ))
+    .bind(user_id)
+    .bind(name)
+    .bind(&credential.credential_id)
+    .bind(&credential.public_key)
+    .bind(credential.algorithm)
+    .bind(credential.sign_count)
+    .bind(&credential.transports)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(passkey)
+}
+
+pub async fn rename(conn: &mut PgConnection, user_id: i32, id: i32, name: &str) -> Result<Passkey> {
+    sqlx::query_as::<_, Passkey>(&format!(
+        r#"
+        UPDATE passkeys
+        SET name = $1
+        WHERE id = $2 AND user_id = $3
+        RETURNING {PASSKEY_COLUMNS}
+        "#
+    ))
+    .bind(name)
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)
+}
+
+pub async fn record_use(conn: &mut PgConnection, id: i32, sign_count: i64) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE passkeys
+        SET sign_count = $1, last_used_at = now()
+        WHERE id = $2
+        "#,
+    )
+    .bind(sign_count)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Delete a passkey. Removing the last one turns off the passkey requirement,
+/// so the user can still sign in with their password.
+pub async fn delete(conn: &mut PgConnection, user_id: i32, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM passkeys
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET passkey_required = false
+        WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM passkeys WHERE user_id = $1)
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
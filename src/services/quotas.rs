@@ -0,0 +1,469 @@
+//! Per-household quotas.
+//!
+//! The limits of a household are its overrides in `household_quotas`, falling
+//! back to the defaults from the configuration. Checks lock the household (or
+//! for items, the list) before counting, so concurrent requests cannot both
+//! take the last free slot; they are skipped entirely for unlimited quotas.
+//!
+//! Requests are counted in memory instead, see [`RequestCounter`].
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use chrono::{NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use crate::{
+    error::{AppError, Result},
+    models::{HouseholdUsage, QuotaUsage, Quotas},
+};
+
+/// How often counted requests are added to `household_requests`
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Quotas on rows of the household. An attachment quota waits for attachments
+/// to exist.
+#[derive(Debug, Clone, Copy)]
+pub enum Quota {
+    Lists,
+    Names,
+    Categories,
+}
+
+impl Quota {
+    fn name(self) -> &'static str {
+        match self {
+            Quota::Lists => "lists",
+            Quota::Names => "names",
+            Quota::Categories => "categories",
+        }
+    }
+
+    fn limit(self, quotas: &Quotas) -> Option<i64> {
+        match self {
+            Quota::Lists => quotas.lists,
+            Quota::Names => quotas.names,
+            Quota::Categories => quotas.categories,
+        }
+    }
+}
+
+const QUOTA_COLUMNS: &str = "lists, items_per_list, names, categories, requests_per_day";
+
+/// The overrides of a household; unset quotas use the server defaults
+pub async fn overrides(conn: &mut PgConnection, household_id: i32) -> Result<Quotas> {
+    let overrides = sqlx::query_as::<_, Quotas>(&format!(
+        "SELECT {QUOTA_COLUMNS} FROM household_quotas WHERE household_id = $1"
+    ))
+    .bind(household_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(overrides.unwrap_or_default())
+}
+
+/// Replace the overrides of a household
+pub async fn set_overrides(
+    conn: &mut PgConnection,
+    household_id: i32,
+    quotas: &Quotas,
+) -> Result<Quotas> {
+    let quotas = sqlx::query_as::<_, Quotas>(&format!(
+        r#"
+        INSERT INTO household_quotas (household_id, {QUOTA_COLUMNS})
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (household_id) DO UPDATE
+        SET lists = EXCLUDED.lists,
+            items_per_list = EXCLUDED.items_per_list,
+            names = EXCLUDED.names,
+            categories = EXCLUDED.categories,
+            requests_per_day = EXCLUDED.requests_per_day,
+            updated_at = now()
+        RETURNING {QUOTA_COLUMNS}
+        "#
+    ))
+    .bind(household_id)
+    .bind(quotas.lists)
+    .bind(quotas.items_per_list)
+    .bind(quotas.names)
+    .bind(quotas.categories)
+    .bind(quotas.requests_per_day)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(quotas)
+}
+
+/// The limits that apply to a household
+pub async fn limits(
+    conn: &mut PgConnection,
+    defaults: &Quotas,
+    household_id: i32,
+) -> Result<Quotas> {
+    Ok(overrides(conn, household_id).await?.or(defaults))
+}
+
+/// Check that the household may create one more row counted by `quota`.
+/// Holds a lock on the household until the transaction ends.
+pub async fn reserve(
+    conn: &mut PgConnection,
+    limits: &Quotas,
+    household_id: i32,
+    quota: Quota,
+) -> Result<()> {
+    let Some(limit) = quota.limit(limits) else {
+        return Ok(());
+    };
+
+    // Unlike FOR UPDATE, this does not block inserts referencing the household
+    sqlx::query("SELECT 1 FROM households WHERE id = $1 FOR NO KEY UPDATE")
+        .bind(household_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let used = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT COUNT(*) FROM {} WHERE household_id = $1",
+        quota.name()
+    ))
+    .bind(household_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if used >= limit {
+        return Err(AppError::QuotaExceeded {
+            quota: quota.name(),
+            limit,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that one more item fits on a list.
+/// Holds a lock on the list until the transaction ends.
+pub async fn reserve_item(conn: &mut PgConnection, limits: &Quotas, list_id: i32) -> Result<()> {
+    let Some(limit) = limits.items_per_list else {
+        return Ok(());
+    };
+
+    sqlx::query("SELECT 1 FROM lists WHERE id = $1 FOR NO KEY UPDATE")
+        .bind(list_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let used = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM items WHERE list = $1")
+        .bind(list_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if used >= limit {
+        return Err(AppError::QuotaExceeded {
+            quota: "itemsPerList",
+            limit,
+        });
+    }
+
+    Ok(())
+}
+
+/// Counts requests against the households' daily quotas in memory and adds
+/// them to `household_requests` every few seconds, instead of writing a row
+/// per request. The flush also brings in the counts of the other instances and
+/// changed limits, so across instances a household can go over its quota by
+/// what the others counted since.
+#[derive(Clone, Default)]
+pub struct RequestCounter {
+    /// By household ID and UTC day
+    counts: Arc<Mutex<HashMap<(i32, NaiveDate), DailyCount>>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DailyCount {
+    /// Requests stored at the last flush, by all instances
+    stored: i64,
+    /// Requests counted here since
+    pending: i64,
+    limit: Option<i64>,
+}
+
+impl DailyCount {
+    /// Count a request unless the limit is reached
+    fn count(&mut self) -> Result<()> {
+        match self.limit {
+            Some(limit) if self.stored + self.pending >= limit => Err(AppError::QuotaExceeded {
+                quota: "requestsPerDay",
+                limit,
+            }),
+            _ => {
+                self.pending += 1;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl RequestCounter {
+    /// Write the counts in the background until the server stops
+    pub fn flush_periodically(&self, pool: PgPool, defaults: Quotas) {
+        let counter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = counter.flush(&pool, &defaults).await {
+                    tracing::error!("Writing request counts failed: {:?}", e);
+                }
+            }
+        });
+    }
+
+    /// Count a request against the household's daily quota. Requests over the
+    /// limit are rejected and not counted. Only the first request of a
+    /// household each day reads the database.
+    pub async fn count(&self, pool: &PgPool, defaults: &Quotas, household_id: i32) -> Result<()> {
+        let key = (household_id, Utc::now().date_naive());
+        if let Some(counted) = self.count_known(key) {
+            return counted;
+        }
+
+        let (stored, limit) = sqlx::query_as::<_, (i64, Option<i64>)>(
+            r#"
+            SELECT
+                COALESCE((
+                    SELECT count FROM household_requests WHERE household_id = $1 AND day = $3
+                ), 0),
+                COALESCE(
+                    (SELECT requests_per_day FROM household_quotas WHERE household_id = $1),
+                    $2::bigint
+                )
+            "#,
+        )
+        .bind(household_id)
+        .bind(defaults.requests_per_day)
+        .bind(key.1)
+        .fetch_one(pool)
+        .await?;
+
+        self.count_new(key, stored, limit)
+    }
+
+    /// Count a request of a household and day counted before, `None` for
+    /// the first one
+    fn count_known(&self, key: (i32, NaiveDate)) -> Option<Result<()>> {
+        self.lock().get_mut(&key).map(DailyCount::count)
+    }
+
+    /// Count the first request of a household and day with what the database
+    /// says. Concurrent first requests all read; the first to get here sets
+    /// up the count.
+    fn count_new(&self, key: (i32, NaiveDate), stored: i64, limit: Option<i64>) -> Result<()> {
+        self.lock()
+            .entry(key)
+            .or_insert(DailyCount {
+                stored,
+                pending: 0,
+                limit,
+            })
+            .count()
+    }
+
+    /// Take the requests counted since the last flush, by household and day,
+    /// and forget the days before `today`
+    fn take_pending(&self, today: NaiveDate) -> (Vec<i32>, Vec<NaiveDate>, Vec<i64>) {
+        let (mut households, mut days, mut requests) = (Vec::new(), Vec::new(), Vec::new());
+        let mut counts = self.lock();
+        for (&(household_id, day), count) in counts.iter_mut() {
+            if count.pending > 0 {
+                households.push(household_id);
+                days.push(day);
+                requests.push(std::mem::take(&mut count.pending));
+            }
+        }
+        counts.retain(|&(_, day), _| day == today);
+
+        (households, days, requests)
+    }
+
+    /// Add the requests counted since the last flush to the database, then
+    /// read back today's counts and limits
+    async fn flush(&self, pool: &PgPool, defaults: &Quotas) -> Result<()> {
+        let today = Utc::now().date_naive();
+        let (households, days, requests) = self.take_pending(today);
+
+        let written = sqlx::query(
+            r#"
+            INSERT INTO household_requests (household_id, day, count)
+            SELECT * FROM UNNEST($1::int[], $2::date[], $3::bigint[])
+            ON CONFLICT (household_id, day) DO UPDATE
+            SET count = household_requests.count + EXCLUDED.count
+            "#,
+        )
+        .bind(&households)
+        .bind(&days)
+        .bind(&requests)
+        .execute(pool);
+        if let (false, Err(e)) = (households.is_empty(), written.await) {
+            // Written with the next flush instead
+            let mut counts = self.lock();
+            for ((household_id, day), pending) in households.into_iter().zip(days).zip(requests) {
+                counts.entry((household_id, day)).or_default().pending += pending;
+            }
+            return Err(e.into());
+        }
+
+        let known: Vec<i32> = self
+            .lock()
+            .keys()
+            .map(|&(household_id, _)| household_id)
+            .collect();
+        let current = sqlx::query_as::<_, (i32, i64, Option<i64>)>(
+            r#"
+            SELECT h.id,
+                   COALESCE(r.count, 0),
+                   COALESCE(q.requests_per_day, $2::bigint)
+            FROM UNNEST($1::int[]) AS h (id)
+            LEFT JOIN household_requests r ON r.household_id = h.id AND r.day = $3
+            LEFT JOIN household_quotas q ON q.household_id = h.id
+            "#,
+        )
+        .bind(&known)
+        .bind(defaults.requests_per_day)
+        .bind(today)
+        .fetch_all(pool)
+        .await?;
+
+        let mut counts = self.lock();
+        for (household_id, stored, limit) in current {
+            if let Some(count) = counts.get_mut(&(household_id, today)) {
+                count.stored = stored;
+                count.limit = limit;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<(i32, NaiveDate), DailyCount>> {
+        // Counts stay usable even if a holder panicked
+        self.counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Usage of all quotas of a household
+pub async fn usage(
+    conn: &mut PgConnection,
+    defaults: &Quotas,
+    household_id: i32,
+) -> Result<HouseholdUsage> {
+    let limits = limits(conn, defaults, household_id).await?;
+
+    let (lists, items_per_list, names, categories, requests) =
+        sqlx::query_as::<_, (i64, i64, i64, i64, i64)>(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM lists WHERE household_id = $1),
+                (SELECT COALESCE(MAX(n), 0) FROM (
+                    SELECT COUNT(*) AS n
+                    FROM items
+                    WHERE list IN (SELECT id FROM lists WHERE household_id = $1)
+                    GROUP BY list
+                ) counts),
+                (SELECT COUNT(*) FROM names WHERE household_id = $1),
+                (SELECT COUNT(*) FROM categories WHERE household_id = $1),
+                COALESCE((
+                    SELECT count
+                    FROM household_requests
+                    WHERE household_id = $1 AND day = (now() AT TIME ZONE 'UTC')::date
+                ), 0)
+            "#,
+        )
+        .bind(household_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let usage = |used, limit| QuotaUsage { used, limit };
+
+    Ok(HouseholdUsage {
+        household_id,
+        lists: usage(lists, limits.lists),
+        items_per_list: usage(items_per_list, limits.items_per_list),
+        names: usage(names, limits.names),
+        categories: usage(categories, limits.categories),
+        requests_per_day: usage(requests, limits.requests_per_day),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUSEHOLD: i32 = 2;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn exceeded(result: Option<Result<()>>) -> bool {
+        matches!(
+            result,
+            Some(Err(AppError::QuotaExceeded {
+                quota: "requestsPerDay",
+                limit: 3
+            }))
+        )
+    }
+
+    #[test]
+    fn counts_requests_within_a_day() {
+        let counter = RequestCounter::default();
+        let key = (HOUSEHOLD, day(18));
+        assert!(counter.count_known(key).is_none());
+
+        // Another instance stored one already
+        counter.count_new(key, 1, Some(3)).unwrap();
+        assert!(matches!(counter.count_known(key), Some(Ok(()))));
+        assert!(exceeded(counter.count_known(key)));
+        // Rejected requests are not counted
+        assert!(exceeded(counter.count_known(key)));
+        assert_eq!(
+            counter.take_pending(day(18)),
+            (vec![HOUSEHOLD], vec![day(18)], vec![2])
+        );
+        assert_eq!(counter.take_pending(day(18)), (vec![], vec![], vec![]));
+    }
+
+    #[test]
+    fn counts_without_limit() {
+        let counter = RequestCounter::default();
+        let key = (HOUSEHOLD, day(18));
+        counter.count_new(key, 1_000_000, None).unwrap();
+        assert!(matches!(counter.count_known(key), Some(Ok(()))));
+    }
+
+    #[test]
+    fn starts_over_when_the_utc_day_changes() {
+        let counter = RequestCounter::default();
+        let yesterday = (HOUSEHOLD, day(18));
+        counter.count_new(yesterday, 2, Some(3)).unwrap();
+        assert!(exceeded(counter.count_known(yesterday)));
+
+        // The first request of the day reads the database again
+        let today = (HOUSEHOLD, day(19));
+        assert!(counter.count_known(today).is_none());
+        counter.count_new(today, 0, Some(3)).unwrap();
+        assert!(matches!(counter.count_known(today), Some(Ok(()))));
+
+        // Yesterday's requests are still written, then forgotten
+        let (households, days, requests) = counter.take_pending(day(19));
+        let mut written: Vec<_> = households.into_iter().zip(days).zip(requests).collect();
+        written.sort();
+        assert_eq!(written, vec![(yesterday, 1), (today, 2)]);
+        assert!(counter.count_known(yesterday).is_none());
+        assert!(counter.count_known(today).is_some());
+    }
+}
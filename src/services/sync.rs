@@ -0,0 +1,460 @@
+//! Delta sync of lists, items, categories and names.
+//!
+//! Rows carry the version of their last change and the ID of the transaction
+//! that made it, deleted rows leave tombstones (see the `sync` migration). A
+//! sync returns the changes of the transactions from the cursor up to the
+//! oldest one still running, so changes committed late are never skipped;
+//! they come with a later sync. Meant to run in a repeatable read
+//! transaction, so a page is consistent across the tables.
+//!
+//! Changes made offline are replayed one operation per transaction, see
+//! `POST /api/sync`. Lists and items remember when each of their fields was
+//! last changed (see the `client_ids` migration), which settles conflicting
+//! changes field by field.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::{Category, Item, List, Name, SyncChanges, SyncCursor, Synced, Tombstone},
+};
+
+const SYNC_COLUMNS: &str = "created_at, updated_at, version";
+
+/// Changes in the window `$1..$2` after version `$3`; `$4` is the limit
+fn window(table: &str) -> String {
+    format!(
+        "{table}.version_xid >= $1::bigint::text::xid8 \
+         AND {table}.version_xid < $2::bigint::text::xid8 \
+         AND {table}.version > $3"
+    )
+}
+
+/// A page of at most `limit` changes of the household after `since`, or of
+/// all rows without it. `list_ids` and `user_id` limit the lists and items
+/// like in `GET /api/lists`.
+pub async fn changes(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_ids: Option<&[i32]>,
+    user_id: Option<i32>,
+    since: Option<SyncCursor>,
+    limit: i64,
+) -> Result<SyncChanges> {
+    let horizon =
+        sqlx::query_scalar::<_, i64>("SELECT COALESCE((SELECT xid FROM sync_horizon), 0)")
+            .fetch_one(&mut *conn)
+            .await?;
+
+    // Tombstones may be gone for cursors this old, start over
+    let reset = since.is_some_and(|cursor| cursor.from < horizon);
+    let cursor = since.filter(|_| !reset).unwrap_or_default();
+    let to = match cursor.to {
+        Some(to) => to,
+        None => {
+            sqlx::query_scalar::<_, i64>(
+                "SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint",
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+
+    // One more than a page of each, to tell whether another page follows
+    let fetch = limit + 1;
+
+    let lists = sqlx::query_as::<_, Synced<List>>(&format!(
+        r#"
+        SELECT l.id, l.uuid, l.name, l.owner_id, l.private, l.created_at, l.updated_at, l.version
+        FROM lists l
+        WHERE l.household_id = $5
+          AND ($6::int[] IS NULL OR l.id = ANY($6))
+          AND ($7::int IS NULL OR list_role(l.id, $7) IS NOT NULL)
+          AND {}
+        ORDER BY l.version
+        LIMIT $4
+        "#,
+        window("l")
+    ))
+    .bind(cursor.from)
+    .bind(to)
+    .bind(cursor.after)
+    .bind(fetch)
+    .bind(household_id)
+    .bind(list_ids)
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let items = sqlx::query_as::<_, Synced<Item>>(&format!(
+        r#"
+        SELECT i.id, i.uuid, i.name, i.amount, i."amountUnit", i."inCart", i.list, i.category,
+               i.created_at, i.updated_at, i.version
+        FROM items i
+        JOIN lists l ON l.id = i.list
+        WHERE l.household_id = $5
+          AND ($6::int[] IS NULL OR l.id = ANY($6))
+          AND ($7::int IS NULL OR list_role(l.id, $7) IS NOT NULL)
+          AND {}
+        ORDER BY i.version
+        LIMIT $4
+        "#,
+        window("i")
+    ))
+    .bind(cursor.from)
+    .bind(to)
+    .bind(cursor.after)
+    .bind(fetch)
+    .bind(household_id)
+    .bind(list_ids)
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let categories = sqlx::query_as::<_, Synced<Category>>(&format!(
+        r#"
+        SELECT id, name, {SYNC_COLUMNS}
+        FROM categories
+        WHERE household_id = $5
+          AND {}
+        ORDER BY version
+        LIMIT $4
+        "#,
+        window("categories")
+    ))
+    .bind(cursor.from)
+    .bind(to)
+    .bind(cursor.after)
+    .bind(fetch)
+    .bind(household_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let names = sqlx::query_as::<_, Synced<Name>>(&format!(
+        r#"
+        SELECT id, name, count, category, {SYNC_COLUMNS}
+        FROM names
+        WHERE household_id = $5
+          AND {}
+        ORDER BY version
+        LIMIT $4
+        "#,
+        window("names")
+    ))
+    .bind(cursor.from)
+    .bind(to)
+    .bind(cursor.after)
+    .bind(fetch)
+    .bind(household_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // A full sync has nothing to delete. The lists of tombstones are gone,
+    // so users learn of every deleted list.
+    let deleted = if cursor.from == 0 {
+        Vec::new()
+    } else {
+        sqlx::query_as::<_, Tombstone>(&format!(
+            r#"
+            SELECT entity, entity_id AS id, entity_uuid AS uuid, list_id, deleted_at, version
+            FROM tombstones t
+            WHERE household_id = $5
+              AND ($6::int[] IS NULL OR t.list_id IS NULL OR t.list_id = ANY($6))
+              AND ($7::int IS NULL
+                   OR t.entity <> 'items'
+                   OR NOT EXISTS (SELECT 1 FROM lists WHERE id = t.list_id)
+                   OR list_role(t.list_id, $7) IS NOT NULL)
+              AND {}
+            ORDER BY version
+            LIMIT $4
+            "#,
+            window("t")
+        ))
+        .bind(cursor.from)
+        .bind(to)
+        .bind(cursor.after)
+        .bind(fetch)
+        .bind(household_id)
+        .bind(list_ids)
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?
+    };
+
+    let mut versions: Vec<i64> = lists
+        .iter()
+        .map(|synced| synced.row.version)
+        .chain(items.iter().map(|synced| synced.row.version))
+        .chain(categories.iter().map(|synced| synced.row.version))
+        .chain(names.iter().map(|synced| synced.row.version))
+        .chain(deleted.iter().map(|row| row.version))
+        .collect();
+    versions.sort_unstable();
+
+    let page = usize::try_from(limit).unwrap_or(usize::MAX);
+    let (next, last) = match versions.get(page) {
+        // More changes follow the last version of this page
+        Some(_) => {
+            let last = versions[page - 1];
+            let next = SyncCursor {
+                to: Some(to),
+                after: last,
+                ..cursor
+            };
+            (next, last)
+        }
+        None => {
+            let next = SyncCursor {
+                from: to,
+                to: None,
+                after: 0,
+            };
+            (next, i64::MAX)
+        }
+    };
+
+    let on_page = |version: i64| version <= last;
+    Ok(SyncChanges {
+        lists: lists
+            .into_iter()
+            .filter(|synced| on_page(synced.row.version))
+            .collect(),
+        items: items
+            .into_iter()
+            .filter(|synced| on_page(synced.row.version))
+            .collect(),
+        categories: categories
+            .into_iter()
+            .filter(|synced| on_page(synced.row.version))
+            .collect(),
+        names: names
+            .into_iter()
+            .filter(|synced| on_page(synced.row.version))
+            .collect(),
+        deleted: deleted
+            .into_iter()
+            .filter(|row| on_page(row.version))
+            .collect(),
+        cursor: next.to_string(),
+        has_more: next.to.is_some(),
+        reset,
+    })
+}
+
+/// Record an operation as applied; `false` if it was applied before
+pub async fn record_operation(
+    conn: &mut PgConnection,
+    household_id: i32,
+    id: Uuid,
+    user_id: Option<i32>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO sync_operations (household_id, id, user_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(household_id)
+    .bind(id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Stamp the fields changed in this transaction with the time the client
+/// changed them, which is capped at the current time. Returns the stamp.
+pub async fn set_changed_at(conn: &mut PgConnection, at: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let at = sqlx::query_scalar::<_, DateTime<Utc>>(
+        r#"
+        SELECT t.at
+        FROM (SELECT LEAST($1, now()) AS at) t
+        CROSS JOIN LATERAL set_config('lister.changed_at', t.at::text, true)
+        "#,
+    )
+    .bind(at)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(at)
+}
+
+/// When each field of a row of `lists` or `items` was last changed, by
+/// column name. Fields not changed since the `client_ids` migration are missing.
+/// Locks the row until the transaction ends, so no change slips in between
+/// comparing the times and writing.
+pub async fn field_updated_at(
+    conn: &mut PgConnection,
+    table: &'static str,
+    id: i32,
+) -> Result<HashMap<String, DateTime<Utc>>> {
+    let Json(times) = sqlx::query_scalar::<_, Json<HashMap<String, DateTime<Utc>>>>(&format!(
+        "SELECT field_updated_at FROM {table} WHERE id = $1 FOR UPDATE"
+    ))
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(times)
+}
+
+/// Whether a list or item of the household was deleted, as far as the
+/// tombstones remember
+pub async fn is_deleted(
+    conn: &mut PgConnection,
+    household_id: i32,
+    entity: &str,
+    uuid: Uuid,
+) -> Result<bool> {
+    let deleted = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM tombstones
+            WHERE entity_uuid = $1 AND entity = $2 AND household_id = $3
+        )
+        "#,
+    )
+    .bind(uuid)
+    .bind(entity)
+    .bind(household_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(deleted)
+}
+
+/// The lists and items with these UUIDs, limited like in [`changes`]
+pub async fn rows(
+    conn: &mut PgConnection,
+    household_id: i32,
+    list_ids: Option<&[i32]>,
+    user_id: Option<i32>,
+    list_uuids: &[Uuid],
+    item_uuids: &[Uuid],
+) -> Result<(Vec<Synced<List>>, Vec<Synced<Item>>)> {
+    let lists = sqlx::query_as::<_, Synced<List>>(
+        r#"
+        SELECT l.id, l.uuid, l.name, l.owner_id, l.private, l.created_at, l.updated_at, l.version
+        FROM lists l
+        WHERE l.household_id = $1
+          AND ($2::int[] IS NULL OR l.id = ANY($2))
+          AND ($3::int IS NULL OR list_role(l.id, $3) IS NOT NULL)
+          AND l.uuid = ANY($4)
+        ORDER BY l.id
+        "#,
+    )
+    .bind(household_id)
+    .bind(list_ids)
+    .bind(user_id)
+    .bind(list_uuids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let items = sqlx::query_as::<_, Synced<Item>>(
+        r#"
+        SELECT i.id, i.uuid, i.name, i.amount, i."amountUnit", i."inCart", i.list, i.category,
+               i.created_at, i.updated_at, i.version
+        FROM items i
+        JOIN lists l ON l.id = i.list
+        WHERE l.household_id = $1
+          AND ($2::int[] IS NULL OR l.id = ANY($2))
+          AND ($3::int IS NULL OR list_role(l.id, $3) IS NOT NULL)
+          AND i.uuid = ANY($4)
+        ORDER BY i.id
+        "#,
+    )
+    .bind(household_id)
+    .bind(list_ids)
+    .bind(user_id)
+    .bind(item_uuids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok((lists, items))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{PgPool, Postgres, Transaction};
+
+    use super::*;
+    use crate::{
+        models::{CreateItemRequest, CreateListRequest, Quotas},
+        services::{households, items, lists},
+    };
+
+    /// A transaction that is rolled back when dropped, with a new household
+    /// that has a list with an item
+    async fn database() -> (Transaction<'static, Postgres>, i32, List, Item) {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&url).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+
+        let household = households::create(&mut tx, "Tombstones").await.unwrap();
+        let list = CreateListRequest {
+            uuid: None,
+            name: "Groceries".to_string(),
+            private: false,
+        };
+        let list = lists::create(&mut tx, &Quotas::default(), household.id, None, &list)
+            .await
+            .unwrap();
+        let item = CreateItemRequest {
+            uuid: None,
+            name: "Milk".to_string(),
+            amount: None,
+            amount_unit: None,
+            category: None,
+            in_cart: false,
+        };
+        let item = items::create(&mut tx, &Quotas::default(), household.id, list.id, &item)
+            .await
+            .unwrap();
+
+        (tx, household.id, list, item)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn items_deleted_with_their_list_leave_tombstones() {
+        let (mut tx, household_id, list, item) = database().await;
+        lists::delete(&mut tx, household_id, list.id).await.unwrap();
+
+        assert!(is_deleted(&mut tx, household_id, "lists", list.uuid)
+            .await
+            .unwrap());
+        assert!(is_deleted(&mut tx, household_id, "items", item.uuid)
+            .await
+            .unwrap());
+        let tombstones = sqlx::query_scalar::<_, i64>(
+            "SELECT count(*) FROM tombstones WHERE entity = 'items' AND entity_uuid = $1",
+        )
+        .bind(item.uuid)
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        assert_eq!(tombstones, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DATABASE_URL"]
+    async fn rows_deleted_with_their_household_leave_nothing() {
+        let (mut tx, household_id, list, item) = database().await;
+        households::delete(&mut tx, household_id).await.unwrap();
+
+        let tombstones = sqlx::query_scalar::<_, i64>(
+            "SELECT count(*) FROM tombstones WHERE entity_uuid = ANY($1)",
+        )
+        .bind([list.uuid, item.uuid])
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        assert_eq!(tombstones, 0);
+    }
+}
@@ -0,0 +1,220 @@
+use sqlx::PgConnection;
+
+use crate::{
+    auth::{password, session},
+    error::{AppError, Result},
+    i18n::Message,
+    models::{UpdateProfileRequest, UpdateUserRequest, User},
+};
+
+const USER_COLUMNS: &str =
+    "id, username, display_name, is_admin, disabled, language, passkey_required, created_at";
+
+pub async fn find(conn: &mut PgConnection, id: i32) -> Result<User> {
+    sqlx::query_as::<_, User>(&format!("SELECT {USER_COLUMNS} FROM users WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+/// An enabled user by their normalized username
+pub async fn find_by_username(conn: &mut PgConnection, username: &str) -> Result<Option<User>> {
+    let user = sqlx::query_as::<_, User>(&format!(
+        "SELECT {USER_COLUMNS} FROM users WHERE username = $1 AND NOT disabled"
+    ))
+    .bind(username)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(user)
+}
+
+pub async fn list(conn: &mut PgConnection) -> Result<Vec<User>> {
+    let users = sqlx::query_as::<_, User>(&format!(
+        "SELECT {USER_COLUMNS} FROM users ORDER BY username"
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(users)
+}
+
+/// Create a user; the username must already be normalized
+pub async fn create(
+    conn: &mut PgConnection,
+    username: &str,
+    password: &str,
+    display_name: Option<&str>,
+    is_admin: bool,
+) -> Result<User> {
+    let password_hash = password::hash(password.to_string()).await?;
+
+    let user = sqlx::query_as::<_, User>(&format!(
+        r#"
+        INSERT INTO users (username, password_hash, display_name, is_admin)
+        VALUES ($1, $2, $3, $4)
+        RETURNING {USER_COLUMNS}
+        "#
+    ))
+    .bind(username)
+    .bind(password_hash)
+    .bind(display_name)
+    .bind(is_admin)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(user)
+}
+
+/// Check a username and password. Disabled users and users without a
+/// password, who sign in through single sign-on, cannot sign in.
+pub async fn verify_login(
+    conn: &mut PgConnection,
+    username: &str,
+    password: &str,
+) -> Result<Option<User>> {
+    let hash = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        SELECT password_hash
+        FROM users
+        WHERE username = $1 AND NOT disabled
+        "#,
+    )
+    .bind(username)
+    .fetch_optional(&mut *conn)
+    .await?
+    .flatten();
+
+    if !password::verify(password.to_string(), hash).await {
+        return Ok(None);
+    }
+
+    let user = sqlx::query_as::<_, User>(&format!(
+        "SELECT {USER_COLUMNS} FROM users WHERE username = $1"
+    ))
+    .bind(username)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Some(user))
+}
+
+/// Admin changes. A new password or disabling the account ends all sessions.
+pub async fn update(conn: &mut PgConnection, id: i32, request: &UpdateUserRequest) -> Result<User> {
+    let current = find(conn, id).await?;
+
+    let display_name = match &request.display_name {
+        Some(inner) => inner.clone(),
+        None => current.display_name,
+    };
+    let is_admin = request.is_admin.unwrap_or(current.is_admin);
+    let disabled = request.disabled.unwrap_or(current.disabled);
+
+    let user = sqlx::query_as::<_, User>(&format!(
+        r#"
+        UPDATE users
+        SET display_name = $1, is_admin = $2, disabled = $3
+        WHERE id = $4
+        RETURNING {USER_COLUMNS}
+        "#
+    ))
+    .bind(display_name)
+    .bind(is_admin)
+    .bind(disabled)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if let Some(password) = &request.password {
+        set_password(conn, id, password).await?;
+    }
+    if request.password.is_some() || disabled {
+        session::delete_all(conn, id, None).await?;
+    }
+
+    Ok(user)
+}
+
+/// Changes users make to their own account
+pub async fn update_profile(
+    conn: &mut PgConnection,
+    id: i32,
+    request: &UpdateProfileRequest,
+) -> Result<User> {
+    let current = find(conn, id).await?;
+
+    let display_name = match &request.display_name {
+        Some(inner) => inner.clone(),
+        None => current.display_name,
+    };
+    let language = match &request.language {
+        Some(inner) => inner.clone(),
+        None => current.language,
+    };
+    let passkey_required = request.passkey_required.unwrap_or(current.passkey_required);
+    if passkey_required && !current.passkey_required {
+        let has_passkey = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM passkeys WHERE user_id = $1)",
+        )
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if !has_passkey {
+            return Err(AppError::Conflict(Message::new("error.no_passkey")));
+        }
+    }
+
+    let user = sqlx::query_as::<_, User>(&format!(
+        r#"
+        UPDATE users
+        SET display_name = $1, language = $2, passkey_required = $3
+        WHERE id = $4
+        RETURNING {USER_COLUMNS}
+        "#
+    ))
+    .bind(display_name)
+    .bind(language)
+    .bind(passkey_required)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(user)
+}
+
+pub async fn set_password(conn: &mut PgConnection, id: i32, password: &str) -> Result<()> {
+    let password_hash = password::hash(password.to_string()).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash = $1
+        WHERE id = $2
+        "#,
+    )
+    .bind(password_hash)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete(conn: &mut PgConnection, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM users
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
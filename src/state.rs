@@ -1,11 +1,22 @@
+use std::sync::Arc;
+
 use sqlx::PgPool;
 
-use crate::config::Config;
+use crate::{
+    auth::oidc, config::Config, events, jobs::Scheduler, services::quotas::RequestCounter,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub config: Config,
+    pub scheduler: Arc<Scheduler>,
+    /// Live item changes for Server-Sent Events
+    pub events: events::Hub,
+    /// Requests against the daily quotas, written every few seconds
+    pub requests: RequestCounter,
+    /// Set when single sign-on is configured
+    pub oidc: Option<Arc<oidc::Provider>>,
 }
 
 
@@ -0,0 +1,263 @@
+//! HTTPS with optional client certificates.
+//!
+//! With `TLS_CLIENT_CA` set, clients are asked for a certificate signed by one
+//! of the configured CAs. Connecting without one is still allowed, so browsers
+//! and apps keep signing in as usual. A verified certificate is passed to
+//! [`crate::auth::auth_middleware`] as a [`ClientCertificate`], which resolves
+//! it to the API token bound to one of its identities.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use axum::{extract::ConnectInfo, Router};
+use hyper::{body::Incoming, server::conn::http1, service::service_fn, Request};
+use hyper_util::rt::TokioIo;
+use rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+use crate::config::TlsConfig;
+
+/// Clients that do not finish the handshake in time are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A client certificate that passed verification against the client CAs
+#[derive(Debug, Clone)]
+pub struct ClientCertificate {
+    /// `CN=<common name>` from the subject, then `DNS:<name>` and `URI:<uri>`
+    /// from the subject alternative names
+    pub identities: Vec<String>,
+}
+
+impl ClientCertificate {
+    fn parse(der: &CertificateDer<'_>) -> Option<ClientCertificate> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let alt_names = match cert.subject_alternative_name().ok()? {
+            Some(extension) => extension.value.general_names.clone(),
+            None => Vec::new(),
+        };
+
+        let identities = cert
+            .subject()
+            .iter_common_name()
+            .filter_map(|name| name.as_str().ok())
+            .map(|name| format!("CN={name}"))
+            .chain(alt_names.iter().filter_map(|name| match name {
+                GeneralName::DNSName(name) => Some(format!("DNS:{name}")),
+                _ => None,
+            }))
+            .chain(alt_names.iter().filter_map(|name| match name {
+                GeneralName::URI(uri) => Some(format!("URI:{uri}")),
+                _ => None,
+            }))
+            .collect();
+
+        Some(ClientCertificate { identities })
+    }
+}
+
+/// Load the server certificate, its key and the client CAs
+pub fn server_config(config: &TlsConfig) -> anyhow::Result<Arc<ServerConfig>> {
+    let provider = Arc::new(ring::default_provider());
+
+    let certs = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .with_context(|| format!("Failed to read TLS_CERT {}", config.cert_path))?;
+    let key = PrivateKeyDer::from_pem_file(&config.key_path)
+        .with_context(|| format!("Failed to read TLS_KEY {}", config.key_path))?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &config.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(path)
+                .with_context(|| format!("Failed to read TLS_CLIENT_CA {path}"))?
+            {
+                roots
+                    .add(cert.with_context(|| format!("Failed to read TLS_CLIENT_CA {path}"))?)
+                    .with_context(|| format!("Invalid CA certificate in {path}"))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()
+                .with_context(|| format!("No usable CA certificate in {path}"))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(certs, key)
+        .context("TLS_KEY does not match TLS_CERT")?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(server_config))
+}
+
+/// Serve the app over HTTPS. Like `axum::serve` with connect info, requests
+/// carry the peer address; those with a verified client certificate also
+/// carry a [`ClientCertificate`].
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    config: Arc<ServerConfig>,
+) -> anyhow::Result<()> {
+    let acceptor = TlsAcceptor::from(config);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                tracing::warn!(%error, "Failed to accept connection");
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(error)) => {
+                        tracing::debug!(%peer, %error, "TLS handshake failed");
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!(%peer, "TLS handshake timed out");
+                        return;
+                    }
+                };
+            let certificate = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(ClientCertificate::parse);
+
+            let service = service_fn(move |mut request: Request<Incoming>| {
+                request
+                    .extensions_mut()
+                    .insert(ConnectInfo::<SocketAddr>(peer));
+                if let Some(certificate) = &certificate {
+                    request.extensions_mut().insert(certificate.clone());
+                }
+                // Like in `axum::serve`, routers are always ready
+                app.clone().call(request)
+            });
+
+            if let Err(error) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades()
+                .await
+            {
+                tracing::debug!(%peer, %error, "Connection closed with an error");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `O=Home, CN=scanner, CN=kitchen display` with the alternative names
+    /// `DNS:scanner.lan`, an e-mail address, `URI:spiffe://home/scanner` and
+    /// `DNS:scanner.local`
+    const SCANNER: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIICIjCCAcigAwIBAgIULzuoBqFXcTj4WmrlVgQtDPPAuoowCgYIKoZIzj0EAwIw\n\
+OzENMAsGA1UECgwESG9tZTEQMA4GA1UEAwwHc2Nhbm5lcjEYMBYGA1UEAwwPa2l0\n\
+Y2hlbiBkaXNwbGF5MCAXDTI2MTAxODIyMjIxNloYDzIxMjYwOTI0MjIyMjE2WjA7\n\
+MQ0wCwYDVQQKDARIb21lMRAwDgYDVQQDDAdzY2FubmVyMRgwFgYDVQQDDA9raXRj\n\
+aGVuIGRpc3BsYXkwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASFLjYTLgP8FJKa\n\
+dBE+iUjR5xWui6w2waFw3LTBs/by03NGw2Q3QvmWJ4wlHmX/+QHJHllmcmLt1Xqr\n\
+i46W61DFo4GnMIGkMB0GA1UdDgQWBBTJbnOC1X/cs3ZnOd83HGuB90OMzjAfBgNV\n\
+HSMEGDAWgBTJbnOC1X/cs3ZnOd83HGuB90OMzjAPBgNVHRMBAf8EBTADAQH/MFEG\n\
+A1UdEQRKMEiCC3NjYW5uZXIubGFugRNzY2FubmVyQGV4YW1wbGUuY29thhVzcGlm\n\
+ZmU6Ly9ob21lL3NjYW5uZXKCDXNjYW5uZXIubG9jYWwwCgYIKoZIzj0EAwIDSAAw\n\
+RQIhAOG8KxU8rDMuAxDb9ueIuSBLXLApTsVpLBB5q0FfcbqhAiB+Fn/xf1uhfCr3\n\
+tmC1cWBFTGPv7EZ3lGb/czkN35bj+g==\n\
+-----END CERTIFICATE-----
+";
+
+    /// A single common name longer than 127 bytes
+    const SHELF_LABEL: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIICTzCCAfWgAwIBAgIBATAKBggqhkjOPQQDAjCBrzGBrDCBqQYDVQQDDIGhcGFu\n\
+dHJ5IHNoZWxmIHBhbnRyeSBzaGVsZiBwYW50cnkgc2hlbGYgcGFudHJ5IHNoZWxm\n\
+IHBhbnRyeSBzaGVsZiBwYW50cnkgc2hlbGYgcGFudHJ5IHNoZWxmIHBhbnRyeSBz\n\
+aGVsZiBwYW50cnkgc2hlbGYgcGFudHJ5IHNoZWxmIHBhbnRyeSBzaGVsZiBwYW50\n\
+cnkgc2hlbGYgbGFiZWwwIBcNMjYwMTAxMDAwMDAwWhgPMjEyNTEyMDgwMDAwMDBa\n\
+MIGvMYGsMIGpBgNVBAMMgaFwYW50cnkgc2hlbGYgcGFudHJ5IHNoZWxmIHBhbnRy\n\
+eSBzaGVsZiBwYW50cnkgc2hlbGYgcGFudHJ5IHNoZWxmIHBhbnRyeSBzaGVsZiBw\n\
+YW50cnkgc2hlbGYgcGFudHJ5IHNoZWxmIHBhbnRyeSBzaGVsZiBwYW50cnkgc2hl\n\
+bGYgcGFudHJ5IHNoZWxmIHBhbnRyeSBzaGVsZiBsYWJlbDBZMBMGByqGSM49AgEG\n\
+CCqGSM49AwEHA0IABGJW01veKR+B+ZX4FNzIe7CpWij+xVq7+9/zgrKXrPCY5jsI\n\
+vFjtZoY+yUoyqW6i7PGunsCz4khVou81BPKnXicwCgYIKoZIzj0EAwIDSAAwRQIh\n\
+AP7UYr19WJoYmjIrE6+X6cYdehyNsKSbA0tnfOHmNi0rAiB83sKk+k1lJsE8SoBK\n\
+OYX/vLCTUBOl6lKpn0Q2QuXvzg==\n\
+-----END CERTIFICATE-----
+";
+
+    /// `O=Home` and nothing else
+    const NAMELESS: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBdTCCARugAwIBAgIUZzr8+C+DC+3j4da2YXb/rz1NVm4wCgYIKoZIzj0EAwIw\n\
+DzENMAsGA1UECgwESG9tZTAgFw0yNjEwMTgyMjIyMTZaGA8yMTI2MDkyNDIyMjIx\n\
+NlowDzENMAsGA1UECgwESG9tZTBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABO20\n\
+NBl5I0cJUpK/2eJVQa/ZqJ1GkP3WOUw9DLRZKNdnupvOtngjjJwtxSGIPG5FYQww\n\
+ONkTTDubbpAlvPpYaEKjUzBRMB0GA1UdDgQWBBRZ6wm4srqzLoMXvUadAsGY0GXZ\n\
+PTAfBgNVHSMEGDAWgBRZ6wm4srqzLoMXvUadAsGY0GXZPTAPBgNVHRMBAf8EBTAD\n\
+AQH/MAoGCCqGSM49BAMCA0gAMEUCIFxzF1o9jMJskGcJzrDbhtL/nUxpNMfc9Bnb\n\
+RXK+iifkAiEAkwSUker6x6Qby+LG5jjuJNJJazrqZAhsIclZp2ZpwTk=\n\
+-----END CERTIFICATE-----
+";
+
+    fn identities(pem: &str) -> Vec<String> {
+        let der = CertificateDer::from_pem_slice(pem.as_bytes()).unwrap();
+        ClientCertificate::parse(&der).unwrap().identities
+    }
+
+    #[test]
+    fn lists_common_names_then_dns_then_uri() {
+        assert_eq!(
+            identities(SCANNER),
+            [
+                "CN=scanner",
+                "CN=kitchen display",
+                "DNS:scanner.lan",
+                "DNS:scanner.local",
+                "URI:spiffe://home/scanner",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_long_common_names() {
+        let name = format!("CN={}label", "pantry shelf ".repeat(12));
+        assert_eq!(identities(SHELF_LABEL), [name]);
+    }
+
+    #[test]
+    fn certificate_without_names_has_no_identities() {
+        assert!(identities(NAMELESS).is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_and_garbage_der() {
+        let der = CertificateDer::from_pem_slice(SCANNER.as_bytes()).unwrap();
+        let truncated = CertificateDer::from(der[..der.len() / 2].to_vec());
+        assert!(ClientCertificate::parse(&truncated).is_none());
+        let garbage = CertificateDer::from(b"not a certificate".to_vec());
+        assert!(ClientCertificate::parse(&garbage).is_none());
+    }
+}
@@ -1,40 +1,508 @@
-use crate::error::{AppError, Result};
+//! Declarative validation of request models.
+//!
+//! Models implement [`Validate`] by describing their fields with a [`Validator`].
+//! Text fields are trimmed and NFC-normalized in place before any rule runs,
+//! and all failing rules are collected, so clients get every error at once.
 
-const MAX_LENGTH: usize = 200;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use unicode_normalization::UnicodeNormalization;
 
-/// Validates that a string is not empty and does not exceed MAX_LENGTH
-pub fn validate_string(value: &str, field_name: &str) -> Result<()> {
-    if value.is_empty() {
-        return Err(AppError::BadRequest(format!(
-            "{} cannot be empty",
-            field_name
-        )));
+use crate::{
+    error::{AppError, Result},
+    i18n::{Lang, Message},
+};
+
+/// Maximum length of names, units and categories in characters
+pub const MAX_LENGTH: usize = 200;
+
+/// Largest accepted item amount
+pub const MAX_AMOUNT: Decimal = Decimal::from_parts(1_000_000, 0, 0, false, 0);
+
+/// Decimal places accepted for item amounts
+pub const AMOUNT_SCALE: u32 = 3;
+
+pub trait Validate {
+    /// Describe the rules for every field of the model
+    fn validate(&mut self, v: &mut Validator);
+
+    /// Normalize the model and check all rules
+    fn validated(&mut self) -> Result<()> {
+        let mut validator = Validator::default();
+        self.validate(&mut validator);
+        validator.finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldError {
+    /// JSON path of the field, e.g. `$.amountUnit`
+    pub field: String,
+    /// Stable rule identifier, e.g. `too_long`
+    pub code: &'static str,
+    pub message: Message,
+}
+
+impl FieldError {
+    pub fn to_json(&self, lang: Lang) -> Value {
+        json!({
+            "field": self.field,
+            "code": self.code,
+            "message": self.message.render(lang),
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    /// Rules for a text field; the value is trimmed and NFC-normalized right away
+    pub fn text<'a, F: TextField>(&'a mut self, name: &str, value: &'a mut F) -> Text<'a, F> {
+        if let Some(s) = value.value_mut() {
+            let normalized: String = s.trim().nfc().collect();
+            *s = normalized;
+        }
+        Text {
+            field: self.path(name),
+            validator: self,
+            value,
+        }
+    }
+
+    /// Rules for a text field that must be taken exactly as sent, like a password
+    pub fn verbatim<'a, F: TextField>(&'a mut self, name: &str, value: &'a mut F) -> Text<'a, F> {
+        Text {
+            field: self.path(name),
+            validator: self,
+            value,
+        }
+    }
+
+    /// Rules for a numeric field
+    pub fn number<F: NumberField>(&mut self, name: &str, value: &F) -> Number<'_> {
+        Number {
+            field: self.path(name),
+            value: value.value(),
+            validator: self,
+        }
+    }
+
+    /// A list field must have at least one entry
+    pub fn not_empty_list<T>(&mut self, name: &str, values: &[T]) {
+        if values.is_empty() {
+            let field = self.path(name);
+            self.add(&field, "required", Message::new("validation.required"));
+        }
+    }
+
+    /// An optional field that must be given after all
+    pub fn required<T>(&mut self, name: &str, value: &Option<T>) {
+        if value.is_none() {
+            let field = self.path(name);
+            self.add(&field, "required", Message::new("validation.required"));
+        }
+    }
+
+    /// A list field must not have more than `max` entries
+    pub fn max_entries<T>(&mut self, name: &str, values: &[T], max: usize) {
+        if values.len() > max {
+            let field = self.path(name);
+            self.add(
+                &field,
+                "too_many",
+                Message::new("validation.too_many")
+                    .arg("max", max)
+                    .arg("len", values.len()),
+            );
+        }
+    }
+
+    /// Rules for a nested model, e.g. `operations[2].item`
+    pub fn nested<M: Validate>(&mut self, name: &str, model: &mut M) {
+        let mut nested = Validator::default();
+        model.validate(&mut nested);
+        for error in nested.errors {
+            let field = format!("{}{}", self.path(name), &error.field[1..]);
+            self.errors.push(FieldError { field, ..error });
+        }
+    }
+
+    fn path(&self, name: &str) -> String {
+        format!("$.{name}")
     }
 
-    if value.len() > MAX_LENGTH {
-        return Err(AppError::BadRequest(format!(
-            "{} must not exceed {} characters (got {})",
-            field_name,
-            MAX_LENGTH,
-            value.len()
-        )));
+    fn add(&mut self, field: &str, code: &'static str, message: Message) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            code,
+            message,
+        });
     }
 
-    Ok(())
+    pub fn finish(self) -> Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
 }
 
-/// Validates an optional string (if present, must not exceed MAX_LENGTH)
-pub fn validate_optional_string(value: &Option<String>, field_name: &str) -> Result<()> {
-    if let Some(ref s) = value {
-        if s.len() > MAX_LENGTH {
-            return Err(AppError::BadRequest(format!(
-                "{} must not exceed {} characters (got {})",
-                field_name,
-                MAX_LENGTH,
-                s.len()
-            )));
+/// A text-like field: a plain string, an optional one, or an optional nullable one
+pub trait TextField {
+    /// The string value, if one was given
+    fn value_mut(&mut self) -> Option<&mut String>;
+    /// Set the field to null; does nothing for non-nullable fields
+    fn set_null(&mut self);
+}
+
+impl TextField for String {
+    fn value_mut(&mut self) -> Option<&mut String> {
+        Some(self)
+    }
+
+    fn set_null(&mut self) {}
+}
+
+impl TextField for Option<String> {
+    fn value_mut(&mut self) -> Option<&mut String> {
+        self.as_mut()
+    }
+
+    fn set_null(&mut self) {
+        *self = None;
+    }
+}
+
+/// `None` = field missing, `Some(None)` = explicit null
+impl TextField for Option<Option<String>> {
+    fn value_mut(&mut self) -> Option<&mut String> {
+        self.as_mut().and_then(Option::as_mut)
+    }
+
+    fn set_null(&mut self) {
+        if self.is_some() {
+            *self = Some(None);
         }
     }
-    Ok(())
 }
 
+pub struct Text<'a, F: TextField> {
+    validator: &'a mut Validator,
+    field: String,
+    value: &'a mut F,
+}
+
+impl<F: TextField> Text<'_, F> {
+    /// If a value is given, it must not be empty
+    pub fn not_empty(self) -> Self {
+        if self.value.value_mut().is_some_and(|s| s.is_empty()) {
+            self.validator
+                .add(&self.field, "required", Message::new("validation.required"));
+        }
+        self
+    }
+
+    /// Treat an empty value like null (for optional fields)
+    pub fn empty_as_null(self) -> Self {
+        if self.value.value_mut().is_some_and(|s| s.is_empty()) {
+            self.value.set_null();
+        }
+        self
+    }
+
+    /// Lowercase the value, e.g. for case-insensitive identifiers
+    pub fn lowercase(self) -> Self {
+        if let Some(s) = self.value.value_mut() {
+            *s = s.to_lowercase();
+        }
+        self
+    }
+
+    /// Minimum length in characters; empty values are left to `not_empty`
+    pub fn min_chars(self, min: usize) -> Self {
+        if let Some(len) = self.value.value_mut().map(|s| s.chars().count()) {
+            if len > 0 && len < min {
+                self.validator.add(
+                    &self.field,
+                    "too_short",
+                    Message::new("validation.too_short")
+                        .arg("min", min)
+                        .arg("len", len),
+                );
+            }
+        }
+        self
+    }
+
+    /// Every character must satisfy `allowed`; `description` lists the allowed characters
+    pub fn chars(self, allowed: fn(char) -> bool, description: &'static str) -> Self {
+        if self
+            .value
+            .value_mut()
+            .is_some_and(|s| !s.chars().all(allowed))
+        {
+            self.validator.add(
+                &self.field,
+                "invalid_chars",
+                Message::new("validation.invalid_chars").arg("allowed", description),
+            );
+        }
+        self
+    }
+
+    /// The value must satisfy `valid`; `expected` describes the format.
+    /// Empty values are left to `not_empty`.
+    pub fn format(self, valid: fn(&str) -> bool, expected: &'static str) -> Self {
+        if self
+            .value
+            .value_mut()
+            .is_some_and(|s| !s.is_empty() && !valid(s))
+        {
+            self.validator.add(
+                &self.field,
+                "invalid_format",
+                Message::new("validation.invalid_format").arg("expected", expected),
+            );
+        }
+        self
+    }
+
+    /// The value must be one of `options`
+    pub fn one_of(self, options: &[&str]) -> Self {
+        if self
+            .value
+            .value_mut()
+            .is_some_and(|s| !options.contains(&s.as_str()))
+        {
+            self.validator.add(
+                &self.field,
+                "not_allowed",
+                Message::new("validation.not_allowed").arg("options", options.join(", ")),
+            );
+        }
+        self
+    }
+
+    /// Length limit in characters (not bytes)
+    pub fn max_chars(self, max: usize) -> Self {
+        if let Some(len) = self.value.value_mut().map(|s| s.chars().count()) {
+            if len > max {
+                self.validator.add(
+                    &self.field,
+                    "too_long",
+                    Message::new("validation.too_long")
+                        .arg("max", max)
+                        .arg("len", len),
+                );
+            }
+        }
+        self
+    }
+}
+
+/// A decimal or integer field: required, optional, or optional nullable
+pub trait NumberField {
+    fn value(&self) -> Option<Decimal>;
+}
+
+impl NumberField for Decimal {
+    fn value(&self) -> Option<Decimal> {
+        Some(*self)
+    }
+}
+
+impl NumberField for Option<Decimal> {
+    fn value(&self) -> Option<Decimal> {
+        *self
+    }
+}
+
+impl NumberField for Option<Option<Decimal>> {
+    fn value(&self) -> Option<Decimal> {
+        self.flatten()
+    }
+}
+
+impl NumberField for Option<i64> {
+    fn value(&self) -> Option<Decimal> {
+        self.map(Decimal::from)
+    }
+}
+
+impl NumberField for Option<Option<i32>> {
+    fn value(&self) -> Option<Decimal> {
+        self.flatten().map(Decimal::from)
+    }
+}
+
+pub struct Number<'a> {
+    validator: &'a mut Validator,
+    field: String,
+    value: Option<Decimal>,
+}
+
+impl Number<'_> {
+    pub fn min(self, min: impl Into<Decimal>) -> Self {
+        let min = min.into();
+        if self.value.is_some_and(|value| value < min) {
+            self.validator.add(
+                &self.field,
+                "too_small",
+                Message::new("validation.too_small").arg("min", min),
+            );
+        }
+        self
+    }
+
+    pub fn max(self, max: impl Into<Decimal>) -> Self {
+        let max = max.into();
+        if self.value.is_some_and(|value| value > max) {
+            self.validator.add(
+                &self.field,
+                "too_large",
+                Message::new("validation.too_large").arg("max", max),
+            );
+        }
+        self
+    }
+
+    /// Maximum number of decimal places (trailing zeros don't count)
+    pub fn max_scale(self, scale: u32) -> Self {
+        if self
+            .value
+            .is_some_and(|value| value.normalize().scale() > scale)
+        {
+            self.validator.add(
+                &self.field,
+                "too_precise",
+                Message::new("validation.too_precise").arg("scale", scale),
+            );
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Item {
+        name: String,
+        unit: Option<Option<String>>,
+        password: String,
+        amount: Option<Decimal>,
+    }
+
+    impl Validate for Item {
+        fn validate(&mut self, v: &mut Validator) {
+            v.text("name", &mut self.name).not_empty().max_chars(5);
+            v.text("unit", &mut self.unit).empty_as_null();
+            v.verbatim("password", &mut self.password);
+            v.number("amount", &self.amount).max_scale(2);
+        }
+    }
+
+    struct Operation {
+        item: Item,
+    }
+
+    impl Validate for Operation {
+        fn validate(&mut self, v: &mut Validator) {
+            v.nested("operations[2].item", &mut self.item);
+        }
+    }
+
+    fn errors(model: &mut impl Validate) -> Vec<(String, &'static str)> {
+        match model.validated() {
+            Ok(()) => Vec::new(),
+            Err(AppError::Validation(errors)) => errors
+                .into_iter()
+                .map(|error| (error.field, error.code))
+                .collect(),
+            Err(error) => panic!("unexpected error: {error:?}"),
+        }
+    }
+
+    #[test]
+    fn trims_and_composes_text() {
+        // "Café" with a combining accent, five characters before composing
+        let mut item = Item {
+            name: "  \tCafe\u{301} \n".to_string(),
+            ..Item::default()
+        };
+        assert!(errors(&mut item).is_empty());
+        assert_eq!(item.name, "Caf\u{e9}");
+    }
+
+    #[test]
+    fn counts_characters_after_composing() {
+        let mut item = Item {
+            name: "e\u{301}".repeat(5),
+            ..Item::default()
+        };
+        assert!(errors(&mut item).is_empty());
+        assert_eq!(item.name.chars().count(), 5);
+
+        item.name = "\u{e9}".repeat(6);
+        assert_eq!(errors(&mut item), [("$.name".to_string(), "too_long")]);
+    }
+
+    #[test]
+    fn whitespace_only_is_empty() {
+        let mut item = Item {
+            name: " \u{3000} ".to_string(),
+            unit: Some(Some("  ".to_string())),
+            ..Item::default()
+        };
+        assert_eq!(errors(&mut item), [("$.name".to_string(), "required")]);
+        assert_eq!(item.unit, Some(None));
+
+        // A missing field stays missing
+        let mut item = Item {
+            name: "Milk".to_string(),
+            ..Item::default()
+        };
+        assert!(errors(&mut item).is_empty());
+        assert_eq!(item.unit, None);
+    }
+
+    #[test]
+    fn verbatim_text_is_left_alone() {
+        let password = " Cafe\u{301} ".to_string();
+        let mut item = Item {
+            name: "Milk".to_string(),
+            password: password.clone(),
+            ..Item::default()
+        };
+        assert!(errors(&mut item).is_empty());
+        assert_eq!(item.password, password);
+    }
+
+    #[test]
+    fn collects_all_errors_with_nested_paths() {
+        let mut operation = Operation {
+            item: Item {
+                name: "Oat milk".to_string(),
+                amount: Some(Decimal::new(1_005, 3)),
+                ..Item::default()
+            },
+        };
+        assert_eq!(
+            errors(&mut operation),
+            [
+                ("$.operations[2].item.name".to_string(), "too_long"),
+                ("$.operations[2].item.amount".to_string(), "too_precise"),
+            ]
+        );
+
+        // Trailing zeros are not precision
+        operation.item.name = "Milk".to_string();
+        operation.item.amount = Some(Decimal::new(1_500, 3));
+        assert!(errors(&mut operation).is_empty());
+    }
+}
@@ -0,0 +1,516 @@
+//! The WebSocket API at `/api/ws`.
+//!
+//! A client authenticates once with an `auth` message, then subscribes to the
+//! item changes of lists and changes items with commands. Commands run the REST
+//! handlers, so permissions, validation, quotas and the audit log apply just as
+//! they do there. The credentials are checked again for every command and
+//! every [`events::RECHECK_INTERVAL`], together with the roles on the
+//! subscribed lists; once they no longer pass, the connection is closed with
+//! 1008 (policy violation). Every message is a JSON object with a `type`.
+//! Commands may carry an `id`, which the `ack` or `error` answering them repeats.
+
+use std::{collections::HashMap, net::IpAddr, pin::pin, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        State,
+    },
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{Instant, MissedTickBehavior},
+};
+
+use crate::{
+    audit,
+    auth::{self, tenant, AuthError, ListRole, Principal, Tenant, CSRF_HEADER},
+    error::{AppError, Result},
+    etag::{IfMatch, Tagged},
+    events::{self, Delivery},
+    extract::{Path, ValidJson},
+    handlers,
+    i18n::{self, Lang},
+    models::{CreateItemRequest, Item, UpdateItemRequest},
+    problem::Problem,
+    state::AppState,
+    tls::ClientCertificate,
+    validation::Validate,
+};
+
+/// Like axum's limit on REST request bodies
+pub const MAX_MESSAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Events queued for a client before its subscriptions wait
+const CAPACITY: usize = 256;
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum ClientMessage {
+    /// Must come first. Without a token, the credentials of the upgrade
+    /// request are used; a session cookie needs the CSRF token.
+    Auth {
+        token: Option<String>,
+        csrf_token: Option<String>,
+        household_id: Option<i32>,
+    },
+    Subscribe {
+        list_id: i32,
+        /// Resume after this event, like `Last-Event-ID`
+        last_event_id: Option<i64>,
+    },
+    Unsubscribe {
+        list_id: i32,
+    },
+    CreateItem {
+        list_id: i32,
+        item: CreateItemRequest,
+    },
+    /// `ifMatch` works like the `If-Match` header
+    UpdateItem {
+        item_id: i32,
+        item: UpdateItemRequest,
+        if_match: Option<String>,
+    },
+    ToggleItem {
+        item_id: i32,
+        if_match: Option<String>,
+    },
+    DeleteItem {
+        item_id: i32,
+        if_match: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum ServerMessage {
+    Authenticated {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Value>,
+        household_id: i32,
+    },
+    /// A command succeeded; `status` and `result` are those of the REST request
+    Ack {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Value>,
+        status: u16,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        result: Option<Item>,
+    },
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Value>,
+        error: Problem,
+    },
+    Event {
+        list_id: i32,
+        event_id: i64,
+        kind: String,
+        item: Item,
+    },
+    /// The events since `lastEventId` are gone; reload the list
+    Reset { list_id: i32, event_id: i64 },
+}
+
+impl ServerMessage {
+    fn delivery(list_id: i32, delivery: Delivery) -> ServerMessage {
+        match delivery {
+            Delivery::Event(event) => ServerMessage::Event {
+                list_id,
+                event_id: event.id,
+                kind: event.kind.clone(),
+                item: event.item.0.clone(),
+            },
+            Delivery::Reset { last_id } => ServerMessage::Reset {
+                list_id,
+                event_id: last_id,
+            },
+        }
+    }
+}
+
+/// What is known about the client from the upgrade request
+pub struct Upgrade {
+    pub headers: HeaderMap,
+    pub peer: Option<IpAddr>,
+    pub certificate: Option<ClientCertificate>,
+    /// Changes made over the connection are attributed to the upgrade request
+    pub context: audit::Context,
+}
+
+/// Established by the `auth` message
+struct Session {
+    /// The upgrade request's headers plus the credentials from `auth`
+    headers: HeaderMap,
+    household_id: i32,
+}
+
+struct Connection {
+    state: AppState,
+    upgrade: Upgrade,
+    session: Option<Session>,
+    /// Forwarding tasks by list ID
+    subscriptions: HashMap<i32, JoinHandle<()>>,
+    sender: mpsc::Sender<ServerMessage>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        for task in self.subscriptions.values() {
+            task.abort();
+        }
+    }
+}
+
+/// Serve a connection until the client leaves. Clients that do not
+/// authenticate within the request timeout are disconnected, as are those
+/// whose credentials or list roles stop passing.
+pub async fn serve(mut socket: WebSocket, state: AppState, upgrade: Upgrade) {
+    let (sender, mut outbox) = mpsc::channel(CAPACITY);
+    let mut auth_deadline = pin!(tokio::time::sleep(Duration::from_secs(
+        state.config.request_timeout_secs
+    )));
+    let mut recheck = tokio::time::interval_at(
+        Instant::now() + events::RECHECK_INTERVAL,
+        events::RECHECK_INTERVAL,
+    );
+    recheck.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut close = None;
+    let mut connection = Connection {
+        state,
+        upgrade,
+        session: None,
+        subscriptions: HashMap::new(),
+        sender,
+    };
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    // Pings are answered by axum
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                    Some(Ok(Message::Binary(bytes))) => {
+                        String::from_utf8_lossy(&bytes).into_owned()
+                    }
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                };
+                let reply = connection.handle(&text).await;
+                if send(&mut socket, &reply).await.is_err() || connection.session.is_none() {
+                    break;
+                }
+            }
+            Some(message) = outbox.recv() => {
+                if send(&mut socket, &message).await.is_err() {
+                    break;
+                }
+            }
+            _ = &mut auth_deadline, if connection.session.is_none() => break,
+            _ = recheck.tick(), if connection.session.is_some() => {
+                match connection.recheck().await {
+                    Ok(()) => {}
+                    Err(error @ (AppError::Auth(_) | AppError::NotFound)) => {
+                        let reply = connection.error(None, error, None);
+                        let _ = send(&mut socket, &reply).await;
+                        close = Some(CloseFrame {
+                            code: close_code::POLICY,
+                            reason: "credentials no longer valid".into(),
+                        });
+                        break;
+                    }
+                    // Not the client's fault, the next tick looks again
+                    Err(e) => tracing::error!("Checking WebSocket credentials failed: {:?}", e),
+                }
+            }
+        }
+    }
+
+    let _ = socket.send(Message::Close(close)).await;
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage) -> std::result::Result<(), ()> {
+    let text = serde_json::to_string(message).map_err(|_| ())?;
+    socket.send(Message::Text(text)).await.map_err(|_| ())
+}
+
+impl Connection {
+    async fn handle(&mut self, text: &str) -> ServerMessage {
+        let value = match serde_json::from_str::<Value>(text) {
+            Ok(value) => value,
+            Err(e) => return self.error(None, invalid_message(e), None),
+        };
+        let id = value.get("id").cloned();
+        let message = match serde_json::from_value::<ClientMessage>(value) {
+            Ok(message) => message,
+            Err(e) => return self.error(id, invalid_message(e), None),
+        };
+
+        match message {
+            ClientMessage::Auth {
+                token,
+                csrf_token,
+                household_id,
+            } if self.session.is_none() => {
+                match self.authenticate(token, csrf_token, household_id).await {
+                    Ok(household_id) => ServerMessage::Authenticated { id, household_id },
+                    Err(error) => self.error(id, error, None),
+                }
+            }
+            ClientMessage::Auth { .. } => {
+                let error = AppError::BadRequest(i18n::Message::new("error.ws_authenticated"));
+                self.error(id, error, None)
+            }
+            _ if self.session.is_none() => {
+                let error = AppError::BadRequest(i18n::Message::new("error.ws_auth_required"));
+                self.error(id, error, None)
+            }
+            command => {
+                let (principal, tenant) = match self.principal().await {
+                    Ok(principal) => principal,
+                    Err(error) => return self.error(id, error, None),
+                };
+                let language = principal.user().and_then(|user| user.language);
+                let context = self.upgrade.context.clone().principal(&principal);
+
+                match audit::scope(context, self.execute(principal, tenant, command)).await {
+                    Ok((status, result)) => ServerMessage::Ack {
+                        id,
+                        status: status.as_u16(),
+                        result,
+                    },
+                    Err(error) => self.error(id, error, language),
+                }
+            }
+        }
+    }
+
+    async fn authenticate(
+        &mut self,
+        token: Option<String>,
+        csrf_token: Option<String>,
+        household_id: Option<i32>,
+    ) -> Result<i32> {
+        let mut headers = self.upgrade.headers.clone();
+        if let Some(token) = token {
+            let value = HeaderValue::from_str(&format!("Bearer {token}"))
+                .map_err(|_| AuthError::InvalidFormat)?;
+            headers.insert(header::AUTHORIZATION, value);
+        }
+        if let Some(csrf_token) = csrf_token {
+            let value = HeaderValue::from_str(&csrf_token).map_err(|_| AuthError::CsrfFailed)?;
+            headers.insert(CSRF_HEADER, value);
+        }
+
+        // Authenticated like a state-changing request, so a cookie session
+        // needs the CSRF token and other sites cannot connect on its behalf
+        let principal = auth::authenticate(
+            &self.state,
+            &headers,
+            &Method::POST,
+            self.upgrade.peer,
+            self.upgrade.certificate.as_ref(),
+        )
+        .await?;
+        let Tenant(household_id) = tenant::resolve(&self.state, &principal, household_id).await?;
+        self.count_request(household_id).await?;
+
+        self.session = Some(Session {
+            headers,
+            household_id,
+        });
+        Ok(household_id)
+    }
+
+    /// The principal and household of a command, checked like a REST request
+    async fn principal(&self) -> Result<(Principal, Tenant)> {
+        let (principal, tenant) = self.authenticated().await?;
+        self.count_request(tenant.0).await?;
+
+        Ok((principal, tenant))
+    }
+
+    /// Whether the session may still see the subscribed lists; not counted
+    /// as a request
+    async fn recheck(&self) -> Result<()> {
+        let (principal, Tenant(household_id)) = self.authenticated().await?;
+        for &list_id in self.subscriptions.keys() {
+            principal
+                .require_list_role(&self.state.pool, household_id, list_id, ListRole::Viewer)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn authenticated(&self) -> Result<(Principal, Tenant)> {
+        let Some(session) = &self.session else {
+            return Err(AuthError::MissingToken.into());
+        };
+
+        let principal = auth::authenticate(
+            &self.state,
+            &session.headers,
+            &Method::POST,
+            self.upgrade.peer,
+            self.upgrade.certificate.as_ref(),
+        )
+        .await?;
+        let tenant = tenant::resolve(&self.state, &principal, Some(session.household_id)).await?;
+
+        Ok((principal, tenant))
+    }
+
+    async fn count_request(&self, household_id: i32) -> Result<()> {
+        let state = &self.state;
+        state
+            .requests
+            .count(&state.pool, &state.config.quotas, household_id)
+            .await
+    }
+
+    async fn execute(
+        &mut self,
+        principal: Principal,
+        tenant: Tenant,
+        command: ClientMessage,
+    ) -> Result<(StatusCode, Option<Item>)> {
+        let state = State(self.state.clone());
+
+        match command {
+            ClientMessage::Subscribe {
+                list_id,
+                last_event_id,
+            } => {
+                self.subscribe(principal, tenant, list_id, last_event_id)
+                    .await?;
+                Ok((StatusCode::OK, None))
+            }
+            ClientMessage::Unsubscribe { list_id } => {
+                let task = self
+                    .subscriptions
+                    .remove(&list_id)
+                    .ok_or(AppError::NotFound)?;
+                task.abort();
+                Ok((StatusCode::NO_CONTENT, None))
+            }
+            ClientMessage::CreateItem { list_id, mut item } => {
+                item.validated()?;
+                let (status, Tagged(item)) =
+                    handlers::create_item(state, principal, tenant, Path(list_id), ValidJson(item))
+                        .await?;
+                Ok((status, Some(item)))
+            }
+            ClientMessage::UpdateItem {
+                item_id,
+                mut item,
+                if_match,
+            } => {
+                item.validated()?;
+                let Tagged(item) = handlers::update_item(
+                    state,
+                    principal,
+                    tenant,
+                    Path(item_id),
+                    IfMatch(if_match),
+                    ValidJson(item),
+                )
+                .await?;
+                Ok((StatusCode::OK, Some(item)))
+            }
+            ClientMessage::ToggleItem { item_id, if_match } => {
+                let Tagged(item) = handlers::toggle_item(
+                    state,
+                    principal,
+                    tenant,
+                    Path(item_id),
+                    IfMatch(if_match),
+                )
+                .await?;
+                Ok((StatusCode::OK, Some(item)))
+            }
+            ClientMessage::DeleteItem { item_id, if_match } => {
+                let status = handlers::delete_item(
+                    state,
+                    principal,
+                    tenant,
+                    Path(item_id),
+                    IfMatch(if_match),
+                )
+                .await?;
+                Ok((status, None))
+            }
+            ClientMessage::Auth { .. } => unreachable!("handled by Connection::handle"),
+        }
+    }
+
+    /// Forward the list's events to the client, replacing an earlier
+    /// subscription to the same list
+    async fn subscribe(
+        &mut self,
+        principal: Principal,
+        Tenant(household_id): Tenant,
+        list_id: i32,
+        last_event_id: Option<i64>,
+    ) -> Result<()> {
+        principal
+            .require_list_role(&self.state.pool, household_id, list_id, ListRole::Viewer)
+            .await?;
+
+        let stream = events::subscribe(
+            &self.state.events,
+            self.state.pool.clone(),
+            principal,
+            household_id,
+            Some(list_id),
+            last_event_id,
+        )
+        .await?;
+
+        let sender = self.sender.clone();
+        let task = tokio::spawn(async move {
+            let mut stream = pin!(stream);
+            while let Some(delivery) = stream.next().await {
+                let message = ServerMessage::delivery(list_id, delivery);
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        if let Some(previous) = self.subscriptions.insert(list_id, task) {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// The error as a problem in the client's language: the user's preference,
+    /// or else the upgrade request's `Accept-Language`
+    fn error(&self, id: Option<Value>, error: AppError, language: Option<Lang>) -> ServerMessage {
+        if let AppError::Database(ref e) = error {
+            tracing::error!("Database error: {:?}", e);
+        }
+
+        let mut problem = error.problem();
+        problem.request_id = self.upgrade.context.request_id.clone();
+        problem.localize(language.unwrap_or_else(|| Lang::from_headers(&self.upgrade.headers)));
+        ServerMessage::Error { id, error: problem }
+    }
+}
+
+fn invalid_message(error: serde_json::Error) -> AppError {
+    AppError::BadRequest(i18n::Message::new("error.ws_invalid_message").arg("reason", error))
+}